@@ -51,7 +51,7 @@ impl ApplicationHandler for Application {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         log::debug!("Resumed");
         self.windows.create_window(
-            &event_loop,
+            event_loop,
             &self.context,
             &WindowDescriptor::default(),
             |_| {},
@@ -1,3 +1,4 @@
+use shader_library::{include_spirv, msaa_sample_count, Shader, ShaderError};
 use std::sync::Arc;
 
 use vulkano::{
@@ -6,6 +7,9 @@ use vulkano::{
         allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
         PrimaryCommandBufferAbstract, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents,
     },
+    device::Device,
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageUsage, SampleCount},
     memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
     pipeline::{
         graphics::{
@@ -20,7 +24,7 @@ use vulkano::{
         layout::PipelineDescriptorSetLayoutCreateInfo,
         DynamicState, GraphicsPipeline, PipelineLayout, PipelineShaderStageCreateInfo,
     },
-    render_pass::{Framebuffer, FramebufferCreateInfo, Subpass},
+    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
     sync::GpuFuture,
 };
 use vulkano_util::{context::VulkanoContext, renderer::VulkanoWindowRenderer};
@@ -32,49 +36,34 @@ struct MyVertex {
     position: [f32; 2],
 }
 
-mod vs {
-    vulkano_shaders::shader! {
-        ty: "vertex",
-        src: r"
-            #version 460
-
-            layout(location = 0) in vec2 position;
-
-            void main() {
-                gl_Position = vec4(position, 0.0, 1.0);
-            }
-        ",
-    }
-}
-
-mod fs {
-    vulkano_shaders::shader! {
-        ty: "fragment",
-        src: r"
-            #version 460
-
-            layout(location = 0) out vec4 f_color;
-
-            void main() {
-                f_color = vec4(1.0, 0.0, 0.0, 1.0);
-            }
-        ",
-    }
+// the shaders take the vertices of MyVertex and nothing else
+fn load_shaders(device: &Arc<Device>) -> Result<[Shader; 2], ShaderError> {
+    let vs = Shader::from_spirv(
+        device,
+        "triangle.vert",
+        include_spirv!("shaders/triangle.vert"),
+    )?;
+    let fs = Shader::from_spirv(
+        device,
+        "triangle.frag",
+        include_spirv!("shaders/triangle.frag"),
+    )?;
+    vs.validate_vertex_input(&[MyVertex::per_vertex()])?;
+    Ok([vs, fs])
 }
 
-pub struct TriangleRenderer;
+// multi-sample anti-aliasing, clamped down to what the device supports, 1 disables it
+const MSAA_SAMPLES: u32 = 4;
 
-impl TriangleRenderer {
-    pub fn draw(
-        before_future: Box<dyn GpuFuture>,
-        context: &VulkanoContext,
-        renderer: &VulkanoWindowRenderer,
-    ) -> Box<dyn GpuFuture> {
-        let render_pass = vulkano::single_pass_renderpass!(
-            context.device().clone(),
+// draws into a multisampled color attachment which is resolved into the swapchain image,
+// or into the swapchain image directly without MSAA
+fn get_render_pass(device: Arc<Device>, format: Format, samples: SampleCount) -> Arc<RenderPass> {
+    if samples == SampleCount::Sample1 {
+        return vulkano::single_pass_renderpass!(
+            device,
             attachments: {
                 color: {
-                    format: renderer.swapchain_format(), // set the format the same as the swapchain
+                    format: format,
                     samples: 1,
                     load_op: Clear,
                     store_op: Store,
@@ -86,11 +75,72 @@ impl TriangleRenderer {
             },
         )
         .unwrap();
+    }
+    vulkano::single_pass_renderpass!(
+        device,
+        attachments: {
+            msaa: {
+                format: format,
+                samples: samples,
+                load_op: Clear,
+                store_op: DontCare,
+            },
+            color: {
+                format: format,
+                samples: 1,
+                load_op: DontCare,
+                store_op: Store,
+            },
+        },
+        pass: {
+            color: [msaa],
+            color_resolve: [color],
+            depth_stencil: {},
+        },
+    )
+    .unwrap()
+}
+
+pub struct TriangleRenderer;
+
+impl TriangleRenderer {
+    pub fn draw(
+        before_future: Box<dyn GpuFuture>,
+        context: &VulkanoContext,
+        renderer: &VulkanoWindowRenderer,
+    ) -> Box<dyn GpuFuture> {
+        let samples = msaa_sample_count(context.device(), MSAA_SAMPLES);
+        // set the format the same as the swapchain
+        let render_pass = get_render_pass(
+            context.device().clone(),
+            renderer.swapchain_format(),
+            samples,
+        );
+
+        // the multisampled image only lives inside the render pass
+        let mut attachments = vec![renderer.swapchain_image_view()];
+        let mut clear_values = vec![Some([0.0, 0.0, 1.0, 1.0].into())];
+        if samples != SampleCount::Sample1 {
+            let msaa_image = Image::new(
+                context.memory_allocator().clone(),
+                ImageCreateInfo {
+                    format: renderer.swapchain_format(),
+                    extent: renderer.swapchain_image_view().image().extent(),
+                    samples,
+                    usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSIENT_ATTACHMENT,
+                    ..Default::default()
+                },
+                AllocationCreateInfo::default(),
+            )
+            .unwrap();
+            attachments.insert(0, ImageView::new_default(msaa_image).unwrap());
+            clear_values.push(None);
+        }
 
         let framebuffer = Framebuffer::new(
             render_pass.clone(),
             FramebufferCreateInfo {
-                attachments: vec![renderer.swapchain_image_view()],
+                attachments,
                 ..Default::default()
             },
         )
@@ -116,18 +166,13 @@ impl TriangleRenderer {
                     | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
                 ..Default::default()
             },
-            vec![vertex1, vertex2, vertex3].into_iter(),
+            vec![vertex1, vertex2, vertex3],
         )
         .unwrap();
 
-        let vs = vs::load(context.device().clone())
-            .unwrap()
-            .entry_point("main")
-            .unwrap();
-        let fs = fs::load(context.device().clone())
-            .unwrap()
-            .entry_point("main")
-            .unwrap();
+        let [vs, fs] = load_shaders(context.device()).unwrap_or_else(|e| panic!("{e}"));
+        let vs = vs.entry_point();
+        let fs = fs.entry_point();
 
         let vertex_input_state = MyVertex::per_vertex().definition(&vs).unwrap();
 
@@ -160,7 +205,10 @@ impl TriangleRenderer {
                 vertex_input_state: Some(vertex_input_state),
                 input_assembly_state: Some(InputAssemblyState::default()),
                 rasterization_state: Some(RasterizationState::default()),
-                multisample_state: Some(MultisampleState::default()),
+                multisample_state: Some(MultisampleState {
+                    rasterization_samples: samples,
+                    ..Default::default()
+                }),
                 color_blend_state: Some(ColorBlendState::with_attachment_states(
                     subpass.num_color_attachments(),
                     ColorBlendAttachmentState::default(),
@@ -189,7 +237,7 @@ impl TriangleRenderer {
             builder
                 .begin_render_pass(
                     RenderPassBeginInfo {
-                        clear_values: vec![Some([0.0, 0.0, 1.0, 1.0].into())],
+                        clear_values,
                         ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
                     },
                     SubpassBeginInfo {
@@ -212,9 +260,9 @@ impl TriangleRenderer {
             builder.build().unwrap()
         };
 
-        return command_buffer
+        command_buffer
             .execute_after(before_future, renderer.graphics_queue())
             .unwrap()
-            .boxed();
+            .boxed()
     }
 }
@@ -0,0 +1,29 @@
+use std::{fs, path::Path};
+
+// Compiles the GLSL files in dirs into OUT_DIR/<dir>/*.spv, the builtin shaders a crate embeds
+// with include_spirv!. Called from the build script of the crate, e.g.
+// shader_library::build::compile_dirs(&["shaders"]);
+pub fn compile_dirs(dirs: &[&str]) {
+    for dir in dirs {
+        println!("cargo:rerun-if-changed={dir}");
+    }
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    let compiler = shaderc::Compiler::new().expect("failed to create the shader compiler");
+    let entries = dirs.iter().flat_map(|dir| fs::read_dir(dir).unwrap());
+    for entry in entries {
+        let path = entry.unwrap().path();
+        let kind = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("vert") => shaderc::ShaderKind::Vertex,
+            Some("frag") => shaderc::ShaderKind::Fragment,
+            _ => continue,
+        };
+        let source = fs::read_to_string(&path).unwrap();
+        let name = path.to_string_lossy();
+        let spirv = compiler
+            .compile_into_spirv(&source, kind, &name, "main", None)
+            .unwrap_or_else(|e| panic!("{e}"));
+        let out = Path::new(&out_dir).join(format!("{name}.spv"));
+        fs::create_dir_all(out.parent().unwrap()).unwrap();
+        fs::write(out, spirv.as_binary_u8()).unwrap();
+    }
+}
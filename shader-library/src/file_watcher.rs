@@ -0,0 +1,79 @@
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    hash::Hash,
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime},
+};
+
+// seconds between two checks of the watched files for changes
+const RELOAD_INTERVAL: f32 = 0.5;
+
+// the modification time of the file at path, None if it can not be read, e.g. on android where
+// the files are assets of the apk
+fn modified(path: impl AsRef<Path>) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// Polls files for changes during development. Each file is watched under a key of the caller,
+// e.g. its path relative to the crate or the asset it is loaded into.
+pub struct FileWatcher<K> {
+    files: HashMap<K, (PathBuf, SystemTime)>,
+    checked: Instant,
+}
+
+impl<K: Clone + Eq + Hash> Default for FileWatcher<K> {
+    fn default() -> Self {
+        FileWatcher {
+            files: HashMap::new(),
+            checked: Instant::now(),
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash> FileWatcher<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // watch the file at path as it is now, a file which does not exist is not watched, e.g. a
+    // builtin with no file, watching a key again forgets changes which were not reported yet
+    pub fn watch(&mut self, key: K, path: impl Into<PathBuf>) {
+        let path = path.into();
+        match modified(&path) {
+            Some(modified) => self.files.insert(key, (path, modified)),
+            None => self.files.remove(&key),
+        };
+    }
+
+    pub fn unwatch<Q: Eq + Hash + ?Sized>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+    {
+        self.files.remove(key);
+    }
+
+    pub fn is_watched<Q: Eq + Hash + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.files.contains_key(key)
+    }
+
+    // the keys of the files which changed since the last call, they are checked at most every
+    // RELOAD_INTERVAL, a file which was removed keeps its last version
+    pub fn changed(&mut self) -> Vec<K> {
+        if self.checked.elapsed().as_secs_f32() < RELOAD_INTERVAL {
+            return Vec::new();
+        }
+        self.checked = Instant::now();
+        let mut changed = Vec::new();
+        for (key, (path, loaded_modified)) in &mut self.files {
+            if let Some(modified) = modified(&*path).filter(|m| m != loaded_modified) {
+                *loaded_modified = modified;
+                changed.push(key.clone());
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,805 @@
+use std::{
+    borrow::Cow, collections::HashMap, error::Error, fmt, mem::size_of, path::Path, sync::Arc,
+};
+use vulkano::{
+    buffer::BufferContents,
+    descriptor_set::layout::DescriptorType,
+    device::Device,
+    format::{Format, NumericType},
+    image::SampleCount,
+    pipeline::{
+        graphics::vertex_input::{Vertex, VertexBufferDescription},
+        layout::PushConstantRange,
+    },
+    shader::{
+        reflect,
+        spirv::{bytes_to_words, Decoration, ExecutionModel, Id, Instruction, Spirv, StorageClass},
+        DescriptorBindingRequirements, EntryPoint, ShaderModule, ShaderModuleCreateInfo,
+    },
+    Validated, VulkanError,
+};
+
+#[cfg(feature = "build")]
+pub mod build;
+mod file_watcher;
+
+pub use file_watcher::FileWatcher;
+
+// the SPIR-V the build script of the calling crate compiled from a file in its shaders directory,
+// e.g. include_spirv!("shaders/quad.vert") embeds OUT_DIR/shaders/quad.vert.spv
+#[macro_export]
+macro_rules! include_spirv {
+    ($path:literal) => {
+        include_bytes!(concat!(env!("OUT_DIR"), "/", $path, ".spv"))
+    };
+}
+
+#[derive(Debug)]
+pub struct ShaderError {
+    pub name: String, // the file or builtin the shader was loaded from
+    pub kind: ShaderErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ShaderErrorKind {
+    Io(std::io::Error),
+    UnknownStage,
+    Compile(String),
+    InvalidSpirv(String),
+    NoMainFunction,
+    Vulkan(Validated<VulkanError>),
+    NotVertexShader,
+    MissingVertexMember {
+        input: String,
+        location: u32,
+    },
+    VertexMemberMismatch {
+        input: String,
+        location: u32,
+        shader: String,
+        format: Format,
+        num_elements: u32,
+    },
+    PushConstantsSize {
+        shader: u32,
+        rust: u32,
+        rust_type: &'static str,
+    },
+    MissingPushConstantMember {
+        member: String,
+        offset: u32,
+    },
+    PushConstantMemberMismatch {
+        member: String,
+        offset: u32,
+        shader: String,
+        rust_offset: u32,
+        format: Format,
+        num_elements: u32,
+    },
+    MissingDescriptor {
+        set: u32,
+        binding: u32,
+    },
+    DescriptorTypeMismatch {
+        set: u32,
+        binding: u32,
+        shader: Vec<DescriptorType>,
+        rust: DescriptorType,
+    },
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.name)?;
+        match &self.kind {
+            ShaderErrorKind::Io(e) => write!(f, "{e}"),
+            ShaderErrorKind::UnknownStage => {
+                write!(f, "unknown shader stage, expected .vert or .frag")
+            }
+            ShaderErrorKind::Compile(e) => write!(f, "{e}"),
+            ShaderErrorKind::InvalidSpirv(e) => write!(f, "invalid SPIR-V: {e}"),
+            ShaderErrorKind::NoMainFunction => write!(f, "no main function"),
+            ShaderErrorKind::Vulkan(e) => write!(f, "{e}"),
+            ShaderErrorKind::NotVertexShader => {
+                write!(f, "vertex input is only checked for vertex shaders")
+            }
+            ShaderErrorKind::MissingVertexMember { input, location } => write!(
+                f,
+                "input `{input}` at location {location} is not a member of the vertex buffers"
+            ),
+            ShaderErrorKind::VertexMemberMismatch {
+                input,
+                location,
+                shader,
+                format,
+                num_elements,
+            } => write!(
+                f,
+                "input `{input}` at location {location} is {shader} in the shader, but the \
+                vertex member is {num_elements} x {format:?}"
+            ),
+            ShaderErrorKind::PushConstantsSize {
+                shader,
+                rust,
+                rust_type,
+            } => write!(
+                f,
+                "push constants are {shader} bytes in the shader, but {rust_type} is {rust} bytes"
+            ),
+            ShaderErrorKind::MissingPushConstantMember { member, offset } => write!(
+                f,
+                "push constant `{member}` at offset {offset} is not a member of the Rust struct"
+            ),
+            ShaderErrorKind::PushConstantMemberMismatch {
+                member,
+                offset,
+                shader,
+                rust_offset,
+                format,
+                num_elements,
+            } => write!(
+                f,
+                "push constant `{member}` is {shader} at offset {offset} in the shader, but the \
+                Rust member is {num_elements} x {format:?} at offset {rust_offset}"
+            ),
+            ShaderErrorKind::MissingDescriptor { set, binding } => {
+                write!(f, "no descriptor at set {set} binding {binding}")
+            }
+            ShaderErrorKind::DescriptorTypeMismatch {
+                set,
+                binding,
+                shader,
+                rust,
+            } => write!(
+                f,
+                "descriptor at set {set} binding {binding} is {shader:?} in the shader, but \
+                {rust:?} is bound"
+            ),
+        }
+    }
+}
+
+impl Error for ShaderError {}
+
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+// the SPIR-V of a shader file, e.g. an asset. Data which is not SPIR-V already is compiled as GLSL
+// with the glsl feature, the stage is given by the extension of name, .vert or .frag.
+pub fn to_spirv<'a>(name: &str, data: &'a [u8]) -> Result<Cow<'a, [u8]>, ShaderError> {
+    if data.starts_with(&SPIRV_MAGIC.to_le_bytes()) {
+        return Ok(Cow::Borrowed(data));
+    }
+    let error = |kind| ShaderError {
+        name: name.into(),
+        kind,
+    };
+    #[cfg(feature = "glsl")]
+    {
+        let kind = match Path::new(name).extension().and_then(|e| e.to_str()) {
+            Some("vert") => shaderc::ShaderKind::Vertex,
+            Some("frag") => shaderc::ShaderKind::Fragment,
+            _ => return Err(error(ShaderErrorKind::UnknownStage)),
+        };
+        let source = std::str::from_utf8(data)
+            .map_err(|e| error(ShaderErrorKind::Compile(e.to_string())))?;
+        let compiler = shaderc::Compiler::new().ok_or(error(ShaderErrorKind::Compile(
+            "failed to create the shader compiler".into(),
+        )))?;
+        let spirv = compiler
+            .compile_into_spirv(source, kind, name, "main", None)
+            .map_err(|e| error(ShaderErrorKind::Compile(e.to_string())))?;
+        Ok(Cow::Owned(spirv.as_binary_u8().to_vec()))
+    }
+    #[cfg(not(feature = "glsl"))]
+    Err(error(ShaderErrorKind::Compile(
+        "GLSL can only be compiled with the glsl feature".into(),
+    )))
+}
+
+// the highest sample count for color attachments of the device which is at most samples,
+// the pipelines of a multisampled render pass are created with it too
+pub fn msaa_sample_count(device: &Device, samples: u32) -> SampleCount {
+    let supported = device
+        .physical_device()
+        .properties()
+        .framebuffer_color_sample_counts;
+    [
+        SampleCount::Sample8,
+        SampleCount::Sample4,
+        SampleCount::Sample2,
+    ]
+    .into_iter()
+    .find(|&sample_count| sample_count as u32 <= samples && supported.contains_enum(sample_count))
+    .unwrap_or(SampleCount::Sample1)
+}
+
+// A vertex shader input variable
+#[derive(Clone, Debug)]
+pub struct ShaderInput {
+    pub name: String,
+    pub location: u32,
+    pub numeric_type: NumericType,
+    pub components: u32, // per column for matrices
+    pub columns: u32,
+}
+
+// the GLSL name of a type, e.g. vec2 or mat4
+fn glsl_type(numeric_type: NumericType, components: u32, columns: u32) -> String {
+    let prefix = match numeric_type {
+        NumericType::Float => "",
+        NumericType::Int => "i",
+        NumericType::Uint => "u",
+    };
+    match (components, columns) {
+        (1, _) => match numeric_type {
+            NumericType::Float => "float".into(),
+            NumericType::Int => "int".into(),
+            NumericType::Uint => "uint".into(),
+        },
+        (n, 1) => format!("{prefix}vec{n}"),
+        (n, m) if n == m => format!("mat{n}"),
+        (n, m) => format!("mat{m}x{n}"),
+    }
+}
+
+// whether a vertex member of format with num_elements holds a value of the type, e.g.
+// R32G32_SFLOAT and vec2, or 4 x R32G32B32A32_SFLOAT and mat4
+fn format_fits(
+    format: Format,
+    num_elements: u32,
+    numeric_type: NumericType,
+    components: u32,
+    columns: u32,
+) -> bool {
+    let format_components = format.components().iter().filter(|c| **c > 0).count();
+    let format_numeric_type = format
+        .numeric_format_color()
+        .map(|numeric_format| numeric_format.numeric_type());
+    format_components as u32 == components
+        && num_elements == columns
+        && format_numeric_type == Some(numeric_type)
+}
+
+// A member of the push constant block
+#[derive(Clone, Debug)]
+pub struct PushConstantMember {
+    pub name: String,
+    pub offset: u32, // bytes from the start of the block
+    pub numeric_type: NumericType,
+    pub components: u32, // per column for matrices
+    pub columns: u32,
+}
+
+// A member of a UniformBlock
+#[derive(Clone, Debug)]
+pub struct UniformMember {
+    pub name: String,
+    pub offset: u32, // bytes from the start of the block
+    pub numeric_type: NumericType,
+    pub components: u32,
+}
+
+// A uniform buffer block of 32 bit scalars and vectors, e.g. the parameters of a material
+#[derive(Clone, Debug)]
+pub struct UniformBlock {
+    pub size: u32, // bytes up to the end of the last member
+    pub members: Vec<UniformMember>,
+}
+
+// The main function of a SPIR-V module with what it expects from the pipeline, so the Rust side
+// vertex types, push constant structs and descriptor sets can be checked against it
+pub struct Shader {
+    module: Arc<ShaderModule>,
+    reflection: ShaderReflection,
+}
+
+impl Shader {
+    pub fn from_spirv(device: &Arc<Device>, name: &str, spirv: &[u8]) -> Result<Self, ShaderError> {
+        let words = spirv_words(name, spirv)?;
+        let reflection = ShaderReflection::from_words(name, &words)?;
+        let module =
+            unsafe { ShaderModule::new(device.clone(), ShaderModuleCreateInfo::new(&words)) }
+                .map_err(|e| reflection.error(ShaderErrorKind::Vulkan(e)))?;
+        Ok(Shader { module, reflection })
+    }
+
+    // a .spv file, or with the glsl feature a .vert or .frag file
+    pub fn load(device: &Arc<Device>, path: impl AsRef<Path>) -> Result<Self, ShaderError> {
+        let path = path.as_ref();
+        let name = path.to_string_lossy();
+        let data = std::fs::read(path).map_err(|e| ShaderError {
+            name: name.to_string(),
+            kind: ShaderErrorKind::Io(e),
+        })?;
+        Self::from_spirv(device, &name, &to_spirv(&name, &data)?)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.reflection.name
+    }
+
+    pub fn module(&self) -> &Arc<ShaderModule> {
+        &self.module
+    }
+
+    pub fn entry_point(&self) -> EntryPoint {
+        self.module.entry_point("main").unwrap()
+    }
+
+    pub fn reflection(&self) -> &ShaderReflection {
+        &self.reflection
+    }
+
+    pub fn execution_model(&self) -> ExecutionModel {
+        self.reflection.execution_model
+    }
+
+    pub fn inputs(&self) -> &[ShaderInput] {
+        &self.reflection.inputs
+    }
+
+    pub fn push_constants(&self) -> Option<PushConstantRange> {
+        self.reflection.push_constants
+    }
+
+    pub fn descriptors(&self) -> &HashMap<(u32, u32), DescriptorBindingRequirements> {
+        &self.reflection.descriptors
+    }
+
+    // the layout of the uniform buffer at set and binding, if it is made of scalars and vectors
+    pub fn uniform_block(&self, set: u32, binding: u32) -> Option<&UniformBlock> {
+        self.reflection.uniform_blocks.get(&(set, binding))
+    }
+
+    pub fn validate_vertex_input(
+        &self,
+        buffers: &[VertexBufferDescription],
+    ) -> Result<(), ShaderError> {
+        self.reflection.validate_vertex_input(buffers)
+    }
+
+    pub fn validate_push_constants<T: BufferContents + Vertex>(&self) -> Result<(), ShaderError> {
+        self.reflection.validate_push_constants::<T>()
+    }
+
+    pub fn validate_descriptor(
+        &self,
+        set: u32,
+        binding: u32,
+        descriptor_type: DescriptorType,
+    ) -> Result<(), ShaderError> {
+        self.reflection
+            .validate_descriptor(set, binding, descriptor_type)
+    }
+}
+
+// the words of SPIR-V bytes
+fn spirv_words<'a>(name: &str, spirv: &'a [u8]) -> Result<Cow<'a, [u32]>, ShaderError> {
+    let error = |kind| ShaderError {
+        name: name.into(),
+        kind,
+    };
+    let words =
+        bytes_to_words(spirv).map_err(|e| error(ShaderErrorKind::InvalidSpirv(e.to_string())))?;
+    if words.first() != Some(&SPIRV_MAGIC) {
+        return Err(error(ShaderErrorKind::InvalidSpirv(
+            "wrong magic number".into(),
+        )));
+    }
+    Ok(words)
+}
+
+// What the main function of a SPIR-V module expects from the pipeline. It needs no device, so
+// shaders can be checked without creating their module.
+pub struct ShaderReflection {
+    name: String, // the file or builtin the shader was loaded from
+    execution_model: ExecutionModel,
+    inputs: Vec<ShaderInput>,
+    push_constants: Option<PushConstantRange>,
+    push_constant_members: Vec<PushConstantMember>,
+    descriptors: HashMap<(u32, u32), DescriptorBindingRequirements>,
+    uniform_blocks: HashMap<(u32, u32), UniformBlock>,
+}
+
+impl ShaderReflection {
+    pub fn from_spirv(name: &str, spirv: &[u8]) -> Result<Self, ShaderError> {
+        Self::from_words(name, &spirv_words(name, spirv)?)
+    }
+
+    fn from_words(name: &str, words: &[u32]) -> Result<Self, ShaderError> {
+        let error = |kind| ShaderError {
+            name: name.into(),
+            kind,
+        };
+        let spirv =
+            Spirv::new(words).map_err(|e| error(ShaderErrorKind::InvalidSpirv(e.to_string())))?;
+        let (id, info) = reflect::entry_points(&spirv)
+            .find(|(_, info)| info.name == "main")
+            .ok_or(error(ShaderErrorKind::NoMainFunction))?;
+        let inputs = if info.execution_model == ExecutionModel::Vertex {
+            vertex_inputs(&spirv, id).map_err(error)?
+        } else {
+            Vec::new()
+        };
+        Ok(ShaderReflection {
+            name: name.into(),
+            execution_model: info.execution_model,
+            inputs,
+            push_constants: info.push_constant_requirements,
+            push_constant_members: push_constant_members(&spirv).map_err(error)?,
+            descriptors: info.descriptor_binding_requirements.into_iter().collect(),
+            uniform_blocks: uniform_blocks(&spirv),
+        })
+    }
+
+    pub fn push_constant_members(&self) -> &[PushConstantMember] {
+        &self.push_constant_members
+    }
+
+    fn error(&self, kind: ShaderErrorKind) -> ShaderError {
+        ShaderError {
+            name: self.name.clone(),
+            kind,
+        }
+    }
+
+    // every input of a vertex shader needs a member of the same name in one of the buffers, with
+    // as many components of the same numeric type, e.g. vec2 position and R32G32_SFLOAT
+    pub fn validate_vertex_input(
+        &self,
+        buffers: &[VertexBufferDescription],
+    ) -> Result<(), ShaderError> {
+        if self.execution_model != ExecutionModel::Vertex {
+            return Err(self.error(ShaderErrorKind::NotVertexShader));
+        }
+        for input in &self.inputs {
+            let Some(member) = buffers
+                .iter()
+                .find_map(|buffer| buffer.members.get(&input.name))
+            else {
+                return Err(self.error(ShaderErrorKind::MissingVertexMember {
+                    input: input.name.clone(),
+                    location: input.location,
+                }));
+            };
+            if !format_fits(
+                member.format,
+                member.num_elements,
+                input.numeric_type,
+                input.components,
+                input.columns,
+            ) {
+                return Err(self.error(ShaderErrorKind::VertexMemberMismatch {
+                    input: input.name.clone(),
+                    location: input.location,
+                    shader: glsl_type(input.numeric_type, input.components, input.columns),
+                    format: member.format,
+                    num_elements: member.num_elements,
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    // the push constant block of the shader has the size of T, and every member of it is a
+    // member of T with the same name, offset and type. The Vertex derive of T describes its
+    // members like the members of a vertex buffer, e.g. a mat4 is 4 x R32G32B32A32_SFLOAT.
+    pub fn validate_push_constants<T: BufferContents + Vertex>(&self) -> Result<(), ShaderError> {
+        let shader = self
+            .push_constants
+            .map_or(0, |range| range.offset + range.size);
+        let rust = size_of::<T>() as u32;
+        if shader != rust {
+            return Err(self.error(ShaderErrorKind::PushConstantsSize {
+                shader,
+                rust,
+                rust_type: std::any::type_name::<T>(),
+            }));
+        }
+        let members = T::per_vertex().members;
+        for member in &self.push_constant_members {
+            let Some(rust_member) = members.get(&member.name) else {
+                return Err(self.error(ShaderErrorKind::MissingPushConstantMember {
+                    member: member.name.clone(),
+                    offset: member.offset,
+                }));
+            };
+            if rust_member.offset != member.offset
+                || !format_fits(
+                    rust_member.format,
+                    rust_member.num_elements,
+                    member.numeric_type,
+                    member.components,
+                    member.columns,
+                )
+            {
+                return Err(self.error(ShaderErrorKind::PushConstantMemberMismatch {
+                    member: member.name.clone(),
+                    offset: member.offset,
+                    shader: glsl_type(member.numeric_type, member.components, member.columns),
+                    rust_offset: rust_member.offset,
+                    format: rust_member.format,
+                    num_elements: rust_member.num_elements,
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    // the shader declares the binding, and accepts descriptors of the type
+    pub fn validate_descriptor(
+        &self,
+        set: u32,
+        binding: u32,
+        descriptor_type: DescriptorType,
+    ) -> Result<(), ShaderError> {
+        let Some(requirements) = self.descriptors.get(&(set, binding)) else {
+            return Err(self.error(ShaderErrorKind::MissingDescriptor { set, binding }));
+        };
+        if !requirements.descriptor_types.contains(&descriptor_type) {
+            return Err(self.error(ShaderErrorKind::DescriptorTypeMismatch {
+                set,
+                binding,
+                shader: requirements.descriptor_types.clone(),
+                rust: descriptor_type,
+            }));
+        }
+        Ok(())
+    }
+}
+
+// the input variables of the interface of the entry point, without builtins like gl_VertexIndex
+fn vertex_inputs(spirv: &Spirv, entry_point: Id) -> Result<Vec<ShaderInput>, ShaderErrorKind> {
+    let Some(Instruction::EntryPoint { interface, .. }) = spirv.function(entry_point).entry_point()
+    else {
+        return Err(ShaderErrorKind::NoMainFunction);
+    };
+    let mut inputs = Vec::new();
+    for &id in interface {
+        let variable = spirv.id(id);
+        let &Instruction::Variable {
+            result_type_id,
+            storage_class: StorageClass::Input,
+            ..
+        } = variable.instruction()
+        else {
+            continue;
+        };
+        let builtin = variable.decorations().iter().any(|decoration| {
+            matches!(
+                decoration,
+                Instruction::Decorate {
+                    decoration: Decoration::BuiltIn { .. },
+                    ..
+                }
+            )
+        });
+        let location = variable
+            .decorations()
+            .iter()
+            .find_map(|decoration| match decoration {
+                Instruction::Decorate {
+                    decoration: Decoration::Location { location },
+                    ..
+                } => Some(*location),
+                _ => None,
+            });
+        if builtin {
+            continue;
+        }
+        let Some(location) = location else {
+            continue;
+        };
+        let name = variable
+            .names()
+            .iter()
+            .find_map(|name| match name {
+                Instruction::Name { name, .. } => Some(name.clone()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let &Instruction::TypePointer { ty, .. } = spirv.id(result_type_id).instruction() else {
+            return Err(ShaderErrorKind::InvalidSpirv(format!(
+                "input `{name}` is not a pointer"
+            )));
+        };
+        let (ty, columns) = match *spirv.id(ty).instruction() {
+            Instruction::TypeMatrix {
+                column_type,
+                column_count,
+                ..
+            } => (column_type, column_count),
+            _ => (ty, 1),
+        };
+        let Some((numeric_type, components)) = scalar_or_vector(spirv, ty) else {
+            return Err(ShaderErrorKind::InvalidSpirv(format!(
+                "input `{name}` has an unsupported type"
+            )));
+        };
+        inputs.push(ShaderInput {
+            name,
+            location,
+            numeric_type,
+            components,
+            columns,
+        });
+    }
+    inputs.sort_by_key(|input| input.location);
+    Ok(inputs)
+}
+
+// the numeric type and component count of a scalar or vector type
+fn scalar_or_vector(spirv: &Spirv, ty: Id) -> Option<(NumericType, u32)> {
+    let (ty, components) = match *spirv.id(ty).instruction() {
+        Instruction::TypeVector {
+            component_type,
+            component_count,
+            ..
+        } => (component_type, component_count),
+        _ => (ty, 1),
+    };
+    let numeric_type = match *spirv.id(ty).instruction() {
+        Instruction::TypeFloat { width: 32, .. } => NumericType::Float,
+        Instruction::TypeInt {
+            width: 32,
+            signedness: 1,
+            ..
+        } => NumericType::Int,
+        Instruction::TypeInt { width: 32, .. } => NumericType::Uint,
+        _ => return None,
+    };
+    Some((numeric_type, components))
+}
+
+// the members of the push constant block, members which are not 32 bit scalars, vectors or
+// matrices are left out, only the size of the block covers them
+fn push_constant_members(spirv: &Spirv) -> Result<Vec<PushConstantMember>, ShaderErrorKind> {
+    let Some(&Instruction::Variable { result_type_id, .. }) =
+        spirv.global_variables().iter().find(|variable| {
+            matches!(
+                variable,
+                Instruction::Variable {
+                    storage_class: StorageClass::PushConstant,
+                    ..
+                }
+            )
+        })
+    else {
+        return Ok(Vec::new());
+    };
+    let &Instruction::TypePointer { ty, .. } = spirv.id(result_type_id).instruction() else {
+        return Err(ShaderErrorKind::InvalidSpirv(
+            "the push constant block is not a pointer".into(),
+        ));
+    };
+    let struct_info = spirv.id(ty);
+    let Instruction::TypeStruct { member_types, .. } = struct_info.instruction() else {
+        return Err(ShaderErrorKind::InvalidSpirv(
+            "the push constant block is not a struct".into(),
+        ));
+    };
+    let mut members = Vec::new();
+    for (&member_type, member) in member_types.iter().zip(struct_info.members()) {
+        let (column_type, columns) = match *spirv.id(member_type).instruction() {
+            Instruction::TypeMatrix {
+                column_type,
+                column_count,
+                ..
+            } => (column_type, column_count),
+            _ => (member_type, 1),
+        };
+        let Some((numeric_type, components)) = scalar_or_vector(spirv, column_type) else {
+            continue;
+        };
+        let name = member
+            .names()
+            .iter()
+            .find_map(|name| match name {
+                Instruction::MemberName { name, .. } => Some(name.clone()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let Some(offset) = member
+            .decorations()
+            .iter()
+            .find_map(|decoration| match decoration {
+                Instruction::MemberDecorate {
+                    decoration: Decoration::Offset { byte_offset },
+                    ..
+                } => Some(*byte_offset),
+                _ => None,
+            })
+        else {
+            return Err(ShaderErrorKind::InvalidSpirv(format!(
+                "push constant `{name}` has no offset"
+            )));
+        };
+        members.push(PushConstantMember {
+            name,
+            offset,
+            numeric_type,
+            components,
+            columns,
+        });
+    }
+    Ok(members)
+}
+
+// the uniform blocks made of 32 bit scalars and vectors by set and binding, others are left out
+fn uniform_blocks(spirv: &Spirv) -> HashMap<(u32, u32), UniformBlock> {
+    let mut blocks = HashMap::new();
+    for variable in spirv.global_variables() {
+        let &Instruction::Variable {
+            result_type_id,
+            result_id,
+            storage_class: StorageClass::Uniform,
+            ..
+        } = variable
+        else {
+            continue;
+        };
+        let (mut set, mut binding) = (None, None);
+        for decoration in spirv.id(result_id).decorations() {
+            match decoration {
+                Instruction::Decorate {
+                    decoration: Decoration::DescriptorSet { descriptor_set },
+                    ..
+                } => set = Some(*descriptor_set),
+                Instruction::Decorate {
+                    decoration: Decoration::Binding { binding_point },
+                    ..
+                } => binding = Some(*binding_point),
+                _ => (),
+            }
+        }
+        let (Some(set), Some(binding)) = (set, binding) else {
+            continue;
+        };
+        let &Instruction::TypePointer { ty, .. } = spirv.id(result_type_id).instruction() else {
+            continue;
+        };
+        let struct_info = spirv.id(ty);
+        let Instruction::TypeStruct { member_types, .. } = struct_info.instruction() else {
+            continue;
+        };
+        let members = member_types
+            .iter()
+            .zip(struct_info.members())
+            .map(|(&member_type, member)| {
+                let (numeric_type, components) = scalar_or_vector(spirv, member_type)?;
+                let name = member.names().iter().find_map(|name| match name {
+                    Instruction::MemberName { name, .. } => Some(name.clone()),
+                    _ => None,
+                })?;
+                let offset =
+                    member
+                        .decorations()
+                        .iter()
+                        .find_map(|decoration| match decoration {
+                            Instruction::MemberDecorate {
+                                decoration: Decoration::Offset { byte_offset },
+                                ..
+                            } => Some(*byte_offset),
+                            _ => None,
+                        })?;
+                Some(UniformMember {
+                    name,
+                    offset,
+                    numeric_type,
+                    components,
+                })
+            })
+            .collect::<Option<Vec<_>>>();
+        let Some(members) = members else {
+            continue;
+        };
+        let size = members
+            .iter()
+            .map(|member| member.offset + member.components * 4)
+            .max()
+            .unwrap_or(0);
+        blocks.insert((set, binding), UniformBlock { size, members });
+    }
+    blocks
+}
@@ -0,0 +1,3 @@
+fn main() {
+    shader_library::build::compile_dirs(&["shaders", "materials"]);
+}
@@ -0,0 +1,23 @@
+#version 460
+
+// a fragment shader of a material gets the same inputs and descriptor set 0 as shaders/quad.frag,
+// the parameters of the material are in the uniform block at set 1 binding 0
+
+layout(location = 0) in vec2 v_uv;
+layout(location = 1) in vec4 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler s;
+layout(set = 0, binding = 1) uniform texture2D tex;
+
+layout(set = 1, binding = 0) uniform Params {
+    vec4 dark;   // multiplied with every other cell
+    float cells; // per side of the quad
+} params;
+
+void main() {
+    vec2 cell = floor(v_uv * params.cells);
+    vec4 color = v_color * texture(sampler2D(tex, s), v_uv);
+    f_color = mod(cell.x + cell.y, 2.0) < 1.0 ? color : color * params.dark;
+}
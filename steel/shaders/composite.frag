@@ -0,0 +1,11 @@
+#version 460
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler s;
+layout(set = 0, binding = 1) uniform texture2D light;
+
+// multiplied with the scene image by the blend state
+void main() {
+    f_color = vec4(texelFetch(sampler2D(light, s), ivec2(gl_FragCoord.xy), 0).rgb, 1.0);
+}
@@ -0,0 +1,38 @@
+#version 460
+
+layout(push_constant) uniform PushConstants {
+    mat4 projection_view;
+    vec2 position;
+    float radius;
+    float height;
+    vec4 color;
+    vec2 direction;
+    float cos_inner;
+    float cos_outer;
+} pcs;
+
+layout(location = 0) in vec2 v_position;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler s;
+layout(set = 0, binding = 1) uniform texture2D normals;
+
+void main() {
+    vec2 to_light = pcs.position - v_position;
+    float distance = length(to_light);
+    float falloff = clamp(1.0 - distance / pcs.radius, 0.0, 1.0);
+    falloff *= falloff;
+    // spot lights fade out between the inner and the outer angle of their cone
+    if (pcs.cos_outer > -1.0 && distance > 0.0) {
+        float cos_angle = dot(-to_light / distance, pcs.direction);
+        falloff *= smoothstep(pcs.cos_outer, pcs.cos_inner, cos_angle);
+    }
+    // normal mapped pixels are lit by the angle to the light, which is height above the scene
+    vec4 normal = texelFetch(sampler2D(normals, s), ivec2(gl_FragCoord.xy), 0);
+    if (normal.a > 0.0) {
+        vec3 light_direction = normalize(vec3(to_light, pcs.height));
+        falloff *= max(dot(normalize(normal.xyz * 2.0 - 1.0), light_direction), 0.0);
+    }
+    f_color = vec4(pcs.color.rgb * falloff, 1.0);
+}
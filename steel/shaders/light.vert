@@ -0,0 +1,21 @@
+#version 460
+
+layout(push_constant) uniform PushConstants {
+    mat4 projection_view;
+    vec2 position;
+    float radius;
+    float height;
+    vec4 color;
+    vec2 direction;
+    float cos_inner;
+    float cos_outer;
+} pcs;
+
+layout(location = 0) in vec2 position;
+
+layout(location = 0) out vec2 v_position;
+
+void main() {
+    gl_Position = pcs.projection_view * vec4(position, 0.0, 1.0);
+    v_position = position;
+}
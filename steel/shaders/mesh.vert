@@ -0,0 +1,18 @@
+#version 460
+
+layout(push_constant) uniform PushConstants {
+    mat4 transform;
+    vec4 color;
+} pcs;
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 uv;
+
+layout(location = 0) out vec2 v_uv;
+layout(location = 1) out vec4 v_color;
+
+void main() {
+    gl_Position = pcs.transform * vec4(position, 0.0, 1.0);
+    v_uv = uv;
+    v_color = pcs.color;
+}
@@ -0,0 +1,26 @@
+#version 460
+
+layout(location = 0) in vec2 v_uv;
+layout(location = 1) in vec2 v_x_axis;
+layout(location = 2) in vec2 v_y_axis;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler s;
+layout(set = 0, binding = 1) uniform texture2D normal_map;
+
+// normal maps are uploaded as srgb like every texture, so sampling decoded them
+vec3 srgb_encode(vec3 linear) {
+    return mix(linear * 12.92, 1.055 * pow(linear, vec3(1.0 / 2.4)) - 0.055,
+        step(0.0031308, linear));
+}
+
+void main() {
+    vec4 texel = texture(sampler2D(normal_map, s), v_uv);
+    if (texel.a < 0.5) {
+        discard;
+    }
+    vec3 normal = srgb_encode(texel.rgb) * 2.0 - 1.0;
+    vec2 xy = normal.x * v_x_axis + normal.y * v_y_axis;
+    f_color = vec4(normalize(vec3(xy, normal.z)) * 0.5 + 0.5, 1.0);
+}
@@ -0,0 +1,22 @@
+#version 460
+
+layout(push_constant) uniform PushConstants {
+    mat4 projection_view;
+} pcs;
+
+layout(location = 0) in vec2 corner;
+layout(location = 1) in mat4 model;
+layout(location = 5) in vec2 uv_min;
+layout(location = 6) in vec2 uv_max;
+
+layout(location = 0) out vec2 v_uv;
+layout(location = 1) out vec2 v_x_axis;
+layout(location = 2) out vec2 v_y_axis;
+
+void main() {
+    gl_Position = pcs.projection_view * model * vec4(corner, 0.0, 1.0);
+    v_uv = mix(uv_min, uv_max, vec2(corner.x + 0.5, 0.5 - corner.y));
+    // the normals of the map turn and flip with the sprite
+    v_x_axis = normalize(model[0].xy);
+    v_y_axis = normalize(model[1].xy);
+}
@@ -0,0 +1,13 @@
+#version 460
+
+layout(location = 0) in vec2 v_uv;
+layout(location = 1) in vec4 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler s;
+layout(set = 0, binding = 1) uniform texture2D particle;
+
+void main() {
+    f_color = v_color * texture(sampler2D(particle, s), v_uv);
+}
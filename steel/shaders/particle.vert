@@ -0,0 +1,19 @@
+#version 460
+
+layout(push_constant) uniform PushConstants {
+    mat4 projection_view;
+} pcs;
+
+layout(location = 0) in vec2 corner;
+layout(location = 1) in vec2 center;
+layout(location = 2) in float size;
+layout(location = 3) in vec4 color;
+
+layout(location = 0) out vec2 v_uv;
+layout(location = 1) out vec4 v_color;
+
+void main() {
+    gl_Position = pcs.projection_view * vec4(center + corner * size, 0.0, 1.0);
+    v_uv = vec2(corner.x + 0.5, 0.5 - corner.y);
+    v_color = color;
+}
@@ -0,0 +1,18 @@
+#version 460
+
+layout(push_constant) uniform PushConstants {
+    mat4 projection_view;
+} pcs;
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in mat4 model;
+layout(location = 5) in vec4 color;
+
+layout(location = 0) out vec2 v_uv;
+layout(location = 1) out vec4 v_color;
+
+void main() {
+    gl_Position = pcs.projection_view * model * vec4(position, 0.0, 1.0);
+    v_uv = vec2(position.x + 0.5, 0.5 - position.y);
+    v_color = color;
+}
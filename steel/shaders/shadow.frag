@@ -0,0 +1,10 @@
+#version 460
+
+layout(location = 0) in float v_alpha;
+
+layout(location = 0) out vec4 f_color;
+
+// only the alpha is written, it is how much of the light reaches the pixel
+void main() {
+    f_color = vec4(0.0, 0.0, 0.0, v_alpha);
+}
@@ -0,0 +1,15 @@
+#version 460
+
+layout(push_constant) uniform PushConstants {
+    mat4 projection_view;
+} pcs;
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in float alpha;
+
+layout(location = 0) out float v_alpha;
+
+void main() {
+    gl_Position = pcs.projection_view * vec4(position, 0.0, 1.0);
+    v_alpha = alpha;
+}
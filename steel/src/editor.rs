@@ -0,0 +1,158 @@
+use crate::{
+    gizmo::{Gizmo, GizmoMode},
+    steel::{Engine, EngineBuilder, Plugin, SceneUi},
+};
+use glam::Vec2;
+use shipyard::EntityId;
+
+// The editor in the Scene Window: editor camera, selection, gizmos, prefabs and debugging
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, builder: &mut EngineBuilder) {
+        builder.add_scene_ui(Editor {
+            editor_camera: true,
+            physics_debug_view: false,
+            msaa_samples: 4,
+            selected: None,
+            painting: false,
+            paint_tile: 1,
+            gizmo: Gizmo::new(),
+        });
+    }
+}
+
+struct Editor {
+    editor_camera: bool, // view the scene through the editor camera instead of the game cameras
+    physics_debug_view: bool,
+    msaa_samples: u32, // 1 is off
+    selected: Option<EntityId>,
+    painting: bool, // clicks and drags paint tiles on the selected tilemap instead of selecting
+    paint_tile: i32, // 0 erases
+    gizmo: Gizmo,
+}
+
+impl SceneUi for Editor {
+    fn toolbar(&mut self, ui: &mut egui::Ui, engine: &mut dyn Engine, scene_size: Vec2) {
+        ui.checkbox(&mut self.editor_camera, "Editor camera");
+        engine.set_editor_camera(self.editor_camera);
+        ui.separator();
+        ui.selectable_value(&mut self.gizmo.mode, GizmoMode::Translate, "Move");
+        ui.selectable_value(&mut self.gizmo.mode, GizmoMode::Rotate, "Rotate");
+        ui.selectable_value(&mut self.gizmo.mode, GizmoMode::Scale, "Scale");
+        ui.separator();
+        ui.toggle_value(&mut self.painting, "Paint");
+        if self.painting {
+            ui.add(egui::DragValue::new(&mut self.paint_tile).range(0..=255))
+                .on_hover_text("The tile painted on the selected tilemap, 0 erases");
+        }
+        ui.separator();
+        ui.menu_button("Spawn", |ui| {
+            for path in engine.prefabs() {
+                if ui.button(&path).clicked() {
+                    // at the center of the scene
+                    let position = engine
+                        .screen_to_world(scene_size / 2.0, scene_size, self.editor_camera)
+                        .unwrap_or_default();
+                    self.selected = engine.spawn_prefab(&path, position);
+                    log::info!("Spawned {path}, entity={:?}", self.selected);
+                    ui.close_menu();
+                }
+            }
+        });
+        if let Some(selected) = self.selected {
+            if ui.button("Apply to prefab").clicked() {
+                engine.apply_to_prefab(selected);
+            }
+        }
+        ui.separator();
+        if ui.button("Dump world").clicked() {
+            engine.dump_world_data();
+        }
+        ui.checkbox(&mut self.physics_debug_view, "Physics debug");
+        engine.set_physics_debug_view(self.physics_debug_view);
+        let msaa_text = |samples: u32| match samples {
+            1 => "MSAA off".to_string(),
+            samples => format!("MSAA {samples}x"),
+        };
+        egui::ComboBox::from_id_salt("msaa")
+            .selected_text(msaa_text(self.msaa_samples))
+            .show_ui(ui, |ui| {
+                for samples in [1, 2, 4, 8] {
+                    ui.selectable_value(&mut self.msaa_samples, samples, msaa_text(samples));
+                }
+            })
+            .response
+            .on_hover_text("Clamped to the sample counts the device supports");
+        engine.set_msaa_samples(self.msaa_samples);
+    }
+
+    fn scene(
+        &mut self,
+        ui: &mut egui::Ui,
+        response: &egui::Response,
+        engine: &mut dyn Engine,
+        scene_size: Vec2,
+    ) {
+        if let (true, Some(selected)) = (self.painting, self.selected) {
+            let painted = response.clicked() || response.dragged_by(egui::PointerButton::Primary);
+            if let (true, Some(pointer_pos)) = (painted, response.interact_pointer_pos()) {
+                let position = pointer_pos - response.rect.min;
+                if let Some(position) = engine.screen_to_world(
+                    Vec2::new(position.x, position.y),
+                    scene_size,
+                    self.editor_camera,
+                ) {
+                    engine.set_tile(selected, position, self.paint_tile);
+                }
+            }
+        } else if let (true, Some(pointer_pos)) =
+            (response.clicked(), response.interact_pointer_pos())
+        {
+            let position = pointer_pos - response.rect.min;
+            self.selected = engine.pick(
+                Vec2::new(position.x, position.y),
+                scene_size,
+                self.editor_camera,
+            );
+            log::info!("Selected entity {:?}", self.selected);
+        }
+        if let (false, Some(selected)) = (self.painting, self.selected) {
+            self.gizmo.ui(
+                ui,
+                response,
+                engine,
+                selected,
+                scene_size,
+                self.editor_camera,
+            );
+        }
+        if self.editor_camera {
+            // pan with the right or middle mouse button, zoom with the wheel,
+            // on touch screens pan and pinch with two fingers
+            let mut pan = egui::Vec2::ZERO;
+            if response.dragged_by(egui::PointerButton::Secondary)
+                || response.dragged_by(egui::PointerButton::Middle)
+            {
+                pan += response.drag_delta();
+            }
+            let mut zoom = 1.0;
+            if response.hovered() {
+                let (scroll, multi_touch) =
+                    ui.input(|i| (i.smooth_scroll_delta.y, i.multi_touch()));
+                zoom *= (scroll * 0.002).exp();
+                if let Some(multi_touch) = multi_touch {
+                    pan += multi_touch.translation_delta;
+                    zoom *= multi_touch.zoom_delta;
+                }
+            }
+            if pan != egui::Vec2::ZERO {
+                engine.pan_editor_camera(Vec2::new(pan.x, pan.y), scene_size);
+            }
+            if let (true, Some(hover_pos)) = (zoom != 1.0, response.hover_pos()) {
+                let position = hover_pos - response.rect.min;
+                engine.zoom_editor_camera(zoom, Vec2::new(position.x, position.y), scene_size);
+            }
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use crate::steel::{ComponentData, Engine, Value};
+use glam::Vec2;
+use shipyard::EntityId;
+
+const AXIS_LENGTH: f32 = 60.0; // length of the axis handles in pixels
+const HANDLE_RADIUS: f32 = 6.0; // size of the handle knobs in pixels, also used for hit testing
+const RING_RADIUS: f32 = 50.0; // radius of the rotation ring in pixels
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GizmoHandle {
+    X,      // along the x axis, world axis when translating and local axis when scaling
+    Y,      // along the y axis, world axis when translating and local axis when scaling
+    Center, // free translation or uniform scale
+    Ring,   // rotation
+}
+
+struct GizmoDrag {
+    handle: GizmoHandle,
+    start_pointer: Vec2, // world position of the pointer when the drag started
+    start_position: Vec2,
+    start_rotation: f32,
+    start_scale: Vec2,
+}
+
+// Selection outline and translate/rotate/scale handles drawn over the Scene Window,
+// the transform is read and written through the same component data as the inspector
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    drag: Option<GizmoDrag>,
+}
+
+impl Gizmo {
+    pub fn new() -> Self {
+        Gizmo {
+            mode: GizmoMode::Translate,
+            drag: None,
+        }
+    }
+
+    // response is the response of the scene image, window_size is the size of the scene
+    pub fn ui(
+        &mut self,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        engine: &mut dyn Engine,
+        entity: EntityId,
+        window_size: Vec2,
+        editor_camera: bool,
+    ) {
+        let origin = Vec2::new(response.rect.min.x, response.rect.min.y);
+        let Some(layout) = self.layout(engine, entity, origin, window_size, editor_camera) else {
+            self.drag = None;
+            return;
+        };
+
+        // start, continue and stop dragging a handle
+        if response.drag_started_by(egui::PointerButton::Primary) {
+            self.drag = response.interact_pointer_pos().and_then(|pointer| {
+                let pointer = Vec2::new(pointer.x, pointer.y);
+                Some(GizmoDrag {
+                    handle: self.hit_test(&layout, pointer)?,
+                    start_pointer: engine.screen_to_world(
+                        pointer - origin,
+                        window_size,
+                        editor_camera,
+                    )?,
+                    start_position: layout.position,
+                    start_rotation: layout.rotation,
+                    start_scale: layout.scale,
+                })
+            });
+        }
+        if let (Some(drag), true, Some(pointer)) = (
+            &self.drag,
+            response.dragged_by(egui::PointerButton::Primary),
+            response.interact_pointer_pos(),
+        ) {
+            let pointer = Vec2::new(pointer.x, pointer.y) - origin;
+            if let Some(pointer) = engine.screen_to_world(pointer, window_size, editor_camera) {
+                let (position, rotation, scale) = drag.apply(self.mode, pointer);
+                write_transform(engine, entity, self.mode, position, rotation, scale);
+            }
+        }
+        if response.drag_stopped() {
+            self.drag = None;
+        }
+
+        // paint with the transform written above
+        if let Some(layout) = self.layout(engine, entity, origin, window_size, editor_camera) {
+            self.paint(ui, response, &layout);
+        }
+    }
+
+    fn layout(
+        &self,
+        engine: &dyn Engine,
+        entity: EntityId,
+        origin: Vec2,
+        window_size: Vec2,
+        editor_camera: bool,
+    ) -> Option<GizmoLayout> {
+        let (position, rotation, scale) = read_transform(engine, entity)?;
+        let to_screen = |world: Vec2| {
+            engine
+                .world_to_screen(world, position, window_size, editor_camera)
+                .map(|p| p + origin)
+        };
+        let center = to_screen(position)?;
+
+        // handle directions on screen, the camera may be rotated or flipped
+        let (sin, cos) = rotation.sin_cos();
+        let (world_x, world_y) = match self.mode {
+            GizmoMode::Scale => (Vec2::new(cos, sin), Vec2::new(-sin, cos)),
+            _ => (Vec2::X, Vec2::Y),
+        };
+        let screen_axis = |axis: Vec2| {
+            to_screen(position + axis)
+                .map(|p| (p - center).normalize_or_zero() * AXIS_LENGTH)
+                .unwrap_or_default()
+        };
+
+        // the outline of the unit quad the entity is drawn with
+        let outline = [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)]
+            .into_iter()
+            .filter_map(|(x, y)| {
+                let local = Vec2::new(x * scale.x, y * scale.y);
+                to_screen(
+                    position
+                        + Vec2::new(local.x * cos - local.y * sin, local.x * sin + local.y * cos),
+                )
+            })
+            .collect();
+
+        Some(GizmoLayout {
+            position,
+            rotation,
+            scale,
+            center,
+            axis_x: screen_axis(world_x),
+            axis_y: screen_axis(world_y),
+            outline,
+        })
+    }
+
+    fn hit_test(&self, layout: &GizmoLayout, pointer: Vec2) -> Option<GizmoHandle> {
+        let near = |p: Vec2| pointer.distance(p) <= HANDLE_RADIUS * 1.5;
+        match self.mode {
+            GizmoMode::Rotate => ((pointer.distance(layout.center) - RING_RADIUS).abs()
+                <= HANDLE_RADIUS)
+                .then_some(GizmoHandle::Ring),
+            GizmoMode::Translate | GizmoMode::Scale => {
+                if near(layout.center + layout.axis_x) {
+                    Some(GizmoHandle::X)
+                } else if near(layout.center + layout.axis_y) {
+                    Some(GizmoHandle::Y)
+                } else if near(layout.center) {
+                    Some(GizmoHandle::Center)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn paint(&self, ui: &egui::Ui, response: &egui::Response, layout: &GizmoLayout) {
+        let painter = ui.painter_at(response.rect);
+        let pos2 = |p: Vec2| egui::pos2(p.x, p.y);
+        let color = |handle: GizmoHandle, color: egui::Color32| match &self.drag {
+            Some(drag) if drag.handle == handle => egui::Color32::YELLOW,
+            _ => color,
+        };
+        let knob =
+            |p: Vec2| egui::Rect::from_center_size(pos2(p), egui::Vec2::splat(HANDLE_RADIUS * 2.0));
+        let center = layout.center;
+
+        painter.add(egui::Shape::closed_line(
+            layout.outline.iter().copied().map(pos2).collect(),
+            egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 165, 0)),
+        ));
+
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                for (handle, axis, base) in [
+                    (GizmoHandle::X, layout.axis_x, egui::Color32::RED),
+                    (GizmoHandle::Y, layout.axis_y, egui::Color32::GREEN),
+                ] {
+                    let stroke = egui::Stroke::new(2.0, color(handle, base));
+                    if self.mode == GizmoMode::Translate {
+                        painter.arrow(pos2(center), egui::vec2(axis.x, axis.y), stroke);
+                    } else {
+                        painter.line_segment([pos2(center), pos2(center + axis)], stroke);
+                        painter.rect_filled(knob(center + axis), 0.0, color(handle, base));
+                    }
+                }
+                painter.rect_filled(
+                    knob(center),
+                    0.0,
+                    color(GizmoHandle::Center, egui::Color32::from_rgb(80, 160, 255)),
+                );
+            }
+            GizmoMode::Rotate => {
+                painter.circle_stroke(
+                    pos2(center),
+                    RING_RADIUS,
+                    egui::Stroke::new(
+                        2.0,
+                        color(GizmoHandle::Ring, egui::Color32::from_rgb(80, 160, 255)),
+                    ),
+                );
+            }
+        }
+    }
+}
+
+// where the selected entity and its handles are on screen, in egui coordinates
+struct GizmoLayout {
+    position: Vec2,
+    rotation: f32,
+    scale: Vec2,
+    center: Vec2,
+    axis_x: Vec2, // from center to the end of the x handle
+    axis_y: Vec2, // from center to the end of the y handle
+    outline: Vec<Vec2>,
+}
+
+impl GizmoDrag {
+    // the new position, rotation and scale for the pointer at world position
+    fn apply(&self, mode: GizmoMode, pointer: Vec2) -> (Vec2, f32, Vec2) {
+        let (mut position, mut rotation, mut scale) =
+            (self.start_position, self.start_rotation, self.start_scale);
+        let delta = pointer - self.start_pointer;
+        match mode {
+            GizmoMode::Translate => match self.handle {
+                GizmoHandle::X => position.x += delta.x,
+                GizmoHandle::Y => position.y += delta.y,
+                _ => position += delta,
+            },
+            GizmoMode::Rotate => {
+                let angle = |p: Vec2| {
+                    let p = p - self.start_position;
+                    p.y.atan2(p.x)
+                };
+                rotation += angle(pointer) - angle(self.start_pointer);
+            }
+            GizmoMode::Scale => {
+                let (sin, cos) = self.start_rotation.sin_cos();
+                let ratio = |axis: Option<Vec2>| {
+                    let (from, to) = (
+                        self.start_pointer - self.start_position,
+                        pointer - self.start_position,
+                    );
+                    let (from, to) = match axis {
+                        Some(axis) => (from.dot(axis), to.dot(axis)),
+                        None => (from.length(), to.length()),
+                    };
+                    if from.abs() < 1e-4 {
+                        1.0
+                    } else {
+                        to / from
+                    }
+                };
+                match self.handle {
+                    GizmoHandle::X => scale.x *= ratio(Some(Vec2::new(cos, sin))),
+                    GizmoHandle::Y => scale.y *= ratio(Some(Vec2::new(-sin, cos))),
+                    _ => scale *= ratio(None),
+                }
+            }
+        }
+        (position, rotation, scale)
+    }
+}
+
+fn read_transform(engine: &dyn Engine, entity: EntityId) -> Option<(Vec2, f32, Vec2)> {
+    let data = engine.component_data(entity, "Transform2D")?;
+    let (mut position, mut rotation, mut scale) = (None, None, None);
+    for v in data.variants {
+        match (v.name, v.value) {
+            ("position", Value::Vec3(v)) => position = Some(v.truncate()),
+            ("rotation", Value::Float32(v)) => rotation = Some(v),
+            ("scale", Value::Vec2(v)) => scale = Some(v),
+            _ => (),
+        }
+    }
+    Some((position?, rotation?, scale?))
+}
+
+// writes only the variant mode changes, so a drag does not override the others of a prefab instance
+fn write_transform(
+    engine: &mut dyn Engine,
+    entity: EntityId,
+    mode: GizmoMode,
+    position: Vec2,
+    rotation: f32,
+    scale: Vec2,
+) {
+    let Some(ComponentData { name, mut variants }) = engine.component_data(entity, "Transform2D")
+    else {
+        return;
+    };
+    let changed = match mode {
+        GizmoMode::Translate => "position",
+        GizmoMode::Rotate => "rotation",
+        GizmoMode::Scale => "scale",
+    };
+    variants.retain(|v| v.name == changed);
+    for v in &mut variants {
+        match (v.name, &mut v.value) {
+            ("position", Value::Vec3(v)) => (v.x, v.y) = (position.x, position.y),
+            ("rotation", Value::Float32(v)) => *v = rotation,
+            ("scale", Value::Vec2(v)) => *v = scale,
+            _ => (),
+        }
+    }
+    engine.set_component_data(entity, ComponentData { name, variants });
+}
@@ -1,22 +1,28 @@
 use egui_winit_vulkano::{Gui, GuiConfig};
 use glam::Vec2;
-use std::sync::Arc;
-use vulkano::{
-    image::{view::ImageView, Image, ImageCreateInfo, ImageUsage},
-    memory::allocator::AllocationCreateInfo,
-    sync::GpuFuture,
-};
+use vulkano::sync::GpuFuture;
 use vulkano_util::{
     context::VulkanoContext,
     window::{VulkanoWindows, WindowDescriptor},
 };
 use winit::{
     application::ApplicationHandler,
-    event::WindowEvent,
+    event::{TouchPhase, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
 };
+mod editor;
+mod gizmo;
+mod scene_target;
 mod steel;
-use crate::steel::DrawInfo;
+use crate::{
+    editor::EditorPlugin,
+    scene_target::SceneTarget,
+    steel::{
+        Animation2DPlugin, AudioPlugin, DebugDrawPlugin, DemoPlugin, DrawInfo, EngineBuilder,
+        Light2DPlugin, Mesh2DPlugin, Particle2DPlugin, Physics2DPlugin, Renderer2DPlugin,
+        Text2DPlugin, TilemapPlugin,
+    },
+};
 
 #[cfg(target_os = "android")]
 use winit::platform::android::{activity::AndroidApp, EventLoopBuilderExtAndroid};
@@ -27,6 +33,8 @@ fn android_main(app: AndroidApp) {
     android_logger::init_once(
         android_logger::Config::default().with_max_level(log::LevelFilter::Trace),
     );
+    // assets are read from the apk
+    steel::set_android_app(app.clone());
     let event_loop = EventLoop::builder().with_android_app(app).build().unwrap();
     _main(event_loop);
 }
@@ -54,24 +62,41 @@ struct Application {
     windows: VulkanoWindows,
     gui: Option<Gui>,
     demo_windows: egui_demo_lib::DemoWindows,
-    scene_image: Option<Arc<ImageView>>,
-    scene_texture_id: Option<egui::TextureId>,
-    scene_size: Vec2,
+    scene_target: SceneTarget,
+    scene_size: Vec2,    // in logical pixels
+    scene_offset: Vec2,  // top left of the scene image in the window, in logical pixels
+    scene_hovered: bool, // pointer events over the scene go to the game even though egui wants them
     engine: Box<dyn steel::Engine>,
 }
 
 impl Application {
     fn new() -> Self {
-        let mut engine = steel::create();
+        // leave out a plugin to run without it, e.g. the editor
+        let mut engine = EngineBuilder::new()
+            .add_plugin(Physics2DPlugin)
+            .add_plugin(TilemapPlugin)
+            .add_plugin(Renderer2DPlugin)
+            .add_plugin(Mesh2DPlugin)
+            .add_plugin(Animation2DPlugin)
+            .add_plugin(Particle2DPlugin)
+            // lights what the plugins above draw, the debug lines and text stay unlit
+            .add_plugin(Light2DPlugin)
+            .add_plugin(AudioPlugin)
+            .add_plugin(DebugDrawPlugin)
+            .add_plugin(Text2DPlugin)
+            .add_plugin(EditorPlugin)
+            .add_plugin(DemoPlugin)
+            .build();
         engine.init();
         Self {
             context: VulkanoContext::default(),
             windows: VulkanoWindows::default(),
             gui: None,
             demo_windows: egui_demo_lib::DemoWindows::default(),
-            scene_image: None,
-            scene_texture_id: None,
+            scene_target: SceneTarget::new(),
             scene_size: Vec2::ZERO,
+            scene_offset: Vec2::ZERO,
+            scene_hovered: false,
             engine,
         }
     }
@@ -81,14 +106,14 @@ impl ApplicationHandler for Application {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         log::debug!("Resumed");
         self.windows.create_window(
-            &event_loop,
+            event_loop,
             &self.context,
             &WindowDescriptor::default(),
             |_| {},
         );
         let renderer = self.windows.get_primary_renderer().unwrap();
         self.gui = Some(Gui::new(
-            &event_loop,
+            event_loop,
             renderer.surface(),
             renderer.graphics_queue(),
             renderer.swapchain_format(),
@@ -101,8 +126,7 @@ impl ApplicationHandler for Application {
 
     fn suspended(&mut self, _: &winit::event_loop::ActiveEventLoop) {
         log::debug!("Suspended");
-        self.scene_texture_id = None;
-        self.scene_image = None;
+        self.scene_target.release();
         self.gui = None;
         self.windows
             .remove_renderer(self.windows.primary_window_id().unwrap());
@@ -115,7 +139,32 @@ impl ApplicationHandler for Application {
         event: WindowEvent,
     ) {
         if let Some(gui) = self.gui.as_mut() {
-            let _pass_events_to_game = !gui.update(&event);
+            let consumed = gui.update(&event);
+            let pass_events_to_game = match &event {
+                // always let releases, ended touches and focus changes through, otherwise keys,
+                // buttons and touches could get stuck
+                WindowEvent::KeyboardInput { event, .. } if !event.state.is_pressed() => true,
+                WindowEvent::MouseInput { state, .. } if !state.is_pressed() => true,
+                WindowEvent::Touch(touch)
+                    if matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled) =>
+                {
+                    true
+                }
+                WindowEvent::Focused(_) => true,
+                WindowEvent::CursorMoved { .. }
+                | WindowEvent::CursorLeft { .. }
+                | WindowEvent::MouseInput { .. }
+                | WindowEvent::MouseWheel { .. }
+                | WindowEvent::Touch(_) => !consumed || self.scene_hovered,
+                _ => !consumed,
+            };
+            if pass_events_to_game {
+                let scale_factor = self
+                    .windows
+                    .get_primary_window()
+                    .map_or(1.0, |window| window.scale_factor() as f32);
+                self.engine.input(&event, self.scene_offset, scale_factor);
+            }
         }
         match event {
             WindowEvent::CloseRequested => {
@@ -146,67 +195,54 @@ impl ApplicationHandler for Application {
                         egui::Window::new("Scene Window")
                             .resizable(true)
                             .show(&ctx, |ui| {
+                                ui.horizontal(|ui| {
+                                    self.engine.toolbar_ui(ui, self.scene_size);
+                                });
                                 let available_size = ui.available_size();
-                                if self.scene_image.is_none()
-                                    || self.scene_size.x != available_size.x
-                                    || self.scene_size.y != available_size.y
-                                {
-                                    (self.scene_size.x, self.scene_size.y) =
-                                        (available_size.x, available_size.y);
-                                    let image = Image::new(
-                                        self.context.memory_allocator().clone(),
-                                        ImageCreateInfo {
-                                            format: renderer.swapchain_format(),
-                                            extent: [
-                                                self.scene_size.x as u32,
-                                                self.scene_size.y as u32,
-                                                1,
-                                            ],
-                                            usage: ImageUsage::SAMPLED
-                                                | ImageUsage::COLOR_ATTACHMENT,
-                                            ..Default::default()
-                                        },
-                                        AllocationCreateInfo::default(),
-                                    )
-                                    .unwrap();
-                                    self.scene_image = Some(ImageView::new_default(image).unwrap());
-                                    if let Some(scene_texture_id) = self.scene_texture_id {
-                                        gui.unregister_user_image(scene_texture_id);
-                                    }
-                                    self.scene_texture_id = Some(gui.register_user_image_view(
-                                        self.scene_image.as_ref().unwrap().clone(),
-                                        Default::default(),
-                                    ));
-                                    log::info!(
-                                        "Created scene image, scene_size={}",
-                                        self.scene_size
-                                    );
-                                }
-                                ui.image(egui::ImageSource::Texture(
-                                    egui::load::SizedTexture::new(
-                                        *self.scene_texture_id.as_ref().unwrap(),
-                                        available_size,
-                                    ),
-                                ));
+                                self.scene_size = Vec2::new(available_size.x, available_size.y);
+                                self.scene_target.resize(
+                                    gui,
+                                    self.context.memory_allocator(),
+                                    renderer.swapchain_format(),
+                                    self.scene_size,
+                                    ctx.pixels_per_point(),
+                                );
+                                let (texture_id, uv) = self.scene_target.texture().unwrap();
+                                let response = ui.add(
+                                    egui::Image::new(egui::ImageSource::Texture(
+                                        egui::load::SizedTexture::new(texture_id, available_size),
+                                    ))
+                                    .uv(uv)
+                                    .sense(egui::Sense::click_and_drag()),
+                                );
+                                self.scene_offset =
+                                    Vec2::new(response.rect.min.x, response.rect.min.y);
+                                self.scene_hovered = response.hovered();
+                                self.engine.scene_ui(ui, &response, self.scene_size);
                             });
                     });
 
                     let gpu_future = renderer.acquire(None, |_| {}).unwrap();
 
-                    self.engine.update();
-
-                    let draw_future = self.engine.draw(DrawInfo {
-                        before_future: vulkano::sync::now(self.context.device().clone()).boxed(),
-                        context: &self.context,
-                        renderer: &renderer,
-                        image: self.scene_image.as_ref().unwrap().clone(),
-                        window_size: self.scene_size,
-                    });
+                    // the scene window is collapsed before the first scene image
+                    let gpu_future = match self.scene_target.image() {
+                        Some(image) => {
+                            let draw_future = self.engine.update_and_draw(DrawInfo {
+                                before_future: vulkano::sync::now(self.context.device().clone())
+                                    .boxed(),
+                                context: &self.context,
+                                renderer,
+                                image: image.clone(),
+                                viewport: self.scene_target.viewport(),
+                                window_size: self.scene_size,
+                                scale_factor: gui.context().pixels_per_point(),
+                            });
+                            gpu_future.join(draw_future).boxed()
+                        }
+                        None => gpu_future,
+                    };
 
-                    let gpu_future = gui.draw_on_image(
-                        gpu_future.join(draw_future),
-                        renderer.swapchain_image_view(),
-                    );
+                    let gpu_future = gui.draw_on_image(gpu_future, renderer.swapchain_image_view());
 
                     renderer.present(gpu_future, true);
 
@@ -0,0 +1,104 @@
+use egui_winit_vulkano::Gui;
+use glam::{UVec2, Vec2};
+use std::sync::Arc;
+use vulkano::{
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator},
+};
+
+const BUCKET: u32 = 256; // the image grows and shrinks in steps of this many physical pixels
+
+// The image the scene is drawn into and its egui texture. The scene is drawn into the top left
+// of the image in physical pixels, the image is only recreated and registered with egui again
+// when the scene outgrows it or shrinks to half of it, so resizing the window does not churn
+pub struct SceneTarget {
+    image: Option<Arc<ImageView>>,
+    texture_id: Option<egui::TextureId>,
+    extent: UVec2, // of the scene in physical pixels, at most the extent of the image
+}
+
+impl SceneTarget {
+    pub fn new() -> Self {
+        SceneTarget {
+            image: None,
+            texture_id: None,
+            extent: UVec2::ZERO,
+        }
+    }
+
+    // fit the target to a scene of size logical pixels, a collapsed scene keeps 1 pixel
+    // so the engine can still draw and update
+    pub fn resize(
+        &mut self,
+        gui: &mut Gui,
+        memory_allocator: &Arc<StandardMemoryAllocator>,
+        format: Format,
+        size: Vec2,
+        scale_factor: f32,
+    ) {
+        self.extent = (size * scale_factor).round().as_uvec2().max(UVec2::ONE);
+        let capacity = (self.extent + UVec2::splat(BUCKET - 1)) / BUCKET * BUCKET;
+        if let Some(image) = &self.image {
+            let image = image.image();
+            let current = UVec2::new(image.extent()[0], image.extent()[1]);
+            if image.format() == format
+                && self.extent.cmple(current).all()
+                && (capacity * 2).cmpgt(current).all()
+            {
+                return;
+            }
+        }
+
+        let image = Image::new(
+            memory_allocator.clone(),
+            ImageCreateInfo {
+                format,
+                extent: [capacity.x, capacity.y, 1],
+                usage: ImageUsage::SAMPLED
+                    | ImageUsage::COLOR_ATTACHMENT
+                    | ImageUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )
+        .unwrap();
+        let image = ImageView::new_default(image).unwrap();
+        if let Some(texture_id) = self.texture_id.take() {
+            gui.unregister_user_image(texture_id);
+        }
+        self.texture_id = Some(gui.register_user_image_view(image.clone(), Default::default()));
+        self.image = Some(image);
+        log::info!(
+            "Created scene image, extent={capacity}, scene extent={}",
+            self.extent
+        );
+    }
+
+    // the image is registered with the gui, which drops it with its textures
+    pub fn release(&mut self) {
+        self.image = None;
+        self.texture_id = None;
+    }
+
+    pub fn image(&self) -> Option<&Arc<ImageView>> {
+        self.image.as_ref()
+    }
+
+    // the scene in physical pixels of the image
+    pub fn viewport(&self) -> Vec2 {
+        self.extent.as_vec2()
+    }
+
+    // the texture of the image and the uv rect of the scene in it
+    pub fn texture(&self) -> Option<(egui::TextureId, egui::Rect)> {
+        let (texture_id, image) = (self.texture_id?, self.image.as_ref()?);
+        let image_extent = image.image().extent();
+        let uv_max =
+            self.extent.as_vec2() / Vec2::new(image_extent[0] as f32, image_extent[1] as f32);
+        Some((
+            texture_id,
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(uv_max.x, uv_max.y)),
+        ))
+    }
+}
@@ -1,356 +1,966 @@
-use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
-use rapier2d::prelude::*;
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use rapier2d::{
+    crossbeam::channel::{unbounded, Receiver},
+    prelude::*,
+};
 use rayon::iter::ParallelIterator;
+use shader_library::msaa_sample_count;
 use shipyard::{
-    AddComponent, Component, EntityId, Get, IntoIter, IntoWithId, Unique, UniqueViewMut, View,
+    track, AddComponent, AllStorages, AllStoragesView, Component, EntitiesView, EntityId, Get,
+    IntoIter, IntoWithId, Remove, SystemModificator, Unique, UniqueView, UniqueViewMut, View,
     ViewMut, World,
 };
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    sync::Arc,
+    time::Instant,
+};
 use vulkano::{
-    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage},
     command_buffer::{
-        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
-        PrimaryCommandBufferAbstract, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents,
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, ClearColorImageInfo,
+        CommandBufferUsage, PrimaryCommandBufferAbstract, ResolveImageInfo,
     },
-    image::view::ImageView,
-    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
-    pipeline::{
-        graphics::{
-            color_blend::{ColorBlendAttachmentState, ColorBlendState},
-            input_assembly::InputAssemblyState,
-            multisample::MultisampleState,
-            rasterization::RasterizationState,
-            vertex_input::{Vertex, VertexDefinition},
-            viewport::{Scissor, Viewport, ViewportState},
-            GraphicsPipelineCreateInfo,
-        },
-        layout::PipelineDescriptorSetLayoutCreateInfo,
-        GraphicsPipeline, Pipeline, PipelineLayout, PipelineShaderStageCreateInfo,
-    },
-    render_pass::{Framebuffer, FramebufferCreateInfo, Subpass},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageUsage, SampleCount},
+    memory::allocator::AllocationCreateInfo,
+    render_pass::Subpass,
     sync::GpuFuture,
 };
 use vulkano_util::{context::VulkanoContext, renderer::VulkanoWindowRenderer};
+use winit::{
+    dpi::PhysicalPosition,
+    event::{MouseButton, MouseScrollDelta, TouchPhase, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+mod animation;
+mod asset;
+mod audio;
+mod clip;
+mod debug_draw;
+mod demo;
+mod gamepad;
+mod light;
+mod material;
+mod mesh;
+mod particle;
+mod plugin;
+mod prefab;
+mod render;
+mod schedule;
+mod script;
+mod shader;
+mod text;
+mod tiled;
+mod tilemap;
+pub use animation::{Animation2DPlugin, AnimationManager, Animator2D};
+use asset::asset_server_system;
+#[cfg(target_os = "android")]
+pub use asset::set_android_app;
+pub use asset::AssetServer;
+pub use audio::{AudioManager, AudioPlugin, AudioSource};
+pub use debug_draw::{DebugDraw, DebugDrawPlugin};
+pub use demo::DemoPlugin;
+use gamepad::{gamepad_system, GamepadAxis, GamepadButton, Gamepads};
+pub use light::{AmbientLight2D, Light2DPlugin, PointLight2D, SpotLight2D};
+use material::{renderer2d_extract_system, MaterialManager, Renderer2DPass};
+pub use mesh::{Mesh2DPlugin, MeshRenderer2D};
+pub use particle::{Particle2DPlugin, ParticleEmitter2D};
+use plugin::ComponentRegistry;
+pub use plugin::{EngineBuilder, Plugin, SceneUi};
+use prefab::{
+    apply_to_prefab, prefab_reload_system, record_override, spawn_prefab, PrefabInstance,
+    PrefabManager,
+};
+use render::{
+    begin_scene, camera2d_extract_system, clear_viewport, scene_render_pass, CLEAR_COLOR,
+};
+pub use render::{
+    Glyph2D, GlyphAtlasImage, Light2D, Line2D, Mesh2D, MeshVertex2D, Particle2D, Quad2D,
+    RenderInfo, RenderList, RenderPass, ShadowShape2D, Sprite2D, TextSpace, TextureImage,
+    TileChunk2D, TileQuad,
+};
+use schedule::{Schedule, Stage};
+use script::{script_collision_system, script_update_system, Script, ScriptManager};
+use shader::{Shader, ShaderManager};
+pub use text::{DebugText, Text2D, Text2DPlugin, TextAlign};
+pub use tilemap::{Tilemap, TilemapPlugin};
 
 pub struct DrawInfo<'a> {
     pub before_future: Box<dyn GpuFuture>,
     pub context: &'a VulkanoContext,
     pub renderer: &'a VulkanoWindowRenderer,
-    pub image: Arc<ImageView>, // the image we will draw
-    pub window_size: Vec2,
+    pub image: Arc<ImageView>, // the image we will draw, can be larger than the scene
+    pub viewport: Vec2,        // size of the scene in physical pixels, at the top left of image
+    pub window_size: Vec2,     // size of the scene in logical pixels, which input positions are in
+    pub scale_factor: f32,     // physical pixels per logical pixel
 }
 
 pub trait Engine {
     fn init(&mut self);
-    fn update(&mut self);
-    fn draw(&mut self, info: DrawInfo) -> Box<dyn GpuFuture>;
-}
-
-pub fn create() -> Box<dyn Engine> {
-    let world = World::new();
-    Box::new(EngineImpl { world })
+    // draws the frame extracted by the last update while the next update runs,
+    // so the scene image is one update behind the world
+    fn update_and_draw(&mut self, info: DrawInfo) -> Box<dyn GpuFuture>;
+    // returns the entity under position, which is in pixels relative to the top left of the scene
+    fn pick(&mut self, position: Vec2, window_size: Vec2, editor_camera: bool) -> Option<EntityId>;
+    // move the editor camera by delta pixels
+    fn pan_editor_camera(&mut self, delta: Vec2, window_size: Vec2);
+    // scale the editor camera zoom by factor, keeping the world point under position in place
+    fn zoom_editor_camera(&mut self, factor: f32, position: Vec2, window_size: Vec2);
+    // convert pixels relative to the top left of the scene to world coordinates with the camera
+    // under the position, None if no camera covers it
+    fn screen_to_world(
+        &self,
+        position: Vec2,
+        window_size: Vec2,
+        editor_camera: bool,
+    ) -> Option<Vec2>;
+    // convert world coordinates to pixels relative to the top left of the scene with the top
+    // camera whose viewport shows anchor, so all points of e.g. a gizmo use the same camera and
+    // the one screen_to_world uses around it, None if no camera shows anchor
+    fn world_to_screen(
+        &self,
+        position: Vec2,
+        anchor: Vec2,
+        window_size: Vec2,
+        editor_camera: bool,
+    ) -> Option<Vec2>;
+    // the edit path shared by the inspector and the gizmos, component is the name from Edit::name
+    fn component_data(&self, entity: EntityId, component: &str) -> Option<ComponentData>;
+    fn set_component_data(&mut self, entity: EntityId, data: ComponentData);
+    // paths of the prefabs which can be spawned
+    fn prefabs(&self) -> Vec<String>;
+    // spawn an instance of the prefab at the world position
+    fn spawn_prefab(&mut self, path: &str, position: Vec2) -> Option<EntityId>;
+    // move the edits of a prefab instance into its prefab, which updates the other instances
+    fn apply_to_prefab(&mut self, entity: EntityId);
+    // feed a window event which was not consumed by the ui to the game,
+    // scene_offset is the top left of the scene in logical pixels of the window
+    fn input(&mut self, event: &WindowEvent, scene_offset: Vec2, scale_factor: f32);
+    // log all editable component data at the end of the next update
+    fn dump_world_data(&mut self);
+    // draw with the editor camera instead of the Camera2D components
+    fn set_editor_camera(&mut self, enabled: bool);
+    // draw colliders, contacts and velocities, does nothing without DebugDrawPlugin
+    fn set_physics_debug_view(&mut self, enabled: bool);
+    // multi-sample anti-aliasing of the scene image, 1 disables it, 2, 4 or 8 are clamped down
+    // to the highest sample count the device supports
+    fn set_msaa_samples(&mut self, samples: u32);
+    // set the tile of the Tilemap of entity at the world position,
+    // returns false if the entity has no tilemap or the position is outside of it
+    fn set_tile(&mut self, entity: EntityId, position: Vec2, tile: i32) -> bool;
+    // the egui of the plugins in the Scene Window, toolbar is the row above the scene image
+    fn toolbar_ui(&mut self, ui: &mut egui::Ui, scene_size: Vec2);
+    fn scene_ui(&mut self, ui: &mut egui::Ui, response: &egui::Response, scene_size: Vec2);
 }
 
+// created by EngineBuilder::build
 struct EngineImpl {
-    world: World, // ecs world, also contains resources and managers
+    world: World,       // ecs world, also contains resources and managers
+    schedule: Schedule, // systems run by init and update
+    render_passes: Vec<Box<dyn RenderPass>>,
+    scene_uis: Vec<Box<dyn SceneUi>>,
+    textures: HashMap<u64, Arc<ImageView>>, // uploaded textures of the last frame by id
+    shaders: Arc<ShaderManager>,
+    msaa_samples: u32, // requested by set_msaa_samples, clamped to what the device supports
+    msaa_image: Option<Arc<ImageView>>, // drawn instead of the scene image with MSAA
+    scene_render_pass: Option<Arc<vulkano::render_pass::RenderPass>>, // begun on the drawn image
 }
 
-impl Engine for EngineImpl {
-    fn init(&mut self) {
-        log::info!("Engine::init");
-
-        self.world.add_unique(Physics2DManager::new());
-
-        self.world.add_entity((
-            Transform2D {
-                position: Vec3 {
-                    x: 0.0,
-                    y: 10.0,
-                    z: 0.0,
-                },
-                rotation: 0.0,
-                scale: Vec2::ONE,
-            },
-            RigidBody2D::new(RigidBodyType::Dynamic),
-            Collider2D::new(SharedShape::cuboid(0.5, 0.5), 0.7),
-            Renderer2D,
-        ));
-        self.world.add_entity((
-            Transform2D {
-                position: Vec3 {
-                    x: 0.0,
-                    y: 0.0,
-                    z: 0.0,
-                },
-                rotation: 0.0,
-                scale: Vec2 { x: 20.0, y: 0.2 },
+impl EngineImpl {
+    fn cameras(&self, editor_camera: bool) -> Vec<Camera2D> {
+        let alpha = self.interpolation_alpha();
+        self.world.run(
+            |transform2d: View<Transform2D>,
+             interpolation2d: View<Interpolation2D>,
+             camera2d: View<Camera2D>,
+             editor_camera2d: UniqueView<EditorCamera2D>| {
+                active_cameras(
+                    editor_camera,
+                    &camera2d,
+                    &editor_camera2d,
+                    &transform2d,
+                    &interpolation2d,
+                    alpha,
+                )
             },
-            Collider2D::new(SharedShape::cuboid(10.0, 0.1), 0.7),
-            Renderer2D,
-        ));
+        )
     }
 
-    fn update(&mut self) {
-        log::info!("Engine::update");
+    // the render pass the passes draw the image in, recreated when its format or samples change
+    fn scene_render_pass(
+        &mut self,
+        context: &VulkanoContext,
+        format: Format,
+        samples: SampleCount,
+    ) -> Arc<vulkano::render_pass::RenderPass> {
+        let attachment = |render_pass: &Arc<vulkano::render_pass::RenderPass>| {
+            let attachment = &render_pass.attachments()[0];
+            (attachment.format, attachment.samples)
+        };
+        match &self.scene_render_pass {
+            Some(render_pass) if attachment(render_pass) == (format, samples) => {
+                render_pass.clone()
+            }
+            _ => self
+                .scene_render_pass
+                .insert(scene_render_pass(context.device(), format, samples))
+                .clone(),
+        }
+    }
 
-        self.world.run(physics2d_maintain_system);
-        self.world.run(physics2d_update_system);
+    // the image the passes draw, the scene image itself without MSAA,
+    // recreated when the size or format of the scene image or the sample count changes
+    fn msaa_image(
+        &mut self,
+        context: &VulkanoContext,
+        scene_image: &Arc<ImageView>,
+    ) -> Arc<ImageView> {
+        let samples = msaa_sample_count(context.device(), self.msaa_samples);
+        if samples == SampleCount::Sample1 {
+            self.msaa_image = None;
+            return scene_image.clone();
+        }
+        let scene = scene_image.image();
+        if let Some(msaa_image) = self.msaa_image.as_ref().filter(|msaa_image| {
+            let image = msaa_image.image();
+            image.extent() == scene.extent()
+                && image.format() == scene.format()
+                && image.samples() == samples
+        }) {
+            return msaa_image.clone();
+        }
+        let image = Image::new(
+            context.memory_allocator().clone(),
+            ImageCreateInfo {
+                format: scene.format(),
+                extent: scene.extent(),
+                samples,
+                usage: ImageUsage::COLOR_ATTACHMENT
+                    | ImageUsage::TRANSFER_SRC
+                    | ImageUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )
+        .unwrap();
+        log::info!(
+            "Created msaa image, extent={:?}, samples={samples:?}",
+            scene.extent()
+        );
+        let msaa_image = ImageView::new_default(image).unwrap();
+        self.msaa_image = Some(msaa_image.clone());
+        msaa_image
+    }
 
-        let mut world_data = WorldData::new();
-        world_data.add_component::<Transform2D>(&self.world);
-        world_data.add_component::<RigidBody2D>(&self.world);
-        world_data.add_component::<Collider2D>(&self.world);
-        log::info!("world_data={:?}", world_data);
+    // 1.0 without Physics2DPlugin, there is nothing to interpolate then
+    fn interpolation_alpha(&self) -> f32 {
+        self.world
+            .borrow::<UniqueView<Physics2DManager>>()
+            .map_or(1.0, |physics2d_manager| {
+                physics2d_manager.interpolation_alpha()
+            })
     }
+}
 
-    fn draw(&mut self, info: DrawInfo) -> Box<dyn GpuFuture> {
-        self.world.run(
-            |transform2d: View<Transform2D>, renderer2d: View<Renderer2D>| {
-                let render_pass = vulkano::single_pass_renderpass!(
-                    info.context.device().clone(),
-                    attachments: {
-                        color: {
-                            format: info.renderer.swapchain_format(), // set the format the same as the swapchain
-                            samples: 1,
-                            load_op: Clear,
-                            store_op: Store,
-                        },
-                    },
-                    pass: {
-                        color: [color],
-                        depth_stencil: {},
-                    },
-                )
-                .unwrap();
+impl Engine for EngineImpl {
+    fn init(&mut self) {
+        log::info!("Engine::init");
 
-                let framebuffer = Framebuffer::new(
-                    render_pass.clone(),
-                    FramebufferCreateInfo {
-                        attachments: vec![info.image],
-                        ..Default::default()
-                    },
-                )
-                .unwrap();
+        self.schedule.run(&self.world, Stage::Startup);
+        // so that the first frame has something to draw
+        self.schedule.run(&self.world, Stage::RenderExtract);
+    }
 
-                let vs = vs::load(info.context.device().clone())
-                    .unwrap()
-                    .entry_point("main")
-                    .unwrap();
-                let fs = fs::load(info.context.device().clone())
-                    .unwrap()
-                    .entry_point("main")
-                    .unwrap();
-                let vertex_input_state = MyVertex::per_vertex().definition(&vs).unwrap();
-                let stages = [
-                    PipelineShaderStageCreateInfo::new(vs),
-                    PipelineShaderStageCreateInfo::new(fs),
-                ];
-                let layout = PipelineLayout::new(
-                    info.context.device().clone(),
-                    PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
-                        .into_pipeline_layout_create_info(info.context.device().clone())
-                        .unwrap(),
-                )
-                .unwrap();
-                let subpass = Subpass::from(render_pass, 0).unwrap();
-
-                let viewport = Viewport {
-                    offset: [0.0, 0.0],
-                    extent: info.window_size.into(),
-                    depth_range: 0.0..=1.0,
-                };
-
-                let pipeline = GraphicsPipeline::new(
-                    info.context.device().clone(),
-                    None,
-                    GraphicsPipelineCreateInfo {
-                        stages: stages.into_iter().collect(),
-                        vertex_input_state: Some(vertex_input_state),
-                        input_assembly_state: Some(InputAssemblyState::default()),
-                        rasterization_state: Some(RasterizationState::default()),
-                        multisample_state: Some(MultisampleState::default()),
-                        color_blend_state: Some(ColorBlendState::with_attachment_states(
-                            subpass.num_color_attachments(),
-                            ColorBlendAttachmentState::default(),
-                        )),
-                        viewport_state: Some(ViewportState {
-                            viewports: [viewport].into_iter().collect(),
-                            scissors: [Scissor::default()].into_iter().collect(),
-                            ..Default::default()
-                        }),
-                        subpass: Some(subpass.into()),
-                        ..GraphicsPipelineCreateInfo::layout(layout)
-                    },
-                )
-                .unwrap();
+    fn update_and_draw(&mut self, info: DrawInfo) -> Box<dyn GpuFuture> {
+        log::trace!("Engine::update_and_draw");
+
+        let mut render_list =
+            std::mem::take(&mut *self.world.borrow::<UniqueViewMut<RenderList>>().unwrap());
+        {
+            let mut input = self.world.borrow::<UniqueViewMut<Input>>().unwrap();
+            input.cursor_world_position = input.cursor_position.and_then(|position| {
+                render_list
+                    .cameras
+                    .iter()
+                    .rev()
+                    .find(|camera| camera.viewport_contains(position, info.window_size))
+                    .and_then(|camera| camera.screen_to_world(position, info.window_size))
+            });
+        }
 
+        let scene_image = info.image;
+        let image = self.msaa_image(info.context, &scene_image);
+        let format = info.renderer.swapchain_format();
+        let render_pass = self.scene_render_pass(info.context, format, image.image().samples());
+        let mut render_info = RenderInfo {
+            device: info.context.device().clone(),
+            memory_allocator: info.context.memory_allocator().clone(),
+            queue: info.renderer.graphics_queue(),
+            format,
+            samples: image.image().samples(),
+            subpass: Subpass::from(render_pass, 0).unwrap(),
+            image,
+            window_size: info.viewport,
+            scale_factor: info.scale_factor,
+            last_camera: false,
+            textures: HashMap::new(),
+            shaders: self.shaders.clone(),
+        };
+        let queue = render_info.queue.clone();
+        let (render_passes, textures) = (&mut self.render_passes, &mut self.textures);
+        let (world, schedule) = (&self.world, &self.schedule);
+        let (command_buffer, ()) = rayon::join(
+            move || {
                 let command_buffer_allocator = Arc::new(StandardCommandBufferAllocator::new(
-                    info.context.device().clone(),
+                    render_info.device.clone(),
                     Default::default(),
                 ));
 
                 let mut command_buffer_builder = AutoCommandBufferBuilder::primary(
                     command_buffer_allocator.clone(),
-                    info.renderer.graphics_queue().queue_family_index(),
+                    render_info.queue.queue_family_index(),
                     CommandBufferUsage::MultipleSubmit,
                 )
                 .unwrap();
 
                 command_buffer_builder
-                    .begin_render_pass(
-                        RenderPassBeginInfo {
-                            clear_values: vec![Some([0.0, 0.0, 1.0, 1.0].into())],
-                            ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
-                        },
-                        SubpassBeginInfo {
-                            contents: SubpassContents::Inline,
-                            ..Default::default()
-                        },
-                    )
-                    .unwrap()
-                    .bind_pipeline_graphics(pipeline.clone())
+                    .clear_color_image(ClearColorImageInfo {
+                        clear_value: CLEAR_COLOR.into(),
+                        ..ClearColorImageInfo::image(render_info.image.image().clone())
+                    })
                     .unwrap();
 
-                let camera_pos = Vec3::ZERO;
-                let view = Mat4::look_at_lh(camera_pos, camera_pos + Vec3::NEG_Z, Vec3::Y);
-                let half_height = 10.0;
-                let half_width = half_height * info.window_size.x / info.window_size.y as f32;
-                let projection = Mat4::orthographic_lh(
-                    half_width,
-                    -half_width,
-                    half_height,
-                    -half_height,
-                    -1000.0,
-                    1000.0,
-                );
-
-                for (transform2d, _renderer2d) in (&transform2d, &renderer2d).iter() {
-                    let model = Mat4::from_scale_rotation_translation(
-                        Vec3 {
-                            x: transform2d.scale.x,
-                            y: transform2d.scale.y,
-                            z: 1.0,
-                        },
-                        Quat::from_axis_angle(Vec3::Z, transform2d.rotation),
-                        transform2d.position,
-                    );
-
-                    let push_constants = vs::PushConstants {
-                        projection_view: (projection * view).to_cols_array_2d(),
-                        model: model.to_cols_array_2d(),
-                    };
+                // the textures drawn for the first time are uploaded together before all passes,
+                // the ones which are not drawn any more are released
+                let used = render_list
+                    .textures()
+                    .map(|texture| texture.id)
+                    .collect::<HashSet<_>>();
+                textures.retain(|id, _| used.contains(id));
+                for texture in render_list.textures() {
+                    if let Entry::Vacant(entry) = textures.entry(texture.id) {
+                        entry.insert(texture.upload(&render_info, &mut command_buffer_builder));
+                    }
+                }
+                render_info.textures = textures.clone();
 
-                    let vertex1 = MyVertex {
-                        position: [-0.5, -0.5],
-                    };
-                    let vertex2 = MyVertex {
-                        position: [-0.5, 0.5],
-                    };
-                    let vertex3 = MyVertex {
-                        position: [0.5, 0.5],
-                    };
-                    let vertex4 = MyVertex {
-                        position: [0.5, -0.5],
-                    };
-                    let vertex_buffer = Buffer::from_iter(
-                        info.context.memory_allocator().clone(),
-                        BufferCreateInfo {
-                            usage: BufferUsage::VERTEX_BUFFER,
-                            ..Default::default()
-                        },
-                        AllocationCreateInfo {
-                            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
-                                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-                            ..Default::default()
-                        },
-                        vec![vertex1, vertex2, vertex3, vertex4].into_iter(),
-                    )
-                    .unwrap();
+                for render_pass in render_passes.iter_mut() {
+                    render_pass.prepare(&render_list, &render_info, &mut command_buffer_builder);
+                }
 
-                    let index_buffer = Buffer::from_iter(
-                        info.context.memory_allocator().clone(),
-                        BufferCreateInfo {
-                            usage: BufferUsage::INDEX_BUFFER,
-                            ..Default::default()
-                        },
-                        AllocationCreateInfo {
-                            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
-                                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-                            ..Default::default()
-                        },
-                        vec![0u16, 1, 2, 2, 3, 0].into_iter(),
-                    )
+                // a camera on top covers everything the cameras below it drew in its viewport
+                begin_scene(&render_info, &mut command_buffer_builder);
+                let cameras = std::mem::take(&mut render_list.cameras);
+                for (i, camera) in cameras.iter().enumerate() {
+                    clear_viewport(camera, &render_info, &mut command_buffer_builder);
+                    render_list.cameras = vec![camera.clone()];
+                    render_info.last_camera = i + 1 == cameras.len();
+                    for render_pass in render_passes.iter_mut() {
+                        render_pass.draw(&render_list, &render_info, &mut command_buffer_builder);
+                    }
+                }
+                command_buffer_builder
+                    .end_render_pass(Default::default())
                     .unwrap();
 
+                if render_info.samples != SampleCount::Sample1 {
                     command_buffer_builder
-                        .push_constants(pipeline.layout().clone(), 0, push_constants)
-                        .unwrap()
-                        .bind_vertex_buffers(0, vertex_buffer.clone())
-                        .unwrap()
-                        .bind_index_buffer(index_buffer.clone())
+                        .resolve_image(ResolveImageInfo::images(
+                            render_info.image.image().clone(),
+                            scene_image.image().clone(),
+                        ))
                         .unwrap();
+                }
 
-                    unsafe {
-                        command_buffer_builder.draw_indexed(index_buffer.len() as u32, 1, 0, 0, 0)
-                    }
-                    .unwrap();
+                command_buffer_builder.build().unwrap()
+            },
+            || {
+                for stage in Stage::UPDATE {
+                    schedule.run(world, stage);
                 }
+            },
+        );
 
-                command_buffer_builder
-                    .end_render_pass(Default::default())
-                    .unwrap();
-                let command_buffer = command_buffer_builder.build().unwrap();
-                command_buffer
-                    .execute_after(info.before_future, info.renderer.graphics_queue())
-                    .unwrap()
-                    .boxed()
+        command_buffer
+            .execute_after(info.before_future, queue)
+            .unwrap()
+            .boxed()
+    }
+
+    fn pick(&mut self, position: Vec2, window_size: Vec2, editor_camera: bool) -> Option<EntityId> {
+        let world_position = self.screen_to_world(position, window_size, editor_camera)?;
+        // quads are drawn by blend mode and then in this order, so the last drawn one is on top,
+        // without Renderer2DPlugin there are no blend modes and only the order is left
+        let material_manager = self.world.borrow::<UniqueView<MaterialManager>>().ok();
+        let drawn = self.world.run(
+            |transform2d: View<Transform2D>, renderer2d: View<Renderer2D>| {
+                (&transform2d, &renderer2d)
+                    .iter()
+                    .with_id()
+                    .enumerate()
+                    .map(|(i, (e, (transform2d, renderer2d)))| {
+                        let local = transform2d.to_local(world_position);
+                        let under = local.x.abs() <= 0.5 && local.y.abs() <= 0.5;
+                        let blend = material_manager
+                            .as_ref()
+                            .map(|m| m.blend(&renderer2d.material));
+                        (e, (blend, i), under)
+                    })
+                    .collect::<Vec<_>>()
             },
-        )
+        );
+        let draw_order = drawn
+            .iter()
+            .map(|(e, order, _)| (*e, *order))
+            .collect::<HashMap<_, _>>();
+        let hits = self
+            .world
+            .borrow::<UniqueView<Physics2DManager>>()
+            .map(|physics2d_manager| {
+                physics2d_manager.intersections_with_point(world_position, QueryFilter2D::default())
+            })
+            .unwrap_or_default();
+        // the collider of the entity drawn on top, colliders of entities which are not drawn with
+        // a quad, e.g. tilemaps, are below them and ordered by entity, not by rapier
+        let hit = hits
+            .into_iter()
+            .max_by_key(|e| (draw_order.get(e).copied(), e.inner()));
+        // entities without collider are picked by the quad they are drawn with
+        hit.or_else(|| {
+            drawn
+                .into_iter()
+                .filter(|(_, _, under)| *under)
+                .max_by_key(|(_, order, _)| *order)
+                .map(|(e, _, _)| e)
+        })
+    }
+
+    fn screen_to_world(
+        &self,
+        position: Vec2,
+        window_size: Vec2,
+        editor_camera: bool,
+    ) -> Option<Vec2> {
+        // cameras drawn later are on top, so they get the position first
+        self.cameras(editor_camera)
+            .iter()
+            .rev()
+            .find(|camera| camera.viewport_contains(position, window_size))
+            .and_then(|camera| camera.screen_to_world(position, window_size))
+    }
+
+    fn world_to_screen(
+        &self,
+        position: Vec2,
+        anchor: Vec2,
+        window_size: Vec2,
+        editor_camera: bool,
+    ) -> Option<Vec2> {
+        self.cameras(editor_camera)
+            .iter()
+            .rev()
+            .find(|camera| {
+                camera
+                    .world_to_screen(anchor, window_size)
+                    .is_some_and(|anchor| camera.viewport_contains(anchor, window_size))
+            })
+            .and_then(|camera| camera.world_to_screen(position, window_size))
+    }
+
+    fn component_data(&self, entity: EntityId, component: &str) -> Option<ComponentData> {
+        self.world.run(|all_storages: AllStoragesView| {
+            component_data_by_name(&all_storages, entity, component)
+        })
+    }
+
+    fn set_component_data(&mut self, entity: EntityId, data: ComponentData) {
+        self.world.run(|all_storages: AllStoragesView| {
+            // edits from the editor stay on prefab instances when their prefab changes
+            record_override(&all_storages, entity, &data);
+            set_component_data_by_name(&all_storages, entity, data)
+        })
+    }
+
+    fn prefabs(&self) -> Vec<String> {
+        self.world
+            .borrow::<UniqueView<PrefabManager>>()
+            .unwrap()
+            .paths()
+    }
+
+    fn spawn_prefab(&mut self, path: &str, position: Vec2) -> Option<EntityId> {
+        self.world.run(|all_storages: AllStoragesView| {
+            spawn_prefab(&all_storages, path, vec![placement(position)])
+        })
+    }
+
+    fn apply_to_prefab(&mut self, entity: EntityId) {
+        self.world
+            .run(|all_storages: AllStoragesView| apply_to_prefab(&all_storages, entity))
+    }
+
+    fn input(&mut self, event: &WindowEvent, scene_offset: Vec2, scale_factor: f32) {
+        self.world
+            .borrow::<UniqueViewMut<Input>>()
+            .unwrap()
+            .handle_event(event, scene_offset, scale_factor);
+    }
+
+    fn dump_world_data(&mut self) {
+        self.world
+            .borrow::<UniqueViewMut<WorldDataDump>>()
+            .unwrap()
+            .requested = true;
+    }
+
+    fn set_editor_camera(&mut self, enabled: bool) {
+        self.world
+            .borrow::<UniqueViewMut<EditorCamera2D>>()
+            .unwrap()
+            .enabled = enabled;
+    }
+
+    fn set_physics_debug_view(&mut self, enabled: bool) {
+        if let Ok(mut debug_draw) = self.world.borrow::<UniqueViewMut<DebugDraw>>() {
+            debug_draw.physics = enabled;
+        }
+    }
+
+    fn set_msaa_samples(&mut self, samples: u32) {
+        self.msaa_samples = samples;
+    }
+
+    fn set_tile(&mut self, entity: EntityId, position: Vec2, tile: i32) -> bool {
+        self.world.run(|all_storages: AllStoragesView| {
+            let data = all_storages.run(
+                |mut tilemap: ViewMut<Tilemap>, transform2d: View<Transform2D>| {
+                    let (Ok(mut tilemap), Ok(transform2d)) =
+                        ((&mut tilemap).get(entity), transform2d.get(entity))
+                    else {
+                        return None;
+                    };
+                    let (x, y) = tilemap.tile_coords(transform2d.position.truncate(), position)?;
+                    if tilemap.tile(x, y) != tile {
+                        tilemap.set_tile(x, y, tile);
+                    }
+                    let mut data = tilemap.to_data();
+                    data.variants.retain(|v| v.name == "tiles");
+                    Some(data)
+                },
+            );
+            match data {
+                Some(data) => {
+                    // painted tiles stay on prefab instances like the other edits
+                    record_override(&all_storages, entity, &data);
+                    true
+                }
+                None => false,
+            }
+        })
+    }
+
+    fn toolbar_ui(&mut self, ui: &mut egui::Ui, scene_size: Vec2) {
+        // scene uis get the engine, so take them out while they run
+        let mut scene_uis = std::mem::take(&mut self.scene_uis);
+        for scene_ui in &mut scene_uis {
+            scene_ui.toolbar(ui, self, scene_size);
+        }
+        self.scene_uis = scene_uis;
+    }
+
+    fn scene_ui(&mut self, ui: &mut egui::Ui, response: &egui::Response, scene_size: Vec2) {
+        let mut scene_uis = std::mem::take(&mut self.scene_uis);
+        for scene_ui in &mut scene_uis {
+            scene_ui.scene(ui, response, self, scene_size);
+        }
+        self.scene_uis = scene_uis;
+
+        // the passes keep drawing with the last good shaders meanwhile
+        let errors = self.shaders.errors();
+        if !errors.is_empty() {
+            egui::Window::new("Shader errors").show(ui.ctx(), |ui| {
+                for error in errors {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+            });
+        }
+    }
+
+    fn pan_editor_camera(&mut self, delta: Vec2, window_size: Vec2) {
+        let mut editor_camera2d = self
+            .world
+            .borrow::<UniqueViewMut<EditorCamera2D>>()
+            .unwrap();
+        let camera = &mut editor_camera2d.camera;
+        let (Some(moved), Some(origin)) = (
+            camera.screen_to_world(delta, window_size),
+            camera.screen_to_world(Vec2::ZERO, window_size),
+        ) else {
+            return;
+        };
+        camera.position -= moved - origin;
+    }
+
+    fn zoom_editor_camera(&mut self, factor: f32, position: Vec2, window_size: Vec2) {
+        let mut editor_camera2d = self
+            .world
+            .borrow::<UniqueViewMut<EditorCamera2D>>()
+            .unwrap();
+        let camera = &mut editor_camera2d.camera;
+        let Some(before) = camera.screen_to_world(position, window_size) else {
+            return;
+        };
+        camera.zoom = (camera.zoom * factor).clamp(0.01, 100.0);
+        if let Some(after) = camera.screen_to_world(position, window_size) {
+            camera.position += before - after;
+        }
     }
 }
 
-#[derive(Component, Debug)]
-struct Renderer2D; // can only render cuboid currently. TODO: render multiple shape
+// time, input, cameras, scripts and prefabs, always added by EngineBuilder::new
+struct CorePlugin;
+
+impl Plugin for CorePlugin {
+    fn build(&self, builder: &mut EngineBuilder) {
+        builder
+            .add_unique(Time::new())
+            .add_unique(Input::default())
+            .add_unique(Gamepads::new())
+            .add_unique(ScriptManager::new())
+            .add_unique(PrefabManager::new())
+            .add_unique(EditorCamera2D {
+                camera: Camera2D::default(),
+                enabled: false,
+            })
+            .add_unique(WorldDataDump::default())
+            .add_unique(RenderList::default())
+            .add_unique(AssetServer::default())
+            .add_component(|| Transform2D {
+                scale: Vec2::ONE,
+                ..Default::default()
+            })
+            .add_component(Camera2D::default)
+            .add_component(|| Script::new(""))
+            .add_component(PrefabInstance::default)
+            .add_system(Stage::PreUpdate, time_update_system)
+            .add_system(Stage::PreUpdate, asset_server_system)
+            .add_system(Stage::PreUpdate, gamepad_system)
+            .add_system(
+                Stage::PreUpdate,
+                prefab_reload_system
+                    .after_all(time_update_system)
+                    .after_all(asset_server_system),
+            )
+            .add_system(
+                Stage::PreUpdate,
+                script_update_system
+                    .after_all(prefab_reload_system)
+                    .after_all(gamepad_system),
+            )
+            .add_system(
+                Stage::PostPhysics,
+                script_collision_system.skip_if_missing_unique::<Physics2DManager>(),
+            )
+            .add_system(Stage::PostPhysics, camera2d_follow_system)
+            // the last one, scripts read input until their collision callbacks
+            .add_system(
+                Stage::PostPhysics,
+                input_clear_system.after_all(script_collision_system),
+            )
+            .add_system(Stage::RenderExtract, world_data_dump_system)
+            .add_system(Stage::RenderExtract, camera2d_extract_system);
+    }
+}
+
+#[derive(Component, Debug, Clone)]
+#[track(All)]
+pub struct Camera2D {
+    position: Vec2,
+    rotation: f32,
+    zoom: f32,                // larger zoom shows a smaller part of the world
+    height: f32,              // how many world units are visible vertically when zoom is 1
+    follow: Option<EntityId>, // keep the camera centered on this entity
+    viewport: Vec4, // x, y, width and height of the drawn area, relative to the scene size
+    order: i32,     // cameras with a larger order are drawn later, on top of the others
+}
 
-#[derive(BufferContents, Vertex)]
-#[repr(C)]
-struct MyVertex {
-    #[format(R32G32_SFLOAT)]
-    position: [f32; 2],
+impl Default for Camera2D {
+    fn default() -> Self {
+        Camera2D {
+            position: Vec2::ZERO,
+            rotation: 0.0,
+            zoom: 1.0,
+            height: 20.0,
+            follow: None,
+            viewport: Vec4::new(0.0, 0.0, 1.0, 1.0),
+            order: 0,
+        }
+    }
 }
 
-mod vs {
-    vulkano_shaders::shader! {
-        ty: "vertex",
-        src: r"
-            #version 460
+impl Camera2D {
+    // returns offset and extent of the viewport in pixels
+    fn viewport_rect(&self, window_size: Vec2) -> (Vec2, Vec2) {
+        (
+            self.viewport.truncate().truncate() * window_size,
+            Vec2::new(self.viewport.z, self.viewport.w) * window_size,
+        )
+    }
+
+    fn viewport_contains(&self, position: Vec2, window_size: Vec2) -> bool {
+        let (offset, extent) = self.viewport_rect(window_size);
+        let position = position - offset;
+        position.x >= 0.0 && position.y >= 0.0 && position.x < extent.x && position.y < extent.y
+    }
+
+    fn projection_view(&self, viewport_size: Vec2) -> Mat4 {
+        let camera_pos = self.position.extend(0.0);
+        let up = Vec3::new(-self.rotation.sin(), self.rotation.cos(), 0.0);
+        let view = Mat4::look_at_lh(camera_pos, camera_pos + Vec3::NEG_Z, up);
+        let half_height = self.height / 2.0 / self.zoom;
+        let half_width = half_height * viewport_size.x / viewport_size.y;
+        let projection = Mat4::orthographic_lh(
+            half_width,
+            -half_width,
+            half_height,
+            -half_height,
+            -1000.0,
+            1000.0,
+        );
+        projection * view
+    }
+
+    // world bounds of what the camera sees in a viewport of this size, larger than the view
+    // when the camera is rotated
+    fn view_aabb(&self, viewport_size: Vec2) -> (Vec2, Vec2) {
+        let half_height = self.height / 2.0 / self.zoom;
+        let half_width = half_height * viewport_size.x / viewport_size.y;
+        let (sin, cos) = (self.rotation.sin().abs(), self.rotation.cos().abs());
+        let half_extents = Vec2::new(
+            half_width * cos + half_height * sin,
+            half_width * sin + half_height * cos,
+        );
+        (self.position - half_extents, self.position + half_extents)
+    }
+
+    // None if the viewport is empty
+    fn world_to_screen(&self, position: Vec2, window_size: Vec2) -> Option<Vec2> {
+        let (offset, extent) = self.viewport_rect(window_size);
+        if extent.x < 1.0 || extent.y < 1.0 {
+            return None;
+        }
+        let ndc = self
+            .projection_view(extent)
+            .project_point3(position.extend(0.0))
+            .truncate();
+        Some(offset + (ndc + Vec2::ONE) / 2.0 * extent)
+    }
 
-            layout(push_constant) uniform PushConstants {
-                mat4 projection_view;
-                mat4 model;
-            } pcs;
+    // position is in pixels relative to the top left of the scene, None if the viewport is empty
+    fn screen_to_world(&self, position: Vec2, window_size: Vec2) -> Option<Vec2> {
+        let (offset, extent) = self.viewport_rect(window_size);
+        if extent.x < 1.0 || extent.y < 1.0 {
+            return None;
+        }
+        let ndc = (position - offset) / extent * 2.0 - Vec2::ONE;
+        Some(
+            self.projection_view(extent)
+                .inverse()
+                .project_point3(Vec3::new(ndc.x, ndc.y, 0.0))
+                .truncate(),
+        )
+    }
+}
 
-            layout(location = 0) in vec2 position;
+impl Edit for Camera2D {
+    fn name() -> &'static str {
+        "Camera2D"
+    }
 
-            void main() {
-                gl_Position = pcs.projection_view * pcs.model * vec4(position, 0.0, 1.0);
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "position",
+            value: Value::Vec2(self.position),
+        });
+        data.variants.push(Variant {
+            name: "rotation",
+            value: Value::Float32(self.rotation),
+        });
+        data.variants.push(Variant {
+            name: "zoom",
+            value: Value::Float32(self.zoom),
+        });
+        data.variants.push(Variant {
+            name: "height",
+            value: Value::Float32(self.height),
+        });
+        data.variants.push(Variant {
+            name: "follow_enabled",
+            value: Value::Bool(self.follow.is_some()),
+        });
+        data.variants.push(Variant {
+            name: "follow",
+            value: Value::Entity(self.follow.unwrap_or_else(EntityId::dead)),
+        });
+        data.variants.push(Variant {
+            name: "viewport",
+            value: Value::Vec4(self.viewport),
+        });
+        data.variants.push(Variant {
+            name: "order",
+            value: Value::Int32(self.order),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            match (v.name, v.value) {
+                ("position", Value::Vec2(v)) => self.position = v,
+                ("rotation", Value::Float32(v)) => self.rotation = v,
+                ("zoom", Value::Float32(v)) => self.zoom = v,
+                ("height", Value::Float32(v)) => self.height = v,
+                ("follow_enabled", Value::Bool(enabled)) => {
+                    self.follow = match (enabled, self.follow) {
+                        (true, follow) => Some(follow.unwrap_or_else(EntityId::dead)),
+                        (false, _) => None,
+                    }
+                }
+                ("follow", Value::Entity(e)) if self.follow.is_some() => self.follow = Some(e),
+                ("viewport", Value::Vec4(v)) => self.viewport = v,
+                ("order", Value::Int32(v)) => self.order = v,
+                _ => (),
             }
-        ",
+        }
     }
 }
 
-mod fs {
-    vulkano_shaders::shader! {
-        ty: "fragment",
-        src: r"
-            #version 460
+// the camera of the scene window in editor mode, it is not part of the scene
+#[derive(Unique)]
+struct EditorCamera2D {
+    camera: Camera2D,
+    enabled: bool, // used instead of the Camera2D components
+}
+
+// the cameras to draw with, sorted by order, follow targets are resolved with the interpolated transform
+fn active_cameras(
+    editor_camera: bool,
+    camera2d: &View<Camera2D>,
+    editor_camera2d: &EditorCamera2D,
+    transform2d: &View<Transform2D>,
+    interpolation2d: &View<Interpolation2D>,
+    alpha: f32,
+) -> Vec<Camera2D> {
+    if editor_camera {
+        return vec![editor_camera2d.camera.clone()];
+    }
+    let mut cameras = camera2d.iter().cloned().collect::<Vec<_>>();
+    if cameras.is_empty() {
+        cameras.push(Camera2D::default());
+    }
+    for camera in &mut cameras {
+        if let Some(Ok(transform2d)) = camera.follow.map(|e| transform2d.get(e)) {
+            let position = match camera.follow.map(|e| interpolation2d.get(e)) {
+                Some(Ok(interpolation2d)) => interpolation2d.blend(transform2d, alpha).0,
+                _ => transform2d.position,
+            };
+            camera.position = position.truncate();
+        }
+    }
+    cameras.sort_by_key(|camera| camera.order);
+    cameras
+}
 
-            layout(location = 0) out vec4 f_color;
+fn camera2d_follow_system(mut camera2d: ViewMut<Camera2D>, transform2d: View<Transform2D>) {
+    for mut camera2d in (&mut camera2d).iter() {
+        if let Some(Ok(transform2d)) = camera2d.follow.map(|e| transform2d.get(e)) {
+            camera2d.position = transform2d.position.truncate();
+        }
+    }
+}
 
-            void main() {
-                f_color = vec4(1.0, 0.0, 0.0, 1.0);
+#[derive(Component, Debug)]
+#[track(All)]
+struct Renderer2D {
+    color: Vec4,      // multiplied with the color of the material
+    material: String, // path of a Material2D json, the default white material if empty
+} // can only render cuboid currently. TODO: render multiple shape
+
+impl Default for Renderer2D {
+    fn default() -> Self {
+        Renderer2D {
+            color: Vec4::ONE,
+            material: String::new(),
+        }
+    }
+}
+
+impl Edit for Renderer2D {
+    fn name() -> &'static str {
+        "Renderer2D"
+    }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "color",
+            value: Value::Vec4(self.color),
+        });
+        data.variants.push(Variant {
+            name: "material",
+            value: Value::String(self.material.clone()),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            match (v.name, v.value) {
+                ("color", Value::Vec4(v)) => self.color = v,
+                ("material", Value::String(v)) => self.material = v,
+                _ => (),
             }
-        ",
+        }
+    }
+}
+
+// Renderer2D components and the pass which draws them
+pub struct Renderer2DPlugin;
+
+impl Plugin for Renderer2DPlugin {
+    fn build(&self, builder: &mut EngineBuilder) {
+        builder
+            .add_unique(MaterialManager::new())
+            .add_component(Renderer2D::default)
+            .add_system(
+                Stage::RenderExtract,
+                renderer2d_extract_system.after_all(camera2d_extract_system),
+            )
+            .add_render_pass(Renderer2DPass::default());
+    }
+}
+
+// rapier simulation of RigidBody2D, Collider2D and Joint2D
+pub struct Physics2DPlugin;
+
+impl Plugin for Physics2DPlugin {
+    fn build(&self, builder: &mut EngineBuilder) {
+        let mut physics2d_manager = Physics2DManager::new();
+        physics2d_manager.set_step_rate(60.0);
+        physics2d_manager.set_max_substeps(8);
+        builder
+            .add_unique(physics2d_manager)
+            .add_component(|| RigidBody2D::new(RigidBodyType::Dynamic))
+            .add_component(|| Collider2D::new(SharedShape::cuboid(0.5, 0.5), 0.0))
+            .add_component(|| {
+                Joint2D::new(JointType2D::Revolute, EntityId::dead(), EntityId::dead())
+            })
+            .add_system(Stage::Physics, physics2d_maintain_system)
+            .add_system(
+                Stage::Physics,
+                physics2d_update_system.after_all(physics2d_maintain_system),
+            );
     }
 }
 
@@ -365,14 +975,22 @@ struct Physics2DManager {
     broad_phase: BroadPhase,
     narrow_phase: NarrowPhase,
     impulse_joint_set: ImpulseJointSet,
+    joints: HashMap<EntityId, ImpulseJointHandle>, // by the entity of the Joint2D, for removal
     multibody_joint_set: MultibodyJointSet,
     ccd_solver: CCDSolver,
+    query_pipeline: QueryPipeline,
     physics_hooks: Box<dyn PhysicsHooks>,
     event_handler: Box<dyn EventHandler>,
+    collision_event_receiver: Receiver<CollisionEvent>,
+    collision_events: Vec<CollisionEvent2D>, // collisions started or stopped during this frame
+    accumulator: f32,  // unsimulated time carried over to the next frame, in seconds
+    max_substeps: u32, // upper bound of fixed steps per frame, extra time is dropped
 }
 
 impl Physics2DManager {
     fn new() -> Self {
+        let (collision_event_sender, collision_event_receiver) = unbounded();
+        let (contact_force_event_sender, _) = unbounded();
         Physics2DManager {
             rigid_body_set: RigidBodySet::new(),
             collider_set: ColliderSet::new(),
@@ -383,14 +1001,51 @@ impl Physics2DManager {
             broad_phase: BroadPhase::new(),
             narrow_phase: NarrowPhase::new(),
             impulse_joint_set: ImpulseJointSet::new(),
+            joints: HashMap::new(),
             multibody_joint_set: MultibodyJointSet::new(),
             ccd_solver: CCDSolver::new(),
+            query_pipeline: QueryPipeline::new(),
             physics_hooks: Box::new(()),
-            event_handler: Box::new(()),
+            event_handler: Box::new(ChannelEventCollector::new(
+                collision_event_sender,
+                contact_force_event_sender,
+            )),
+            collision_event_receiver,
+            collision_events: Vec::new(),
+            accumulator: 0.0,
+            max_substeps: 8,
         }
     }
 
-    fn update(&mut self) {
+    fn set_step_rate(&mut self, steps_per_second: f32) {
+        self.integration_parameters.dt = 1.0 / steps_per_second;
+    }
+
+    fn set_max_substeps(&mut self, max_substeps: u32) {
+        self.max_substeps = max_substeps;
+    }
+
+    // Adds delta time to the accumulator and returns how many fixed steps should run this frame.
+    fn advance(&mut self, delta: f32) -> u32 {
+        let dt = self.integration_parameters.dt;
+        self.accumulator += delta;
+        let steps = (self.accumulator / dt) as u32;
+        if steps > self.max_substeps {
+            // we can not catch up, drop the backlog instead of spiraling into more substeps
+            self.accumulator = self.accumulator.rem_euclid(dt);
+            self.max_substeps
+        } else {
+            self.accumulator -= steps as f32 * dt;
+            steps
+        }
+    }
+
+    // How far we are between the previous and the current physics state, in range [0, 1).
+    fn interpolation_alpha(&self) -> f32 {
+        (self.accumulator / self.integration_parameters.dt).clamp(0.0, 1.0)
+    }
+
+    fn step(&mut self) {
         self.physics_pipeline.step(
             &self.gravity,
             &self.integration_parameters,
@@ -402,34 +1057,375 @@ impl Physics2DManager {
             &mut self.impulse_joint_set,
             &mut self.multibody_joint_set,
             &mut self.ccd_solver,
-            None,
+            Some(&mut self.query_pipeline),
             self.physics_hooks.as_ref(),
             self.event_handler.as_ref(),
         );
     }
+
+    // move the collision events reported by rapier into collision_events
+    fn collect_collision_events(&mut self) {
+        while let Ok(event) = self.collision_event_receiver.try_recv() {
+            let (Some(entity1), Some(entity2)) = (
+                self.entity_of(event.collider1()),
+                self.entity_of(event.collider2()),
+            ) else {
+                continue; // one of the colliders has been removed
+            };
+            self.collision_events.push(CollisionEvent2D {
+                entity1,
+                entity2,
+                started: event.started(),
+            });
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CollisionEvent2D {
+    entity1: EntityId,
+    entity2: EntityId,
+    started: bool, // true when the colliders started touching, false when they stopped
+}
+
+// Scene queries of the editor and of scripts
+impl Physics2DManager {
+    fn entity_of(&self, handle: ColliderHandle) -> Option<EntityId> {
+        EntityId::from_inner(self.collider_set.get(handle)?.user_data as u64)
+    }
+
+    // the closest hit along the ray, direction does not need to be normalized
+    fn cast_ray(
+        &self,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        solid: bool,
+        filter: QueryFilter2D,
+    ) -> Option<RayHit2D> {
+        let direction = direction.normalize_or_zero();
+        let ray = Ray::new(
+            point![origin.x, origin.y],
+            vector![direction.x, direction.y],
+        );
+        let predicate = filter.predicate();
+        let (handle, intersection) = self.query_pipeline.cast_ray_and_get_normal(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &ray,
+            max_distance,
+            solid,
+            filter.to_rapier(&predicate),
+        )?;
+        Some(RayHit2D::new(self.entity_of(handle)?, &ray, intersection))
+    }
+
+    // all hits along the ray, in no particular order
+    fn cast_ray_all(
+        &self,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        solid: bool,
+        filter: QueryFilter2D,
+    ) -> Vec<RayHit2D> {
+        let direction = direction.normalize_or_zero();
+        let ray = Ray::new(
+            point![origin.x, origin.y],
+            vector![direction.x, direction.y],
+        );
+        let predicate = filter.predicate();
+        let mut hits = Vec::new();
+        self.query_pipeline.intersections_with_ray(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &ray,
+            max_distance,
+            solid,
+            filter.to_rapier(&predicate),
+            |handle, intersection| {
+                if let Some(entity) = self.entity_of(handle) {
+                    hits.push(RayHit2D::new(entity, &ray, intersection));
+                }
+                true
+            },
+        );
+        hits
+    }
+
+    // sweeps shape from position along velocity, returns the first hit within max_time
+    fn cast_shape(
+        &self,
+        shape: &SharedShape,
+        position: Vec2,
+        rotation: f32,
+        velocity: Vec2,
+        max_time: f32,
+        filter: QueryFilter2D,
+    ) -> Option<ShapeHit2D> {
+        let shape_pos = Isometry::new(vector![position.x, position.y], rotation);
+        let predicate = filter.predicate();
+        let (handle, toi) = self.query_pipeline.cast_shape(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &shape_pos,
+            &vector![velocity.x, velocity.y],
+            shape.as_ref(),
+            max_time,
+            true,
+            filter.to_rapier(&predicate),
+        )?;
+        // witness1 and normal1 are in the local space of the collider we hit
+        let collider_pos = self.collider_set.get(handle)?.position();
+        let point = collider_pos * toi.witness1;
+        let normal = collider_pos * toi.normal1;
+        Some(ShapeHit2D {
+            entity: self.entity_of(handle)?,
+            time: toi.toi,
+            point: Vec2::new(point.x, point.y),
+            normal: Vec2::new(normal.x, normal.y),
+        })
+    }
+
+    // the closest point on any collider, with solid the point itself is returned when it is inside
+    fn project_point(
+        &self,
+        point: Vec2,
+        solid: bool,
+        filter: QueryFilter2D,
+    ) -> Option<PointProjection2D> {
+        let predicate = filter.predicate();
+        let (handle, projection) = self.query_pipeline.project_point(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &point![point.x, point.y],
+            solid,
+            filter.to_rapier(&predicate),
+        )?;
+        Some(PointProjection2D {
+            entity: self.entity_of(handle)?,
+            point: Vec2::new(projection.point.x, projection.point.y),
+            is_inside: projection.is_inside,
+        })
+    }
+
+    // all entities whose collider contains the point
+    fn intersections_with_point(&self, point: Vec2, filter: QueryFilter2D) -> Vec<EntityId> {
+        let predicate = filter.predicate();
+        let mut entities = Vec::new();
+        self.query_pipeline.intersections_with_point(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &point![point.x, point.y],
+            filter.to_rapier(&predicate),
+            |handle| {
+                entities.extend(self.entity_of(handle));
+                true
+            },
+        );
+        entities
+    }
+
+    // all entities whose collider bounding box intersects the box from min to max
+    fn intersections_with_aabb(
+        &self,
+        min: Vec2,
+        max: Vec2,
+        filter: QueryFilter2D,
+    ) -> Vec<EntityId> {
+        let aabb = Aabb::new(point![min.x, min.y], point![max.x, max.y]);
+        let predicate = filter.predicate();
+        let filter = filter.to_rapier(&predicate);
+        let mut entities = Vec::new();
+        self.query_pipeline
+            .colliders_with_aabb_intersecting_aabb(&aabb, |handle| {
+                if let Some(collider) = self.collider_set.get(*handle) {
+                    if filter.test(&self.rigid_body_set, *handle, collider) {
+                        entities.extend(self.entity_of(*handle));
+                    }
+                }
+                true
+            });
+        entities
+    }
+}
+
+// Which colliders a scene query should look at
+#[derive(Debug, Clone, Copy)]
+struct QueryFilter2D {
+    groups: InteractionGroups, // only colliders whose collision groups interact with these
+    exclude: Option<EntityId>, // e.g. the entity that is casting
+    exclude_sensors: bool,
+}
+
+impl Default for QueryFilter2D {
+    fn default() -> Self {
+        QueryFilter2D {
+            groups: InteractionGroups::all(),
+            exclude: None,
+            exclude_sensors: false,
+        }
+    }
+}
+
+impl QueryFilter2D {
+    fn predicate(self) -> impl Fn(ColliderHandle, &Collider) -> bool {
+        let exclude = self.exclude.map(|e| e.inner() as u128);
+        move |_, collider| Some(collider.user_data) != exclude
+    }
+
+    fn to_rapier<'a>(
+        self,
+        predicate: &'a impl Fn(ColliderHandle, &Collider) -> bool,
+    ) -> QueryFilter<'a> {
+        let filter = QueryFilter::new().groups(self.groups).predicate(predicate);
+        if self.exclude_sensors {
+            filter.exclude_sensors()
+        } else {
+            filter
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RayHit2D {
+    entity: EntityId,
+    distance: f32,
+    point: Vec2,
+    normal: Vec2,
+}
+
+impl RayHit2D {
+    fn new(entity: EntityId, ray: &Ray, intersection: RayIntersection) -> Self {
+        let point = ray.point_at(intersection.toi);
+        RayHit2D {
+            entity,
+            distance: intersection.toi,
+            point: Vec2::new(point.x, point.y),
+            normal: Vec2::new(intersection.normal.x, intersection.normal.y),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ShapeHit2D {
+    entity: EntityId,
+    time: f32,    // the shape touches the hit collider at position + velocity * time
+    point: Vec2,  // contact point on the hit collider
+    normal: Vec2, // outward normal of the hit collider at point
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PointProjection2D {
+    entity: EntityId,
+    point: Vec2,
+    is_inside: bool,
 }
 
 fn physics2d_maintain_system(
     mut physics2d_manager: UniqueViewMut<Physics2DManager>,
     mut rb2d: ViewMut<RigidBody2D>,
     mut col2d: ViewMut<Collider2D>,
+    mut joint2d: ViewMut<Joint2D>,
     mut transform2d: ViewMut<Transform2D>,
+    mut interpolation2d: ViewMut<Interpolation2D>,
 ) {
     let physics2d_manager = physics2d_manager.as_mut();
+    // tracking is per system in a workload, so the values physics2d_update_system wrote back
+    // from rapier show up as modified here too, they are skipped by comparing with rapier
+    let mut changed = false;
+
+    // rapier bodies of removed RigidBody2D components and deleted entities, found by user data,
+    // their colliders stay until the Collider2D is gone too
+    let removed = rb2d.removed_or_deleted().collect::<HashSet<_>>();
+    let handles = physics2d_manager
+        .rigid_body_set
+        .iter()
+        .filter(|(_, rigid_body)| {
+            EntityId::from_inner(rigid_body.user_data as u64).is_some_and(|e| removed.contains(&e))
+        })
+        .map(|(handle, _)| handle)
+        .collect::<Vec<_>>();
+    for handle in handles {
+        physics2d_manager.rigid_body_set.remove(
+            handle,
+            &mut physics2d_manager.island_manager,
+            &mut physics2d_manager.collider_set,
+            &mut physics2d_manager.impulse_joint_set,
+            &mut physics2d_manager.multibody_joint_set,
+            false,
+        );
+        changed = true;
+    }
+    for e in removed {
+        interpolation2d.remove(e);
+    }
+    let handles = col2d
+        .deleted()
+        .map(|(_, col2d)| col2d.handle)
+        .collect::<Vec<_>>();
+    for handle in handles {
+        physics2d_manager.collider_set.remove(
+            handle,
+            &mut physics2d_manager.island_manager,
+            &mut physics2d_manager.rigid_body_set,
+            true,
+        );
+        changed = true;
+    }
+
+    // transforms written by gameplay code or the editor teleport the bodies and colliders
+    for (e, transform2d) in transform2d.modified().iter().with_id() {
+        let position = Isometry::new(
+            vector![transform2d.position.x, transform2d.position.y],
+            transform2d.rotation,
+        );
+        let synced = |current: &Isometry<Real>| {
+            current.translation.vector == position.translation.vector
+                && current.rotation.angle() == transform2d.rotation
+        };
+        if let Ok(rb2d) = rb2d.get(e) {
+            if let Some(rigid_body) = physics2d_manager.rigid_body_set.get_mut(rb2d.handle) {
+                if synced(rigid_body.position()) {
+                    continue;
+                }
+                changed = true;
+                rigid_body.set_position(position, true);
+                if let Ok(interpolation2d) = (&mut interpolation2d).get(e) {
+                    *interpolation2d = Interpolation2D::new(transform2d);
+                }
+            }
+        } else if let Ok(col2d) = col2d.get(e) {
+            if let Some(collider) = physics2d_manager.collider_set.get_mut(col2d.handle) {
+                if !synced(collider.position()) {
+                    changed = true;
+                    collider.set_position(position);
+                }
+            }
+        }
+    }
+
     for (e, mut rb2d) in rb2d.inserted_or_modified_mut().iter().with_id() {
         if let Some(rigid_body) = physics2d_manager.rigid_body_set.get_mut(rb2d.handle) {
-            rigid_body.set_body_type(rb2d.body_type, true);
+            if rb2d.synced(rigid_body) {
+                continue;
+            }
+            rb2d.apply(rigid_body);
         } else {
             if !transform2d.contains(e) {
                 transform2d.add_component_unchecked(e, Transform2D::default());
             }
             let transform2d = transform2d.get(e).unwrap();
-            let rigid_body = RigidBodyBuilder::new(rb2d.body_type)
+            let mut rigid_body = RigidBodyBuilder::new(rb2d.body_type)
                 .translation(vector![transform2d.position.x, transform2d.position.y])
                 .rotation(transform2d.rotation)
+                .user_data(e.inner() as u128)
                 .build();
+            rb2d.apply(&mut rigid_body);
             rb2d.handle = physics2d_manager.rigid_body_set.insert(rigid_body);
+            interpolation2d.add_component_unchecked(e, Interpolation2D::new(transform2d));
         }
+        changed = true;
 
         if let Ok(col2d) = col2d.get(e) {
             if physics2d_manager.collider_set.contains(col2d.handle) {
@@ -443,17 +1439,18 @@ fn physics2d_maintain_system(
     }
 
     for (e, mut col2d) in col2d.inserted_or_modified_mut().iter().with_id() {
+        changed = true;
         if let Some(collider) = physics2d_manager.collider_set.get_mut(col2d.handle) {
-            collider.set_shape(col2d.shape.clone());
-            collider.set_restitution(col2d.restitution);
+            col2d.apply(collider);
         } else {
             if !transform2d.contains(e) {
                 transform2d.add_component_unchecked(e, Transform2D::default());
             }
             let transform2d = transform2d.get(e).unwrap();
             let mut collider = ColliderBuilder::new(col2d.shape.clone())
-                .restitution(col2d.restitution)
+                .user_data(e.inner() as u128)
                 .build();
+            col2d.apply(&mut collider);
             if let Ok(rb2d) = &rb2d.get(e) {
                 // TODO: add position and rotation relative to parent
                 col2d.handle = physics2d_manager.collider_set.insert_with_parent(
@@ -462,64 +1459,334 @@ fn physics2d_maintain_system(
                     &mut physics2d_manager.rigid_body_set,
                 );
             } else {
-                collider.set_translation(vector![transform2d.position.x, transform2d.position.y]);
-                //collider.set_rotation(transform2d.rotation); TODO: how to set_rotation?
+                collider.set_position(Isometry::new(
+                    vector![transform2d.position.x, transform2d.position.y],
+                    transform2d.rotation,
+                ));
                 col2d.handle = physics2d_manager.collider_set.insert(collider);
             }
         }
     }
 
-    rb2d.clear_all_inserted_and_modified();
-    col2d.clear_all_inserted_and_modified();
+    // the handle of a removed component is gone with it, so joints are looked up by entity
+    for e in joint2d.removed_or_deleted() {
+        if let Some(handle) = physics2d_manager.joints.remove(&e) {
+            physics2d_manager.impulse_joint_set.remove(handle, true);
+        }
+    }
+
+    // joints are (re)inserted after bodies, because they need the rigid body handles of both sides
+    let pending = joint2d
+        .iter()
+        .with_id()
+        .filter(|(e, j)| {
+            joint2d.is_inserted_or_modified(*e)
+                || !physics2d_manager.impulse_joint_set.contains(j.handle)
+        })
+        .map(|(e, _)| e)
+        .collect::<Vec<_>>();
+    for e in pending {
+        let mut joint2d = (&mut joint2d).get(e).unwrap();
+        if physics2d_manager
+            .impulse_joint_set
+            .remove(joint2d.handle, true)
+            .is_some()
+        {
+            joint2d.handle = ImpulseJointHandle::invalid();
+        }
+        let (Ok(body1), Ok(body2)) = (rb2d.get(joint2d.entity1), rb2d.get(joint2d.entity2)) else {
+            continue; // wait until both entities have a rigid body
+        };
+        joint2d.handle = physics2d_manager.impulse_joint_set.insert(
+            body1.handle,
+            body2.handle,
+            joint2d.build(),
+            true,
+        );
+        physics2d_manager.joints.insert(e, joint2d.handle);
+    }
+
+    if changed {
+        // let queries see new colliders before the next physics step
+        physics2d_manager.query_pipeline.update(
+            &physics2d_manager.rigid_body_set,
+            &physics2d_manager.collider_set,
+        );
+    }
+}
+
+fn physics2d_update_system(
+    mut physics2d_manager: UniqueViewMut<Physics2DManager>,
+    time: UniqueView<Time>,
+    mut rb2d: ViewMut<RigidBody2D>,
+    mut transform2d: ViewMut<Transform2D>,
+    mut interpolation2d: ViewMut<Interpolation2D>,
+) {
+    physics2d_manager.collision_events.clear();
+    let steps = physics2d_manager.advance(time.delta);
+    if steps == 0 {
+        return;
+    }
+    for _ in 1..steps {
+        physics2d_manager.step();
+    }
+    // remember the state before the last step so that rendering can blend towards the new one
+    (&rb2d, &mut interpolation2d)
+        .par_iter()
+        .for_each(|(rb2d, interpolation2d)| {
+            let rigid_body = &physics2d_manager.rigid_body_set[rb2d.handle];
+            interpolation2d.previous_position.x = rigid_body.translation().x;
+            interpolation2d.previous_position.y = rigid_body.translation().y;
+            interpolation2d.previous_rotation = rigid_body.rotation().angle();
+        });
+    physics2d_manager.step();
+    physics2d_manager.collect_collision_events();
+    (&rb2d, &mut transform2d)
+        .par_iter()
+        .for_each(|(rb2d, mut transform2d)| {
+            let rigid_body = &physics2d_manager.rigid_body_set[rb2d.handle];
+            transform2d.position.x = rigid_body.translation().x;
+            transform2d.position.y = rigid_body.translation().y;
+            transform2d.rotation = rigid_body.rotation().angle();
+        });
+    (&mut rb2d).par_iter().for_each(|mut rb2d| {
+        let rigid_body = &physics2d_manager.rigid_body_set[rb2d.handle];
+        rb2d.linear_velocity = Vec2::new(rigid_body.linvel().x, rigid_body.linvel().y);
+        rb2d.angular_velocity = rigid_body.angvel();
+        rb2d.sleeping = rigid_body.is_sleeping();
+    });
+}
+
+#[derive(Unique)]
+struct Time {
+    delta: f32,   // seconds since last update
+    elapsed: f32, // seconds since the first update
+    last_instant: Option<Instant>,
+}
+
+impl Time {
+    fn new() -> Self {
+        Time {
+            delta: 0.0,
+            elapsed: 0.0,
+            last_instant: None,
+        }
+    }
+
+    fn update(&mut self) {
+        let now = Instant::now();
+        self.delta = match self.last_instant {
+            Some(last_instant) => (now - last_instant).as_secs_f32(),
+            None => 0.0,
+        };
+        self.elapsed += self.delta;
+        self.last_instant = Some(now);
+    }
+}
+
+fn time_update_system(mut time: UniqueViewMut<Time>) {
+    time.update();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton), // of any connected gamepad
+}
+
+// Input state of the current frame, fed by window events which egui did not consume
+#[derive(Unique, Default)]
+struct Input {
+    pressed: HashSet<InputBinding>,
+    just_pressed: HashSet<InputBinding>, // pressed since the last update
+    just_released: HashSet<InputBinding>, // released since the last update
+    cursor_position: Option<Vec2>, // pixels relative to the top left of the scene, None if outside
+    cursor_world_position: Option<Vec2>, // unprojected with the camera under the cursor in the last drawn frame
+    touches: HashMap<u64, Vec2>, // touch id -> position in pixels relative to the top left of the scene
+    just_started_touches: HashSet<u64>,
+    just_ended_touches: HashSet<u64>,
+    scroll: Vec2, // accumulated since the last update, in lines
+    gamepad_axes: HashMap<GamepadAxis, f32>, // of the gamepad which moved the axis last
+    actions: HashMap<String, Vec<InputBinding>>, // action name -> bindings which trigger it
+}
+
+impl Input {
+    // scene_offset is the top left of the scene in logical pixels of the window
+    fn handle_event(&mut self, event: &WindowEvent, scene_offset: Vec2, scale_factor: f32) {
+        let to_scene = |position: PhysicalPosition<f64>| {
+            Vec2::new(position.x as f32, position.y as f32) / scale_factor - scene_offset
+        };
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let PhysicalKey::Code(code) = event.physical_key {
+                    if !event.repeat {
+                        self.set_pressed(InputBinding::Key(code), event.state.is_pressed());
+                    }
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.set_pressed(InputBinding::Mouse(*button), state.is_pressed());
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some(to_scene(*position));
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor_position = None;
+                self.cursor_world_position = None;
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll += match delta {
+                    MouseScrollDelta::LineDelta(x, y) => Vec2::new(*x, *y),
+                    MouseScrollDelta::PixelDelta(delta) => {
+                        Vec2::new(delta.x as f32, delta.y as f32) / 20.0
+                    }
+                }
+            }
+            WindowEvent::Touch(touch) => match touch.phase {
+                TouchPhase::Started => {
+                    self.touches.insert(touch.id, to_scene(touch.location));
+                    self.just_started_touches.insert(touch.id);
+                }
+                TouchPhase::Moved => {
+                    self.touches.insert(touch.id, to_scene(touch.location));
+                }
+                TouchPhase::Ended | TouchPhase::Cancelled => {
+                    self.touches.remove(&touch.id);
+                    self.just_ended_touches.insert(touch.id);
+                }
+            },
+            WindowEvent::Focused(false) => {
+                // we will not receive the release events while unfocused
+                self.just_released.extend(self.pressed.drain());
+                self.just_ended_touches
+                    .extend(self.touches.drain().map(|(id, _)| id));
+            }
+            _ => (),
+        }
+    }
+
+    fn set_pressed(&mut self, binding: InputBinding, pressed: bool) {
+        if pressed {
+            if self.pressed.insert(binding) {
+                self.just_pressed.insert(binding);
+            }
+        } else if self.pressed.remove(&binding) {
+            self.just_released.insert(binding);
+        }
+    }
+
+    // forget the per frame state, called at the end of every update
+    fn clear(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+        self.just_started_touches.clear();
+        self.just_ended_touches.clear();
+        self.scroll = Vec2::ZERO;
+    }
+
+    fn bind_action(&mut self, action: impl Into<String>, binding: InputBinding) {
+        self.actions.entry(action.into()).or_default().push(binding);
+    }
+
+    fn action_bindings(&self, action: &str) -> impl Iterator<Item = &InputBinding> {
+        self.actions.get(action).into_iter().flatten()
+    }
+}
+
+// Input queries of scripts
+impl Input {
+    fn pressed(&self, binding: InputBinding) -> bool {
+        self.pressed.contains(&binding)
+    }
+
+    fn just_pressed(&self, binding: InputBinding) -> bool {
+        self.just_pressed.contains(&binding)
+    }
+
+    fn just_released(&self, binding: InputBinding) -> bool {
+        self.just_released.contains(&binding)
+    }
+
+    fn action_pressed(&self, action: &str) -> bool {
+        self.action_bindings(action).any(|b| self.pressed(*b))
+    }
+
+    fn action_just_pressed(&self, action: &str) -> bool {
+        self.action_bindings(action).any(|b| self.just_pressed(*b))
+    }
+
+    fn action_just_released(&self, action: &str) -> bool {
+        self.action_bindings(action).any(|b| self.just_released(*b))
+    }
 }
 
-fn physics2d_update_system(
-    mut physics2d_manager: UniqueViewMut<Physics2DManager>,
-    rb2d: View<RigidBody2D>,
-    mut transform2d: ViewMut<Transform2D>,
-) {
-    physics2d_manager.update();
-    (&rb2d, &mut transform2d)
-        .par_iter()
-        .for_each(|(rb2d, transform2d)| {
-            let rigid_body = &physics2d_manager.rigid_body_set[rb2d.handle];
-            transform2d.position.x = rigid_body.translation().x;
-            transform2d.position.y = rigid_body.translation().y;
-            transform2d.rotation = rigid_body.rotation().angle();
-        });
+fn input_clear_system(mut input: UniqueViewMut<Input>) {
+    input.clear();
+}
+
+// The pose of a rigid body before the last physics step, used to render between two fixed steps
+#[derive(Component, Debug)]
+struct Interpolation2D {
+    previous_position: Vec3,
+    previous_rotation: f32,
+}
+
+impl Interpolation2D {
+    fn new(transform2d: &Transform2D) -> Self {
+        Interpolation2D {
+            previous_position: transform2d.position,
+            previous_rotation: transform2d.rotation,
+        }
+    }
+
+    fn blend(&self, transform2d: &Transform2D, alpha: f32) -> (Vec3, f32) {
+        let position = Vec3 {
+            z: transform2d.position.z,
+            ..self.previous_position.lerp(transform2d.position, alpha)
+        };
+        // take the shortest way around, rapier angles wrap at [-pi, pi]
+        let delta = (transform2d.rotation - self.previous_rotation + std::f32::consts::PI)
+            .rem_euclid(std::f32::consts::TAU)
+            - std::f32::consts::PI;
+        (position, self.previous_rotation + delta * alpha)
+    }
 }
 
-trait Edit: Component {
+#[allow(clippy::wrong_self_convention)]
+pub trait Edit: Component {
     fn name() -> &'static str;
 
     fn to_data(&self) -> ComponentData {
         ComponentData::new(Self::name())
     }
 
-    fn from_data(&mut self, data: ComponentData) {}
+    fn from_data(&mut self, _data: ComponentData) {}
 }
 
-#[derive(Debug)]
-enum Value {
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
     Int32(i32),
     Float32(f32),
     String(String),
     Vec2(Vec2),
     Vec3(Vec3),
     Vec4(Vec4),
+    Entity(EntityId),
 }
 
-#[derive(Debug)]
-struct Variant {
-    name: &'static str,
-    value: Value,
+#[derive(Debug, Clone)]
+pub struct Variant {
+    pub name: &'static str,
+    pub value: Value,
 }
 
 // ComponentData contains all variant in a component
-#[derive(Debug)]
-struct ComponentData {
-    name: &'static str,
-    variants: Vec<Variant>,
+#[derive(Debug, Clone)]
+pub struct ComponentData {
+    pub name: &'static str,
+    pub variants: Vec<Variant>,
 }
 
 impl ComponentData {
@@ -532,6 +1799,7 @@ impl ComponentData {
 }
 
 // EntityData contains all component data in a entity, key is component name
+#[allow(dead_code)] // only read through the Debug output of the dump
 #[derive(Debug)]
 struct EntityData {
     id: EntityId,
@@ -553,29 +1821,132 @@ impl WorldData {
         }
     }
 
-    fn add_component<T: Edit + Send + Sync>(&mut self, world: &World) {
-        world.run(|c: View<T>| {
-            for (e, c) in c.iter().with_id() {
-                let index = *self.id_index_map.entry(e).or_insert(self.entities.len());
-                if index == self.entities.len() {
-                    self.entities.push(EntityData {
-                        id: e,
-                        components: Vec::new(),
-                    });
-                }
-                self.entities[index].components.push(c.to_data());
-            }
-        })
+    fn add_component(&mut self, e: EntityId, data: ComponentData) {
+        let index = *self.id_index_map.entry(e).or_insert(self.entities.len());
+        if index == self.entities.len() {
+            self.entities.push(EntityData {
+                id: e,
+                components: Vec::new(),
+            });
+        }
+        self.entities[index].components.push(data);
+    }
+}
+
+// set by Engine::dump_world_data, building WorldData every frame is too slow and noisy
+#[derive(Unique, Default)]
+struct WorldDataDump {
+    requested: bool,
+}
+
+fn world_data_dump_system(all_storages: AllStoragesView) {
+    let mut world_data_dump = all_storages
+        .borrow::<UniqueViewMut<WorldDataDump>>()
+        .unwrap();
+    if !std::mem::take(&mut world_data_dump.requested) {
+        return;
+    }
+    let mut world_data = WorldData::new();
+    let component_registry = all_storages
+        .borrow::<UniqueView<ComponentRegistry>>()
+        .unwrap();
+    for (e, data) in component_registry.all(&all_storages) {
+        world_data.add_component(e, data);
+    }
+    log::info!("world_data={:?}", world_data);
+}
+
+fn component_data<T: Edit + Send + Sync>(
+    all_storages: &AllStorages,
+    entity: EntityId,
+) -> Option<ComponentData> {
+    all_storages.run(|c: View<T>| c.get(entity).ok().map(|c| c.to_data()))
+}
+
+// edited components are marked as modified, so that systems like physics can pick up the change
+fn set_component_data<T: Edit<Tracking = track::All> + Send + Sync>(
+    all_storages: &AllStorages,
+    entity: EntityId,
+    data: ComponentData,
+) {
+    all_storages.run(|mut c: ViewMut<T>| {
+        if let Ok(mut c) = (&mut c).get(entity) {
+            c.from_data(data);
+        }
+    })
+}
+
+// the edit path by component name, shared by the editor, scripts and prefabs
+fn component_data_by_name(
+    all_storages: &AllStorages,
+    entity: EntityId,
+    component: &str,
+) -> Option<ComponentData> {
+    all_storages
+        .borrow::<UniqueView<ComponentRegistry>>()
+        .unwrap()
+        .get(all_storages, entity, component)
+}
+
+fn set_component_data_by_name(all_storages: &AllStorages, entity: EntityId, data: ComponentData) {
+    let name = data.name;
+    let component_registry = all_storages
+        .borrow::<UniqueView<ComponentRegistry>>()
+        .unwrap();
+    if !component_registry.set(all_storages, entity, data) {
+        log::warn!("Can not edit unknown component {name}");
+    }
+}
+
+fn add_component<T: Component + Send + Sync>(
+    all_storages: &AllStorages,
+    entity: EntityId,
+    component: T,
+) {
+    all_storages.run(|entities: EntitiesView, mut c: ViewMut<T>| {
+        entities.add_component(entity, &mut c, component)
+    })
+}
+
+// the override which places a prefab instance at position
+fn placement(position: Vec2) -> ComponentData {
+    ComponentData {
+        name: "Transform2D",
+        variants: vec![Variant {
+            name: "position",
+            value: Value::Vec3(position.extend(0.0)),
+        }],
     }
 }
 
+// add the component with its default values, returns false for unknown components
+fn add_component_by_name(all_storages: &AllStorages, entity: EntityId, component: &str) -> bool {
+    all_storages
+        .borrow::<UniqueView<ComponentRegistry>>()
+        .unwrap()
+        .add(all_storages, entity, component)
+}
+
 #[derive(Component, Debug, Default)]
+#[track(All)]
 struct Transform2D {
     position: Vec3,
     rotation: f32, // radian
     scale: Vec2,
 }
 
+impl Transform2D {
+    // world position into the space where the entity is the unit square around the origin
+    fn to_local(&self, position: Vec2) -> Vec2 {
+        let relative = position - self.position.truncate();
+        let (sin, cos) = (-self.rotation).sin_cos();
+        Vec2::new(
+            relative.x * cos - relative.y * sin,
+            relative.x * sin + relative.y * cos,
+        ) / self.scale
+    }
+}
+
 impl Edit for Transform2D {
     fn name() -> &'static str {
         "Transform2D"
@@ -633,6 +2004,15 @@ impl Edit for Transform2D {
 struct RigidBody2D {
     handle: RigidBodyHandle,
     body_type: RigidBodyType,
+    linear_velocity: Vec2, // written back from rapier after every physics step
+    angular_velocity: f32, // written back from rapier after every physics step
+    linear_damping: f32,
+    angular_damping: f32,
+    gravity_scale: f32,
+    ccd_enabled: bool,
+    rotation_locked: bool,
+    sleeping: bool, // written back from rapier after every physics step, set to false to wake the body up
+    additional_mass: f32,
 }
 
 impl RigidBody2D {
@@ -640,14 +2020,154 @@ impl RigidBody2D {
         RigidBody2D {
             handle: RigidBodyHandle::invalid(),
             body_type,
+            linear_velocity: Vec2::ZERO,
+            angular_velocity: 0.0,
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            gravity_scale: 1.0,
+            ccd_enabled: false,
+            rotation_locked: false,
+            sleeping: false,
+            additional_mass: 0.0,
+        }
+    }
+
+    // whether the rapier rigid body already has the properties of this component,
+    // e.g. right after the velocities were written back from it
+    fn synced(&self, rigid_body: &RigidBody) -> bool {
+        let additional_mass = match rigid_body
+            .mass_properties()
+            .additional_local_mprops
+            .as_deref()
+        {
+            Some(RigidBodyAdditionalMassProps::Mass(mass)) => *mass,
+            _ => 0.0,
+        };
+        rigid_body.body_type() == self.body_type
+            && rigid_body.linvel().x == self.linear_velocity.x
+            && rigid_body.linvel().y == self.linear_velocity.y
+            && rigid_body.angvel() == self.angular_velocity
+            && rigid_body.linear_damping() == self.linear_damping
+            && rigid_body.angular_damping() == self.angular_damping
+            && rigid_body.gravity_scale() == self.gravity_scale
+            && rigid_body.is_ccd_enabled() == self.ccd_enabled
+            && rigid_body.is_rotation_locked() == self.rotation_locked
+            && rigid_body.is_sleeping() == self.sleeping
+            && additional_mass == self.additional_mass
+    }
+
+    // push the properties of this component into the rapier rigid body
+    fn apply(&self, rigid_body: &mut RigidBody) {
+        rigid_body.set_body_type(self.body_type, true);
+        rigid_body.set_linvel(
+            vector![self.linear_velocity.x, self.linear_velocity.y],
+            !self.sleeping,
+        );
+        rigid_body.set_angvel(self.angular_velocity, !self.sleeping);
+        rigid_body.set_linear_damping(self.linear_damping);
+        rigid_body.set_angular_damping(self.angular_damping);
+        rigid_body.set_gravity_scale(self.gravity_scale, !self.sleeping);
+        rigid_body.enable_ccd(self.ccd_enabled);
+        rigid_body.lock_rotations(self.rotation_locked, !self.sleeping);
+        rigid_body.set_additional_mass(self.additional_mass, !self.sleeping);
+        if self.sleeping {
+            rigid_body.sleep();
+        } else if rigid_body.is_sleeping() {
+            rigid_body.wake_up(true);
         }
     }
 }
 
+fn body_type_name(body_type: RigidBodyType) -> &'static str {
+    match body_type {
+        RigidBodyType::Dynamic => "Dynamic",
+        RigidBodyType::Fixed => "Fixed",
+        RigidBodyType::KinematicPositionBased => "KinematicPositionBased",
+        RigidBodyType::KinematicVelocityBased => "KinematicVelocityBased",
+    }
+}
+
+fn body_type_from_name(name: &str) -> Option<RigidBodyType> {
+    match name {
+        "Dynamic" => Some(RigidBodyType::Dynamic),
+        "Fixed" => Some(RigidBodyType::Fixed),
+        "KinematicPositionBased" => Some(RigidBodyType::KinematicPositionBased),
+        "KinematicVelocityBased" => Some(RigidBodyType::KinematicVelocityBased),
+        _ => None,
+    }
+}
+
 impl Edit for RigidBody2D {
     fn name() -> &'static str {
         "RigidBody2D"
     }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "body_type",
+            value: Value::String(body_type_name(self.body_type).into()),
+        });
+        data.variants.push(Variant {
+            name: "linear_velocity",
+            value: Value::Vec2(self.linear_velocity),
+        });
+        data.variants.push(Variant {
+            name: "angular_velocity",
+            value: Value::Float32(self.angular_velocity),
+        });
+        data.variants.push(Variant {
+            name: "linear_damping",
+            value: Value::Float32(self.linear_damping),
+        });
+        data.variants.push(Variant {
+            name: "angular_damping",
+            value: Value::Float32(self.angular_damping),
+        });
+        data.variants.push(Variant {
+            name: "gravity_scale",
+            value: Value::Float32(self.gravity_scale),
+        });
+        data.variants.push(Variant {
+            name: "ccd_enabled",
+            value: Value::Bool(self.ccd_enabled),
+        });
+        data.variants.push(Variant {
+            name: "rotation_locked",
+            value: Value::Bool(self.rotation_locked),
+        });
+        data.variants.push(Variant {
+            name: "sleeping",
+            value: Value::Bool(self.sleeping),
+        });
+        data.variants.push(Variant {
+            name: "additional_mass",
+            value: Value::Float32(self.additional_mass),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            match (v.name, v.value) {
+                ("body_type", Value::String(name)) => {
+                    if let Some(body_type) = body_type_from_name(&name) {
+                        self.body_type = body_type;
+                    }
+                }
+                ("linear_velocity", Value::Vec2(v)) => self.linear_velocity = v,
+                ("angular_velocity", Value::Float32(v)) => self.angular_velocity = v,
+                ("linear_damping", Value::Float32(v)) => self.linear_damping = v,
+                ("angular_damping", Value::Float32(v)) => self.angular_damping = v,
+                ("gravity_scale", Value::Float32(v)) => self.gravity_scale = v,
+                ("ccd_enabled", Value::Bool(v)) => self.ccd_enabled = v,
+                ("rotation_locked", Value::Bool(v)) => self.rotation_locked = v,
+                ("sleeping", Value::Bool(v)) => self.sleeping = v,
+                ("additional_mass", Value::Float32(v)) => self.additional_mass = v,
+                _ => (),
+            }
+        }
+    }
 }
 
 struct ShapeWrapper(SharedShape);
@@ -674,6 +2194,10 @@ struct Collider2D {
     handle: ColliderHandle,
     shape: ShapeWrapper,
     restitution: f32,
+    friction: f32,
+    density: f32,
+    sensor: bool, // sensors only report intersections, they never generate contact forces
+    collision_groups: InteractionGroups,
 }
 
 impl Collider2D {
@@ -682,12 +2206,460 @@ impl Collider2D {
             handle: ColliderHandle::invalid(),
             shape: ShapeWrapper(shape),
             restitution,
+            friction: 0.5,
+            density: 1.0,
+            sensor: false,
+            collision_groups: InteractionGroups::all(),
+        }
+    }
+
+    // the shapes which can be edited: cuboid with half extents or ball with radius
+    fn shape_params(&self) -> (&'static str, Vec2, f32) {
+        if let Some(cuboid) = self.shape.as_cuboid() {
+            let half_extents = cuboid.half_extents;
+            ("Cuboid", Vec2::new(half_extents.x, half_extents.y), 0.0)
+        } else if let Some(ball) = self.shape.as_ball() {
+            ("Ball", Vec2::ZERO, ball.radius)
+        } else {
+            ("Unsupported", Vec2::ZERO, 0.0)
         }
     }
+
+    // push the properties of this component into the rapier collider
+    fn apply(&self, collider: &mut Collider) {
+        collider.set_shape(self.shape.clone());
+        collider.set_restitution(self.restitution);
+        collider.set_friction(self.friction);
+        collider.set_density(self.density);
+        collider.set_sensor(self.sensor);
+        collider.set_collision_groups(self.collision_groups);
+        collider.set_active_events(ActiveEvents::COLLISION_EVENTS);
+    }
 }
 
 impl Edit for Collider2D {
     fn name() -> &'static str {
         "Collider2D"
     }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        let (shape_type, half_extents, radius) = self.shape_params();
+        data.variants.push(Variant {
+            name: "shape_type",
+            value: Value::String(shape_type.into()),
+        });
+        data.variants.push(Variant {
+            name: "half_extents",
+            value: Value::Vec2(half_extents),
+        });
+        data.variants.push(Variant {
+            name: "radius",
+            value: Value::Float32(radius),
+        });
+        data.variants.push(Variant {
+            name: "restitution",
+            value: Value::Float32(self.restitution),
+        });
+        data.variants.push(Variant {
+            name: "friction",
+            value: Value::Float32(self.friction),
+        });
+        data.variants.push(Variant {
+            name: "density",
+            value: Value::Float32(self.density),
+        });
+        data.variants.push(Variant {
+            name: "sensor",
+            value: Value::Bool(self.sensor),
+        });
+        data.variants.push(Variant {
+            name: "collision_memberships",
+            value: Value::Int32(self.collision_groups.memberships.bits() as i32),
+        });
+        data.variants.push(Variant {
+            name: "collision_filter",
+            value: Value::Int32(self.collision_groups.filter.bits() as i32),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        let (shape_type, mut half_extents, mut radius) = self.shape_params();
+        let mut shape_type = shape_type.to_string();
+        let mut shape_changed = false;
+        for v in data.variants {
+            match (v.name, v.value) {
+                ("shape_type", Value::String(v)) => {
+                    shape_changed |= v != shape_type;
+                    shape_type = v;
+                }
+                ("half_extents", Value::Vec2(v)) => {
+                    shape_changed |= v != half_extents;
+                    half_extents = v;
+                }
+                ("radius", Value::Float32(v)) => {
+                    shape_changed |= v != radius;
+                    radius = v;
+                }
+                ("restitution", Value::Float32(v)) => self.restitution = v,
+                ("friction", Value::Float32(v)) => self.friction = v,
+                ("density", Value::Float32(v)) => self.density = v,
+                ("sensor", Value::Bool(v)) => self.sensor = v,
+                ("collision_memberships", Value::Int32(v)) => {
+                    self.collision_groups.memberships = Group::from_bits_truncate(v as u32)
+                }
+                ("collision_filter", Value::Int32(v)) => {
+                    self.collision_groups.filter = Group::from_bits_truncate(v as u32)
+                }
+                _ => (),
+            }
+        }
+        if shape_changed {
+            match shape_type.as_str() {
+                "Cuboid" => {
+                    self.shape = ShapeWrapper(SharedShape::cuboid(half_extents.x, half_extents.y))
+                }
+                "Ball" => self.shape = ShapeWrapper(SharedShape::ball(radius)),
+                _ => log::warn!("Collider2D does not support shape type {shape_type}"),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JointType2D {
+    Revolute, // rotate freely around the anchor
+    Prismatic {
+        axis: Vec2, // slide along this axis, in local space of the first body
+    },
+    Fixed, // no relative movement at all
+    Rope {
+        max_length: f32, // anchors can get closer but never further apart than this
+    },
+    Spring {
+        rest_length: f32,
+        stiffness: f32,
+        damping: f32,
+    },
+}
+
+impl JointType2D {
+    fn name(&self) -> &'static str {
+        match self {
+            JointType2D::Revolute => "Revolute",
+            JointType2D::Prismatic { .. } => "Prismatic",
+            JointType2D::Fixed => "Fixed",
+            JointType2D::Rope { .. } => "Rope",
+            JointType2D::Spring { .. } => "Spring",
+        }
+    }
+
+    // creates a joint type with default parameters from its name
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Revolute" => Some(JointType2D::Revolute),
+            "Prismatic" => Some(JointType2D::Prismatic { axis: Vec2::X }),
+            "Fixed" => Some(JointType2D::Fixed),
+            "Rope" => Some(JointType2D::Rope { max_length: 1.0 }),
+            "Spring" => Some(JointType2D::Spring {
+                rest_length: 1.0,
+                stiffness: 10.0,
+                damping: 1.0,
+            }),
+            _ => None,
+        }
+    }
+}
+
+// Drives the free axis of a revolute, prismatic or rope joint
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct JointMotor2D {
+    target_position: f32, // radian for revolute joints
+    target_velocity: f32,
+    stiffness: f32,
+    damping: f32,
+    max_force: f32,
+}
+
+impl Default for JointMotor2D {
+    fn default() -> Self {
+        JointMotor2D {
+            target_position: 0.0,
+            target_velocity: 0.0,
+            stiffness: 0.0,
+            damping: 0.0,
+            max_force: f32::MAX,
+        }
+    }
+}
+
+// A joint between the rigid bodies of two entities, it can live on any entity
+#[derive(Component, Debug)]
+#[track(All)]
+struct Joint2D {
+    handle: ImpulseJointHandle,
+    joint_type: JointType2D,
+    entity1: EntityId,
+    entity2: EntityId,
+    anchor1: Vec2,               // in local space of entity1
+    anchor2: Vec2,               // in local space of entity2
+    limits: Option<[f32; 2]>, // min and max of the free axis, ignored by fixed, rope and spring joints
+    motor: Option<JointMotor2D>, // ignored by fixed and spring joints
+    contacts_enabled: bool,   // whether the two bodies still collide with each other
+}
+
+impl Joint2D {
+    fn new(joint_type: JointType2D, entity1: EntityId, entity2: EntityId) -> Self {
+        Joint2D {
+            handle: ImpulseJointHandle::invalid(),
+            joint_type,
+            entity1,
+            entity2,
+            anchor1: Vec2::ZERO,
+            anchor2: Vec2::ZERO,
+            limits: None,
+            motor: None,
+            contacts_enabled: true,
+        }
+    }
+
+    fn build(&self) -> GenericJoint {
+        let (mut joint, free_axis) = match self.joint_type {
+            JointType2D::Revolute => (RevoluteJointBuilder::new().build().data, JointAxis::AngX),
+            JointType2D::Prismatic { axis } => (
+                PrismaticJointBuilder::new(UnitVector::new_normalize(vector![axis.x, axis.y]))
+                    .build()
+                    .data,
+                JointAxis::X,
+            ),
+            JointType2D::Fixed => (FixedJointBuilder::new().build().data, JointAxis::X),
+            JointType2D::Rope { max_length } => (
+                RopeJointBuilder::new()
+                    .limits([0.0, max_length])
+                    .build()
+                    .data,
+                JointAxis::X,
+            ),
+            JointType2D::Spring {
+                rest_length,
+                stiffness,
+                damping,
+            } => (
+                // rapier 0.17 has no spring joint, this is how later versions build it
+                GenericJointBuilder::new(JointAxesMask::empty())
+                    .coupled_axes(JointAxesMask::LIN_AXES)
+                    .motor_position(JointAxis::X, rest_length, stiffness, damping)
+                    .motor_model(JointAxis::X, MotorModel::ForceBased)
+                    .build(),
+                JointAxis::X,
+            ),
+        };
+        joint
+            .set_local_anchor1(point![self.anchor1.x, self.anchor1.y])
+            .set_local_anchor2(point![self.anchor2.x, self.anchor2.y])
+            .set_contacts_enabled(self.contacts_enabled);
+        let has_free_axis = matches!(
+            self.joint_type,
+            JointType2D::Revolute | JointType2D::Prismatic { .. }
+        );
+        if let (Some(limits), true) = (self.limits, has_free_axis) {
+            joint.set_limits(free_axis, limits);
+        }
+        let has_motor = has_free_axis || matches!(self.joint_type, JointType2D::Rope { .. });
+        if let (Some(motor), true) = (self.motor, has_motor) {
+            joint
+                .set_motor(
+                    free_axis,
+                    motor.target_position,
+                    motor.target_velocity,
+                    motor.stiffness,
+                    motor.damping,
+                )
+                .set_motor_max_force(free_axis, motor.max_force);
+        }
+        joint
+    }
+}
+
+impl Edit for Joint2D {
+    fn name() -> &'static str {
+        "Joint2D"
+    }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "joint_type",
+            value: Value::String(self.joint_type.name().into()),
+        });
+        match self.joint_type {
+            JointType2D::Prismatic { axis } => data.variants.push(Variant {
+                name: "axis",
+                value: Value::Vec2(axis),
+            }),
+            JointType2D::Rope { max_length } => data.variants.push(Variant {
+                name: "max_length",
+                value: Value::Float32(max_length),
+            }),
+            JointType2D::Spring {
+                rest_length,
+                stiffness,
+                damping,
+            } => {
+                data.variants.push(Variant {
+                    name: "rest_length",
+                    value: Value::Float32(rest_length),
+                });
+                data.variants.push(Variant {
+                    name: "stiffness",
+                    value: Value::Float32(stiffness),
+                });
+                data.variants.push(Variant {
+                    name: "damping",
+                    value: Value::Float32(damping),
+                });
+            }
+            _ => (),
+        }
+        data.variants.push(Variant {
+            name: "entity1",
+            value: Value::Entity(self.entity1),
+        });
+        data.variants.push(Variant {
+            name: "entity2",
+            value: Value::Entity(self.entity2),
+        });
+        data.variants.push(Variant {
+            name: "anchor1",
+            value: Value::Vec2(self.anchor1),
+        });
+        data.variants.push(Variant {
+            name: "anchor2",
+            value: Value::Vec2(self.anchor2),
+        });
+        data.variants.push(Variant {
+            name: "limits_enabled",
+            value: Value::Bool(self.limits.is_some()),
+        });
+        data.variants.push(Variant {
+            name: "limits",
+            value: Value::Vec2(self.limits.unwrap_or_default().into()),
+        });
+        let motor = self.motor.unwrap_or_default();
+        data.variants.push(Variant {
+            name: "motor_enabled",
+            value: Value::Bool(self.motor.is_some()),
+        });
+        data.variants.push(Variant {
+            name: "motor_target_position",
+            value: Value::Float32(motor.target_position),
+        });
+        data.variants.push(Variant {
+            name: "motor_target_velocity",
+            value: Value::Float32(motor.target_velocity),
+        });
+        data.variants.push(Variant {
+            name: "motor_stiffness",
+            value: Value::Float32(motor.stiffness),
+        });
+        data.variants.push(Variant {
+            name: "motor_damping",
+            value: Value::Float32(motor.damping),
+        });
+        data.variants.push(Variant {
+            name: "motor_max_force",
+            value: Value::Float32(motor.max_force),
+        });
+        data.variants.push(Variant {
+            name: "contacts_enabled",
+            value: Value::Bool(self.contacts_enabled),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            match (v.name, v.value) {
+                ("joint_type", Value::String(name)) if name != self.joint_type.name() => {
+                    if let Some(joint_type) = JointType2D::from_name(&name) {
+                        self.joint_type = joint_type;
+                    }
+                }
+                ("axis", Value::Vec2(v)) => {
+                    if let JointType2D::Prismatic { axis } = &mut self.joint_type {
+                        *axis = v;
+                    }
+                }
+                ("max_length", Value::Float32(v)) => {
+                    if let JointType2D::Rope { max_length } = &mut self.joint_type {
+                        *max_length = v;
+                    }
+                }
+                ("rest_length", Value::Float32(v)) => {
+                    if let JointType2D::Spring { rest_length, .. } = &mut self.joint_type {
+                        *rest_length = v;
+                    }
+                }
+                ("stiffness", Value::Float32(v)) => {
+                    if let JointType2D::Spring { stiffness, .. } = &mut self.joint_type {
+                        *stiffness = v;
+                    }
+                }
+                ("damping", Value::Float32(v)) => {
+                    if let JointType2D::Spring { damping, .. } = &mut self.joint_type {
+                        *damping = v;
+                    }
+                }
+                ("entity1", Value::Entity(e)) => self.entity1 = e,
+                ("entity2", Value::Entity(e)) => self.entity2 = e,
+                ("anchor1", Value::Vec2(v)) => self.anchor1 = v,
+                ("anchor2", Value::Vec2(v)) => self.anchor2 = v,
+                ("limits_enabled", Value::Bool(enabled)) => {
+                    self.limits = match (enabled, self.limits) {
+                        (true, limits) => Some(limits.unwrap_or_default()),
+                        (false, _) => None,
+                    }
+                }
+                ("limits", Value::Vec2(v)) => {
+                    if let Some(limits) = &mut self.limits {
+                        *limits = v.into();
+                    }
+                }
+                ("motor_enabled", Value::Bool(enabled)) => {
+                    self.motor = match (enabled, self.motor) {
+                        (true, motor) => Some(motor.unwrap_or_default()),
+                        (false, _) => None,
+                    }
+                }
+                ("motor_target_position", Value::Float32(v)) => {
+                    if let Some(motor) = &mut self.motor {
+                        motor.target_position = v;
+                    }
+                }
+                ("motor_target_velocity", Value::Float32(v)) => {
+                    if let Some(motor) = &mut self.motor {
+                        motor.target_velocity = v;
+                    }
+                }
+                ("motor_stiffness", Value::Float32(v)) => {
+                    if let Some(motor) = &mut self.motor {
+                        motor.stiffness = v;
+                    }
+                }
+                ("motor_damping", Value::Float32(v)) => {
+                    if let Some(motor) = &mut self.motor {
+                        motor.damping = v;
+                    }
+                }
+                ("motor_max_force", Value::Float32(v)) => {
+                    if let Some(motor) = &mut self.motor {
+                        motor.max_force = v;
+                    }
+                }
+                ("contacts_enabled", Value::Bool(v)) => self.contacts_enabled = v,
+                _ => (),
+            }
+        }
+    }
 }
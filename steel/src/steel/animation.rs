@@ -0,0 +1,627 @@
+use super::{
+    asset::{Asset, AssetCache, AssetReader},
+    camera2d_extract_system,
+    clip::{parse_clips, AnimationClip, AnimationClips, LoopMode, TweenProperty},
+    input_clear_system,
+    script::script_animation_event_system,
+    script_update_system, AssetServer, ComponentData, Edit, EngineBuilder, Interpolation2D, Plugin,
+    RenderInfo, RenderList, RenderPass, Shader, Sprite2D, Stage, TextureImage, Time, Transform2D,
+    Value, Variant,
+};
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use shader_library::include_spirv;
+use shipyard::{
+    Component, EntityId, Get, IntoIter, IntoWithId, SystemModificator, Unique, UniqueView,
+    UniqueViewMut, View, ViewMut,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorType, DescriptorSet,
+        WriteDescriptorSet,
+    },
+    image::sampler::{Filter, Sampler, SamplerCreateInfo},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{
+        graphics::{
+            color_blend::{AttachmentBlend, ColorBlendAttachmentState, ColorBlendState},
+            input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            rasterization::RasterizationState,
+            vertex_input::{Vertex, VertexDefinition},
+            viewport::{Viewport, ViewportState},
+            GraphicsPipelineCreateInfo,
+        },
+        DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineShaderStageCreateInfo,
+    },
+};
+
+// Animator2D, the clip files it plays and the pass which draws its sprites
+pub struct Animation2DPlugin;
+
+impl Plugin for Animation2DPlugin {
+    fn build(&self, builder: &mut EngineBuilder) {
+        builder
+            .add_unique(AnimationManager::new())
+            .add_component(Animator2D::default)
+            // after the scripts, so clips they start play in the same update
+            .add_system(
+                Stage::PreUpdate,
+                animator2d_update_system.after_all(script_update_system),
+            )
+            .add_system(
+                Stage::PostPhysics,
+                script_animation_event_system.before_all(input_clear_system),
+            )
+            .add_system(
+                Stage::RenderExtract,
+                animator2d_extract_system.after_all(camera2d_extract_system),
+            )
+            .add_render_pass(Sprite2DPass::default());
+    }
+}
+
+// the transform when a clip with tweens started, its tweens are relative to it
+#[derive(Debug, Clone, Copy)]
+struct TweenBase {
+    position: Vec2,
+    rotation: f32,
+    scale: Vec2,
+}
+
+// where the animator is in its clip
+#[derive(Debug, Default)]
+struct AnimatorState {
+    clip: String, // the clip this state belongs to, it restarts when Animator2D::clip changes
+    frame: usize, // index into the frames of the clip
+    time: f32,    // seconds since the frame started
+    backwards: bool, // a PingPong clip is on its way back to the first frame
+    finished: bool, // a Once clip reached the end of its last frame
+    base: Option<TweenBase>,
+}
+
+// Plays a clip of a clip file: the current frame of the sprite atlas is drawn on the unit square
+// of the entity like Renderer2D, and tweens of the clip move the Transform2D. Events of the
+// frames are sent to the script of the entity as on_animation_event(entity, clip, name).
+#[derive(Component, Debug)]
+#[track(All)]
+pub struct Animator2D {
+    pub clips: String, // path of a steel clip file or an Aseprite JSON export
+    pub clip: String,  // name of the playing clip
+    pub speed: f32,    // 2 plays twice as fast
+    pub paused: bool,
+    pub color: Vec4, // multiplied with the sprite
+    state: AnimatorState,
+}
+
+impl Default for Animator2D {
+    fn default() -> Self {
+        Animator2D {
+            clips: String::new(),
+            clip: String::new(),
+            speed: 1.0,
+            paused: false,
+            color: Vec4::ONE,
+            state: AnimatorState::default(),
+        }
+    }
+}
+
+impl Animator2D {
+    pub fn new(clips: impl Into<String>, clip: impl Into<String>) -> Self {
+        Animator2D {
+            clips: clips.into(),
+            clip: clip.into(),
+            ..Default::default()
+        }
+    }
+
+    // go to the next frame, returns false when a Once clip has no next frame
+    fn step(&mut self, clip: &AnimationClip) -> bool {
+        let state = &mut self.state;
+        let last = clip.frames.len() - 1;
+        match clip.mode {
+            LoopMode::Once if state.frame == last => return false,
+            LoopMode::Once => state.frame += 1,
+            LoopMode::Loop => state.frame = (state.frame + 1) % clip.frames.len(),
+            LoopMode::PingPong if last == 0 => (),
+            LoopMode::PingPong => {
+                if state.frame == last {
+                    state.backwards = true;
+                } else if state.frame == 0 {
+                    state.backwards = false;
+                }
+                if state.backwards {
+                    state.frame -= 1;
+                } else {
+                    state.frame += 1;
+                }
+            }
+        }
+        true
+    }
+
+    // seconds into the clip for its tweens, on the way back of a PingPong clip time runs backwards
+    fn clip_time(&self, clip: &AnimationClip) -> f32 {
+        let state = &self.state;
+        let duration = clip.frames[state.frame].duration;
+        let time = if state.backwards {
+            duration - state.time
+        } else {
+            state.time
+        };
+        clip.frame_start(state.frame) + time
+    }
+}
+
+impl Edit for Animator2D {
+    fn name() -> &'static str {
+        "Animator2D"
+    }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "clips",
+            value: Value::String(self.clips.clone()),
+        });
+        data.variants.push(Variant {
+            name: "clip",
+            value: Value::String(self.clip.clone()),
+        });
+        data.variants.push(Variant {
+            name: "speed",
+            value: Value::Float32(self.speed),
+        });
+        data.variants.push(Variant {
+            name: "paused",
+            value: Value::Bool(self.paused),
+        });
+        data.variants.push(Variant {
+            name: "color",
+            value: Value::Vec4(self.color),
+        });
+        // the state, frame and time can be set to scrub through a paused clip
+        data.variants.push(Variant {
+            name: "frame",
+            value: Value::Int32(self.state.frame as i32),
+        });
+        data.variants.push(Variant {
+            name: "time",
+            value: Value::Float32(self.state.time),
+        });
+        data.variants.push(Variant {
+            name: "finished",
+            value: Value::Bool(self.state.finished),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            match (v.name, v.value) {
+                ("clips", Value::String(clips)) if clips != self.clips => {
+                    self.clips = clips;
+                    self.state.clip.clear();
+                }
+                ("clip", Value::String(clip)) => self.clip = clip,
+                ("speed", Value::Float32(speed)) => self.speed = speed,
+                ("paused", Value::Bool(paused)) => self.paused = paused,
+                ("color", Value::Vec4(color)) => self.color = color,
+                // the update clamps them to the clip
+                ("frame", Value::Int32(frame)) => self.state.frame = frame.max(0) as usize,
+                ("time", Value::Float32(time)) => self.state.time = time.max(0.0),
+                _ => (),
+            }
+        }
+    }
+}
+
+// An event of a frame which started in this update
+#[derive(Debug, Clone)]
+pub struct AnimationEvent2D {
+    pub entity: EntityId,
+    pub clip: String,
+    pub name: String,
+}
+
+// Clip files by path and the events of the current update
+#[derive(Unique)]
+pub struct AnimationManager {
+    clips: AssetCache<AnimationClips>,
+    pub events: Vec<AnimationEvent2D>,
+}
+
+impl AnimationManager {
+    pub fn new() -> Self {
+        AnimationManager {
+            clips: AssetCache::new(),
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Asset for AnimationClips {
+    fn load(path: &str, data: Vec<u8>, reader: &AssetReader) -> Result<Self, String> {
+        let load_atlas = |atlas: &str| {
+            reader
+                .read(atlas)
+                .and_then(|data| TextureImage::decode(&data))
+                .map_err(|e| log::error!("Failed to load atlas {atlas}: {e}"))
+                .ok()
+        };
+        parse_clips(path, &data, load_atlas)
+    }
+}
+
+// advances the animators by the time of this update, fires the events of the frames they reach
+// and applies the tweens of their clips
+fn animator2d_update_system(
+    asset_server: UniqueView<AssetServer>,
+    time: UniqueView<Time>,
+    mut animator2d: ViewMut<Animator2D>,
+    mut transform2d: ViewMut<Transform2D>,
+    mut animation_manager: UniqueViewMut<AnimationManager>,
+) {
+    animation_manager.events.clear();
+    let mut events = Vec::new();
+    for (e, mut animator2d) in (&mut animator2d).iter().with_id() {
+        let Some(clips) = animation_manager
+            .clips
+            .get(&asset_server, &animator2d.clips)
+        else {
+            continue;
+        };
+        let Some(clip) = clips.clips.get(&animator2d.clip) else {
+            continue;
+        };
+        let animator2d = &mut *animator2d;
+        let mut fire = |animator2d: &Animator2D| {
+            for (_, name) in clip
+                .events
+                .iter()
+                .filter(|(frame, _)| *frame == animator2d.state.frame)
+            {
+                events.push(AnimationEvent2D {
+                    entity: e,
+                    clip: animator2d.clip.clone(),
+                    name: name.clone(),
+                });
+            }
+        };
+
+        if animator2d.state.clip != animator2d.clip {
+            let base = transform2d.get(e).ok().map(|transform2d| TweenBase {
+                position: transform2d.position.truncate(),
+                rotation: transform2d.rotation,
+                scale: transform2d.scale,
+            });
+            animator2d.state = AnimatorState {
+                clip: animator2d.clip.clone(),
+                base,
+                ..Default::default()
+            };
+            fire(animator2d);
+        } else {
+            // frame and time may have been set by the editor
+            animator2d.state.frame = animator2d.state.frame.min(clip.frames.len() - 1);
+        }
+
+        if !animator2d.paused && !animator2d.state.finished {
+            animator2d.state.time += time.delta * animator2d.speed.max(0.0);
+            loop {
+                let duration = clip.frames[animator2d.state.frame].duration;
+                if animator2d.state.time < duration {
+                    break;
+                }
+                if !animator2d.step(clip) {
+                    animator2d.state.time = duration;
+                    animator2d.state.finished = true;
+                    break;
+                }
+                animator2d.state.time -= duration;
+                fire(animator2d);
+            }
+        }
+
+        let Some(base) = animator2d.state.base.filter(|_| !clip.tweens.is_empty()) else {
+            continue;
+        };
+        let Ok(mut transform2d) = (&mut transform2d).get(e) else {
+            continue;
+        };
+        let clip_time = animator2d.clip_time(clip);
+        for tween in &clip.tweens {
+            let Some(value) = tween.value(clip_time) else {
+                continue;
+            };
+            match tween.property {
+                TweenProperty::Position => {
+                    let position = base.position + value;
+                    transform2d.position = position.extend(transform2d.position.z);
+                }
+                TweenProperty::Rotation => transform2d.rotation = base.rotation + value.x,
+                TweenProperty::Scale => transform2d.scale = base.scale * value,
+            }
+        }
+    }
+    animation_manager.events = events;
+    animation_manager.clips.retain_used();
+}
+
+fn animator2d_extract_system(
+    transform2d: View<Transform2D>,
+    animator2d: View<Animator2D>,
+    interpolation2d: View<Interpolation2D>,
+    animation_manager: UniqueView<AnimationManager>,
+    mut render_list: UniqueViewMut<RenderList>,
+) {
+    let alpha = render_list.alpha;
+    for (e, (transform2d, animator2d)) in (&transform2d, &animator2d).iter().with_id() {
+        let Some(clips) = animation_manager.clips.loaded(&animator2d.clips) else {
+            continue;
+        };
+        let Some(frame) = clips
+            .clips
+            .get(&animator2d.state.clip)
+            .and_then(|clip| clip.frames.get(animator2d.state.frame))
+        else {
+            continue;
+        };
+        let (position, rotation) = match interpolation2d.get(e) {
+            Ok(interpolation2d) => interpolation2d.blend(transform2d, alpha),
+            Err(_) => (transform2d.position, transform2d.rotation),
+        };
+        let model = Mat4::from_scale_rotation_translation(
+            transform2d.scale.extend(1.0),
+            Quat::from_axis_angle(Vec3::Z, rotation),
+            position,
+        );
+        render_list.sprites.push(Sprite2D {
+            model,
+            uv_min: frame.uv_min,
+            uv_max: frame.uv_max,
+            color: animator2d.color,
+            atlas: clips.atlas.clone(),
+            normal_map: clips.normal_map.clone(),
+        });
+    }
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct SpriteVertex {
+    #[format(R32G32_SFLOAT)]
+    position: [f32; 2],
+    #[format(R32G32_SFLOAT)]
+    uv: [f32; 2],
+    #[format(R32G32B32A32_SFLOAT)]
+    color: [f32; 4],
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct SpritePushConstants {
+    #[format(R32G32B32A32_SFLOAT)]
+    projection_view: [[f32; 4]; 4],
+}
+
+const SPRITE_SHADERS: [Shader; 2] = [
+    Shader {
+        path: "shaders/sprite.vert",
+        spirv: include_spirv!("shaders/sprite.vert"),
+        validate: |vs| {
+            vs.validate_vertex_input(&[SpriteVertex::per_vertex()])?;
+            vs.validate_push_constants::<SpritePushConstants>()
+        },
+    },
+    Shader {
+        path: "shaders/sprite.frag",
+        spirv: include_spirv!("shaders/sprite.frag"),
+        validate: |fs| {
+            fs.validate_descriptor(0, 0, DescriptorType::Sampler)?;
+            fs.validate_descriptor(0, 1, DescriptorType::SampledImage)
+        },
+    },
+];
+
+fn sprite_vertices(sprites: &[Sprite2D]) -> Vec<SpriteVertex> {
+    let mut vertices = Vec::with_capacity(sprites.len() * 6);
+    for sprite in sprites {
+        // top left, bottom left, bottom right, top right
+        let corners = [
+            (Vec2::new(-0.5, 0.5), sprite.uv_min),
+            (
+                Vec2::new(-0.5, -0.5),
+                Vec2::new(sprite.uv_min.x, sprite.uv_max.y),
+            ),
+            (Vec2::new(0.5, -0.5), sprite.uv_max),
+            (
+                Vec2::new(0.5, 0.5),
+                Vec2::new(sprite.uv_max.x, sprite.uv_min.y),
+            ),
+        ];
+        for i in [0, 1, 2, 2, 3, 0] {
+            let (corner, uv) = corners[i];
+            vertices.push(SpriteVertex {
+                position: sprite
+                    .model
+                    .transform_point3(corner.extend(0.0))
+                    .truncate()
+                    .to_array(),
+                uv: uv.to_array(),
+                color: sprite.color.to_array(),
+            });
+        }
+    }
+    vertices
+}
+
+// draws the sprites of the render list with every camera, one draw per run of sprites which
+// share an atlas
+#[derive(Default)]
+struct Sprite2DPass {
+    descriptor_set_allocator: Option<Arc<StandardDescriptorSetAllocator>>,
+}
+
+impl RenderPass for Sprite2DPass {
+    fn draw(
+        &mut self,
+        render_list: &RenderList,
+        info: &RenderInfo,
+        command_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) {
+        if render_list.sprites.is_empty() {
+            return;
+        }
+        let atlas_ids = render_list
+            .sprites
+            .iter()
+            .map(|sprite| sprite.atlas.id)
+            .collect::<HashSet<_>>();
+
+        let subpass = info.subpass.clone();
+        let pipeline =
+            info.shaders
+                .graphics_pipeline(&info.device, &SPRITE_SHADERS, |vs, fs, layout| {
+                    let vertex_input_state = SpriteVertex::per_vertex().definition(&vs)?;
+                    let stages = [
+                        PipelineShaderStageCreateInfo::new(vs),
+                        PipelineShaderStageCreateInfo::new(fs),
+                    ];
+                    Ok(GraphicsPipeline::new(
+                        info.device.clone(),
+                        None,
+                        GraphicsPipelineCreateInfo {
+                            stages: stages.into_iter().collect(),
+                            vertex_input_state: Some(vertex_input_state),
+                            input_assembly_state: Some(InputAssemblyState::default()),
+                            rasterization_state: Some(RasterizationState::default()),
+                            multisample_state: Some(MultisampleState {
+                                rasterization_samples: subpass.num_samples().unwrap(),
+                                ..Default::default()
+                            }),
+                            color_blend_state: Some(ColorBlendState::with_attachment_states(
+                                subpass.num_color_attachments(),
+                                ColorBlendAttachmentState {
+                                    blend: Some(AttachmentBlend::alpha()),
+                                    ..Default::default()
+                                },
+                            )),
+                            viewport_state: Some(ViewportState::default()),
+                            dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                            subpass: Some(subpass.clone().into()),
+                            ..GraphicsPipelineCreateInfo::layout(layout)
+                        },
+                    )?)
+                });
+
+        // nearest filtering keeps the pixels of the sprites sharp
+        let sampler = Sampler::new(
+            info.device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Nearest,
+                min_filter: Filter::Nearest,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let descriptor_set_allocator = self
+            .descriptor_set_allocator
+            .get_or_insert_with(|| {
+                Arc::new(StandardDescriptorSetAllocator::new(
+                    info.device.clone(),
+                    Default::default(),
+                ))
+            })
+            .clone();
+        let descriptor_sets = atlas_ids
+            .iter()
+            .map(|id| {
+                let descriptor_set = DescriptorSet::new(
+                    descriptor_set_allocator.clone(),
+                    pipeline.layout().set_layouts()[0].clone(),
+                    [
+                        WriteDescriptorSet::sampler(0, sampler.clone()),
+                        WriteDescriptorSet::image_view(1, info.textures[id].clone()),
+                    ],
+                    [],
+                )
+                .unwrap();
+                (*id, descriptor_set)
+            })
+            .collect::<HashMap<_, _>>();
+
+        let vertex_buffer = Buffer::from_iter(
+            info.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::VERTEX_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            sprite_vertices(&render_list.sprites),
+        )
+        .unwrap();
+
+        command_buffer_builder
+            .bind_pipeline_graphics(pipeline.clone())
+            .unwrap()
+            .bind_vertex_buffers(0, vertex_buffer)
+            .unwrap();
+
+        for camera in &render_list.cameras {
+            let (offset, extent) = camera.viewport_rect(info.window_size);
+            if extent.x < 1.0 || extent.y < 1.0 {
+                continue;
+            }
+            command_buffer_builder
+                .set_viewport(
+                    0,
+                    [Viewport {
+                        offset: offset.into(),
+                        extent: extent.into(),
+                        depth_range: 0.0..=1.0,
+                    }]
+                    .into_iter()
+                    .collect(),
+                )
+                .unwrap()
+                .push_constants(
+                    pipeline.layout().clone(),
+                    0,
+                    SpritePushConstants {
+                        projection_view: camera.projection_view(extent).to_cols_array_2d(),
+                    },
+                )
+                .unwrap();
+            let mut first = 0;
+            for run in render_list
+                .sprites
+                .chunk_by(|a, b| a.atlas.id == b.atlas.id)
+            {
+                command_buffer_builder
+                    .bind_descriptor_sets(
+                        PipelineBindPoint::Graphics,
+                        pipeline.layout().clone(),
+                        0,
+                        descriptor_sets[&run[0].atlas.id].clone(),
+                    )
+                    .unwrap();
+                unsafe {
+                    command_buffer_builder.draw(run.len() as u32 * 6, 1, first as u32 * 6, 0)
+                }
+                .unwrap();
+                first += run.len();
+            }
+        }
+    }
+}
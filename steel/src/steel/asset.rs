@@ -0,0 +1,295 @@
+use super::TextureImage;
+use shader_library::FileWatcher;
+use shipyard::{Unique, UniqueView};
+use std::{
+    any::{Any, TypeId},
+    collections::{HashMap, HashSet},
+    marker::PhantomData,
+    sync::{Arc, Condvar, Mutex, Weak},
+};
+
+// Decoded from the data of a file by AssetServer::load
+pub trait Asset: Send + Sync + Sized + 'static {
+    // runs on a rayon worker thread, reader reads the other files the asset refers to
+    fn load(path: &str, data: Vec<u8>, reader: &AssetReader) -> Result<Self, String>;
+}
+
+impl Asset for TextureImage {
+    fn load(_path: &str, data: Vec<u8>, _reader: &AssetReader) -> Result<Self, String> {
+        TextureImage::decode(&data)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    Failed(String),
+}
+
+enum SlotState {
+    Loading,
+    Loaded(Arc<dyn Any + Send + Sync>),
+    Failed(String),
+}
+
+type LoadAny = fn(&str, Vec<u8>, &AssetReader) -> Result<Arc<dyn Any + Send + Sync>, String>;
+
+fn load_any<T: Asset>(
+    path: &str,
+    data: Vec<u8>,
+    reader: &AssetReader,
+) -> Result<Arc<dyn Any + Send + Sync>, String> {
+    T::load(path, data, reader).map(|asset| Arc::new(asset) as _)
+}
+
+// where a worker thread puts the asset, shared by all handles of it
+struct Slot {
+    state: Mutex<SlotState>,
+    loaded: Condvar, // notified when state is no longer Loading
+    path: String,
+    load: LoadAny,
+}
+
+impl Slot {
+    // reads and decodes the asset on a worker thread, a failed reload keeps the loaded version
+    fn spawn_load(self: &Arc<Self>, reader: &AssetReader) {
+        let weak = Arc::downgrade(self);
+        let (path, load, reader) = (self.path.clone(), self.load, reader.clone());
+        rayon::spawn(move || {
+            // all handles were dropped before loading started
+            if weak.strong_count() == 0 {
+                return;
+            }
+            let asset = reader
+                .read(&path)
+                .and_then(|data| load(&path, data, &reader));
+            let Some(slot) = weak.upgrade() else {
+                return;
+            };
+            let mut state = slot.state.lock().unwrap();
+            let reload = matches!(*state, SlotState::Loaded(_));
+            *state = match asset {
+                Ok(asset) => {
+                    log::info!("{} {path}", if reload { "Reloaded" } else { "Loaded" });
+                    SlotState::Loaded(asset)
+                }
+                Err(e) if reload => {
+                    log::error!("Failed to reload {path}: {e}");
+                    return;
+                }
+                Err(e) => {
+                    log::error!("Failed to load {path}: {e}");
+                    SlotState::Failed(e)
+                }
+            };
+            slot.loaded.notify_all();
+        });
+    }
+}
+
+// A reference counted asset of type T. It is loaded once for all handles with the same path and
+// unloaded when the last of them is dropped.
+pub struct Handle<T> {
+    slot: Arc<Slot>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle {
+            slot: self.slot.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Asset> Handle<T> {
+    pub fn state(&self) -> LoadState {
+        match &*self.slot.state.lock().unwrap() {
+            SlotState::Loading => LoadState::Loading,
+            SlotState::Loaded(_) => LoadState::Loaded,
+            SlotState::Failed(e) => LoadState::Failed(e.clone()),
+        }
+    }
+
+    // None while the asset is loading or if it failed to load
+    pub fn get(&self) -> Option<Arc<T>> {
+        match &*self.slot.state.lock().unwrap() {
+            SlotState::Loaded(asset) => asset.clone().downcast::<T>().ok(),
+            _ => None,
+        }
+    }
+
+    // blocks until the asset is loaded or failed to load, for callers which can not wait for
+    // it frame by frame. On a rayon worker thread other jobs run meanwhile, so waiting there can
+    // not keep the load of the asset from running.
+    pub fn wait(&self) -> Option<Arc<T>> {
+        let mut state = self.slot.state.lock().unwrap();
+        while matches!(*state, SlotState::Loading) {
+            drop(state);
+            if rayon::yield_now() == Some(rayon::Yield::Executed) {
+                state = self.slot.state.lock().unwrap();
+                continue;
+            }
+            // the load is running on another thread or already done
+            state = self.slot.state.lock().unwrap();
+            if matches!(*state, SlotState::Loading) {
+                state = self.slot.loaded.wait(state).unwrap();
+            }
+        }
+        drop(state);
+        self.get()
+    }
+}
+
+// Reads files by path from the file system, on android from the assets of the apk. Builtin data
+// compiled into the engine is used for files which can not be read.
+#[derive(Clone, Default)]
+pub struct AssetReader {
+    builtins: Arc<HashMap<String, &'static [u8]>>,
+}
+
+impl AssetReader {
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, String> {
+        match read_file(path) {
+            Ok(data) => Ok(data),
+            Err(e) => match self.builtins.get(path) {
+                Some(data) => Ok(data.to_vec()),
+                None => Err(e.to_string()),
+            },
+        }
+    }
+}
+
+#[cfg(target_os = "android")]
+static ANDROID_APP: std::sync::OnceLock<winit::platform::android::activity::AndroidApp> =
+    std::sync::OnceLock::new();
+
+// files are read from the assets of the apk of app from now on
+#[cfg(target_os = "android")]
+pub fn set_android_app(app: winit::platform::android::activity::AndroidApp) {
+    let _ = ANDROID_APP.set(app);
+}
+
+// the file at path, on android the asset at path in the apk
+pub fn read_file(path: &str) -> std::io::Result<Vec<u8>> {
+    #[cfg(target_os = "android")]
+    {
+        use std::io::{Error, ErrorKind, Read};
+        let app = ANDROID_APP
+            .get()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "the android app is not set"))?;
+        let path =
+            std::ffi::CString::new(path).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        let mut asset = app
+            .asset_manager()
+            .open(&path)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "no such asset"))?;
+        let mut data = Vec::new();
+        asset.read_to_end(&mut data)?;
+        Ok(data)
+    }
+    #[cfg(not(target_os = "android"))]
+    std::fs::read(path)
+}
+
+// Loads assets by path on rayon worker threads into handles. Loading an asset which is already
+// loaded or loading returns another handle of it. Assets whose files change are reloaded into
+// their handles.
+#[derive(Unique, Default)]
+pub struct AssetServer {
+    reader: AssetReader,
+    slots: Mutex<HashMap<(TypeId, String), Weak<Slot>>>,
+    watcher: Mutex<FileWatcher<(TypeId, String)>>, // the files of the slots, the apk has none
+}
+
+impl AssetServer {
+    // the file is compiled into the engine, so it is available where files are not
+    pub fn add_builtin(&mut self, path: impl Into<String>, data: &'static [u8]) {
+        Arc::make_mut(&mut self.reader.builtins).insert(path.into(), data);
+    }
+
+    pub fn load<T: Asset>(&self, path: &str) -> Handle<T> {
+        let mut slots = self.slots.lock().unwrap();
+        let key = (TypeId::of::<T>(), path.to_string());
+        if let Some(slot) = slots.get(&key).and_then(Weak::upgrade) {
+            return Handle {
+                slot,
+                marker: PhantomData,
+            };
+        }
+        let slot = Arc::new(Slot {
+            state: Mutex::new(SlotState::Loading),
+            loaded: Condvar::new(),
+            path: path.to_string(),
+            load: load_any::<T>,
+        });
+        self.watcher.lock().unwrap().watch(key.clone(), path);
+        slots.insert(key, Arc::downgrade(&slot));
+        slot.spawn_load(&self.reader);
+        Handle {
+            slot,
+            marker: PhantomData,
+        }
+    }
+}
+
+// forgets the assets whose handles were all dropped, they are already unloaded,
+// and reloads the others if their files changed
+pub fn asset_server_system(asset_server: UniqueView<AssetServer>) {
+    let mut slots = asset_server.slots.lock().unwrap();
+    let mut watcher = asset_server.watcher.lock().unwrap();
+    slots.retain(|key, slot| {
+        let used = slot.strong_count() > 0;
+        if !used {
+            watcher.unwatch(key);
+        }
+        used
+    });
+    for key in watcher.changed() {
+        if let Some(slot) = slots.get(&key).and_then(Weak::upgrade) {
+            slot.spawn_load(&asset_server.reader);
+        }
+    }
+}
+
+// The handles a manager keeps by path. Paths which were not used since the last retain_used are
+// dropped, so their assets unload once no component refers to them any more.
+pub struct AssetCache<T> {
+    handles: HashMap<String, Handle<T>>,
+    used: HashSet<String>,
+}
+
+impl<T: Asset> AssetCache<T> {
+    pub fn new() -> Self {
+        AssetCache {
+            handles: HashMap::new(),
+            used: HashSet::new(),
+        }
+    }
+
+    // the asset at path once it is loaded, it starts loading when it is used the first time
+    pub fn get(&mut self, asset_server: &AssetServer, path: &str) -> Option<Arc<T>> {
+        if !self.used.contains(path) {
+            self.used.insert(path.to_string());
+        }
+        if let Some(handle) = self.handles.get(path) {
+            return handle.get();
+        }
+        let handle = asset_server.load::<T>(path);
+        let asset = handle.get();
+        self.handles.insert(path.to_string(), handle);
+        asset
+    }
+
+    // the asset at path if it is loaded, without starting to load it
+    pub fn loaded(&self, path: &str) -> Option<Arc<T>> {
+        self.handles.get(path).and_then(Handle::get)
+    }
+
+    pub fn retain_used(&mut self) {
+        let used = std::mem::take(&mut self.used);
+        self.handles.retain(|path, _| used.contains(path));
+    }
+}
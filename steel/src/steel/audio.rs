@@ -0,0 +1,654 @@
+use super::{
+    asset::{Asset, AssetCache, AssetReader, Handle, LoadState},
+    AssetServer, Camera2D, ComponentData, Edit, EngineBuilder, Plugin, Stage, Time, Transform2D,
+    Value, Variant,
+};
+use glam::Vec2;
+use shipyard::{
+    Component, EntityId, Get, IntoIter, IntoWithId, Unique, UniqueView, UniqueViewMut, View,
+    ViewMut,
+};
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    sync::{Arc, Mutex},
+};
+
+// sample rate of the mixer when there is no output device to ask
+const DEFAULT_SAMPLE_RATE: u32 = 48000;
+
+// AudioSource, the sounds it plays and the mixer which plays them
+pub struct AudioPlugin;
+
+impl Plugin for AudioPlugin {
+    fn build(&self, builder: &mut EngineBuilder) {
+        builder
+            .add_unique(AudioManager::new())
+            .add_component(AudioSource::default)
+            // after physics and scripts, so the pan follows where the entity is drawn
+            .add_system(Stage::PostPhysics, audio_update_system);
+    }
+}
+
+// Plays a sound file for its entity. Set playing to start or stop it, playing is reset when a
+// sound which does not loop ends. The sound stops when the component is removed.
+#[derive(Component, Debug)]
+#[track(All)]
+pub struct AudioSource {
+    pub sound: String, // path of a wav or ogg file
+    pub bus: String,   // the volumes of this bus and of the master bus scale the volume
+    pub volume: f32,
+    pub looping: bool,
+    pub playing: bool,
+    pub positional: bool, // pan by the position of the entity relative to the active camera
+}
+
+impl Default for AudioSource {
+    fn default() -> Self {
+        AudioSource {
+            sound: String::new(),
+            bus: MASTER_BUS.into(),
+            volume: 1.0,
+            looping: false,
+            playing: false,
+            positional: false,
+        }
+    }
+}
+
+impl Edit for AudioSource {
+    fn name() -> &'static str {
+        "AudioSource"
+    }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "sound",
+            value: Value::String(self.sound.clone()),
+        });
+        data.variants.push(Variant {
+            name: "bus",
+            value: Value::String(self.bus.clone()),
+        });
+        data.variants.push(Variant {
+            name: "volume",
+            value: Value::Float32(self.volume),
+        });
+        data.variants.push(Variant {
+            name: "looping",
+            value: Value::Bool(self.looping),
+        });
+        data.variants.push(Variant {
+            name: "playing",
+            value: Value::Bool(self.playing),
+        });
+        data.variants.push(Variant {
+            name: "positional",
+            value: Value::Bool(self.positional),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            match (v.name, v.value) {
+                ("sound", Value::String(sound)) => self.sound = sound,
+                ("bus", Value::String(bus)) => self.bus = bus,
+                ("volume", Value::Float32(volume)) => self.volume = volume,
+                ("looping", Value::Bool(looping)) => self.looping = looping,
+                ("playing", Value::Bool(playing)) => self.playing = playing,
+                ("positional", Value::Bool(positional)) => self.positional = positional,
+                _ => (),
+            }
+        }
+    }
+}
+
+// the bus every sound goes through, its volume scales all of them
+const MASTER_BUS: &str = "master";
+
+// the decoded samples of a sound file
+struct Sound {
+    sample_rate: u32,
+    channels: usize,
+    samples: Vec<f32>, // interleaved
+}
+
+impl Asset for Sound {
+    fn load(path: &str, data: Vec<u8>, _reader: &AssetReader) -> Result<Self, String> {
+        Sound::decode(path, &data)
+    }
+}
+
+impl Sound {
+    // wav files with integer or float samples and ogg vorbis files, chosen by the extension
+    fn decode(path: &str, data: &[u8]) -> Result<Sound, String> {
+        let sound = if path.ends_with(".ogg") {
+            let mut reader = lewton::inside_ogg::OggStreamReader::new(Cursor::new(data))
+                .map_err(|e| e.to_string())?;
+            let mut samples = Vec::new();
+            while let Some(packet) = reader.read_dec_packet_itl().map_err(|e| e.to_string())? {
+                samples.extend(packet.into_iter().map(|s| s as f32 / 32768.0));
+            }
+            Sound {
+                sample_rate: reader.ident_hdr.audio_sample_rate,
+                channels: reader.ident_hdr.audio_channels as usize,
+                samples,
+            }
+        } else {
+            let mut reader = hound::WavReader::new(Cursor::new(data)).map_err(|e| e.to_string())?;
+            let spec = reader.spec();
+            let samples = match spec.sample_format {
+                hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>(),
+                hound::SampleFormat::Int => {
+                    let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+                    reader
+                        .samples::<i32>()
+                        .map(|s| s.map(|s| s as f32 * scale))
+                        .collect()
+                }
+            }
+            .map_err(|e| e.to_string())?;
+            Sound {
+                sample_rate: spec.sample_rate,
+                channels: spec.channels as usize,
+                samples,
+            }
+        };
+        if sound.channels == 0 || sound.sample_rate == 0 {
+            return Err("no channels".into());
+        }
+        Ok(sound)
+    }
+
+    fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    // left and right sample, mono sounds play on both, channels after the second are ignored
+    fn frame(&self, index: usize) -> (f32, f32) {
+        let i = index * self.channels;
+        if self.channels == 1 {
+            (self.samples[i], self.samples[i])
+        } else {
+            (self.samples[i], self.samples[i + 1])
+        }
+    }
+}
+
+// a sound being played by the mixer
+struct Voice {
+    id: u64,
+    sound: Arc<Sound>,
+    position: f64, // frames of the sound already played, fractional when the sample rates differ
+    volume: f32,
+    pan: f32, // -1 is left, 1 is right
+    bus: String,
+    looping: bool,
+}
+
+impl Voice {
+    fn ended(&self) -> bool {
+        !self.looping && self.position >= self.sound.frames() as f64
+    }
+}
+
+// Mixes the playing sounds into stereo samples. The output device pulls the samples from it,
+// without a device they are rendered by AudioManager::render.
+struct Mixer {
+    sample_rate: u32,
+    voices: Vec<Voice>,
+    next_id: u64,
+    buses: HashMap<String, f32>, // buses which are not in here have volume 1
+}
+
+impl Mixer {
+    fn new(sample_rate: u32) -> Self {
+        Mixer {
+            sample_rate,
+            voices: Vec::new(),
+            next_id: 0,
+            buses: HashMap::new(),
+        }
+    }
+
+    fn play(&mut self, sound: Arc<Sound>, bus: &str, volume: f32, pan: f32, looping: bool) -> u64 {
+        self.next_id += 1;
+        self.voices.push(Voice {
+            id: self.next_id,
+            sound,
+            position: 0.0,
+            volume,
+            pan,
+            bus: bus.into(),
+            looping,
+        });
+        self.next_id
+    }
+
+    fn stop(&mut self, id: u64) {
+        self.voices.retain(|voice| voice.id != id);
+    }
+
+    // None once the voice ended or was stopped
+    fn voice_mut(&mut self, id: u64) -> Option<&mut Voice> {
+        self.voices.iter_mut().find(|voice| voice.id == id)
+    }
+
+    fn bus_volume(&self, bus: &str) -> f32 {
+        self.buses.get(bus).copied().unwrap_or(1.0)
+    }
+
+    // writes the next frames into interleaved stereo samples and removes the voices which ended
+    fn mix(&mut self, output: &mut [f32]) {
+        output.fill(0.0);
+        let master = self.bus_volume(MASTER_BUS);
+        for voice in &mut self.voices {
+            let frames = voice.sound.frames();
+            if frames == 0 {
+                voice.looping = false;
+                continue;
+            }
+            let mut gain = voice.volume * master;
+            if voice.bus != MASTER_BUS {
+                gain *= self.buses.get(&voice.bus).copied().unwrap_or(1.0);
+            }
+            // balance, a centered sound keeps its full volume on both sides
+            let pan = voice.pan.clamp(-1.0, 1.0);
+            let left = gain * (1.0 - pan).min(1.0);
+            let right = gain * (1.0 + pan).min(1.0);
+            let step = voice.sound.sample_rate as f64 / self.sample_rate as f64;
+            for out in output.chunks_exact_mut(2) {
+                if voice.position >= frames as f64 {
+                    if !voice.looping {
+                        break;
+                    }
+                    voice.position %= frames as f64;
+                }
+                // linear interpolation resamples to the rate of the mixer
+                let index = voice.position as usize;
+                let t = (voice.position - index as f64) as f32;
+                let next = if index + 1 < frames {
+                    index + 1
+                } else if voice.looping {
+                    0
+                } else {
+                    index
+                };
+                let (l0, r0) = voice.sound.frame(index);
+                let (l1, r1) = voice.sound.frame(next);
+                out[0] += (l0 + (l1 - l0) * t) * left;
+                out[1] += (r0 + (r1 - r0) * t) * right;
+                voice.position += step;
+            }
+        }
+        self.voices.retain(|voice| !voice.ended());
+        for sample in output {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+    }
+}
+
+// Where the mixed samples go. Without an output device they are rendered and dropped as time
+// passes, so sounds still end and AudioSource::playing is reset like with a device.
+enum AudioOutput {
+    Null {
+        pending: f64, // frames which were not rendered yet, less than one
+    },
+    #[cfg(feature = "audio-device")]
+    Device {
+        _stop: std::sync::mpsc::Sender<()>, // the thread which owns the stream ends when this is dropped
+    },
+}
+
+// a sound of AudioManager::play which may still be loading
+struct OneShot {
+    sound: Handle<Sound>,
+    bus: String,
+    volume: f32,
+    pan: f32,
+}
+
+// Sounds of the AudioSources by path, the mixer and its output
+#[derive(Unique)]
+pub struct AudioManager {
+    sounds: AssetCache<Sound>,
+    one_shots: Vec<OneShot>,
+    mixer: Arc<Mutex<Mixer>>,
+    output: AudioOutput,
+    sources: HashMap<EntityId, (u64, String)>, // voice and sound of the playing AudioSources
+}
+
+impl AudioManager {
+    // plays on the default output device with the audio-device feature if there is one,
+    // otherwise falls back to the null output
+    pub fn new() -> Self {
+        #[cfg(feature = "audio-device")]
+        {
+            let mixer = Arc::new(Mutex::new(Mixer::new(DEFAULT_SAMPLE_RATE)));
+            match open_device(mixer.clone()) {
+                Ok(stop) => return Self::with_output(mixer, AudioOutput::Device { _stop: stop }),
+                Err(e) => {
+                    log::error!("Failed to open the audio output device, sounds are muted: {e}")
+                }
+            }
+        }
+        Self::null(DEFAULT_SAMPLE_RATE)
+    }
+
+    // no output device, nothing is heard, render returns what would be heard
+    pub fn null(sample_rate: u32) -> Self {
+        Self::with_output(
+            Arc::new(Mutex::new(Mixer::new(sample_rate))),
+            AudioOutput::Null { pending: 0.0 },
+        )
+    }
+
+    fn with_output(mixer: Arc<Mutex<Mixer>>, output: AudioOutput) -> Self {
+        AudioManager {
+            sounds: AssetCache::new(),
+            one_shots: Vec::new(),
+            mixer,
+            output,
+            sources: HashMap::new(),
+        }
+    }
+
+    // plays the sound once as soon as it is loaded, pan is -1 for left and 1 for right
+    pub fn play(
+        &mut self,
+        asset_server: &AssetServer,
+        path: &str,
+        bus: &str,
+        volume: f32,
+        pan: f32,
+    ) {
+        self.one_shots.push(OneShot {
+            sound: asset_server.load(path),
+            bus: bus.into(),
+            volume,
+            pan,
+        });
+    }
+
+    pub fn set_bus_volume(&mut self, bus: impl Into<String>, volume: f32) {
+        self.mixer.lock().unwrap().buses.insert(bus.into(), volume);
+    }
+
+    // the next frames of the mix as interleaved stereo samples at the sample rate of the mixer,
+    // only the null output may call it, a device takes the frames itself
+    pub fn render(&mut self, frames: usize) -> Vec<f32> {
+        let mut output = vec![0.0; frames * 2];
+        self.mixer.lock().unwrap().mix(&mut output);
+        output
+    }
+
+    // renders the frames of delta seconds with the null output
+    fn advance(&mut self, delta: f32) {
+        let sample_rate = self.mixer.lock().unwrap().sample_rate;
+        let frames = match &mut self.output {
+            AudioOutput::Null { pending } => {
+                *pending += delta as f64 * sample_rate as f64;
+                let frames = *pending as usize;
+                *pending -= frames as f64;
+                frames
+            }
+            #[cfg(feature = "audio-device")]
+            AudioOutput::Device { .. } => return,
+        };
+        self.render(frames);
+    }
+}
+
+// runs the output stream on its own thread, streams can not be sent between threads on every platform
+#[cfg(feature = "audio-device")]
+fn open_device(mixer: Arc<Mutex<Mixer>>) -> Result<std::sync::mpsc::Sender<()>, String> {
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+    let (result_sender, result_receiver) = std::sync::mpsc::channel();
+    let (stop_sender, stop_receiver) = std::sync::mpsc::channel::<()>();
+    std::thread::spawn(move || {
+        let stream = (|| -> Result<cpal::Stream, String> {
+            let device = cpal::default_host()
+                .default_output_device()
+                .ok_or("no output device")?;
+            let config = device.default_output_config().map_err(|e| e.to_string())?;
+            let channels = config.channels() as usize;
+            mixer.lock().unwrap().sample_rate = config.sample_rate().0;
+            let mut stereo = Vec::new();
+            let stream = device
+                .build_output_stream::<f32, _, _>(
+                    &config.config(),
+                    move |data, _| {
+                        stereo.resize(data.len() / channels * 2, 0.0);
+                        mixer.lock().unwrap().mix(&mut stereo);
+                        for (frame, stereo) in
+                            data.chunks_exact_mut(channels).zip(stereo.chunks_exact(2))
+                        {
+                            if channels == 1 {
+                                frame[0] = (stereo[0] + stereo[1]) * 0.5;
+                            } else {
+                                frame[..2].copy_from_slice(stereo);
+                                frame[2..].fill(0.0);
+                            }
+                        }
+                    },
+                    |e| log::error!("Audio output error: {e}"),
+                    None,
+                )
+                .map_err(|e| e.to_string())?;
+            stream.play().map_err(|e| e.to_string())?;
+            Ok(stream)
+        })();
+        match stream {
+            Ok(stream) => {
+                let _ = result_sender.send(Ok(()));
+                // blocks until the AudioManager is dropped
+                let _ = stop_receiver.recv();
+                drop(stream);
+            }
+            Err(e) => {
+                let _ = result_sender.send(Err(e));
+            }
+        }
+    });
+    result_receiver
+        .recv()
+        .map_err(|e| e.to_string())?
+        .map(|_| stop_sender)
+}
+
+// pan of a sound at position, relative to the active camera, which is the one drawn first
+pub fn listener_pan(camera2d: &View<Camera2D>, position: Vec2) -> f32 {
+    let Some(camera) = camera2d.iter().min_by_key(|camera| camera.order) else {
+        return 0.0;
+    };
+    let relative = Vec2::from_angle(-camera.rotation).rotate(position - camera.position);
+    // full pan at about the edge of the view
+    (relative.x * 2.0 * camera.zoom / camera.height).clamp(-1.0, 1.0)
+}
+
+// starts the loaded one shot sounds and starts, updates and stops the voices of the AudioSources
+fn audio_update_system(
+    asset_server: UniqueView<AssetServer>,
+    time: UniqueView<Time>,
+    transform2d: View<Transform2D>,
+    camera2d: View<Camera2D>,
+    mut audio_source: ViewMut<AudioSource>,
+    mut audio_manager: UniqueViewMut<AudioManager>,
+) {
+    let audio_manager = audio_manager.as_mut();
+    let mixer = audio_manager.mixer.clone();
+    let mut mixer = mixer.lock().unwrap();
+    audio_manager.sources.retain(|e, (voice, _)| {
+        let keep = audio_source.contains(*e);
+        if !keep {
+            mixer.stop(*voice);
+        }
+        keep
+    });
+    audio_manager
+        .one_shots
+        .retain(|one_shot| match one_shot.sound.state() {
+            LoadState::Loading => true,
+            LoadState::Loaded => {
+                let sound = one_shot.sound.get().unwrap();
+                mixer.play(sound, &one_shot.bus, one_shot.volume, one_shot.pan, false);
+                false
+            }
+            // the AssetServer logs why it failed
+            LoadState::Failed(_) => false,
+        });
+
+    let mut ended = Vec::new();
+    for (e, source) in audio_source.iter().with_id() {
+        // sounds load before they play, and stay loaded while their sources exist
+        let sound = audio_manager.sounds.get(&asset_server, &source.sound);
+        let pan = match transform2d.get(e) {
+            Ok(transform2d) if source.positional => {
+                listener_pan(&camera2d, transform2d.position.truncate())
+            }
+            _ => 0.0,
+        };
+        if let Some((voice, sound)) = audio_manager.sources.get(&e) {
+            if !source.playing || *sound != source.sound {
+                mixer.stop(*voice);
+                audio_manager.sources.remove(&e);
+            } else if let Some(voice) = mixer.voice_mut(*voice) {
+                voice.volume = source.volume;
+                voice.pan = pan;
+                voice.looping = source.looping;
+                voice.bus.clone_from(&source.bus);
+                continue;
+            } else {
+                audio_manager.sources.remove(&e);
+                ended.push(e);
+                continue;
+            }
+        }
+        if source.playing {
+            if let Some(sound) = sound {
+                let voice = mixer.play(sound, &source.bus, source.volume, pan, source.looping);
+                audio_manager
+                    .sources
+                    .insert(e, (voice, source.sound.clone()));
+            }
+        }
+    }
+    for e in ended {
+        (&mut audio_source).get(e).unwrap().playing = false;
+    }
+    audio_manager.sounds.retain_used();
+
+    drop(mixer);
+    audio_manager.advance(time.delta);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sound(sample_rate: u32, channels: usize, samples: &[f32]) -> Arc<Sound> {
+        Arc::new(Sound {
+            sample_rate,
+            channels,
+            samples: samples.to_vec(),
+        })
+    }
+
+    fn mix(mixer: &mut Mixer, frames: usize) -> Vec<f32> {
+        let mut output = vec![0.0; frames * 2];
+        mixer.mix(&mut output);
+        output
+    }
+
+    #[test]
+    fn bus_and_master_volume_scale_voices() {
+        let mut mixer = Mixer::new(100);
+        mixer.buses.insert(MASTER_BUS.into(), 0.5);
+        mixer.buses.insert("sfx".into(), 0.5);
+        mixer.play(sound(100, 1, &[1.0; 4]), "sfx", 0.5, 0.0, false);
+        mixer.play(sound(100, 1, &[1.0; 4]), MASTER_BUS, 0.5, 0.0, false);
+        assert_eq!(mix(&mut mixer, 2), [0.375; 4]);
+    }
+
+    #[test]
+    fn pan_keeps_the_near_side_and_fades_the_far_side() {
+        let mut mixer = Mixer::new(100);
+        let id = mixer.play(sound(100, 2, &[0.5; 8]), MASTER_BUS, 1.0, -1.0, false);
+        assert_eq!(mix(&mut mixer, 1), [0.5, 0.0]);
+        mixer.voice_mut(id).unwrap().pan = 0.5;
+        assert_eq!(mix(&mut mixer, 1), [0.25, 0.5]);
+        mixer.voice_mut(id).unwrap().pan = 2.0;
+        assert_eq!(mix(&mut mixer, 1), [0.0, 0.5]);
+    }
+
+    #[test]
+    fn sounds_are_resampled_to_the_rate_of_the_mixer() {
+        let mut mixer = Mixer::new(100);
+        let id = mixer.play(sound(50, 1, &[0.0, 0.5, 1.0]), MASTER_BUS, 1.0, 0.0, false);
+        let output = mix(&mut mixer, 4);
+        assert_eq!(output, [0.0, 0.0, 0.25, 0.25, 0.5, 0.5, 0.75, 0.75]);
+        assert_eq!(mixer.voice_mut(id).unwrap().position, 2.0);
+    }
+
+    #[test]
+    fn looping_voices_wrap_around() {
+        let mut mixer = Mixer::new(100);
+        let id = mixer.play(
+            sound(100, 1, &[0.25, 0.5, 0.75]),
+            MASTER_BUS,
+            1.0,
+            0.0,
+            true,
+        );
+        let output = mix(&mut mixer, 7);
+        let left = output.iter().step_by(2).copied().collect::<Vec<_>>();
+        assert_eq!(left, [0.25, 0.5, 0.75, 0.25, 0.5, 0.75, 0.25]);
+        assert_eq!(mixer.voice_mut(id).unwrap().position, 1.0);
+    }
+
+    #[test]
+    fn looping_voices_interpolate_across_the_wrap() {
+        let mut mixer = Mixer::new(100);
+        mixer.play(sound(50, 1, &[0.0, 1.0]), MASTER_BUS, 1.0, 0.0, true);
+        let output = mix(&mut mixer, 5);
+        let left = output.iter().step_by(2).copied().collect::<Vec<_>>();
+        assert_eq!(left, [0.0, 0.5, 1.0, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn finished_voices_are_removed() {
+        let mut audio_manager = AudioManager::null(100);
+        let id = audio_manager.mixer.lock().unwrap().play(
+            sound(100, 1, &[0.5, 0.25]),
+            MASTER_BUS,
+            1.0,
+            0.0,
+            false,
+        );
+        let output = audio_manager.render(3);
+        assert_eq!(output, [0.5, 0.5, 0.25, 0.25, 0.0, 0.0]);
+        let mut mixer = audio_manager.mixer.lock().unwrap();
+        assert!(mixer.voice_mut(id).is_none());
+        assert!(mixer.voices.is_empty());
+    }
+
+    #[test]
+    fn voices_which_end_mid_render_are_removed_after_it() {
+        let mut audio_manager = AudioManager::null(100);
+        audio_manager.set_bus_volume(MASTER_BUS, 0.5);
+        let id = audio_manager.mixer.lock().unwrap().play(
+            sound(100, 1, &[1.0; 4]),
+            MASTER_BUS,
+            1.0,
+            0.0,
+            false,
+        );
+        assert_eq!(audio_manager.render(2), [0.5; 4]);
+        assert!(audio_manager.mixer.lock().unwrap().voice_mut(id).is_some());
+        assert_eq!(audio_manager.render(3), [0.5, 0.5, 0.5, 0.5, 0.0, 0.0]);
+        assert!(audio_manager.mixer.lock().unwrap().voice_mut(id).is_none());
+    }
+}
@@ -0,0 +1,468 @@
+use super::TextureImage;
+use glam::Vec2;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+// frames which are shorter are stretched to this, so a frame always takes some time
+const MIN_FRAME_DURATION: f32 = 0.001;
+
+// What happens when a clip reaches its last frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopMode {
+    Once,     // stay on the last frame
+    Loop,     // start again from the first frame
+    PingPong, // play backwards to the first frame, then forwards again
+}
+
+impl LoopMode {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Once" => Some(LoopMode::Once),
+            "Loop" => Some(LoopMode::Loop),
+            "PingPong" => Some(LoopMode::PingPong),
+            _ => None,
+        }
+    }
+}
+
+// Easing curves of tweens, they map 0..1 to 0..1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    BackOut,   // overshoots the end a little and comes back
+    BounceOut, // bounces on the end like a dropped ball
+}
+
+impl Easing {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Linear" => Some(Easing::Linear),
+            "QuadIn" => Some(Easing::QuadIn),
+            "QuadOut" => Some(Easing::QuadOut),
+            "QuadInOut" => Some(Easing::QuadInOut),
+            "CubicIn" => Some(Easing::CubicIn),
+            "CubicOut" => Some(Easing::CubicOut),
+            "CubicInOut" => Some(Easing::CubicInOut),
+            "SineIn" => Some(Easing::SineIn),
+            "SineOut" => Some(Easing::SineOut),
+            "SineInOut" => Some(Easing::SineInOut),
+            "BackOut" => Some(Easing::BackOut),
+            "BounceOut" => Some(Easing::BounceOut),
+            _ => None,
+        }
+    }
+
+    pub fn apply(self, t: f32) -> f32 {
+        use std::f32::consts::PI;
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut if t < 0.5 => 2.0 * t * t,
+            Easing::QuadInOut => 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0,
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::CubicInOut => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+            Easing::SineIn => 1.0 - (t * PI / 2.0).cos(),
+            Easing::SineOut => (t * PI / 2.0).sin(),
+            Easing::SineInOut => -((t * PI).cos() - 1.0) / 2.0,
+            Easing::BackOut => {
+                let (c1, c3) = (1.70158, 2.70158);
+                1.0 + c3 * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2)
+            }
+            Easing::BounceOut => {
+                let (n1, d1) = (7.5625, 2.75);
+                if t < 1.0 / d1 {
+                    n1 * t * t
+                } else if t < 2.0 / d1 {
+                    let t = t - 1.5 / d1;
+                    n1 * t * t + 0.75
+                } else if t < 2.5 / d1 {
+                    let t = t - 2.25 / d1;
+                    n1 * t * t + 0.9375
+                } else {
+                    let t = t - 2.625 / d1;
+                    n1 * t * t + 0.984375
+                }
+            }
+        }
+    }
+}
+
+// the Transform2D property a tween changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TweenProperty {
+    Position, // added to the position when the clip started
+    Rotation, // x is added to the rotation when the clip started, in radians
+    Scale,    // multiplied with the scale when the clip started
+}
+
+// Changes a property from one value to another during a part of a clip. After its end the
+// tween keeps the property at its last value until a later tween of the property starts.
+#[derive(Debug, Clone)]
+pub struct Tween {
+    pub property: TweenProperty,
+    pub from: Vec2,
+    pub to: Vec2,
+    pub start: f32, // seconds from the start of the clip
+    pub duration: f32,
+    pub easing: Easing,
+}
+
+impl Tween {
+    // the value at time seconds into the clip, None before the tween starts
+    pub fn value(&self, time: f32) -> Option<Vec2> {
+        if time < self.start {
+            return None;
+        }
+        let t = if self.duration > 0.0 {
+            (time - self.start) / self.duration
+        } else {
+            1.0
+        };
+        Some(self.from.lerp(self.to, self.easing.apply(t)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationFrame {
+    pub uv_min: Vec2, // the top left of the frame in the atlas
+    pub uv_max: Vec2,
+    pub duration: f32, // seconds
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationClip {
+    pub frames: Vec<AnimationFrame>, // never empty
+    pub mode: LoopMode,
+    pub events: Vec<(usize, String)>, // fired whenever the frame with this index starts
+    pub tweens: Vec<Tween>,
+}
+
+impl AnimationClip {
+    // seconds from the start of the clip to the start of the frame
+    pub fn frame_start(&self, frame: usize) -> f32 {
+        self.frames[..frame]
+            .iter()
+            .map(|frame| frame.duration)
+            .sum()
+    }
+}
+
+// The clips of a clip file and the sprite atlas their frames are in
+pub struct AnimationClips {
+    pub atlas: TextureImage,
+    pub normal_map: Option<TextureImage>, // with the layout of the atlas, for Light2DPlugin
+    pub clips: HashMap<String, AnimationClip>,
+}
+
+// A rectangle of the atlas in pixels and how long it is shown
+struct FrameRect {
+    position: Vec2,
+    size: Vec2,
+    duration: f32,
+}
+
+// Reads a steel clip file or an Aseprite JSON export at path, the atlas image is relative to it
+// and load_atlas decodes it. A steel clip file looks like this:
+// {
+//   "atlas": "coin.png",
+//   "normal_map": "coin_normal.png", // optional, lights the frames per pixel
+//   "frame_size": [16, 16], // the atlas is a grid of frames, numbered row by row
+//   "clips": {
+//     "spin": {
+//       "frames": [0, 1, 2, 1],
+//       "duration": 0.1, // of every frame, or "durations": [0.3, 0.1, 0.1, 0.1]
+//       "mode": "Loop", // Once, Loop or PingPong
+//       "events": [{ "frame": 0, "name": "shine" }],
+//       "tweens": [{ "property": "Position", "from": [0, 0], "to": [0, 1],
+//                    "start": 0.0, "duration": 0.5, "easing": "SineOut" }]
+//     }
+//   }
+// }
+// Each frame tag of an Aseprite export becomes a clip, forward and reverse tags loop unless they
+// repeat once, a sprite without tags has one looping clip named "default". Hash exports are
+// ordered by the number in their frame names.
+pub fn parse_clips(
+    path: &str,
+    data: &[u8],
+    load_atlas: impl Fn(&str) -> Option<TextureImage>,
+) -> Result<AnimationClips, String> {
+    let json: JsonValue = serde_json::from_slice(data).map_err(|e| e.to_string())?;
+    if json["meta"].is_object() {
+        parse_aseprite(path, &json, load_atlas)
+    } else {
+        parse_steel(path, &json, load_atlas)
+    }
+}
+
+// path relative to the directory of the file at base
+pub fn relative_to(base: &str, path: &str) -> String {
+    match base.rfind('/') {
+        Some(i) => format!("{}/{path}", &base[..i]),
+        None => path.to_string(),
+    }
+}
+
+fn json_f32(value: &JsonValue, name: &str) -> Result<f32, String> {
+    value[name]
+        .as_f64()
+        .map(|v| v as f32)
+        .ok_or_else(|| format!("{name} is missing or not a number"))
+}
+
+fn json_vec2(value: &JsonValue, name: &str) -> Result<Vec2, String> {
+    match value[name].as_array().map(|array| array.as_slice()) {
+        Some([x, y]) => match (x.as_f64(), y.as_f64()) {
+            (Some(x), Some(y)) => Ok(Vec2::new(x as f32, y as f32)),
+            _ => Err(format!("{name} is not an array of 2 numbers")),
+        },
+        _ => Err(format!("{name} is missing or not an array of 2 numbers")),
+    }
+}
+
+fn load(
+    path: &str,
+    image: &str,
+    load_atlas: impl Fn(&str) -> Option<TextureImage>,
+) -> Result<TextureImage, String> {
+    let image = relative_to(path, image);
+    load_atlas(&image).ok_or(format!("failed to load the image {image}"))
+}
+
+fn frames(atlas: &TextureImage, rects: impl IntoIterator<Item = FrameRect>) -> Vec<AnimationFrame> {
+    let atlas_size = Vec2::new(atlas.width as f32, atlas.height as f32);
+    rects
+        .into_iter()
+        .map(|rect| AnimationFrame {
+            uv_min: rect.position / atlas_size,
+            uv_max: (rect.position + rect.size) / atlas_size,
+            duration: rect.duration.max(MIN_FRAME_DURATION),
+        })
+        .collect()
+}
+
+fn parse_steel(
+    path: &str,
+    json: &JsonValue,
+    load_atlas: impl Fn(&str) -> Option<TextureImage>,
+) -> Result<AnimationClips, String> {
+    let atlas = json["atlas"].as_str().ok_or("the atlas is missing")?;
+    let atlas = load(path, atlas, &load_atlas)?;
+    let normal_map = json["normal_map"]
+        .as_str()
+        .map(|normal_map| load(path, normal_map, &load_atlas))
+        .transpose()?;
+    let frame_size = json_vec2(json, "frame_size")?;
+    let columns = (atlas.width as f32 / frame_size.x).floor().max(1.0) as usize;
+
+    let mut clips = HashMap::new();
+    for (name, clip) in json["clips"].as_object().ok_or("the clips are missing")? {
+        let error = |e: String| format!("clip {name}: {e}");
+        let indices = clip["frames"]
+            .as_array()
+            .ok_or("the frames are missing".to_string())
+            .and_then(|frames| {
+                frames
+                    .iter()
+                    .map(|i| i.as_u64().map(|i| i as usize))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or("a frame is not an index".to_string())
+            })
+            .map_err(error)?;
+        if indices.is_empty() {
+            return Err(error("it has no frames".into()));
+        }
+        let durations = match clip["durations"].as_array() {
+            Some(durations) if durations.len() == indices.len() => durations
+                .iter()
+                .map(|d| d.as_f64().map(|d| d as f32))
+                .collect::<Option<Vec<_>>>()
+                .ok_or(error("a duration is not a number".into()))?,
+            Some(_) => return Err(error("it needs one duration per frame".into())),
+            None => vec![json_f32(clip, "duration").map_err(error)?; indices.len()],
+        };
+        let mode = match clip["mode"].as_str() {
+            Some(mode) => LoopMode::from_name(mode).ok_or(error(format!("unknown mode {mode}")))?,
+            None => LoopMode::Loop,
+        };
+        let mut events = Vec::new();
+        for event in clip["events"].as_array().into_iter().flatten() {
+            let frame = event["frame"]
+                .as_u64()
+                .filter(|frame| (*frame as usize) < indices.len())
+                .ok_or(error("an event has no valid frame".into()))?;
+            let event_name = event["name"]
+                .as_str()
+                .ok_or(error("an event has no name".into()))?;
+            events.push((frame as usize, event_name.to_string()));
+        }
+        let mut tweens = Vec::new();
+        for tween in clip["tweens"].as_array().into_iter().flatten() {
+            tweens.push(parse_tween(tween).map_err(error)?);
+        }
+        let rects = indices
+            .iter()
+            .zip(durations)
+            .map(|(i, duration)| FrameRect {
+                position: Vec2::new((i % columns) as f32, (i / columns) as f32) * frame_size,
+                size: frame_size,
+                duration,
+            });
+        clips.insert(
+            name.clone(),
+            AnimationClip {
+                frames: frames(&atlas, rects),
+                mode,
+                events,
+                tweens,
+            },
+        );
+    }
+    Ok(AnimationClips {
+        atlas,
+        normal_map,
+        clips,
+    })
+}
+
+fn parse_tween(tween: &JsonValue) -> Result<Tween, String> {
+    let property = match tween["property"].as_str() {
+        Some("Position") => TweenProperty::Position,
+        Some("Rotation") => TweenProperty::Rotation,
+        Some("Scale") => TweenProperty::Scale,
+        _ => return Err("a tween has no valid property".into()),
+    };
+    // rotations are single numbers
+    let value = |name| match (property, tween[name].as_f64()) {
+        (TweenProperty::Rotation, Some(rotation)) => Ok(Vec2::new(rotation as f32, 0.0)),
+        _ => json_vec2(tween, name),
+    };
+    let easing = match tween["easing"].as_str() {
+        Some(easing) => Easing::from_name(easing).ok_or(format!("unknown easing {easing}"))?,
+        None => Easing::Linear,
+    };
+    Ok(Tween {
+        property,
+        from: value("from")?,
+        to: value("to")?,
+        start: tween["start"].as_f64().unwrap_or(0.0) as f32,
+        duration: json_f32(tween, "duration")?,
+        easing,
+    })
+}
+
+// the number at the end of an Aseprite frame name like "coin 3.aseprite"
+fn frame_number(name: &str) -> Option<u64> {
+    let name = name.rsplit_once('.').map_or(name, |(name, _)| name);
+    let digits = name.len() - name.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    name[name.len() - digits..].parse().ok()
+}
+
+fn aseprite_frame(frame: &JsonValue) -> Result<FrameRect, String> {
+    let rect = &frame["frame"];
+    Ok(FrameRect {
+        position: Vec2::new(json_f32(rect, "x")?, json_f32(rect, "y")?),
+        size: Vec2::new(json_f32(rect, "w")?, json_f32(rect, "h")?),
+        // milliseconds
+        duration: frame["duration"].as_f64().unwrap_or(100.0) as f32 / 1000.0,
+    })
+}
+
+fn parse_aseprite(
+    path: &str,
+    json: &JsonValue,
+    load_atlas: impl Fn(&str) -> Option<TextureImage>,
+) -> Result<AnimationClips, String> {
+    let meta = &json["meta"];
+    let image = meta["image"].as_str().ok_or("the image is missing")?;
+    let atlas = load(path, image, load_atlas)?;
+    let rects = match &json["frames"] {
+        JsonValue::Array(frames) => frames
+            .iter()
+            .map(aseprite_frame)
+            .collect::<Result<Vec<_>, _>>()?,
+        JsonValue::Object(frames) => {
+            let mut frames = frames.iter().collect::<Vec<_>>();
+            frames.sort_by_key(|(name, _)| frame_number(name));
+            frames
+                .into_iter()
+                .map(|(_, frame)| aseprite_frame(frame))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+        _ => return Err("the frames are missing".into()),
+    };
+    if rects.is_empty() {
+        return Err("the sprite has no frames".into());
+    }
+    let all = frames(&atlas, rects);
+
+    let mut clips = HashMap::new();
+    let tags = meta["frameTags"].as_array().cloned().unwrap_or_default();
+    if tags.is_empty() {
+        clips.insert(
+            "default".to_string(),
+            AnimationClip {
+                frames: all,
+                mode: LoopMode::Loop,
+                events: Vec::new(),
+                tweens: Vec::new(),
+            },
+        );
+        return Ok(AnimationClips {
+            atlas,
+            normal_map: None,
+            clips,
+        });
+    }
+    for tag in tags {
+        let name = tag["name"].as_str().ok_or("a frame tag has no name")?;
+        let (from, to) = (tag["from"].as_u64(), tag["to"].as_u64());
+        let (Some(from), Some(to)) = (from, to) else {
+            return Err(format!("frame tag {name} has no from or to"));
+        };
+        let mut frames = all
+            .get(from as usize..=to as usize)
+            .ok_or(format!("frame tag {name} is out of the frames"))?
+            .to_vec();
+        let direction = tag["direction"].as_str().unwrap_or("forward");
+        if direction.ends_with("reverse") {
+            frames.reverse();
+        }
+        // newer versions write repeat as a string
+        let repeat = match &tag["repeat"] {
+            JsonValue::String(repeat) => repeat.parse().ok(),
+            repeat => repeat.as_u64(),
+        };
+        let mode = match (direction, repeat) {
+            ("pingpong" | "pingpong_reverse", _) => LoopMode::PingPong,
+            (_, Some(1)) => LoopMode::Once,
+            _ => LoopMode::Loop,
+        };
+        clips.insert(
+            name.to_string(),
+            AnimationClip {
+                frames,
+                mode,
+                events: Vec::new(),
+                tweens: Vec::new(),
+            },
+        );
+    }
+    Ok(AnimationClips {
+        atlas,
+        normal_map: None,
+        clips,
+    })
+}
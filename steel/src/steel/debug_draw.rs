@@ -0,0 +1,322 @@
+use super::{
+    camera2d_extract_system, EngineBuilder, Line2D, Physics2DManager, Plugin, RenderInfo,
+    RenderList, RenderPass, Shader, Stage,
+};
+use glam::{Vec2, Vec4};
+use rapier2d::prelude::*;
+use shader_library::include_spirv;
+use shipyard::{SystemModificator, Unique, UniqueView, UniqueViewMut};
+use std::f32::consts::TAU;
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{
+        graphics::{
+            color_blend::{ColorBlendAttachmentState, ColorBlendState},
+            input_assembly::{InputAssemblyState, PrimitiveTopology},
+            multisample::MultisampleState,
+            rasterization::RasterizationState,
+            vertex_input::{Vertex, VertexDefinition},
+            viewport::{Viewport, ViewportState},
+            GraphicsPipelineCreateInfo,
+        },
+        DynamicState, GraphicsPipeline, Pipeline, PipelineShaderStageCreateInfo,
+    },
+};
+
+// segments of a circle outline
+const CIRCLE_SEGMENTS: usize = 24;
+// length of a velocity arrow of the physics view per meter per second
+const VELOCITY_SCALE: f32 = 0.25;
+
+// The DebugDraw resource, the physics view and the pass which draws their lines
+pub struct DebugDrawPlugin;
+
+impl Plugin for DebugDrawPlugin {
+    fn build(&self, builder: &mut EngineBuilder) {
+        builder
+            .add_unique(DebugDraw::default())
+            .add_system(
+                Stage::RenderExtract,
+                physics2d_debug_draw_system.skip_if_missing_unique::<Physics2DManager>(),
+            )
+            .add_system(
+                Stage::RenderExtract,
+                debug_draw_extract_system
+                    .after_all(camera2d_extract_system)
+                    .after_all(physics2d_debug_draw_system),
+            )
+            .add_render_pass(DebugDrawPass);
+    }
+}
+
+// Lines in world space which are drawn for one frame over the scene,
+// any system can push them, call it every update to keep them on screen.
+#[derive(Unique, Default)]
+pub struct DebugDraw {
+    lines: Vec<Line2D>,
+    pub physics: bool, // draw colliders, contacts and velocities of Physics2DManager
+}
+
+impl DebugDraw {
+    pub fn line(&mut self, start: Vec2, end: Vec2, color: Vec4) {
+        self.lines.push(Line2D { start, end, color });
+    }
+
+    pub fn circle(&mut self, center: Vec2, radius: f32, color: Vec4) {
+        let point =
+            |i: usize| center + Vec2::from_angle(i as f32 * TAU / CIRCLE_SEGMENTS as f32) * radius;
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), color);
+        }
+    }
+
+    pub fn aabb(&mut self, min: Vec2, max: Vec2, color: Vec4) {
+        self.rect(min.lerp(max, 0.5), (max - min) / 2.0, 0.0, color);
+    }
+
+    // a box rotated around its center, rotation is in radians
+    pub fn rect(&mut self, center: Vec2, half_extents: Vec2, rotation: f32, color: Vec4) {
+        let rotation = Vec2::from_angle(rotation);
+        let corners = [
+            Vec2::new(-half_extents.x, -half_extents.y),
+            Vec2::new(half_extents.x, -half_extents.y),
+            Vec2::new(half_extents.x, half_extents.y),
+            Vec2::new(-half_extents.x, half_extents.y),
+        ]
+        .map(|corner| center + rotation.rotate(corner));
+        for i in 0..4 {
+            self.line(corners[i], corners[(i + 1) % 4], color);
+        }
+    }
+
+    pub fn arrow(&mut self, start: Vec2, end: Vec2, color: Vec4) {
+        self.line(start, end, color);
+        let direction = end - start;
+        let head = direction.normalize_or_zero() * (direction.length() * 0.3).min(0.25);
+        self.line(end, end - Vec2::from_angle(0.5).rotate(head), color);
+        self.line(end, end - Vec2::from_angle(-0.5).rotate(head), color);
+    }
+}
+
+// the physics view shows the state after the last physics step, it is not interpolated
+fn physics2d_debug_draw_system(
+    physics2d_manager: UniqueView<Physics2DManager>,
+    mut debug_draw: UniqueViewMut<DebugDraw>,
+) {
+    if !debug_draw.physics {
+        return;
+    }
+    let awake = Vec4::new(0.0, 1.0, 0.0, 1.0);
+    let sleeping = Vec4::new(0.5, 0.5, 0.5, 1.0);
+    let fixed = Vec4::new(0.0, 0.5, 1.0, 1.0);
+    let contact = Vec4::new(1.0, 0.0, 1.0, 1.0);
+    let velocity = Vec4::new(0.0, 1.0, 1.0, 1.0);
+
+    for (_, collider) in physics2d_manager.collider_set.iter() {
+        let rigid_body = collider
+            .parent()
+            .and_then(|handle| physics2d_manager.rigid_body_set.get(handle));
+        let color = match rigid_body {
+            Some(rigid_body) if rigid_body.is_sleeping() => sleeping,
+            Some(rigid_body) if rigid_body.is_dynamic() => awake,
+            _ => fixed,
+        };
+        let position = collider.position();
+        let center = Vec2::new(position.translation.x, position.translation.y);
+        match collider.shape().as_typed_shape() {
+            TypedShape::Cuboid(cuboid) => debug_draw.rect(
+                center,
+                Vec2::new(cuboid.half_extents.x, cuboid.half_extents.y),
+                position.rotation.angle(),
+                color,
+            ),
+            TypedShape::Ball(ball) => {
+                debug_draw.circle(center, ball.radius, color);
+                // a spoke shows the rotation of the ball
+                let spoke = Vec2::from_angle(position.rotation.angle()) * ball.radius;
+                debug_draw.line(center, center + spoke, color);
+            }
+            _ => {
+                let aabb = collider.compute_aabb();
+                debug_draw.aabb(
+                    Vec2::new(aabb.mins.x, aabb.mins.y),
+                    Vec2::new(aabb.maxs.x, aabb.maxs.y),
+                    color,
+                );
+            }
+        }
+    }
+
+    for rigid_body in physics2d_manager.rigid_body_set.iter().map(|(_, rb)| rb) {
+        let linvel = rigid_body.linvel();
+        if rigid_body.is_dynamic() && !rigid_body.is_sleeping() && linvel.norm() > 0.0 {
+            let start = Vec2::new(rigid_body.translation().x, rigid_body.translation().y);
+            let end = start + Vec2::new(linvel.x, linvel.y) * VELOCITY_SCALE;
+            debug_draw.arrow(start, end, velocity);
+        }
+    }
+
+    for pair in physics2d_manager.narrow_phase.contact_pairs() {
+        if !pair.has_any_active_contact {
+            continue;
+        }
+        for manifold in &pair.manifolds {
+            let normal = Vec2::new(manifold.data.normal.x, manifold.data.normal.y);
+            for solver_contact in &manifold.data.solver_contacts {
+                let point = Vec2::new(solver_contact.point.x, solver_contact.point.y);
+                debug_draw.circle(point, 0.05, contact);
+                debug_draw.arrow(point, point + normal * 0.5, contact);
+            }
+        }
+    }
+}
+
+// the last extract system which pushes lines
+fn debug_draw_extract_system(
+    mut debug_draw: UniqueViewMut<DebugDraw>,
+    mut render_list: UniqueViewMut<RenderList>,
+) {
+    render_list.lines = std::mem::take(&mut debug_draw.lines);
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct LineVertex {
+    #[format(R32G32_SFLOAT)]
+    position: [f32; 2],
+    #[format(R32G32B32A32_SFLOAT)]
+    color: [f32; 4],
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct LinePushConstants {
+    #[format(R32G32B32A32_SFLOAT)]
+    projection_view: [[f32; 4]; 4],
+}
+
+const LINE_SHADERS: [Shader; 2] = [
+    Shader {
+        path: "shaders/line.vert",
+        spirv: include_spirv!("shaders/line.vert"),
+        validate: |vs| {
+            vs.validate_vertex_input(&[LineVertex::per_vertex()])?;
+            vs.validate_push_constants::<LinePushConstants>()
+        },
+    },
+    Shader {
+        path: "shaders/line.frag",
+        spirv: include_spirv!("shaders/line.frag"),
+        validate: |_| Ok(()),
+    },
+];
+
+// draws the lines of the render list with every camera
+struct DebugDrawPass;
+
+impl RenderPass for DebugDrawPass {
+    fn draw(
+        &mut self,
+        render_list: &RenderList,
+        info: &RenderInfo,
+        command_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) {
+        if render_list.lines.is_empty() {
+            return;
+        }
+
+        let subpass = info.subpass.clone();
+        let pipeline =
+            info.shaders
+                .graphics_pipeline(&info.device, &LINE_SHADERS, |vs, fs, layout| {
+                    let vertex_input_state = LineVertex::per_vertex().definition(&vs)?;
+                    let stages = [
+                        PipelineShaderStageCreateInfo::new(vs),
+                        PipelineShaderStageCreateInfo::new(fs),
+                    ];
+                    Ok(GraphicsPipeline::new(
+                        info.device.clone(),
+                        None,
+                        GraphicsPipelineCreateInfo {
+                            stages: stages.into_iter().collect(),
+                            vertex_input_state: Some(vertex_input_state),
+                            input_assembly_state: Some(InputAssemblyState {
+                                topology: PrimitiveTopology::LineList,
+                                ..Default::default()
+                            }),
+                            rasterization_state: Some(RasterizationState::default()),
+                            multisample_state: Some(MultisampleState {
+                                rasterization_samples: subpass.num_samples().unwrap(),
+                                ..Default::default()
+                            }),
+                            color_blend_state: Some(ColorBlendState::with_attachment_states(
+                                subpass.num_color_attachments(),
+                                ColorBlendAttachmentState::default(),
+                            )),
+                            viewport_state: Some(ViewportState::default()),
+                            dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                            subpass: Some(subpass.clone().into()),
+                            ..GraphicsPipelineCreateInfo::layout(layout)
+                        },
+                    )?)
+                });
+
+        let vertices = render_list.lines.iter().flat_map(|line| {
+            [line.start, line.end].map(|position| LineVertex {
+                position: position.to_array(),
+                color: line.color.to_array(),
+            })
+        });
+        let vertices = vertices.collect::<Vec<_>>();
+        let vertex_buffer = Buffer::from_iter(
+            info.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::VERTEX_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            vertices,
+        )
+        .unwrap();
+
+        command_buffer_builder
+            .bind_pipeline_graphics(pipeline.clone())
+            .unwrap()
+            .bind_vertex_buffers(0, vertex_buffer.clone())
+            .unwrap();
+
+        for camera in &render_list.cameras {
+            let (offset, extent) = camera.viewport_rect(info.window_size);
+            if extent.x < 1.0 || extent.y < 1.0 {
+                continue;
+            }
+            command_buffer_builder
+                .set_viewport(
+                    0,
+                    [Viewport {
+                        offset: offset.into(),
+                        extent: extent.into(),
+                        depth_range: 0.0..=1.0,
+                    }]
+                    .into_iter()
+                    .collect(),
+                )
+                .unwrap()
+                .push_constants(
+                    pipeline.layout().clone(),
+                    0,
+                    LinePushConstants {
+                        projection_view: camera.projection_view(extent).to_cols_array_2d(),
+                    },
+                )
+                .unwrap();
+            unsafe { command_buffer_builder.draw(vertex_buffer.len() as u32, 1, 0, 0) }.unwrap();
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use super::{
+    add_component, placement, prefab::spawn_prefab, AmbientLight2D, Animator2D, AssetServer,
+    AudioSource, Camera2D, Collider2D, ComponentData, DebugText, EngineBuilder, GamepadButton,
+    Input, InputBinding, Joint2D, JointType2D, MeshRenderer2D, ParticleEmitter2D, Plugin,
+    PointLight2D, PrefabManager, Renderer2D, RigidBody2D, Script, ScriptManager, SpotLight2D,
+    Stage, Text2D, TextAlign, Tilemap, Transform2D, Value, Variant,
+};
+use glam::{Vec2, Vec3, Vec4};
+use rapier2d::prelude::{RigidBodyType, SharedShape};
+use shader_library::include_spirv;
+use shipyard::{AllStoragesViewMut, UniqueViewMut};
+use winit::keyboard::KeyCode;
+
+// The demo scene: boxes falling on the ground, a humming pendulum with a lamp, a tilemap, spinning
+// coins, a star, particles, some text and two cameras. It uses the components of Physics2DPlugin,
+// TilemapPlugin, Renderer2DPlugin, Mesh2DPlugin, Animation2DPlugin, Particle2DPlugin,
+// Light2DPlugin, AudioPlugin and Text2DPlugin, so add them first.
+pub struct DemoPlugin;
+
+impl Plugin for DemoPlugin {
+    fn build(&self, builder: &mut EngineBuilder) {
+        {
+            let world = builder.world();
+            let mut input = world.borrow::<UniqueViewMut<Input>>().unwrap();
+            input.bind_action("jump", InputBinding::Key(KeyCode::Space));
+            input.bind_action("jump", InputBinding::Key(KeyCode::ArrowUp));
+            input.bind_action("jump", InputBinding::Gamepad(GamepadButton::South));
+            world
+                .borrow::<UniqueViewMut<ScriptManager>>()
+                .unwrap()
+                .add_builtin("scripts/jump.rhai", include_str!("../../scripts/jump.rhai"));
+            let mut asset_server = world.borrow::<UniqueViewMut<AssetServer>>().unwrap();
+            let mut prefab_manager = world.borrow::<UniqueViewMut<PrefabManager>>().unwrap();
+            prefab_manager.add_builtin(
+                &mut asset_server,
+                "prefabs/box.json",
+                include_str!("../../prefabs/box.json"),
+            );
+            prefab_manager.add_builtin(
+                &mut asset_server,
+                "prefabs/ground.json",
+                include_str!("../../prefabs/ground.json"),
+            );
+            asset_server.add_builtin(
+                "tilemaps/demo.tmj",
+                include_bytes!("../../tilemaps/demo.tmj"),
+            );
+            asset_server.add_builtin(
+                "tilemaps/demo_tiles.png",
+                include_bytes!("../../tilemaps/demo_tiles.png"),
+            );
+            asset_server.add_builtin(
+                "animations/coin.json",
+                include_bytes!("../../animations/coin.json"),
+            );
+            asset_server.add_builtin(
+                "animations/coin_aseprite.json",
+                include_bytes!("../../animations/coin_aseprite.json"),
+            );
+            asset_server.add_builtin(
+                "animations/coin.png",
+                include_bytes!("../../animations/coin.png"),
+            );
+            asset_server.add_builtin(
+                "animations/coin_normal.png",
+                include_bytes!("../../animations/coin_normal.png"),
+            );
+            asset_server.add_builtin("meshes/star.obj", include_bytes!("../../meshes/star.obj"));
+            asset_server.add_builtin(
+                "particles/soft.png",
+                include_bytes!("../../particles/soft.png"),
+            );
+            asset_server.add_builtin(
+                "materials/checker.json",
+                include_bytes!("../../materials/checker.json"),
+            );
+            // the SPIR-V, so it also loads where GLSL can not be compiled
+            asset_server.add_builtin(
+                "materials/checker.frag",
+                include_spirv!("materials/checker.frag"),
+            );
+            asset_server.add_builtin("sounds/jump.wav", include_bytes!("../../sounds/jump.wav"));
+            asset_server.add_builtin("sounds/hum.wav", include_bytes!("../../sounds/hum.wav"));
+            world.borrow::<UniqueViewMut<DebugText>>().unwrap().show_fps = true;
+        }
+        builder.add_system(Stage::Startup, demo_scene_system);
+    }
+}
+
+fn demo_scene_system(mut all_storages: AllStoragesViewMut) {
+    let falling_box = spawn_prefab(
+        &all_storages,
+        "prefabs/box.json",
+        vec![
+            placement(Vec2 { x: 0.0, y: 10.0 }),
+            ComponentData {
+                name: "RigidBody2D",
+                variants: vec![Variant {
+                    name: "angular_velocity",
+                    value: Value::Float32(2.0),
+                }],
+            },
+        ],
+    )
+    .unwrap();
+    add_component(&all_storages, falling_box, Script::new("scripts/jump.rhai"));
+    // sparks whenever the box hits something
+    add_component(
+        &all_storages,
+        falling_box,
+        ParticleEmitter2D {
+            rate: 0.0,
+            burst: 24,
+            lifetime: 0.6,
+            speed: Vec2 { x: 2.0, y: 5.0 },
+            spread: std::f32::consts::PI,
+            gravity: Vec2 { x: 0.0, y: -9.81 },
+            start_color: Vec4::new(1.0, 0.8, 0.3, 1.0),
+            end_color: Vec4::new(1.0, 0.2, 0.0, 0.0),
+            start_size: 0.1,
+            end_size: 0.02,
+            ..Default::default()
+        },
+    );
+    spawn_prefab(
+        &all_storages,
+        "prefabs/box.json",
+        vec![placement(Vec2 { x: 3.0, y: 6.0 })],
+    );
+    spawn_prefab(&all_storages, "prefabs/ground.json", Vec::new());
+
+    // dust drifting up from the ground
+    all_storages.add_entity((
+        Transform2D {
+            position: Vec3 {
+                x: -8.0,
+                y: 0.2,
+                z: 0.0,
+            },
+            rotation: 0.0,
+            scale: Vec2 { x: 0.2, y: 0.2 },
+        },
+        ParticleEmitter2D {
+            rate: 8.0,
+            lifetime: 2.0,
+            speed: Vec2 { x: 0.3, y: 0.8 },
+            spread: 0.6,
+            start_color: Vec4::new(0.8, 0.7, 0.5, 0.6),
+            end_color: Vec4::new(0.8, 0.7, 0.5, 0.0),
+            start_size: 0.3,
+            end_size: 0.8,
+            texture: "particles/soft.png".into(),
+            ..Default::default()
+        },
+    ));
+
+    // a pendulum hanging from a fixed pivot
+    let pivot = all_storages.add_entity((
+        Transform2D {
+            position: Vec3 {
+                x: -5.0,
+                y: 8.0,
+                z: 0.0,
+            },
+            rotation: 0.0,
+            scale: Vec2 { x: 0.2, y: 0.2 },
+        },
+        RigidBody2D::new(RigidBodyType::Fixed),
+        Renderer2D {
+            color: Vec4::new(0.4, 0.4, 0.4, 1.0),
+            ..Default::default()
+        },
+    ));
+    let bob = all_storages.add_entity((
+        Transform2D {
+            position: Vec3 {
+                x: -2.0,
+                y: 8.0,
+                z: 0.0,
+            },
+            rotation: 0.0,
+            scale: Vec2::ONE,
+        },
+        RigidBody2D::new(RigidBodyType::Dynamic),
+        Collider2D::new(SharedShape::cuboid(0.5, 0.5), 0.7),
+        Renderer2D {
+            color: Vec4::new(1.0, 0.0, 0.0, 1.0),
+            ..Default::default()
+        },
+        // pans from side to side as it swings
+        AudioSource {
+            sound: "sounds/hum.wav".into(),
+            volume: 0.3,
+            looping: true,
+            playing: true,
+            positional: true,
+            ..Default::default()
+        },
+        // a lamp swinging through the scene, the boxes cast shadows from it
+        PointLight2D {
+            color: Vec4::new(1.0, 0.8, 0.5, 1.0),
+            intensity: 1.5,
+            radius: 9.0,
+            ..Default::default()
+        },
+    ));
+    let mut joint2d = Joint2D::new(JointType2D::Revolute, pivot, bob);
+    joint2d.anchor2 = Vec2 { x: -3.0, y: 0.0 };
+    all_storages.add_entity((joint2d,));
+
+    // dim blue light everywhere, and a spot light from above the coins
+    all_storages.add_entity((AmbientLight2D {
+        color: Vec4::new(0.35, 0.35, 0.45, 1.0),
+    },));
+    all_storages.add_entity((
+        Transform2D {
+            position: Vec3 {
+                x: 4.0,
+                y: 11.0,
+                z: 0.0,
+            },
+            rotation: -std::f32::consts::FRAC_PI_2,
+            scale: Vec2::ONE,
+        },
+        SpotLight2D {
+            color: Vec4::new(0.6, 0.8, 1.0, 1.0),
+            radius: 10.0,
+            angle: 0.6,
+            ..Default::default()
+        },
+    ));
+
+    // some terrain from a Tiled map, standing on the ground
+    all_storages.add_entity((
+        Transform2D {
+            position: Vec3 {
+                x: 4.0,
+                y: 4.1,
+                z: 0.0,
+            },
+            rotation: 0.0,
+            scale: Vec2::ONE,
+        },
+        Tilemap::from_tiled("tilemaps/demo.tmj"),
+    ));
+
+    // coins above the tilemap, one from a steel clip file which bobs up and down,
+    // one from an Aseprite export
+    all_storages.add_entity((
+        Transform2D {
+            position: Vec3 {
+                x: 5.0,
+                y: 6.0,
+                z: 0.0,
+            },
+            rotation: 0.0,
+            scale: Vec2::ONE,
+        },
+        Animator2D::new("animations/coin.json", "spin"),
+    ));
+    all_storages.add_entity((
+        Transform2D {
+            position: Vec3 {
+                x: 7.0,
+                y: 6.0,
+                z: 0.0,
+            },
+            rotation: 0.0,
+            scale: Vec2::ONE,
+        },
+        Animator2D::new("animations/coin_aseprite.json", "spin"),
+    ));
+
+    // a star between the coins
+    all_storages.add_entity((
+        Transform2D {
+            position: Vec3 {
+                x: 6.0,
+                y: 7.5,
+                z: 0.0,
+            },
+            rotation: 0.0,
+            scale: Vec2::ONE,
+        },
+        MeshRenderer2D {
+            mesh: "meshes/star.obj".into(),
+            color: Vec4::new(1.0, 0.85, 0.2, 1.0),
+            ..Default::default()
+        },
+    ));
+
+    // a title above the scene and a hint in the top left of the screen, below the fps
+    all_storages.add_entity((
+        Transform2D {
+            position: Vec3 {
+                x: 0.0,
+                y: 14.0,
+                z: 0.0,
+            },
+            rotation: 0.0,
+            scale: Vec2::ONE,
+        },
+        Text2D {
+            text: "Steel".into(),
+            size: 2.0,
+            align: TextAlign::Center,
+            ..Default::default()
+        },
+    ));
+    all_storages.add_entity((
+        Transform2D {
+            position: Vec3 {
+                x: 8.0,
+                y: 28.0,
+                z: 0.0,
+            },
+            rotation: 0.0,
+            scale: Vec2::ONE,
+        },
+        Text2D {
+            text: "Press space or up to make the box jump".into(),
+            size: 16.0,
+            wrap_width: 200.0,
+            screen_space: true,
+            ..Default::default()
+        },
+    ));
+
+    // the main camera sees the whole scene, a small one in the corner follows the falling box
+    all_storages.add_entity((Camera2D {
+        position: Vec2 { x: 0.0, y: 5.0 },
+        ..Default::default()
+    },));
+    all_storages.add_entity((Camera2D {
+        zoom: 2.0,
+        follow: Some(falling_box),
+        viewport: Vec4::new(0.7, 0.0, 0.3, 0.3),
+        order: 1,
+        ..Default::default()
+    },));
+}
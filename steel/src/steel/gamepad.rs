@@ -0,0 +1,180 @@
+// Without the gamepad feature nothing sends gamepad events, so most of this is unused then
+#![cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+
+use super::{Input, InputBinding};
+use shipyard::{Unique, UniqueView, UniqueViewMut};
+use std::sync::{mpsc::Receiver, Mutex};
+
+// buttons of a gamepad by where they are, South is A on an Xbox and cross on a PlayStation pad
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+// sticks of a gamepad, from -1 to 1, up is positive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+}
+
+impl GamepadAxis {
+    // left_x, left_y, right_x or right_y, how scripts name the axes
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "left_x" => Some(GamepadAxis::LeftX),
+            "left_y" => Some(GamepadAxis::LeftY),
+            "right_x" => Some(GamepadAxis::RightX),
+            "right_y" => Some(GamepadAxis::RightY),
+            _ => None,
+        }
+    }
+}
+
+// what happened on one of the connected gamepads, they all feed the same bindings and axes
+enum GamepadEvent {
+    Pressed(GamepadButton),
+    Released(GamepadButton),
+    Axis(GamepadAxis, f32),
+    Disconnected,
+}
+
+// The events of the connected gamepads, read on their own thread with the gamepad feature.
+// Without the feature there are none.
+#[derive(Unique)]
+pub struct Gamepads {
+    events: Option<Mutex<Receiver<GamepadEvent>>>,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        Gamepads {
+            events: read_gamepads(),
+        }
+    }
+}
+
+#[cfg(not(feature = "gamepad"))]
+fn read_gamepads() -> Option<Mutex<Receiver<GamepadEvent>>> {
+    None
+}
+
+// gilrs is not Sync, so it stays on the thread which sends its events
+#[cfg(feature = "gamepad")]
+fn read_gamepads() -> Option<Mutex<Receiver<GamepadEvent>>> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || match gilrs::Gilrs::new() {
+        // blocks until the next event, ends with the first one after Gamepads is dropped
+        Ok(mut gilrs) => {
+            while let Some(event) = gilrs.next_event_blocking(None) {
+                if let Some(event) = gamepad_event(event.event) {
+                    if sender.send(event).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        Err(e) => log::error!("Failed to read gamepads: {e}"),
+    });
+    Some(Mutex::new(receiver))
+}
+
+#[cfg(feature = "gamepad")]
+fn gamepad_event(event: gilrs::EventType) -> Option<GamepadEvent> {
+    use gilrs::{Axis, Button, EventType};
+
+    let button = |button| {
+        Some(match button {
+            Button::South => GamepadButton::South,
+            Button::East => GamepadButton::East,
+            Button::North => GamepadButton::North,
+            Button::West => GamepadButton::West,
+            Button::LeftTrigger => GamepadButton::LeftBumper,
+            Button::RightTrigger => GamepadButton::RightBumper,
+            Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+            Button::RightTrigger2 => GamepadButton::RightTrigger,
+            Button::Select => GamepadButton::Select,
+            Button::Start => GamepadButton::Start,
+            Button::LeftThumb => GamepadButton::LeftStick,
+            Button::RightThumb => GamepadButton::RightStick,
+            Button::DPadUp => GamepadButton::DPadUp,
+            Button::DPadDown => GamepadButton::DPadDown,
+            Button::DPadLeft => GamepadButton::DPadLeft,
+            Button::DPadRight => GamepadButton::DPadRight,
+            _ => return None,
+        })
+    };
+    match event {
+        EventType::ButtonPressed(b, _) => button(b).map(GamepadEvent::Pressed),
+        EventType::ButtonReleased(b, _) => button(b).map(GamepadEvent::Released),
+        EventType::AxisChanged(axis, value, _) => {
+            let axis = match axis {
+                Axis::LeftStickX => GamepadAxis::LeftX,
+                Axis::LeftStickY => GamepadAxis::LeftY,
+                Axis::RightStickX => GamepadAxis::RightX,
+                Axis::RightStickY => GamepadAxis::RightY,
+                _ => return None,
+            };
+            Some(GamepadEvent::Axis(axis, value))
+        }
+        EventType::Disconnected => Some(GamepadEvent::Disconnected),
+        _ => None,
+    }
+}
+
+impl Input {
+    fn handle_gamepad_event(&mut self, event: GamepadEvent) {
+        match event {
+            GamepadEvent::Pressed(button) => self.set_pressed(InputBinding::Gamepad(button), true),
+            GamepadEvent::Released(button) => {
+                self.set_pressed(InputBinding::Gamepad(button), false)
+            }
+            GamepadEvent::Axis(axis, value) => {
+                self.gamepad_axes.insert(axis, value);
+            }
+            // we will not receive its releases any more
+            GamepadEvent::Disconnected => {
+                let buttons = self
+                    .pressed
+                    .iter()
+                    .filter(|binding| matches!(binding, InputBinding::Gamepad(_)))
+                    .copied()
+                    .collect::<Vec<_>>();
+                for binding in buttons {
+                    self.set_pressed(binding, false);
+                }
+                self.gamepad_axes.clear();
+            }
+        }
+    }
+
+    pub fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.gamepad_axes.get(&axis).copied().unwrap_or(0.0)
+    }
+}
+
+// feeds the gamepad events since the last update into Input
+pub fn gamepad_system(gamepads: UniqueView<Gamepads>, mut input: UniqueViewMut<Input>) {
+    if let Some(events) = &gamepads.events {
+        for event in events.lock().unwrap().try_iter() {
+            input.handle_gamepad_event(event);
+        }
+    }
+}
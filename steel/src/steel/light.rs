@@ -0,0 +1,1326 @@
+use super::{
+    camera2d_extract_system, render::clear_rect, Collider2D, ComponentData, Edit, EngineBuilder,
+    Interpolation2D, Light2D, Plugin, RenderInfo, RenderList, RenderPass, Shader, ShadowShape2D,
+    Stage, Transform2D, Value, Variant,
+};
+use glam::{Vec2, Vec3, Vec4};
+use shader_library::include_spirv;
+use shipyard::{
+    Component, EntityId, Get, IntoIter, IntoWithId, SystemModificator, UniqueViewMut, View,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    sync::Arc,
+};
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage},
+    command_buffer::{
+        AutoCommandBufferBuilder, ClearAttachment, PrimaryAutoCommandBuffer, RenderPassBeginInfo,
+        SubpassBeginInfo, SubpassContents,
+    },
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorType, DescriptorSet,
+        WriteDescriptorSet,
+    },
+    format::Format,
+    image::{
+        sampler::{Filter, Sampler, SamplerCreateInfo},
+        view::ImageView,
+        Image, ImageCreateInfo, ImageUsage,
+    },
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{
+        graphics::{
+            color_blend::{
+                AttachmentBlend, BlendFactor, BlendOp, ColorBlendAttachmentState, ColorBlendState,
+                ColorComponents,
+            },
+            input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            rasterization::RasterizationState,
+            vertex_input::{Vertex, VertexDefinition, VertexInputState},
+            viewport::{Viewport, ViewportState},
+            GraphicsPipelineCreateInfo,
+        },
+        DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    render_pass::{Framebuffer, FramebufferCreateInfo, Subpass},
+    shader::EntryPoint,
+};
+
+// corners of a Ball collider in its shadow shape
+const BALL_SEGMENTS: usize = 16;
+
+// shadows are extruded this many light radii away from their caster, far enough to leave the light
+const SHADOW_LENGTH: f32 = 16.0;
+
+// PointLight2D, SpotLight2D, AmbientLight2D, ShadowCaster2D and the pass which lights what the
+// passes before it drew. Add it after the plugins whose drawing should be lit, e.g. before
+// DebugDrawPlugin and Text2DPlugin.
+pub struct Light2DPlugin;
+
+impl Plugin for Light2DPlugin {
+    fn build(&self, builder: &mut EngineBuilder) {
+        builder
+            .add_component(PointLight2D::default)
+            .add_component(SpotLight2D::default)
+            .add_component(AmbientLight2D::default)
+            .add_component(ShadowCaster2D::default)
+            .add_system(
+                Stage::RenderExtract,
+                light2d_extract_system.after_all(camera2d_extract_system),
+            )
+            .add_system(
+                Stage::RenderExtract,
+                shadow2d_extract_system.after_all(light2d_extract_system),
+            )
+            .add_render_pass(Light2DPass::default());
+    }
+}
+
+// Lights everything within radius of its entity, brightest at the center
+#[derive(Component, Debug)]
+#[track(All)]
+pub struct PointLight2D {
+    pub color: Vec4, // alpha is ignored
+    pub intensity: f32,
+    pub radius: f32,
+    pub height: f32,   // above the scene, lower lights make normal maps look steeper
+    pub shadows: bool, // blocked by ShadowCaster2D
+}
+
+impl Default for PointLight2D {
+    fn default() -> Self {
+        PointLight2D {
+            color: Vec4::ONE,
+            intensity: 1.0,
+            radius: 5.0,
+            height: 1.0,
+            shadows: true,
+        }
+    }
+}
+
+impl Edit for PointLight2D {
+    fn name() -> &'static str {
+        "PointLight2D"
+    }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "color",
+            value: Value::Vec4(self.color),
+        });
+        data.variants.push(Variant {
+            name: "intensity",
+            value: Value::Float32(self.intensity),
+        });
+        data.variants.push(Variant {
+            name: "radius",
+            value: Value::Float32(self.radius),
+        });
+        data.variants.push(Variant {
+            name: "height",
+            value: Value::Float32(self.height),
+        });
+        data.variants.push(Variant {
+            name: "shadows",
+            value: Value::Bool(self.shadows),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            match (v.name, v.value) {
+                ("color", Value::Vec4(v)) => self.color = v,
+                ("intensity", Value::Float32(v)) => self.intensity = v,
+                ("radius", Value::Float32(v)) => self.radius = v,
+                ("height", Value::Float32(v)) => self.height = v,
+                ("shadows", Value::Bool(v)) => self.shadows = v,
+                _ => (),
+            }
+        }
+    }
+}
+
+// A PointLight2D which only shines into a cone along the rotation of its entity
+#[derive(Component, Debug)]
+#[track(All)]
+pub struct SpotLight2D {
+    pub color: Vec4, // alpha is ignored
+    pub intensity: f32,
+    pub radius: f32,
+    pub height: f32,
+    pub shadows: bool,
+    pub angle: f32,    // half of the angle of the cone, radians
+    pub softness: f32, // the part of the angle in which the light fades out, 0 to 1
+}
+
+impl Default for SpotLight2D {
+    fn default() -> Self {
+        SpotLight2D {
+            color: Vec4::ONE,
+            intensity: 1.0,
+            radius: 8.0,
+            height: 1.0,
+            shadows: true,
+            angle: 0.5,
+            softness: 0.3,
+        }
+    }
+}
+
+impl Edit for SpotLight2D {
+    fn name() -> &'static str {
+        "SpotLight2D"
+    }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "color",
+            value: Value::Vec4(self.color),
+        });
+        data.variants.push(Variant {
+            name: "intensity",
+            value: Value::Float32(self.intensity),
+        });
+        data.variants.push(Variant {
+            name: "radius",
+            value: Value::Float32(self.radius),
+        });
+        data.variants.push(Variant {
+            name: "height",
+            value: Value::Float32(self.height),
+        });
+        data.variants.push(Variant {
+            name: "shadows",
+            value: Value::Bool(self.shadows),
+        });
+        data.variants.push(Variant {
+            name: "angle",
+            value: Value::Float32(self.angle),
+        });
+        data.variants.push(Variant {
+            name: "softness",
+            value: Value::Float32(self.softness),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            match (v.name, v.value) {
+                ("color", Value::Vec4(v)) => self.color = v,
+                ("intensity", Value::Float32(v)) => self.intensity = v,
+                ("radius", Value::Float32(v)) => self.radius = v,
+                ("height", Value::Float32(v)) => self.height = v,
+                ("shadows", Value::Bool(v)) => self.shadows = v,
+                ("angle", Value::Float32(v)) => self.angle = v,
+                ("softness", Value::Float32(v)) => self.softness = v,
+                _ => (),
+            }
+        }
+    }
+}
+
+// The light everything gets without any other light, the first one found is used. Without it
+// the scene is black where no light reaches, as soon as there is a light.
+#[derive(Component, Debug)]
+#[track(All)]
+pub struct AmbientLight2D {
+    pub color: Vec4, // alpha is ignored
+}
+
+impl Default for AmbientLight2D {
+    fn default() -> Self {
+        AmbientLight2D {
+            color: Vec4::new(0.2, 0.2, 0.2, 1.0),
+        }
+    }
+}
+
+impl Edit for AmbientLight2D {
+    fn name() -> &'static str {
+        "AmbientLight2D"
+    }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "color",
+            value: Value::Vec4(self.color),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            if let ("color", Value::Vec4(v)) = (v.name, v.value) {
+                self.color = v;
+            }
+        }
+    }
+}
+
+// Blocks lights with shadows with the shape of the Collider2D of its entity,
+// cuboids, balls and convex polygons are supported
+#[derive(Component, Debug)]
+#[track(All)]
+pub struct ShadowCaster2D {
+    pub opacity: f32, // 1 blocks all of the light
+}
+
+impl Default for ShadowCaster2D {
+    fn default() -> Self {
+        ShadowCaster2D { opacity: 1.0 }
+    }
+}
+
+impl Edit for ShadowCaster2D {
+    fn name() -> &'static str {
+        "ShadowCaster2D"
+    }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "opacity",
+            value: Value::Float32(self.opacity),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            if let ("opacity", Value::Float32(v)) = (v.name, v.value) {
+                self.opacity = v;
+            }
+        }
+    }
+}
+
+// the corners of the collider in local space, counter clockwise
+fn collider_polygon(collider2d: &Collider2D) -> Option<Vec<Vec2>> {
+    if let Some(cuboid) = collider2d.shape.as_cuboid() {
+        let half_extents = Vec2::new(cuboid.half_extents.x, cuboid.half_extents.y);
+        Some(
+            [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                .map(|(x, y)| half_extents * Vec2::new(x, y))
+                .to_vec(),
+        )
+    } else if let Some(ball) = collider2d.shape.as_ball() {
+        Some(
+            (0..BALL_SEGMENTS)
+                .map(|i| {
+                    let angle = i as f32 / BALL_SEGMENTS as f32 * std::f32::consts::TAU;
+                    Vec2::from_angle(angle) * ball.radius
+                })
+                .collect(),
+        )
+    } else {
+        collider2d.shape.as_convex_polygon().map(|polygon| {
+            polygon
+                .points()
+                .iter()
+                .map(|point| Vec2::new(point.x, point.y))
+                .collect()
+        })
+    }
+}
+
+// position and rotation of the entity, interpolated between the last two physics steps
+fn pose(
+    e: EntityId,
+    transform2d: &Transform2D,
+    interpolation2d: &View<Interpolation2D>,
+    alpha: f32,
+) -> (Vec2, f32) {
+    match interpolation2d.get(e) {
+        Ok(interpolation2d) => {
+            let (position, rotation) = interpolation2d.blend(transform2d, alpha);
+            (position.truncate(), rotation)
+        }
+        Err(_) => (transform2d.position.truncate(), transform2d.rotation),
+    }
+}
+
+fn light2d_extract_system(
+    transform2d: View<Transform2D>,
+    interpolation2d: View<Interpolation2D>,
+    point_light2d: View<PointLight2D>,
+    spot_light2d: View<SpotLight2D>,
+    ambient_light2d: View<AmbientLight2D>,
+    mut render_list: UniqueViewMut<RenderList>,
+) {
+    let alpha = render_list.alpha;
+    render_list.ambient_light = ambient_light2d
+        .iter()
+        .next()
+        .map(|ambient_light2d| ambient_light2d.color.truncate());
+    for (e, (transform2d, light)) in (&transform2d, &point_light2d).iter().with_id() {
+        render_list.lights.push(Light2D {
+            position: pose(e, transform2d, &interpolation2d, alpha).0,
+            color: light.color.truncate() * light.intensity,
+            radius: light.radius.max(0.0),
+            height: light.height,
+            direction: Vec2::X,
+            cone: None,
+            shadows: light.shadows,
+        });
+    }
+    for (e, (transform2d, light)) in (&transform2d, &spot_light2d).iter().with_id() {
+        let (position, rotation) = pose(e, transform2d, &interpolation2d, alpha);
+        let outer = light.angle.clamp(0.0, std::f32::consts::PI);
+        // a little softness always, the cone edge would be undefined without
+        let inner = outer * (1.0 - light.softness.clamp(0.01, 1.0));
+        render_list.lights.push(Light2D {
+            position,
+            color: light.color.truncate() * light.intensity,
+            radius: light.radius.max(0.0),
+            height: light.height,
+            direction: Vec2::from_angle(rotation),
+            cone: Some((inner.cos(), outer.cos())),
+            shadows: light.shadows,
+        });
+    }
+}
+
+// the shapes of the shadow casters, only when a light has shadows
+fn shadow2d_extract_system(
+    transform2d: View<Transform2D>,
+    interpolation2d: View<Interpolation2D>,
+    shadow_caster2d: View<ShadowCaster2D>,
+    collider2d: View<Collider2D>,
+    mut render_list: UniqueViewMut<RenderList>,
+) {
+    if render_list.lights.iter().all(|light| !light.shadows) {
+        return;
+    }
+    let alpha = render_list.alpha;
+    for (e, (transform2d, shadow_caster2d, collider2d)) in
+        (&transform2d, &shadow_caster2d, &collider2d)
+            .iter()
+            .with_id()
+    {
+        let Some(mut points) = collider_polygon(collider2d) else {
+            continue;
+        };
+        let (position, rotation) = pose(e, transform2d, &interpolation2d, alpha);
+        let rotation = Vec2::from_angle(rotation);
+        for point in &mut points {
+            *point = position + rotation.rotate(*point);
+        }
+        render_list.shadow_shapes.push(ShadowShape2D {
+            points,
+            opacity: shadow_caster2d.opacity.clamp(0.0, 1.0),
+        });
+    }
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct LightVertex {
+    #[format(R32G32_SFLOAT)]
+    position: [f32; 2],
+    #[format(R32_SFLOAT)]
+    alpha: f32, // how much light a shadow lets through, 1 for the quad of the light
+}
+
+// the part of the vertex buffer a light uses
+struct LightRange {
+    first: u32,   // 6 vertices of the quad around the light, then its shadows
+    shadows: u32, // vertices of the shadows
+}
+
+// the quad around the light, then a quad for each edge of the shadow shapes which faces away from
+// the light. It starts at the edge and is extruded away from the light.
+fn light_vertices(light: &Light2D, shapes: &[ShadowShape2D], vertices: &mut Vec<LightVertex>) {
+    let vertex = |position: Vec2, alpha| LightVertex {
+        position: position.to_array(),
+        alpha,
+    };
+    let radius = light.radius;
+    vertices.extend(
+        [
+            (-1.0, 1.0),
+            (-1.0, -1.0),
+            (1.0, -1.0),
+            (1.0, -1.0),
+            (1.0, 1.0),
+            (-1.0, 1.0),
+        ]
+        .map(|(x, y)| vertex(light.position + Vec2::new(x, y) * radius, 1.0)),
+    );
+    if !light.shadows {
+        return;
+    }
+    let extrude =
+        |point: Vec2| point + (point - light.position).normalize_or_zero() * radius * SHADOW_LENGTH;
+    for shape in shapes {
+        let center = shape.points.iter().sum::<Vec2>() / shape.points.len() as f32;
+        let extent = shape
+            .points
+            .iter()
+            .map(|point| point.distance(center))
+            .fold(0.0, f32::max);
+        if center.distance(light.position) > radius + extent {
+            continue;
+        }
+        let alpha = 1.0 - shape.opacity;
+        for (i, &a) in shape.points.iter().enumerate() {
+            let b = shape.points[(i + 1) % shape.points.len()];
+            let normal = (b - a).perp(); // points inside for counter clockwise shapes
+            if normal.dot(light.position - a) <= 0.0 {
+                continue;
+            }
+            let (far_a, far_b) = (extrude(a), extrude(b));
+            vertices.extend([a, b, far_b, far_b, far_a, a].map(|point| vertex(point, alpha)));
+        }
+    }
+}
+
+// a corner of the quad every normal mapped sprite is an instance of
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct NormalVertex {
+    #[format(R32G32_SFLOAT)]
+    corner: [f32; 2],
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct NormalInstance {
+    #[format(R32G32B32A32_SFLOAT)]
+    model: [[f32; 4]; 4],
+    #[format(R32G32_SFLOAT)]
+    uv_min: [f32; 2],
+    #[format(R32G32_SFLOAT)]
+    uv_max: [f32; 2],
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct ProjectionPushConstants {
+    #[format(R32G32B32A32_SFLOAT)]
+    projection_view: [[f32; 4]; 4],
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct LightPushConstants {
+    #[format(R32G32B32A32_SFLOAT)]
+    projection_view: [[f32; 4]; 4],
+    #[format(R32G32_SFLOAT)]
+    position: [f32; 2],
+    #[format(R32_SFLOAT)]
+    radius: f32,
+    #[format(R32_SFLOAT)]
+    height: f32,
+    #[format(R32G32B32A32_SFLOAT)]
+    color: [f32; 4],
+    #[format(R32G32_SFLOAT)]
+    direction: [f32; 2],
+    #[format(R32_SFLOAT)]
+    cos_inner: f32,
+    #[format(R32_SFLOAT)]
+    cos_outer: f32, // -1 for point lights
+}
+
+const NORMAL_SHADERS: [Shader; 2] = [
+    Shader {
+        path: "shaders/normal.vert",
+        spirv: include_spirv!("shaders/normal.vert"),
+        validate: |vs| {
+            vs.validate_vertex_input(&[
+                NormalVertex::per_vertex(),
+                NormalInstance::per_instance(),
+            ])?;
+            vs.validate_push_constants::<ProjectionPushConstants>()
+        },
+    },
+    Shader {
+        path: "shaders/normal.frag",
+        spirv: include_spirv!("shaders/normal.frag"),
+        validate: |fs| {
+            fs.validate_descriptor(0, 0, DescriptorType::Sampler)?;
+            fs.validate_descriptor(0, 1, DescriptorType::SampledImage)
+        },
+    },
+];
+
+const SHADOW_SHADERS: [Shader; 2] = [
+    Shader {
+        path: "shaders/shadow.vert",
+        spirv: include_spirv!("shaders/shadow.vert"),
+        validate: |vs| {
+            vs.validate_vertex_input(&[LightVertex::per_vertex()])?;
+            vs.validate_push_constants::<ProjectionPushConstants>()
+        },
+    },
+    Shader {
+        path: "shaders/shadow.frag",
+        spirv: include_spirv!("shaders/shadow.frag"),
+        validate: |_| Ok(()),
+    },
+];
+
+const LIGHT_SHADERS: [Shader; 2] = [
+    Shader {
+        path: "shaders/light.vert",
+        spirv: include_spirv!("shaders/light.vert"),
+        validate: |vs| {
+            vs.validate_vertex_input(&[LightVertex::per_vertex()])?;
+            vs.validate_push_constants::<LightPushConstants>()
+        },
+    },
+    Shader {
+        path: "shaders/light.frag",
+        spirv: include_spirv!("shaders/light.frag"),
+        validate: |fs| {
+            fs.validate_push_constants::<LightPushConstants>()?;
+            fs.validate_descriptor(0, 0, DescriptorType::Sampler)?;
+            fs.validate_descriptor(0, 1, DescriptorType::SampledImage)
+        },
+    },
+];
+
+const COMPOSITE_SHADERS: [Shader; 2] = [
+    Shader {
+        path: "shaders/composite.vert",
+        spirv: include_spirv!("shaders/composite.vert"),
+        validate: |vs| vs.validate_vertex_input(&[]),
+    },
+    Shader {
+        path: "shaders/composite.frag",
+        spirv: include_spirv!("shaders/composite.frag"),
+        validate: |fs| {
+            fs.validate_descriptor(0, 0, DescriptorType::Sampler)?;
+            fs.validate_descriptor(0, 1, DescriptorType::SampledImage)
+        },
+    },
+];
+
+fn create_pipeline(
+    info: &RenderInfo,
+    subpass: &Subpass,
+    (vs, fs, layout): (EntryPoint, EntryPoint, Arc<PipelineLayout>),
+    vertex_input_state: VertexInputState,
+    color_blend_attachment_state: ColorBlendAttachmentState,
+) -> Result<Arc<GraphicsPipeline>, Box<dyn Error>> {
+    let stages = [
+        PipelineShaderStageCreateInfo::new(vs),
+        PipelineShaderStageCreateInfo::new(fs),
+    ];
+    Ok(GraphicsPipeline::new(
+        info.device.clone(),
+        None,
+        GraphicsPipelineCreateInfo {
+            stages: stages.into_iter().collect(),
+            vertex_input_state: Some(vertex_input_state),
+            input_assembly_state: Some(InputAssemblyState::default()),
+            rasterization_state: Some(RasterizationState::default()),
+            multisample_state: Some(MultisampleState {
+                rasterization_samples: subpass.num_samples().unwrap(),
+                ..Default::default()
+            }),
+            color_blend_state: Some(ColorBlendState::with_attachment_states(
+                subpass.num_color_attachments(),
+                color_blend_attachment_state,
+            )),
+            viewport_state: Some(ViewportState::default()),
+            dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+            subpass: Some(subpass.clone().into()),
+            ..GraphicsPipelineCreateInfo::layout(layout)
+        },
+    )?)
+}
+
+// an offscreen image of the pass, which is drawn into and then sampled
+fn create_image(info: &RenderInfo, format: Format, extent: [u32; 3]) -> Arc<ImageView> {
+    let image = Image::new(
+        info.memory_allocator.clone(),
+        ImageCreateInfo {
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED,
+            format,
+            extent,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    )
+    .unwrap();
+    ImageView::new_default(image).unwrap()
+}
+
+fn viewport(offset: Vec2, extent: Vec2) -> Viewport {
+    Viewport {
+        offset: offset.into(),
+        extent: extent.into(),
+        depth_range: 0.0..=1.0,
+    }
+}
+
+// The normals of the normal mapped sprites and the light of the scene are drawn into two offscreen
+// images of the size of the scene image, the light is then multiplied with the scene image.
+// The light image starts with the ambient light, each light is added where its shadows, which
+// are drawn into the alpha of the light image before it, let it through.
+#[derive(Default)]
+struct Light2DPass {
+    descriptor_set_allocator: Option<Arc<StandardDescriptorSetAllocator>>,
+    images: Option<(Arc<ImageView>, Arc<ImageView>)>, // normals and light, kept while the size stays
+}
+
+impl RenderPass for Light2DPass {
+    // the normals and the light of all cameras, each camera multiplies its viewport with the light
+    // when it is drawn, cameras on top cover the light of the ones below like the rest of them
+    fn prepare(
+        &mut self,
+        render_list: &RenderList,
+        info: &RenderInfo,
+        command_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) {
+        if render_list.lights.is_empty() && render_list.ambient_light.is_none() {
+            return;
+        }
+        let ambient_light = render_list.ambient_light.unwrap_or(Vec3::ZERO);
+
+        let image_extent = info.image.image().extent();
+        let (normal_image, light_image) = match &self.images {
+            Some((normal_image, light_image)) if normal_image.image().extent() == image_extent => {
+                (normal_image.clone(), light_image.clone())
+            }
+            _ => {
+                let images = (
+                    create_image(info, Format::R8G8B8A8_UNORM, image_extent),
+                    create_image(info, Format::R16G16B16A16_SFLOAT, image_extent),
+                );
+                self.images = Some(images.clone());
+                images
+            }
+        };
+
+        let sampler = Sampler::new(
+            info.device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let descriptor_set_allocator = self
+            .descriptor_set_allocator
+            .get_or_insert_with(|| {
+                Arc::new(StandardDescriptorSetAllocator::new(
+                    info.device.clone(),
+                    Default::default(),
+                ))
+            })
+            .clone();
+        let descriptor_set = |pipeline: &Arc<GraphicsPipeline>, image_view: Arc<ImageView>| {
+            DescriptorSet::new(
+                descriptor_set_allocator.clone(),
+                pipeline.layout().set_layouts()[0].clone(),
+                [
+                    WriteDescriptorSet::sampler(0, sampler.clone()),
+                    WriteDescriptorSet::image_view(1, image_view),
+                ],
+                [],
+            )
+            .unwrap()
+        };
+        let buffer_create_info = || BufferCreateInfo {
+            usage: BufferUsage::VERTEX_BUFFER,
+            ..Default::default()
+        };
+        let allocation_create_info = || AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        };
+        let cameras = render_list
+            .cameras
+            .iter()
+            .map(|camera| (camera, camera.viewport_rect(info.window_size)))
+            .filter(|(_, (_, extent))| extent.x >= 1.0 && extent.y >= 1.0)
+            .collect::<Vec<_>>();
+
+        // the normals of the normal mapped sprites, alpha is 0 where there are none
+        let render_pass = vulkano::single_pass_renderpass!(
+            info.device.clone(),
+            attachments: {
+                color: {
+                    format: Format::R8G8B8A8_UNORM,
+                    samples: 1,
+                    load_op: Clear,
+                    store_op: Store,
+                },
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {},
+            },
+        )
+        .unwrap();
+        let framebuffer = Framebuffer::new(
+            render_pass.clone(),
+            FramebufferCreateInfo {
+                attachments: vec![normal_image.clone()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let subpass = Subpass::from(render_pass, 0).unwrap();
+        command_buffer_builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![Some([0.0; 4].into())],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassBeginInfo {
+                    contents: SubpassContents::Inline,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let sprites = render_list
+            .sprites
+            .iter()
+            .filter_map(|sprite| Some((sprite, sprite.normal_map.as_ref()?)))
+            .collect::<Vec<_>>();
+        if !sprites.is_empty() {
+            let pipeline =
+                info.shaders
+                    .graphics_pipeline(&info.device, &NORMAL_SHADERS, |vs, fs, layout| {
+                        let vertex_input_state =
+                            [NormalVertex::per_vertex(), NormalInstance::per_instance()]
+                                .definition(&vs)?;
+                        create_pipeline(
+                            info,
+                            &subpass,
+                            (vs, fs, layout),
+                            vertex_input_state,
+                            ColorBlendAttachmentState::default(),
+                        )
+                    });
+            let descriptor_sets = sprites
+                .iter()
+                .map(|(_, normal_map)| normal_map.id)
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .map(|id| (id, descriptor_set(&pipeline, info.textures[&id].clone())))
+                .collect::<HashMap<_, _>>();
+            let vertex_buffer = Buffer::from_iter(
+                info.memory_allocator.clone(),
+                buffer_create_info(),
+                allocation_create_info(),
+                [
+                    [-0.5, 0.5],
+                    [-0.5, -0.5],
+                    [0.5, -0.5],
+                    [0.5, -0.5],
+                    [0.5, 0.5],
+                    [-0.5, 0.5],
+                ]
+                .map(|corner| NormalVertex { corner }),
+            )
+            .unwrap();
+            let instance_buffer = Buffer::from_iter(
+                info.memory_allocator.clone(),
+                buffer_create_info(),
+                allocation_create_info(),
+                sprites
+                    .iter()
+                    .map(|(sprite, _)| NormalInstance {
+                        model: sprite.model.to_cols_array_2d(),
+                        uv_min: sprite.uv_min.to_array(),
+                        uv_max: sprite.uv_max.to_array(),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap();
+            command_buffer_builder
+                .bind_pipeline_graphics(pipeline.clone())
+                .unwrap()
+                .bind_vertex_buffers(0, (vertex_buffer, instance_buffer))
+                .unwrap();
+            for (camera, (offset, extent)) in &cameras {
+                // cameras drawn later cover the normals of the earlier ones
+                command_buffer_builder
+                    .clear_attachments(
+                        [ClearAttachment::Color {
+                            color_attachment: 0,
+                            clear_value: [0.0; 4].into(),
+                        }]
+                        .into_iter()
+                        .collect(),
+                        [clear_rect(*offset, *extent, image_extent)]
+                            .into_iter()
+                            .collect(),
+                    )
+                    .unwrap()
+                    .set_viewport(0, [viewport(*offset, *extent)].into_iter().collect())
+                    .unwrap()
+                    .push_constants(
+                        pipeline.layout().clone(),
+                        0,
+                        ProjectionPushConstants {
+                            projection_view: camera.projection_view(*extent).to_cols_array_2d(),
+                        },
+                    )
+                    .unwrap();
+                let mut first = 0;
+                for run in sprites.chunk_by(|a, b| a.1.id == b.1.id) {
+                    command_buffer_builder
+                        .bind_descriptor_sets(
+                            PipelineBindPoint::Graphics,
+                            pipeline.layout().clone(),
+                            0,
+                            descriptor_sets[&run[0].1.id].clone(),
+                        )
+                        .unwrap();
+                    unsafe { command_buffer_builder.draw(6, run.len() as u32, 0, first as u32) }
+                        .unwrap();
+                    first += run.len();
+                }
+            }
+        }
+        command_buffer_builder
+            .end_render_pass(Default::default())
+            .unwrap();
+
+        // the ambient light, then the lights one after another with their shadows
+        let render_pass = vulkano::single_pass_renderpass!(
+            info.device.clone(),
+            attachments: {
+                color: {
+                    format: Format::R16G16B16A16_SFLOAT,
+                    samples: 1,
+                    load_op: Clear,
+                    store_op: Store,
+                },
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {},
+            },
+        )
+        .unwrap();
+        let framebuffer = Framebuffer::new(
+            render_pass.clone(),
+            FramebufferCreateInfo {
+                attachments: vec![light_image.clone()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let subpass = Subpass::from(render_pass, 0).unwrap();
+        let ambient_clear_value = ambient_light.extend(1.0).to_array();
+        command_buffer_builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![Some(ambient_clear_value.into())],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassBeginInfo {
+                    contents: SubpassContents::Inline,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        if !render_list.lights.is_empty() {
+            // the quad of a light sets the alpha to 1, its shadows lower it to what they let
+            // through, overlapping shadows keep the darkest
+            let alpha_only = |blend| ColorBlendAttachmentState {
+                blend,
+                color_write_mask: ColorComponents::A,
+                ..Default::default()
+            };
+            let mask_pipeline =
+                info.shaders
+                    .graphics_pipeline(&info.device, &SHADOW_SHADERS, |vs, fs, layout| {
+                        let vertex_input_state = LightVertex::per_vertex().definition(&vs)?;
+                        create_pipeline(
+                            info,
+                            &subpass,
+                            (vs, fs, layout),
+                            vertex_input_state,
+                            alpha_only(None),
+                        )
+                    });
+            let shadow_pipeline =
+                info.shaders
+                    .graphics_pipeline(&info.device, &SHADOW_SHADERS, |vs, fs, layout| {
+                        let vertex_input_state = LightVertex::per_vertex().definition(&vs)?;
+                        create_pipeline(
+                            info,
+                            &subpass,
+                            (vs, fs, layout),
+                            vertex_input_state,
+                            alpha_only(Some(AttachmentBlend {
+                                src_alpha_blend_factor: BlendFactor::One,
+                                dst_alpha_blend_factor: BlendFactor::One,
+                                alpha_blend_op: BlendOp::Min,
+                                ..Default::default()
+                            })),
+                        )
+                    });
+            // added to the light image where the alpha lets it through
+            let light_pipeline =
+                info.shaders
+                    .graphics_pipeline(&info.device, &LIGHT_SHADERS, |vs, fs, layout| {
+                        let vertex_input_state = LightVertex::per_vertex().definition(&vs)?;
+                        create_pipeline(
+                            info,
+                            &subpass,
+                            (vs, fs, layout),
+                            vertex_input_state,
+                            ColorBlendAttachmentState {
+                                blend: Some(AttachmentBlend {
+                                    src_color_blend_factor: BlendFactor::DstAlpha,
+                                    dst_color_blend_factor: BlendFactor::One,
+                                    color_blend_op: BlendOp::Add,
+                                    src_alpha_blend_factor: BlendFactor::Zero,
+                                    dst_alpha_blend_factor: BlendFactor::One,
+                                    alpha_blend_op: BlendOp::Add,
+                                }),
+                                ..Default::default()
+                            },
+                        )
+                    });
+            let normals = descriptor_set(&light_pipeline, normal_image.clone());
+
+            let mut vertices = Vec::new();
+            let mut ranges = Vec::new();
+            for light in &render_list.lights {
+                let first = vertices.len() as u32;
+                light_vertices(light, &render_list.shadow_shapes, &mut vertices);
+                ranges.push(LightRange {
+                    first,
+                    shadows: vertices.len() as u32 - first - 6,
+                });
+            }
+            let vertex_buffer = Buffer::from_iter(
+                info.memory_allocator.clone(),
+                buffer_create_info(),
+                allocation_create_info(),
+                vertices,
+            )
+            .unwrap();
+            command_buffer_builder
+                .bind_vertex_buffers(0, vertex_buffer)
+                .unwrap();
+
+            for (camera, (offset, extent)) in &cameras {
+                command_buffer_builder
+                    .clear_attachments(
+                        [ClearAttachment::Color {
+                            color_attachment: 0,
+                            clear_value: ambient_clear_value.into(),
+                        }]
+                        .into_iter()
+                        .collect(),
+                        [clear_rect(*offset, *extent, image_extent)]
+                            .into_iter()
+                            .collect(),
+                    )
+                    .unwrap()
+                    .set_viewport(0, [viewport(*offset, *extent)].into_iter().collect())
+                    .unwrap();
+                let projection_view = camera.projection_view(*extent).to_cols_array_2d();
+                let (view_min, view_max) = camera.view_aabb(*extent);
+                for (light, range) in render_list.lights.iter().zip(&ranges) {
+                    if (light.position - light.position.clamp(view_min, view_max)).length()
+                        > light.radius
+                    {
+                        continue;
+                    }
+                    for (pipeline, first, count) in [
+                        (&mask_pipeline, range.first, 6),
+                        (&shadow_pipeline, range.first + 6, range.shadows),
+                    ] {
+                        if count == 0 {
+                            continue;
+                        }
+                        command_buffer_builder
+                            .bind_pipeline_graphics(pipeline.clone())
+                            .unwrap()
+                            .push_constants(
+                                pipeline.layout().clone(),
+                                0,
+                                ProjectionPushConstants { projection_view },
+                            )
+                            .unwrap();
+                        unsafe { command_buffer_builder.draw(count, 1, first, 0) }.unwrap();
+                    }
+                    let (cos_inner, cos_outer) = light.cone.unwrap_or((-1.0, -1.0));
+                    command_buffer_builder
+                        .bind_pipeline_graphics(light_pipeline.clone())
+                        .unwrap()
+                        .push_constants(
+                            light_pipeline.layout().clone(),
+                            0,
+                            LightPushConstants {
+                                projection_view,
+                                position: light.position.to_array(),
+                                radius: light.radius,
+                                height: light.height,
+                                color: light.color.extend(1.0).to_array(),
+                                direction: light.direction.to_array(),
+                                cos_inner,
+                                cos_outer,
+                            },
+                        )
+                        .unwrap()
+                        .bind_descriptor_sets(
+                            PipelineBindPoint::Graphics,
+                            light_pipeline.layout().clone(),
+                            0,
+                            normals.clone(),
+                        )
+                        .unwrap();
+                    unsafe { command_buffer_builder.draw(6, 1, range.first, 0) }.unwrap();
+                }
+            }
+        }
+        command_buffer_builder
+            .end_render_pass(Default::default())
+            .unwrap();
+    }
+
+    fn draw(
+        &mut self,
+        render_list: &RenderList,
+        info: &RenderInfo,
+        command_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) {
+        if render_list.lights.is_empty() && render_list.ambient_light.is_none() {
+            return;
+        }
+        let (Some((_, light_image)), Some(descriptor_set_allocator)) =
+            (self.images.clone(), self.descriptor_set_allocator.clone())
+        else {
+            return;
+        };
+        let sampler = Sampler::new(
+            info.device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // the scene image times the light image
+        let subpass = info.subpass.clone();
+        let pipeline =
+            info.shaders
+                .graphics_pipeline(&info.device, &COMPOSITE_SHADERS, |vs, fs, layout| {
+                    create_pipeline(
+                        info,
+                        &subpass,
+                        (vs, fs, layout),
+                        VertexInputState::new(),
+                        ColorBlendAttachmentState {
+                            blend: Some(AttachmentBlend {
+                                src_color_blend_factor: BlendFactor::Zero,
+                                dst_color_blend_factor: BlendFactor::SrcColor,
+                                color_blend_op: BlendOp::Add,
+                                src_alpha_blend_factor: BlendFactor::Zero,
+                                dst_alpha_blend_factor: BlendFactor::One,
+                                alpha_blend_op: BlendOp::Add,
+                            }),
+                            ..Default::default()
+                        },
+                    )
+                });
+        command_buffer_builder
+            .bind_pipeline_graphics(pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                pipeline.layout().clone(),
+                0,
+                DescriptorSet::new(
+                    descriptor_set_allocator,
+                    pipeline.layout().set_layouts()[0].clone(),
+                    [
+                        WriteDescriptorSet::sampler(0, sampler),
+                        WriteDescriptorSet::image_view(1, light_image),
+                    ],
+                    [],
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        // only the viewports of the cameras were lit
+        for camera in &render_list.cameras {
+            let (offset, extent) = camera.viewport_rect(info.window_size);
+            if extent.x < 1.0 || extent.y < 1.0 {
+                continue;
+            }
+            command_buffer_builder
+                .set_viewport(0, [viewport(offset, extent)].into_iter().collect())
+                .unwrap();
+            unsafe { command_buffer_builder.draw(3, 1, 0, 0) }.unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shader_library::{ShaderErrorKind, ShaderReflection};
+
+    // light.vert or light.frag as it is compiled into the engine
+    fn reflection(shader: &Shader) -> ShaderReflection {
+        ShaderReflection::from_spirv(shader.path, shader.spirv).unwrap()
+    }
+
+    #[derive(BufferContents, Vertex)]
+    #[repr(C)]
+    struct WideLightVertex {
+        #[format(R32G32B32_SFLOAT)]
+        position: [f32; 3],
+    }
+
+    // the members of LightPushConstants with radius and height before position, so the size
+    // matches but the offsets do not
+    #[derive(BufferContents, Vertex)]
+    #[repr(C)]
+    struct ReorderedLightPushConstants {
+        #[format(R32G32B32A32_SFLOAT)]
+        projection_view: [[f32; 4]; 4],
+        #[format(R32_SFLOAT)]
+        radius: f32,
+        #[format(R32_SFLOAT)]
+        height: f32,
+        #[format(R32G32_SFLOAT)]
+        position: [f32; 2],
+        #[format(R32G32B32A32_SFLOAT)]
+        color: [f32; 4],
+        #[format(R32G32_SFLOAT)]
+        direction: [f32; 2],
+        #[format(R32_SFLOAT)]
+        cos_inner: f32,
+        #[format(R32_SFLOAT)]
+        cos_outer: f32,
+    }
+
+    // LightPushConstants with a uvec4 color, which has the size and offset of the vec4
+    #[derive(BufferContents, Vertex)]
+    #[repr(C)]
+    struct UintColorLightPushConstants {
+        #[format(R32G32B32A32_SFLOAT)]
+        projection_view: [[f32; 4]; 4],
+        #[format(R32G32_SFLOAT)]
+        position: [f32; 2],
+        #[format(R32_SFLOAT)]
+        radius: f32,
+        #[format(R32_SFLOAT)]
+        height: f32,
+        #[format(R32G32B32A32_UINT)]
+        color: [u32; 4],
+        #[format(R32G32_SFLOAT)]
+        direction: [f32; 2],
+        #[format(R32_SFLOAT)]
+        cos_inner: f32,
+        #[format(R32_SFLOAT)]
+        cos_outer: f32,
+    }
+
+    #[test]
+    fn vertex_input() {
+        let vs = reflection(&LIGHT_SHADERS[0]);
+        vs.validate_vertex_input(&[LightVertex::per_vertex()])
+            .unwrap();
+        let error = vs
+            .validate_vertex_input(&[WideLightVertex::per_vertex()])
+            .unwrap_err();
+        assert!(matches!(
+            error.kind,
+            ShaderErrorKind::VertexMemberMismatch { location: 0, .. }
+        ));
+        let fs = reflection(&LIGHT_SHADERS[1]);
+        let error = fs
+            .validate_vertex_input(&[LightVertex::per_vertex()])
+            .unwrap_err();
+        assert!(matches!(error.kind, ShaderErrorKind::NotVertexShader));
+    }
+
+    #[test]
+    fn push_constants() {
+        for shader in &LIGHT_SHADERS {
+            let reflection = reflection(shader);
+            reflection
+                .validate_push_constants::<LightPushConstants>()
+                .unwrap();
+            let error = reflection
+                .validate_push_constants::<ReorderedLightPushConstants>()
+                .unwrap_err();
+            assert!(matches!(
+                error.kind,
+                ShaderErrorKind::PushConstantMemberMismatch {
+                    offset: 64,
+                    rust_offset: 72,
+                    ..
+                }
+            ));
+            let error = reflection
+                .validate_push_constants::<UintColorLightPushConstants>()
+                .unwrap_err();
+            assert!(matches!(
+                error.kind,
+                ShaderErrorKind::PushConstantMemberMismatch {
+                    offset: 80,
+                    rust_offset: 80,
+                    ..
+                }
+            ));
+            let error = reflection
+                .validate_push_constants::<ProjectionPushConstants>()
+                .unwrap_err();
+            assert!(matches!(
+                error.kind,
+                ShaderErrorKind::PushConstantsSize {
+                    shader: 112,
+                    rust: 64,
+                    ..
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn descriptor() {
+        let fs = reflection(&LIGHT_SHADERS[1]);
+        fs.validate_descriptor(0, 0, DescriptorType::Sampler)
+            .unwrap();
+        fs.validate_descriptor(0, 1, DescriptorType::SampledImage)
+            .unwrap();
+        let error = fs
+            .validate_descriptor(0, 1, DescriptorType::Sampler)
+            .unwrap_err();
+        assert!(matches!(
+            error.kind,
+            ShaderErrorKind::DescriptorTypeMismatch {
+                set: 0,
+                binding: 1,
+                rust: DescriptorType::Sampler,
+                ..
+            }
+        ));
+        let error = fs
+            .validate_descriptor(0, 2, DescriptorType::SampledImage)
+            .unwrap_err();
+        assert!(matches!(
+            error.kind,
+            ShaderErrorKind::MissingDescriptor { set: 0, binding: 2 }
+        ));
+    }
+}
@@ -0,0 +1,753 @@
+use super::{
+    asset::{Asset, AssetCache, AssetReader},
+    clip::relative_to,
+    AssetServer, Interpolation2D, Quad2D, RenderInfo, RenderList, RenderPass, Renderer2D, Shader,
+    TextureImage, Transform2D, Value,
+};
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use serde_json::Value as JsonValue;
+use shader_library::{include_spirv, UniformBlock};
+use shipyard::{Get, IntoIter, IntoWithId, Unique, UniqueView, UniqueViewMut, View};
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorType, DescriptorSet,
+        WriteDescriptorSet,
+    },
+    format::{Format, NumericType},
+    image::{
+        sampler::{Filter, Sampler, SamplerCreateInfo},
+        SampleCount,
+    },
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{
+        graphics::{
+            color_blend::{AttachmentBlend, ColorBlendAttachmentState, ColorBlendState},
+            input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            rasterization::RasterizationState,
+            vertex_input::{Vertex, VertexDefinition},
+            viewport::{Viewport, ViewportState},
+            GraphicsPipelineCreateInfo,
+        },
+        layout::PipelineDescriptorSetLayoutCreateInfo,
+        DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    render_pass::Subpass,
+    shader::{spirv::ExecutionModel, EntryPoint},
+};
+
+// ids of loaded Material2Ds
+static NEXT_MATERIAL_ID: AtomicU64 = AtomicU64::new(1);
+
+// how a quad is combined with what is drawn below it, quads are drawn in this order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BlendMode {
+    Opaque,
+    Alpha,
+    Additive,
+}
+
+// a fragment shader of a material, compiled to SPIR-V when the material loads
+pub struct MaterialShader {
+    pub path: String,
+    pub spirv: Arc<[u8]>,
+}
+
+// How the quads of Renderer2D components look, loaded from json:
+// {
+//   "color": [1.0, 1.0, 1.0, 1.0], // multiplied with the texture and the Renderer2D color
+//   "texture": "crate.png", // optional, relative to the material file
+//   "blend": "Alpha", // Opaque, Alpha or Additive
+//   "shader": "checker.frag", // optional fragment shader, GLSL or SPIR-V
+//   "params": { "cells": 4, "dark": [0.5, 0.5, 0.5, 1.0] } // the uniform block of the shader
+// }
+// The shader gets the inputs and descriptor set 0 of shaders/quad.frag. Params are numbers or
+// arrays of 2 to 4 numbers, each is written into the member of the same name of the uniform block
+// at set 1 binding 0 and has to match its type.
+pub struct Material2D {
+    pub id: u64, // unique per loaded material, so passes compile each shader once
+    pub color: Vec4,
+    pub texture: Option<TextureImage>,
+    pub blend: BlendMode,
+    pub shader: Option<MaterialShader>,
+    pub params: Vec<(String, Value)>,
+}
+
+impl Default for Material2D {
+    fn default() -> Self {
+        Material2D {
+            id: NEXT_MATERIAL_ID.fetch_add(1, Ordering::Relaxed),
+            color: Vec4::ONE,
+            texture: None,
+            blend: BlendMode::Opaque,
+            shader: None,
+            params: Vec::new(),
+        }
+    }
+}
+
+impl Asset for Material2D {
+    fn load(path: &str, data: Vec<u8>, reader: &AssetReader) -> Result<Self, String> {
+        let json: JsonValue = serde_json::from_slice(&data).map_err(|e| e.to_string())?;
+        let mut material = Material2D::default();
+        if !json["color"].is_null() {
+            material.color = json_param(&json["color"])
+                .and_then(|color| match color {
+                    Value::Vec4(color) => Some(color),
+                    _ => None,
+                })
+                .ok_or("color is not an array of 4 numbers")?;
+        }
+        if let Some(texture) = json["texture"].as_str() {
+            let texture = relative_to(path, texture);
+            let data = reader.read(&texture)?;
+            material.texture =
+                Some(TextureImage::decode(&data).map_err(|e| format!("{texture}: {e}"))?);
+        }
+        material.blend = match json["blend"].as_str() {
+            None | Some("Opaque") => BlendMode::Opaque,
+            Some("Alpha") => BlendMode::Alpha,
+            Some("Additive") => BlendMode::Additive,
+            Some(blend) => return Err(format!("unknown blend mode {blend}")),
+        };
+        if let Some(shader) = json["shader"].as_str() {
+            let shader = relative_to(path, shader);
+            let data = reader.read(&shader)?;
+            let spirv = shader_library::to_spirv(&shader, &data).map_err(|e| e.to_string())?;
+            material.shader = Some(MaterialShader {
+                path: shader,
+                spirv: spirv.into(),
+            });
+        }
+        if let Some(params) = json["params"].as_object() {
+            for (name, value) in params {
+                let value = json_param(value).ok_or(format!(
+                    "param {name} is not a number or an array of 2 to 4 numbers"
+                ))?;
+                material.params.push((name.clone(), value));
+            }
+        }
+        Ok(material)
+    }
+}
+
+fn json_param(json: &JsonValue) -> Option<Value> {
+    if let Some(value) = json.as_i64() {
+        return Some(Value::Int32(value as i32));
+    }
+    if let Some(value) = json.as_f64() {
+        return Some(Value::Float32(value as f32));
+    }
+    let values = json
+        .as_array()?
+        .iter()
+        .map(|v| v.as_f64().map(|v| v as f32))
+        .collect::<Option<Vec<_>>>()?;
+    match values[..] {
+        [x, y] => Some(Value::Vec2(Vec2::new(x, y))),
+        [x, y, z] => Some(Value::Vec3(Vec3::new(x, y, z))),
+        [x, y, z, w] => Some(Value::Vec4(Vec4::new(x, y, z, w))),
+        _ => None,
+    }
+}
+
+// the GLSL name of a type of a uniform block member, e.g. float or vec4
+fn glsl_type(numeric_type: NumericType, components: u32) -> String {
+    let (scalar, prefix) = match numeric_type {
+        NumericType::Float => ("float", ""),
+        NumericType::Int => ("int", "i"),
+        NumericType::Uint => ("uint", "u"),
+    };
+    match components {
+        1 => scalar.into(),
+        n => format!("{prefix}vec{n}"),
+    }
+}
+
+// the params written into a buffer with the layout of the uniform block
+fn pack_params(block: &UniformBlock, params: &[(String, Value)]) -> Result<Vec<u8>, String> {
+    let mut data = vec![0u8; (block.size as usize).next_multiple_of(16).max(16)];
+    for (name, value) in params {
+        let member = block
+            .members
+            .iter()
+            .find(|member| &member.name == name)
+            .ok_or(format!(
+                "param {name} is not in the uniform block of the shader"
+            ))?;
+        let words: Vec<u32> = match (value, member.numeric_type, member.components) {
+            (Value::Float32(v), NumericType::Float, 1) => vec![v.to_bits()],
+            (Value::Int32(v), NumericType::Float, 1) => vec![(*v as f32).to_bits()],
+            (Value::Int32(v), NumericType::Int | NumericType::Uint, 1) => vec![*v as u32],
+            (Value::Vec2(v), NumericType::Float, 2) => v.to_array().map(f32::to_bits).to_vec(),
+            (Value::Vec3(v), NumericType::Float, 3) => v.to_array().map(f32::to_bits).to_vec(),
+            (Value::Vec4(v), NumericType::Float, 4) => v.to_array().map(f32::to_bits).to_vec(),
+            _ => {
+                return Err(format!(
+                    "param {name} is {value:?}, but {} in the shader",
+                    glsl_type(member.numeric_type, member.components)
+                ))
+            }
+        };
+        for (i, word) in words.into_iter().enumerate() {
+            let offset = member.offset as usize + i * 4;
+            data[offset..offset + 4].copy_from_slice(&word.to_ne_bytes());
+        }
+    }
+    Ok(data)
+}
+
+// Materials of Renderer2D components by path
+#[derive(Unique)]
+pub struct MaterialManager {
+    materials: AssetCache<Material2D>,
+    default: Arc<Material2D>, // for renderers without material
+    white: TextureImage,      // for materials without texture
+}
+
+impl MaterialManager {
+    pub fn new() -> Self {
+        MaterialManager {
+            materials: AssetCache::new(),
+            default: Arc::new(Material2D::default()),
+            white: TextureImage::new(1, 1, Arc::new([255; 4])),
+        }
+    }
+
+    // the material at path once it is loaded, the default material until then or if path is empty
+    fn material(&mut self, asset_server: &AssetServer, path: &str) -> Arc<Material2D> {
+        if path.is_empty() {
+            return self.default.clone();
+        }
+        self.materials
+            .get(asset_server, path)
+            .unwrap_or_else(|| self.default.clone())
+    }
+
+    // the blend mode a renderer with the material at path is drawn with
+    pub fn blend(&self, path: &str) -> BlendMode {
+        self.materials
+            .loaded(path)
+            .map_or(self.default.blend, |material| material.blend)
+    }
+}
+
+pub fn renderer2d_extract_system(
+    asset_server: UniqueView<AssetServer>,
+    transform2d: View<Transform2D>,
+    renderer2d: View<Renderer2D>,
+    interpolation2d: View<Interpolation2D>,
+    mut material_manager: UniqueViewMut<MaterialManager>,
+    mut render_list: UniqueViewMut<RenderList>,
+) {
+    let alpha = render_list.alpha;
+    for (e, (transform2d, renderer2d)) in (&transform2d, &renderer2d).iter().with_id() {
+        let (position, rotation) = match interpolation2d.get(e) {
+            Ok(interpolation2d) => interpolation2d.blend(transform2d, alpha),
+            Err(_) => (transform2d.position, transform2d.rotation),
+        };
+        let model = Mat4::from_scale_rotation_translation(
+            Vec3 {
+                x: transform2d.scale.x,
+                y: transform2d.scale.y,
+                z: 1.0,
+            },
+            Quat::from_axis_angle(Vec3::Z, rotation),
+            position,
+        );
+        let material = material_manager.material(&asset_server, &renderer2d.material);
+        let texture = material
+            .texture
+            .clone()
+            .unwrap_or_else(|| material_manager.white.clone());
+        render_list.quads.push(Quad2D {
+            model,
+            color: renderer2d.color,
+            material,
+            texture,
+        });
+    }
+    material_manager.materials.retain_used();
+}
+
+// a corner of the quad every Quad2D is an instance of
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct QuadVertex {
+    #[format(R32G32_SFLOAT)]
+    position: [f32; 2],
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct QuadInstance {
+    #[format(R32G32B32A32_SFLOAT)]
+    model: [[f32; 4]; 4],
+    #[format(R32G32B32A32_SFLOAT)]
+    color: [f32; 4],
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct QuadPushConstants {
+    #[format(R32G32B32A32_SFLOAT)]
+    projection_view: [[f32; 4]; 4],
+}
+
+const QUAD_SHADERS: [Shader; 2] = [
+    Shader {
+        path: "shaders/quad.vert",
+        spirv: include_spirv!("shaders/quad.vert"),
+        validate: |vs| {
+            vs.validate_vertex_input(&[QuadVertex::per_vertex(), QuadInstance::per_instance()])?;
+            vs.validate_push_constants::<QuadPushConstants>()
+        },
+    },
+    Shader {
+        path: "shaders/quad.frag",
+        spirv: include_spirv!("shaders/quad.frag"),
+        validate: |fs| {
+            fs.validate_descriptor(0, 0, DescriptorType::Sampler)?;
+            fs.validate_descriptor(0, 1, DescriptorType::SampledImage)
+        },
+    },
+];
+
+// the fragment shader of a material if it fits the quad pass and the params of the material
+fn material_shader(
+    info: &RenderInfo,
+    material: &Material2D,
+    shader: &MaterialShader,
+) -> Result<Arc<shader_library::Shader>, String> {
+    let fs = shader_library::Shader::from_spirv(&info.device, &shader.path, &shader.spirv)
+        .map_err(|e| e.to_string())?;
+    let error = |e: String| format!("{}: {e}", shader.path);
+    if fs.execution_model() != ExecutionModel::Fragment {
+        return Err(error("not a fragment shader".into()));
+    }
+    if fs.descriptors().contains_key(&(0, 0)) || fs.descriptors().contains_key(&(0, 1)) {
+        fs.validate_descriptor(0, 0, DescriptorType::Sampler)
+            .and_then(|_| fs.validate_descriptor(0, 1, DescriptorType::SampledImage))
+            .map_err(|e| e.to_string())?;
+    }
+    if fs.descriptors().contains_key(&(1, 0)) {
+        fs.validate_descriptor(1, 0, DescriptorType::UniformBuffer)
+            .map_err(|e| e.to_string())?;
+        let block = fs.uniform_block(1, 0).ok_or(error(
+            "the params block may only contain scalars and vectors".into(),
+        ))?;
+        pack_params(block, &material.params).map_err(error)?;
+    } else if !material.params.is_empty() {
+        return Err(error(
+            "params need a uniform block at set 1 binding 0".into(),
+        ));
+    }
+    Ok(Arc::new(fs))
+}
+
+fn create_pipeline(
+    info: &RenderInfo,
+    subpass: &Subpass,
+    vs: EntryPoint,
+    fs: EntryPoint,
+    layout: Arc<PipelineLayout>,
+    blend: BlendMode,
+) -> Result<Arc<GraphicsPipeline>, Box<dyn Error>> {
+    let vertex_input_state =
+        [QuadVertex::per_vertex(), QuadInstance::per_instance()].definition(&vs)?;
+    let stages = [
+        PipelineShaderStageCreateInfo::new(vs),
+        PipelineShaderStageCreateInfo::new(fs),
+    ];
+    Ok(GraphicsPipeline::new(
+        info.device.clone(),
+        None,
+        GraphicsPipelineCreateInfo {
+            stages: stages.into_iter().collect(),
+            vertex_input_state: Some(vertex_input_state),
+            input_assembly_state: Some(InputAssemblyState::default()),
+            rasterization_state: Some(RasterizationState::default()),
+            multisample_state: Some(MultisampleState {
+                rasterization_samples: subpass.num_samples().unwrap(),
+                ..Default::default()
+            }),
+            color_blend_state: Some(ColorBlendState::with_attachment_states(
+                subpass.num_color_attachments(),
+                ColorBlendAttachmentState {
+                    blend: match blend {
+                        BlendMode::Opaque => None,
+                        BlendMode::Alpha => Some(AttachmentBlend::alpha()),
+                        BlendMode::Additive => Some(AttachmentBlend::additive()),
+                    },
+                    ..Default::default()
+                },
+            )),
+            viewport_state: Some(ViewportState::default()),
+            dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+            subpass: Some(subpass.clone().into()),
+            ..GraphicsPipelineCreateInfo::layout(layout)
+        },
+    )?)
+}
+
+// a pipeline with the fragment shader of a material, its layout comes from both shaders
+fn material_pipeline(
+    info: &RenderInfo,
+    subpass: &Subpass,
+    vs: &shader_library::Shader,
+    fs: &shader_library::Shader,
+    blend: BlendMode,
+) -> Result<Arc<GraphicsPipeline>, Box<dyn Error>> {
+    let vs = vs.entry_point();
+    let fs = fs.entry_point();
+    let stages = [
+        PipelineShaderStageCreateInfo::new(vs.clone()),
+        PipelineShaderStageCreateInfo::new(fs.clone()),
+    ];
+    let layout = PipelineLayout::new(
+        info.device.clone(),
+        PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+            .into_pipeline_layout_create_info(info.device.clone())?,
+    )?;
+    create_pipeline(info, subpass, vs, fs, layout, blend)
+}
+
+// material pipelines by shader path, blend mode, samples and format of the scene image
+type PipelineKey = (String, BlendMode, SampleCount, Format);
+
+// a material pipeline and the shaders it was created with, it is reused while they do not change
+struct MaterialPipeline {
+    vs: Arc<shader_library::Shader>,
+    spirv: Arc<[u8]>,
+    pipeline: Arc<GraphicsPipeline>,
+}
+
+// the pipeline of a material with its loaded fragment shader, created when there is none yet for
+// the shader path or when the shaders changed. When a changed shaders/quad.vert does not fit the
+// material shader, the last pipeline is kept and the error shown with the shader errors.
+fn cached_pipeline(
+    pipelines: &mut HashMap<PipelineKey, MaterialPipeline>,
+    info: &RenderInfo,
+    subpass: &Subpass,
+    material: &Material2D,
+    fs: &shader_library::Shader,
+) -> Result<Arc<GraphicsPipeline>, Box<dyn Error>> {
+    let shader = material.shader.as_ref().unwrap();
+    let vs = info.shaders.current(&info.device, &QUAD_SHADERS[0]);
+    let key = (
+        shader.path.clone(),
+        material.blend,
+        info.samples,
+        info.format,
+    );
+    let cached = pipelines.get_mut(&key);
+    if let Some(cached) = &cached {
+        if Arc::ptr_eq(&cached.vs, &vs) && cached.spirv == shader.spirv {
+            return Ok(cached.pipeline.clone());
+        }
+    }
+    let pipeline = match material_pipeline(info, subpass, &vs, fs, material.blend) {
+        Ok(pipeline) => {
+            info.shaders.used(&QUAD_SHADERS[0], &vs, &shader.path);
+            pipeline
+        }
+        Err(e) => {
+            info.shaders
+                .failed(&QUAD_SHADERS[0], &vs, &shader.path, e.as_ref());
+            return match cached.filter(|cached| cached.spirv == shader.spirv) {
+                Some(cached) => {
+                    // not tried again until the vertex shader changes again
+                    cached.vs = vs;
+                    Ok(cached.pipeline.clone())
+                }
+                None => Err(e),
+            };
+        }
+    };
+    pipelines.insert(
+        key,
+        MaterialPipeline {
+            vs,
+            spirv: shader.spirv.clone(),
+            pipeline: pipeline.clone(),
+        },
+    );
+    Ok(pipeline)
+}
+
+// the quads of a material, drawn as instances at once
+struct Batch {
+    pipeline: Arc<GraphicsPipeline>,
+    descriptor_sets: Vec<(u32, Arc<DescriptorSet>)>, // by set number
+    first: u32,
+    count: u32,
+}
+
+// draws the quads of the render list as instances of one quad with every camera. There is no depth
+// buffer, so they are drawn in the order of the render list within each blend mode, and each run
+// of quads with the same material is one draw.
+#[derive(Default)]
+pub struct Renderer2DPass {
+    descriptor_set_allocator: Option<Arc<StandardDescriptorSetAllocator>>,
+    // the fragment shaders of the drawn materials by id, None if the shader can not be used
+    shaders: HashMap<u64, Option<Arc<shader_library::Shader>>>,
+    pipelines: HashMap<PipelineKey, MaterialPipeline>, // of the drawn material shaders
+}
+
+impl RenderPass for Renderer2DPass {
+    fn draw(
+        &mut self,
+        render_list: &RenderList,
+        info: &RenderInfo,
+        command_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) {
+        if render_list.quads.is_empty() {
+            return;
+        }
+
+        let subpass = info.subpass.clone();
+
+        // shaders of new materials are loaded once, a shader which does not fit is logged and
+        // its material drawn with shaders/quad.frag
+        let materials = render_list
+            .quads
+            .iter()
+            .map(|quad| (quad.material.id, quad.material.clone()))
+            .collect::<HashMap<_, _>>();
+        self.shaders.retain(|id, _| materials.contains_key(id));
+        self.pipelines.retain(|(path, ..), _| {
+            materials.values().any(|material| {
+                material
+                    .shader
+                    .as_ref()
+                    .is_some_and(|shader| &shader.path == path)
+            })
+        });
+        let mut default_pipelines = HashMap::new();
+        let mut pipelines = HashMap::new();
+        for material in materials.values() {
+            let shader = material.shader.as_ref().and_then(|shader| {
+                self.shaders
+                    .entry(material.id)
+                    .or_insert_with(|| {
+                        material_shader(info, material, shader)
+                            .and_then(|fs| {
+                                cached_pipeline(&mut self.pipelines, info, &subpass, material, &fs)
+                                    .map(|_| fs)
+                                    .map_err(|e| format!("{}: {e}", shader.path))
+                            })
+                            .inspect_err(|e| {
+                                log::error!("Failed to use the shader of a material: {e}")
+                            })
+                            .ok()
+                    })
+                    .clone()
+            });
+            // without a pipeline for the material shader, e.g. with other samples, it is drawn
+            // with shaders/quad.frag
+            let pipeline = shader.and_then(|fs| {
+                cached_pipeline(&mut self.pipelines, info, &subpass, material, &fs).ok()
+            });
+            let pipeline = match pipeline {
+                Some(pipeline) => pipeline,
+                None => default_pipelines
+                    .entry(material.blend)
+                    .or_insert_with(|| {
+                        info.shaders.graphics_pipeline(
+                            &info.device,
+                            &QUAD_SHADERS,
+                            |vs, fs, layout| {
+                                create_pipeline(info, &subpass, vs, fs, layout, material.blend)
+                            },
+                        )
+                    })
+                    .clone(),
+            };
+            pipelines.insert(material.id, pipeline);
+        }
+
+        // the sort is stable, so quads which overlap keep their order
+        let mut quads = render_list.quads.iter().collect::<Vec<_>>();
+        quads.sort_by_key(|quad| quad.material.blend);
+
+        let sampler = Sampler::new(
+            info.device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let descriptor_set_allocator = self
+            .descriptor_set_allocator
+            .get_or_insert_with(|| {
+                Arc::new(StandardDescriptorSetAllocator::new(
+                    info.device.clone(),
+                    Default::default(),
+                ))
+            })
+            .clone();
+        let allocation_create_info = || AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        };
+
+        let mut batches = Vec::new();
+        let mut first = 0;
+        for run in quads.chunk_by(|a, b| a.material.id == b.material.id) {
+            let material = &run[0].material;
+            let pipeline = pipelines[&material.id].clone();
+            let set_layouts = pipeline.layout().set_layouts();
+            let mut descriptor_sets = Vec::new();
+            // the texture, unless a material shader does not use it
+            if set_layouts
+                .first()
+                .is_some_and(|set_layout| !set_layout.bindings().is_empty())
+            {
+                let descriptor_set = DescriptorSet::new(
+                    descriptor_set_allocator.clone(),
+                    set_layouts[0].clone(),
+                    [
+                        WriteDescriptorSet::sampler(0, sampler.clone()),
+                        WriteDescriptorSet::image_view(
+                            1,
+                            info.textures[&run[0].texture.id].clone(),
+                        ),
+                    ],
+                    [],
+                )
+                .unwrap();
+                descriptor_sets.push((0, descriptor_set));
+            }
+            // the params of a material shader
+            if let Some(Some(fs)) = self.shaders.get(&material.id) {
+                if let Some(block) = fs.uniform_block(1, 0) {
+                    // the params were checked against the block when the shader was loaded
+                    let params = pack_params(block, &material.params).unwrap();
+                    let buffer = Buffer::from_iter(
+                        info.memory_allocator.clone(),
+                        BufferCreateInfo {
+                            usage: BufferUsage::UNIFORM_BUFFER,
+                            ..Default::default()
+                        },
+                        allocation_create_info(),
+                        params,
+                    )
+                    .unwrap();
+                    let descriptor_set = DescriptorSet::new(
+                        descriptor_set_allocator.clone(),
+                        set_layouts[1].clone(),
+                        [WriteDescriptorSet::buffer(0, buffer)],
+                        [],
+                    )
+                    .unwrap();
+                    descriptor_sets.push((1, descriptor_set));
+                }
+            }
+            batches.push(Batch {
+                pipeline,
+                descriptor_sets,
+                first,
+                count: run.len() as u32,
+            });
+            first += run.len() as u32;
+        }
+
+        let buffer_create_info = || BufferCreateInfo {
+            usage: BufferUsage::VERTEX_BUFFER,
+            ..Default::default()
+        };
+        // two triangles of the quad
+        let vertex_buffer = Buffer::from_iter(
+            info.memory_allocator.clone(),
+            buffer_create_info(),
+            allocation_create_info(),
+            [
+                [-0.5, 0.5],
+                [-0.5, -0.5],
+                [0.5, -0.5],
+                [0.5, -0.5],
+                [0.5, 0.5],
+                [-0.5, 0.5],
+            ]
+            .map(|position| QuadVertex { position }),
+        )
+        .unwrap();
+        let instance_buffer = Buffer::from_iter(
+            info.memory_allocator.clone(),
+            buffer_create_info(),
+            allocation_create_info(),
+            quads
+                .iter()
+                .map(|quad| QuadInstance {
+                    model: quad.model.to_cols_array_2d(),
+                    color: (quad.material.color * quad.color).to_array(),
+                })
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+
+        command_buffer_builder
+            .bind_vertex_buffers(0, (vertex_buffer, instance_buffer))
+            .unwrap();
+
+        for camera in &render_list.cameras {
+            let (offset, extent) = camera.viewport_rect(info.window_size);
+            if extent.x < 1.0 || extent.y < 1.0 {
+                continue;
+            }
+            command_buffer_builder
+                .set_viewport(
+                    0,
+                    [Viewport {
+                        offset: offset.into(),
+                        extent: extent.into(),
+                        depth_range: 0.0..=1.0,
+                    }]
+                    .into_iter()
+                    .collect(),
+                )
+                .unwrap();
+            let projection_view = camera.projection_view(extent).to_cols_array_2d();
+            let mut bound: Option<&Arc<GraphicsPipeline>> = None;
+            for batch in &batches {
+                if !bound.is_some_and(|bound| Arc::ptr_eq(bound, &batch.pipeline)) {
+                    command_buffer_builder
+                        .bind_pipeline_graphics(batch.pipeline.clone())
+                        .unwrap()
+                        .push_constants(
+                            batch.pipeline.layout().clone(),
+                            0,
+                            QuadPushConstants { projection_view },
+                        )
+                        .unwrap();
+                    bound = Some(&batch.pipeline);
+                }
+                for (set, descriptor_set) in &batch.descriptor_sets {
+                    command_buffer_builder
+                        .bind_descriptor_sets(
+                            PipelineBindPoint::Graphics,
+                            batch.pipeline.layout().clone(),
+                            *set,
+                            descriptor_set.clone(),
+                        )
+                        .unwrap();
+                }
+                unsafe { command_buffer_builder.draw(6, batch.count, 0, batch.first) }.unwrap();
+            }
+        }
+    }
+}
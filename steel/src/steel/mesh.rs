@@ -0,0 +1,480 @@
+use super::{
+    asset::{Asset, AssetCache, AssetReader},
+    camera2d_extract_system, AssetServer, ComponentData, Edit, EngineBuilder, Interpolation2D,
+    Mesh2D, MeshVertex2D, Plugin, RenderInfo, RenderList, RenderPass, Shader, Stage, TextureImage,
+    Transform2D, Value, Variant,
+};
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use shader_library::include_spirv;
+use shipyard::{
+    Component, Get, IntoIter, IntoWithId, SystemModificator, Unique, UniqueView, UniqueViewMut,
+    View,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorType, DescriptorSet,
+        WriteDescriptorSet,
+    },
+    image::sampler::{Filter, Sampler, SamplerCreateInfo},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{
+        graphics::{
+            color_blend::{AttachmentBlend, ColorBlendAttachmentState, ColorBlendState},
+            input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            rasterization::RasterizationState,
+            vertex_input::{Vertex, VertexDefinition},
+            viewport::{Viewport, ViewportState},
+            GraphicsPipelineCreateInfo,
+        },
+        DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineShaderStageCreateInfo,
+    },
+};
+
+static NEXT_MESH_ID: AtomicU64 = AtomicU64::new(1);
+
+// MeshRenderer2D components, their mesh files and the pass which draws them
+pub struct Mesh2DPlugin;
+
+impl Plugin for Mesh2DPlugin {
+    fn build(&self, builder: &mut EngineBuilder) {
+        builder
+            .add_unique(MeshManager::new())
+            .add_component(MeshRenderer2D::default)
+            .add_system(
+                Stage::RenderExtract,
+                mesh_renderer2d_extract_system.after_all(camera2d_extract_system),
+            )
+            .add_render_pass(Mesh2DPass::default());
+    }
+}
+
+// Draws the triangles of a mesh file with the transform of its entity, in the units of the
+// transform like Renderer2D draws its unit square
+#[derive(Component, Debug)]
+#[track(All)]
+pub struct MeshRenderer2D {
+    pub mesh: String, // path of a Wavefront obj file, only x and y of the vertices are used
+    pub texture: String, // path of an image, the mesh has its color if it is empty
+    pub color: Vec4,  // multiplied with the texture
+}
+
+impl Default for MeshRenderer2D {
+    fn default() -> Self {
+        MeshRenderer2D {
+            mesh: String::new(),
+            texture: String::new(),
+            color: Vec4::ONE,
+        }
+    }
+}
+
+impl Edit for MeshRenderer2D {
+    fn name() -> &'static str {
+        "MeshRenderer2D"
+    }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "mesh",
+            value: Value::String(self.mesh.clone()),
+        });
+        data.variants.push(Variant {
+            name: "texture",
+            value: Value::String(self.texture.clone()),
+        });
+        data.variants.push(Variant {
+            name: "color",
+            value: Value::Vec4(self.color),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            match (v.name, v.value) {
+                ("mesh", Value::String(v)) => self.mesh = v,
+                ("texture", Value::String(v)) => self.texture = v,
+                ("color", Value::Vec4(v)) => self.color = v,
+                _ => (),
+            }
+        }
+    }
+}
+
+// The triangles of a Wavefront obj file. Faces with more than three corners are split into a fan
+// around their first corner, corners without uv use the top left of the texture.
+pub struct Mesh {
+    id: u64,
+    vertices: Arc<[MeshVertex2D]>,
+}
+
+impl Asset for Mesh {
+    fn load(_path: &str, data: Vec<u8>, _reader: &AssetReader) -> Result<Self, String> {
+        let source = String::from_utf8_lossy(&data);
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        let mut vertices = Vec::new();
+        for (number, line) in source.lines().enumerate() {
+            let error = |e: &str| format!("line {}: {e}", number + 1);
+            let mut words = line.split_whitespace();
+            let floats = |words: std::str::SplitWhitespace| {
+                words
+                    .take(2)
+                    .map(|w| w.parse::<f32>().map_err(|e| error(&e.to_string())))
+                    .collect::<Result<Vec<_>, _>>()
+                    .and_then(|v| match v[..] {
+                        [x, y] => Ok(Vec2::new(x, y)),
+                        _ => Err(error("expected two numbers")),
+                    })
+            };
+            match words.next() {
+                Some("v") => positions.push(floats(words)?),
+                Some("vt") => uvs.push(floats(words)?),
+                Some("f") => {
+                    let corners = words
+                        .map(|corner| {
+                            let mut indices = corner.split('/');
+                            let index = |list_len: usize, index: Option<&str>| {
+                                let Some(index) = index.filter(|i| !i.is_empty()) else {
+                                    return Ok(None);
+                                };
+                                let index =
+                                    index.parse::<i64>().map_err(|e| error(&e.to_string()))?;
+                                // negative indices count back from the last element
+                                let index = if index < 0 {
+                                    list_len as i64 + index
+                                } else {
+                                    index - 1
+                                };
+                                if (0..list_len as i64).contains(&index) {
+                                    Ok(Some(index as usize))
+                                } else {
+                                    Err(error("index out of range"))
+                                }
+                            };
+                            let position = index(positions.len(), indices.next())?
+                                .ok_or_else(|| error("face corner without position"))?;
+                            let uv = index(uvs.len(), indices.next())?;
+                            Ok(MeshVertex2D {
+                                position: positions[position],
+                                // obj uvs start at the bottom left
+                                uv: uv
+                                    .map_or(Vec2::ZERO, |uv| Vec2::new(uvs[uv].x, 1.0 - uvs[uv].y)),
+                            })
+                        })
+                        .collect::<Result<Vec<_>, String>>()?;
+                    for i in 1..corners.len().saturating_sub(1) {
+                        vertices.extend([corners[0], corners[i], corners[i + 1]]);
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(Mesh {
+            id: NEXT_MESH_ID.fetch_add(1, Ordering::Relaxed),
+            vertices: vertices.into(),
+        })
+    }
+}
+
+// Meshes and their textures by path
+#[derive(Unique)]
+pub struct MeshManager {
+    meshes: AssetCache<Mesh>,
+    textures: AssetCache<TextureImage>,
+    white: TextureImage, // for meshes without texture
+}
+
+impl MeshManager {
+    pub fn new() -> Self {
+        MeshManager {
+            meshes: AssetCache::new(),
+            textures: AssetCache::new(),
+            white: TextureImage::new(1, 1, Arc::new([255; 4])),
+        }
+    }
+
+    // the image at path once it is loaded, the white pixel if path is empty
+    fn texture(&mut self, asset_server: &AssetServer, path: &str) -> Option<TextureImage> {
+        if path.is_empty() {
+            return Some(self.white.clone());
+        }
+        self.textures
+            .get(asset_server, path)
+            .map(|texture| (*texture).clone())
+    }
+}
+
+fn mesh_renderer2d_extract_system(
+    asset_server: UniqueView<AssetServer>,
+    transform2d: View<Transform2D>,
+    mesh_renderer2d: View<MeshRenderer2D>,
+    interpolation2d: View<Interpolation2D>,
+    mut mesh_manager: UniqueViewMut<MeshManager>,
+    mut render_list: UniqueViewMut<RenderList>,
+) {
+    let alpha = render_list.alpha;
+    for (e, (transform2d, mesh_renderer2d)) in (&transform2d, &mesh_renderer2d).iter().with_id() {
+        let Some(mesh) = mesh_manager
+            .meshes
+            .get(&asset_server, &mesh_renderer2d.mesh)
+        else {
+            continue;
+        };
+        let Some(texture) = mesh_manager.texture(&asset_server, &mesh_renderer2d.texture) else {
+            continue;
+        };
+        let (position, rotation) = match interpolation2d.get(e) {
+            Ok(interpolation2d) => interpolation2d.blend(transform2d, alpha),
+            Err(_) => (transform2d.position, transform2d.rotation),
+        };
+        render_list.meshes.push(Mesh2D {
+            id: mesh.id,
+            model: Mat4::from_scale_rotation_translation(
+                transform2d.scale.extend(1.0),
+                Quat::from_axis_angle(Vec3::Z, rotation),
+                position,
+            ),
+            color: mesh_renderer2d.color,
+            texture,
+            vertices: mesh.vertices.clone(),
+        });
+    }
+    mesh_manager.meshes.retain_used();
+    mesh_manager.textures.retain_used();
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct MeshVertex {
+    #[format(R32G32_SFLOAT)]
+    position: [f32; 2],
+    #[format(R32G32_SFLOAT)]
+    uv: [f32; 2],
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct MeshPushConstants {
+    #[format(R32G32B32A32_SFLOAT)]
+    transform: [[f32; 4]; 4], // projection, view and model
+    #[format(R32G32B32A32_SFLOAT)]
+    color: [f32; 4],
+}
+
+const MESH_SHADERS: [Shader; 2] = [
+    Shader {
+        path: "shaders/mesh.vert",
+        spirv: include_spirv!("shaders/mesh.vert"),
+        validate: |vs| {
+            vs.validate_vertex_input(&[MeshVertex::per_vertex()])?;
+            vs.validate_push_constants::<MeshPushConstants>()
+        },
+    },
+    Shader {
+        path: "shaders/mesh.frag",
+        spirv: include_spirv!("shaders/mesh.frag"),
+        validate: |fs| {
+            fs.validate_descriptor(0, 0, DescriptorType::Sampler)?;
+            fs.validate_descriptor(0, 1, DescriptorType::SampledImage)
+        },
+    },
+];
+
+// draws the meshes of the render list in their order with every camera. Meshes stay on the gpu
+// while they are used.
+#[derive(Default)]
+struct Mesh2DPass {
+    vertex_buffers: HashMap<u64, Subbuffer<[MeshVertex]>>,
+    descriptor_set_allocator: Option<Arc<StandardDescriptorSetAllocator>>,
+}
+
+impl Mesh2DPass {
+    fn vertex_buffer(&mut self, mesh: &Mesh2D, info: &RenderInfo) -> Subbuffer<[MeshVertex]> {
+        self.vertex_buffers
+            .entry(mesh.id)
+            .or_insert_with(|| {
+                Buffer::from_iter(
+                    info.memory_allocator.clone(),
+                    BufferCreateInfo {
+                        usage: BufferUsage::VERTEX_BUFFER,
+                        ..Default::default()
+                    },
+                    AllocationCreateInfo {
+                        memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                            | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                        ..Default::default()
+                    },
+                    mesh.vertices.iter().map(|vertex| MeshVertex {
+                        position: vertex.position.to_array(),
+                        uv: vertex.uv.to_array(),
+                    }),
+                )
+                .unwrap()
+            })
+            .clone()
+    }
+}
+
+impl RenderPass for Mesh2DPass {
+    fn draw(
+        &mut self,
+        render_list: &RenderList,
+        info: &RenderInfo,
+        command_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) {
+        // drop the meshes which are not drawn anymore
+        let mesh_ids = render_list
+            .meshes
+            .iter()
+            .map(|mesh| mesh.id)
+            .collect::<HashSet<_>>();
+        self.vertex_buffers.retain(|id, _| mesh_ids.contains(id));
+        // a mesh without triangles has no vertex buffer
+        if render_list
+            .meshes
+            .iter()
+            .all(|mesh| mesh.vertices.is_empty())
+        {
+            return;
+        }
+        let texture_ids = render_list
+            .meshes
+            .iter()
+            .map(|mesh| mesh.texture.id)
+            .collect::<HashSet<_>>();
+
+        let subpass = info.subpass.clone();
+        let pipeline =
+            info.shaders
+                .graphics_pipeline(&info.device, &MESH_SHADERS, |vs, fs, layout| {
+                    let vertex_input_state = MeshVertex::per_vertex().definition(&vs)?;
+                    let stages = [
+                        PipelineShaderStageCreateInfo::new(vs),
+                        PipelineShaderStageCreateInfo::new(fs),
+                    ];
+                    Ok(GraphicsPipeline::new(
+                        info.device.clone(),
+                        None,
+                        GraphicsPipelineCreateInfo {
+                            stages: stages.into_iter().collect(),
+                            vertex_input_state: Some(vertex_input_state),
+                            input_assembly_state: Some(InputAssemblyState::default()),
+                            rasterization_state: Some(RasterizationState::default()),
+                            multisample_state: Some(MultisampleState {
+                                rasterization_samples: subpass.num_samples().unwrap(),
+                                ..Default::default()
+                            }),
+                            color_blend_state: Some(ColorBlendState::with_attachment_states(
+                                subpass.num_color_attachments(),
+                                ColorBlendAttachmentState {
+                                    blend: Some(AttachmentBlend::alpha()),
+                                    ..Default::default()
+                                },
+                            )),
+                            viewport_state: Some(ViewportState::default()),
+                            dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                            subpass: Some(subpass.clone().into()),
+                            ..GraphicsPipelineCreateInfo::layout(layout)
+                        },
+                    )?)
+                });
+
+        let sampler = Sampler::new(
+            info.device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let descriptor_set_allocator = self
+            .descriptor_set_allocator
+            .get_or_insert_with(|| {
+                Arc::new(StandardDescriptorSetAllocator::new(
+                    info.device.clone(),
+                    Default::default(),
+                ))
+            })
+            .clone();
+        let descriptor_sets = texture_ids
+            .into_iter()
+            .map(|id| {
+                let descriptor_set = DescriptorSet::new(
+                    descriptor_set_allocator.clone(),
+                    pipeline.layout().set_layouts()[0].clone(),
+                    [
+                        WriteDescriptorSet::sampler(0, sampler.clone()),
+                        WriteDescriptorSet::image_view(1, info.textures[&id].clone()),
+                    ],
+                    [],
+                )
+                .unwrap();
+                (id, descriptor_set)
+            })
+            .collect::<HashMap<_, _>>();
+
+        command_buffer_builder
+            .bind_pipeline_graphics(pipeline.clone())
+            .unwrap();
+
+        for camera in &render_list.cameras {
+            let (offset, extent) = camera.viewport_rect(info.window_size);
+            if extent.x < 1.0 || extent.y < 1.0 {
+                continue;
+            }
+            let projection_view = camera.projection_view(extent);
+            command_buffer_builder
+                .set_viewport(
+                    0,
+                    [Viewport {
+                        offset: offset.into(),
+                        extent: extent.into(),
+                        depth_range: 0.0..=1.0,
+                    }]
+                    .into_iter()
+                    .collect(),
+                )
+                .unwrap();
+            for mesh in &render_list.meshes {
+                if mesh.vertices.is_empty() {
+                    continue;
+                }
+                let vertex_buffer = self.vertex_buffer(mesh, info);
+                let vertex_count = vertex_buffer.len() as u32;
+                command_buffer_builder
+                    .push_constants(
+                        pipeline.layout().clone(),
+                        0,
+                        MeshPushConstants {
+                            transform: (projection_view * mesh.model).to_cols_array_2d(),
+                            color: mesh.color.to_array(),
+                        },
+                    )
+                    .unwrap()
+                    .bind_descriptor_sets(
+                        PipelineBindPoint::Graphics,
+                        pipeline.layout().clone(),
+                        0,
+                        descriptor_sets[&mesh.texture.id].clone(),
+                    )
+                    .unwrap()
+                    .bind_vertex_buffers(0, vertex_buffer)
+                    .unwrap();
+                unsafe { command_buffer_builder.draw(vertex_count, 1, 0, 0) }.unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,574 @@
+use super::{
+    asset::AssetCache, camera2d_extract_system, AssetServer, ComponentData, Edit, EngineBuilder,
+    Particle2D, Physics2DManager, Plugin, RenderInfo, RenderList, RenderPass, Shader, Stage,
+    TextureImage, Time, Transform2D, Value, Variant,
+};
+use glam::{Vec2, Vec4};
+use shader_library::include_spirv;
+use shipyard::{
+    Component, EntityId, Get, IntoIter, IntoWithId, SystemModificator, Unique, UniqueView,
+    UniqueViewMut, View,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorType, DescriptorSet,
+        WriteDescriptorSet,
+    },
+    image::sampler::{Filter, Sampler, SamplerCreateInfo},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{
+        graphics::{
+            color_blend::{AttachmentBlend, ColorBlendAttachmentState, ColorBlendState},
+            input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            rasterization::RasterizationState,
+            vertex_input::{Vertex, VertexDefinition},
+            viewport::{Viewport, ViewportState},
+            GraphicsPipelineCreateInfo,
+        },
+        DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineShaderStageCreateInfo,
+    },
+};
+
+// ParticleEmitter2D, the particles it emits and the pass which draws them
+pub struct Particle2DPlugin;
+
+impl Plugin for Particle2DPlugin {
+    fn build(&self, builder: &mut EngineBuilder) {
+        builder
+            .add_unique(ParticleManager::new())
+            .add_component(ParticleEmitter2D::default)
+            .add_system(
+                Stage::PostPhysics,
+                particle2d_burst_system.skip_if_missing_unique::<Physics2DManager>(),
+            )
+            // after the bursts, so their particles start where the collision happened
+            .add_system(
+                Stage::PostPhysics,
+                particle2d_update_system.after_all(particle2d_burst_system),
+            )
+            .add_system(
+                Stage::RenderExtract,
+                particle2d_extract_system.after_all(camera2d_extract_system),
+            )
+            .add_render_pass(Particle2DPass::default());
+    }
+}
+
+// Emits particles from the position of its entity. They live in world space, so they stay behind
+// when the entity moves, and are simulated on the cpu. The particles of an emitter are removed
+// together with the emitter.
+#[derive(Component, Debug)]
+#[track(All)]
+pub struct ParticleEmitter2D {
+    pub rate: f32,          // particles per second
+    pub burst: i32,         // particles emitted at once when a collision of the entity starts
+    pub max_particles: i32, // no particles are emitted while this many are alive
+    pub lifetime: f32,      // seconds
+    pub speed: Vec2,        // the initial speed is random between x and y
+    pub direction: f32, // center of the cone particles are emitted in, radians from the entity rotation
+    pub spread: f32,    // half of the angle of the cone, radians
+    pub gravity: Vec2,  // acceleration of the particles
+    pub start_color: Vec4, // color and size change from start to end over the life of a particle
+    pub end_color: Vec4,
+    pub start_size: f32,
+    pub end_size: f32,
+    pub texture: String, // path of an image, particles are squares of their color if it is empty
+}
+
+impl Default for ParticleEmitter2D {
+    fn default() -> Self {
+        ParticleEmitter2D {
+            rate: 10.0,
+            burst: 0,
+            max_particles: 256,
+            lifetime: 1.0,
+            speed: Vec2::new(1.0, 2.0),
+            direction: std::f32::consts::FRAC_PI_2,
+            spread: 0.3,
+            gravity: Vec2::ZERO,
+            start_color: Vec4::ONE,
+            end_color: Vec4::new(1.0, 1.0, 1.0, 0.0),
+            start_size: 0.2,
+            end_size: 0.0,
+            texture: String::new(),
+        }
+    }
+}
+
+impl Edit for ParticleEmitter2D {
+    fn name() -> &'static str {
+        "ParticleEmitter2D"
+    }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "rate",
+            value: Value::Float32(self.rate),
+        });
+        data.variants.push(Variant {
+            name: "burst",
+            value: Value::Int32(self.burst),
+        });
+        data.variants.push(Variant {
+            name: "max_particles",
+            value: Value::Int32(self.max_particles),
+        });
+        data.variants.push(Variant {
+            name: "lifetime",
+            value: Value::Float32(self.lifetime),
+        });
+        data.variants.push(Variant {
+            name: "speed",
+            value: Value::Vec2(self.speed),
+        });
+        data.variants.push(Variant {
+            name: "direction",
+            value: Value::Float32(self.direction),
+        });
+        data.variants.push(Variant {
+            name: "spread",
+            value: Value::Float32(self.spread),
+        });
+        data.variants.push(Variant {
+            name: "gravity",
+            value: Value::Vec2(self.gravity),
+        });
+        data.variants.push(Variant {
+            name: "start_color",
+            value: Value::Vec4(self.start_color),
+        });
+        data.variants.push(Variant {
+            name: "end_color",
+            value: Value::Vec4(self.end_color),
+        });
+        data.variants.push(Variant {
+            name: "start_size",
+            value: Value::Float32(self.start_size),
+        });
+        data.variants.push(Variant {
+            name: "end_size",
+            value: Value::Float32(self.end_size),
+        });
+        data.variants.push(Variant {
+            name: "texture",
+            value: Value::String(self.texture.clone()),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            match (v.name, v.value) {
+                ("rate", Value::Float32(rate)) => self.rate = rate,
+                ("burst", Value::Int32(burst)) => self.burst = burst,
+                ("max_particles", Value::Int32(max_particles)) => {
+                    self.max_particles = max_particles
+                }
+                ("lifetime", Value::Float32(lifetime)) => self.lifetime = lifetime,
+                ("speed", Value::Vec2(speed)) => self.speed = speed,
+                ("direction", Value::Float32(direction)) => self.direction = direction,
+                ("spread", Value::Float32(spread)) => self.spread = spread,
+                ("gravity", Value::Vec2(gravity)) => self.gravity = gravity,
+                ("start_color", Value::Vec4(start_color)) => self.start_color = start_color,
+                ("end_color", Value::Vec4(end_color)) => self.end_color = end_color,
+                ("start_size", Value::Float32(start_size)) => self.start_size = start_size,
+                ("end_size", Value::Float32(end_size)) => self.end_size = end_size,
+                ("texture", Value::String(texture)) => self.texture = texture,
+                _ => (),
+            }
+        }
+    }
+}
+
+struct Particle {
+    position: Vec2,
+    velocity: Vec2,
+    age: f32, // seconds
+    lifetime: f32,
+}
+
+// the particles of an emitter
+#[derive(Default)]
+struct ParticlePool {
+    particles: Vec<Particle>,
+    pending: f32, // particles of the rate which were not emitted yet, less than one
+    bursts: u32,  // particles of bursts which are emitted in the next update
+}
+
+// xorshift, particles only need cheap randomness
+struct Random(u64);
+
+impl Random {
+    // a number in [0, 1)
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next()
+    }
+}
+
+// Particle textures by path and the particles of every emitter
+#[derive(Unique)]
+pub struct ParticleManager {
+    textures: AssetCache<TextureImage>,
+    white: TextureImage, // for emitters without texture
+    pools: HashMap<EntityId, ParticlePool>,
+    random: Random,
+}
+
+impl ParticleManager {
+    pub fn new() -> Self {
+        ParticleManager {
+            textures: AssetCache::new(),
+            white: TextureImage::new(1, 1, Arc::new([255; 4])),
+            pools: HashMap::new(),
+            random: Random(0x2545_f491_4f6c_dd1d),
+        }
+    }
+
+    // the image at path once it is loaded, the white pixel if path is empty
+    fn texture(&mut self, asset_server: &AssetServer, path: &str) -> Option<TextureImage> {
+        if path.is_empty() {
+            return Some(self.white.clone());
+        }
+        self.textures
+            .get(asset_server, path)
+            .map(|texture| (*texture).clone())
+    }
+}
+
+// queue a burst for emitters whose entity started touching another collider
+fn particle2d_burst_system(
+    physics2d_manager: UniqueView<Physics2DManager>,
+    emitter: View<ParticleEmitter2D>,
+    mut particle_manager: UniqueViewMut<ParticleManager>,
+) {
+    for event in physics2d_manager
+        .collision_events
+        .iter()
+        .filter(|event| event.started)
+    {
+        for entity in [event.entity1, event.entity2] {
+            if let Ok(emitter) = emitter.get(entity) {
+                particle_manager.pools.entry(entity).or_default().bursts +=
+                    emitter.burst.max(0) as u32;
+            }
+        }
+    }
+}
+
+// moves and ages the particles, then emits the new ones of the rate and the bursts
+fn particle2d_update_system(
+    time: UniqueView<Time>,
+    transform2d: View<Transform2D>,
+    emitter: View<ParticleEmitter2D>,
+    mut particle_manager: UniqueViewMut<ParticleManager>,
+) {
+    let ParticleManager { pools, random, .. } = particle_manager.as_mut();
+    pools.retain(|e, _| emitter.contains(*e));
+    let delta = time.delta;
+    for (e, (transform2d, emitter)) in (&transform2d, &emitter).iter().with_id() {
+        let pool = pools.entry(e).or_default();
+        pool.particles.retain_mut(|particle| {
+            particle.age += delta;
+            particle.velocity += emitter.gravity * delta;
+            particle.position += particle.velocity * delta;
+            particle.age < particle.lifetime
+        });
+
+        pool.pending += emitter.rate.max(0.0) * delta;
+        let count = pool.pending as u32 + std::mem::take(&mut pool.bursts);
+        pool.pending = pool.pending.fract();
+        let free = (emitter.max_particles.max(0) as usize).saturating_sub(pool.particles.len());
+        for _ in 0..(count as usize).min(free) {
+            let angle = transform2d.rotation
+                + emitter.direction
+                + random.range(-emitter.spread, emitter.spread);
+            let speed = random.range(emitter.speed.x, emitter.speed.y);
+            pool.particles.push(Particle {
+                position: transform2d.position.truncate(),
+                velocity: Vec2::from_angle(angle) * speed,
+                age: 0.0,
+                lifetime: emitter.lifetime,
+            });
+        }
+    }
+}
+
+fn particle2d_extract_system(
+    asset_server: UniqueView<AssetServer>,
+    emitter: View<ParticleEmitter2D>,
+    mut particle_manager: UniqueViewMut<ParticleManager>,
+    mut render_list: UniqueViewMut<RenderList>,
+) {
+    for (e, emitter) in emitter.iter().with_id() {
+        let Some(texture) = particle_manager.texture(&asset_server, &emitter.texture) else {
+            continue;
+        };
+        let Some(pool) = particle_manager.pools.get(&e) else {
+            continue;
+        };
+        for particle in &pool.particles {
+            let t = particle.age / particle.lifetime.max(f32::EPSILON);
+            render_list.particles.push(Particle2D {
+                position: particle.position,
+                size: emitter.start_size + (emitter.end_size - emitter.start_size) * t,
+                color: emitter.start_color.lerp(emitter.end_color, t),
+                texture: texture.clone(),
+            });
+        }
+    }
+    particle_manager.textures.retain_used();
+}
+
+// a corner of the quad every particle is an instance of
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct ParticleVertex {
+    #[format(R32G32_SFLOAT)]
+    corner: [f32; 2],
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct ParticleInstance {
+    #[format(R32G32_SFLOAT)]
+    center: [f32; 2],
+    #[format(R32_SFLOAT)]
+    size: f32,
+    #[format(R32G32B32A32_SFLOAT)]
+    color: [f32; 4],
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct ParticlePushConstants {
+    #[format(R32G32B32A32_SFLOAT)]
+    projection_view: [[f32; 4]; 4],
+}
+
+const PARTICLE_SHADERS: [Shader; 2] = [
+    Shader {
+        path: "shaders/particle.vert",
+        spirv: include_spirv!("shaders/particle.vert"),
+        validate: |vs| {
+            vs.validate_vertex_input(&[
+                ParticleVertex::per_vertex(),
+                ParticleInstance::per_instance(),
+            ])?;
+            vs.validate_push_constants::<ParticlePushConstants>()
+        },
+    },
+    Shader {
+        path: "shaders/particle.frag",
+        spirv: include_spirv!("shaders/particle.frag"),
+        validate: |fs| {
+            fs.validate_descriptor(0, 0, DescriptorType::Sampler)?;
+            fs.validate_descriptor(0, 1, DescriptorType::SampledImage)
+        },
+    },
+];
+
+// draws the particles of the render list as instances of one quad with every camera,
+// one draw per run of particles which share a texture
+#[derive(Default)]
+struct Particle2DPass {
+    descriptor_set_allocator: Option<Arc<StandardDescriptorSetAllocator>>,
+}
+
+impl RenderPass for Particle2DPass {
+    fn draw(
+        &mut self,
+        render_list: &RenderList,
+        info: &RenderInfo,
+        command_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) {
+        if render_list.particles.is_empty() {
+            return;
+        }
+        let texture_ids = render_list
+            .particles
+            .iter()
+            .map(|particle| particle.texture.id)
+            .collect::<HashSet<_>>();
+
+        let subpass = info.subpass.clone();
+        let pipeline =
+            info.shaders
+                .graphics_pipeline(&info.device, &PARTICLE_SHADERS, |vs, fs, layout| {
+                    let vertex_input_state = [
+                        ParticleVertex::per_vertex(),
+                        ParticleInstance::per_instance(),
+                    ]
+                    .definition(&vs)?;
+                    let stages = [
+                        PipelineShaderStageCreateInfo::new(vs),
+                        PipelineShaderStageCreateInfo::new(fs),
+                    ];
+                    Ok(GraphicsPipeline::new(
+                        info.device.clone(),
+                        None,
+                        GraphicsPipelineCreateInfo {
+                            stages: stages.into_iter().collect(),
+                            vertex_input_state: Some(vertex_input_state),
+                            input_assembly_state: Some(InputAssemblyState::default()),
+                            rasterization_state: Some(RasterizationState::default()),
+                            multisample_state: Some(MultisampleState {
+                                rasterization_samples: subpass.num_samples().unwrap(),
+                                ..Default::default()
+                            }),
+                            color_blend_state: Some(ColorBlendState::with_attachment_states(
+                                subpass.num_color_attachments(),
+                                ColorBlendAttachmentState {
+                                    blend: Some(AttachmentBlend::alpha()),
+                                    ..Default::default()
+                                },
+                            )),
+                            viewport_state: Some(ViewportState::default()),
+                            dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                            subpass: Some(subpass.clone().into()),
+                            ..GraphicsPipelineCreateInfo::layout(layout)
+                        },
+                    )?)
+                });
+
+        let sampler = Sampler::new(
+            info.device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let descriptor_set_allocator = self
+            .descriptor_set_allocator
+            .get_or_insert_with(|| {
+                Arc::new(StandardDescriptorSetAllocator::new(
+                    info.device.clone(),
+                    Default::default(),
+                ))
+            })
+            .clone();
+        let descriptor_sets = texture_ids
+            .iter()
+            .map(|id| {
+                let descriptor_set = DescriptorSet::new(
+                    descriptor_set_allocator.clone(),
+                    pipeline.layout().set_layouts()[0].clone(),
+                    [
+                        WriteDescriptorSet::sampler(0, sampler.clone()),
+                        WriteDescriptorSet::image_view(1, info.textures[id].clone()),
+                    ],
+                    [],
+                )
+                .unwrap();
+                (*id, descriptor_set)
+            })
+            .collect::<HashMap<_, _>>();
+
+        let buffer_create_info = || BufferCreateInfo {
+            usage: BufferUsage::VERTEX_BUFFER,
+            ..Default::default()
+        };
+        let allocation_create_info = || AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        };
+        // two triangles of the quad
+        let vertex_buffer = Buffer::from_iter(
+            info.memory_allocator.clone(),
+            buffer_create_info(),
+            allocation_create_info(),
+            [
+                [-0.5, 0.5],
+                [-0.5, -0.5],
+                [0.5, -0.5],
+                [0.5, -0.5],
+                [0.5, 0.5],
+                [-0.5, 0.5],
+            ]
+            .map(|corner| ParticleVertex { corner }),
+        )
+        .unwrap();
+        let instance_buffer = Buffer::from_iter(
+            info.memory_allocator.clone(),
+            buffer_create_info(),
+            allocation_create_info(),
+            render_list
+                .particles
+                .iter()
+                .map(|particle| ParticleInstance {
+                    center: particle.position.to_array(),
+                    size: particle.size,
+                    color: particle.color.to_array(),
+                })
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+
+        command_buffer_builder
+            .bind_pipeline_graphics(pipeline.clone())
+            .unwrap()
+            .bind_vertex_buffers(0, (vertex_buffer, instance_buffer))
+            .unwrap();
+
+        for camera in &render_list.cameras {
+            let (offset, extent) = camera.viewport_rect(info.window_size);
+            if extent.x < 1.0 || extent.y < 1.0 {
+                continue;
+            }
+            command_buffer_builder
+                .set_viewport(
+                    0,
+                    [Viewport {
+                        offset: offset.into(),
+                        extent: extent.into(),
+                        depth_range: 0.0..=1.0,
+                    }]
+                    .into_iter()
+                    .collect(),
+                )
+                .unwrap()
+                .push_constants(
+                    pipeline.layout().clone(),
+                    0,
+                    ParticlePushConstants {
+                        projection_view: camera.projection_view(extent).to_cols_array_2d(),
+                    },
+                )
+                .unwrap();
+            let mut first = 0;
+            for run in render_list
+                .particles
+                .chunk_by(|a, b| a.texture.id == b.texture.id)
+            {
+                command_buffer_builder
+                    .bind_descriptor_sets(
+                        PipelineBindPoint::Graphics,
+                        pipeline.layout().clone(),
+                        0,
+                        descriptor_sets[&run[0].texture.id].clone(),
+                    )
+                    .unwrap();
+                unsafe { command_buffer_builder.draw(6, run.len() as u32, 0, first as u32) }
+                    .unwrap();
+                first += run.len();
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use super::{
+    add_component, component_data, set_component_data, ComponentData, CorePlugin, Edit, Engine,
+    EngineImpl, RenderPass, Schedule, Stage,
+};
+use glam::Vec2;
+use shipyard::{
+    track, AllStorages, EntityId, IntoIter, IntoWithId, IntoWorkloadSystem, Unique, View, World,
+};
+
+// A part of the engine which can be left out, e.g. physics, rendering or the editor
+pub trait Plugin {
+    fn build(&self, builder: &mut EngineBuilder);
+}
+
+// The egui of a plugin in the Scene Window
+pub trait SceneUi {
+    // widgets in the row above the scene image
+    fn toolbar(&mut self, ui: &mut egui::Ui, engine: &mut dyn Engine, scene_size: Vec2);
+    // interaction with the scene image, response is the response of the image
+    fn scene(
+        &mut self,
+        ui: &mut egui::Ui,
+        response: &egui::Response,
+        engine: &mut dyn Engine,
+        scene_size: Vec2,
+    );
+}
+
+// adds a component with its default values
+type AddComponentFn = Box<dyn Fn(&AllStorages, EntityId) + Send + Sync>;
+
+// how the editor, scripts and prefabs reach a component by its name
+struct ComponentEntry {
+    name: &'static str,
+    get: fn(&AllStorages, EntityId) -> Option<ComponentData>,
+    set: fn(&AllStorages, EntityId, ComponentData),
+    all: fn(&AllStorages) -> Vec<(EntityId, ComponentData)>,
+    add: AddComponentFn,
+}
+
+// all components registered with EngineBuilder::add_component, in the order they were added
+#[derive(Unique, Default)]
+pub struct ComponentRegistry {
+    components: Vec<ComponentEntry>,
+}
+
+impl ComponentRegistry {
+    fn entry(&self, name: &str) -> Option<&ComponentEntry> {
+        self.components.iter().find(|entry| entry.name == name)
+    }
+
+    pub fn get(
+        &self,
+        all_storages: &AllStorages,
+        entity: EntityId,
+        name: &str,
+    ) -> Option<ComponentData> {
+        (self.entry(name)?.get)(all_storages, entity)
+    }
+
+    // returns false for unknown components
+    pub fn set(&self, all_storages: &AllStorages, entity: EntityId, data: ComponentData) -> bool {
+        match self.entry(data.name) {
+            Some(entry) => (entry.set)(all_storages, entity, data),
+            None => return false,
+        }
+        true
+    }
+
+    // returns false for unknown components
+    pub fn add(&self, all_storages: &AllStorages, entity: EntityId, name: &str) -> bool {
+        match self.entry(name) {
+            Some(entry) => (entry.add)(all_storages, entity),
+            None => return false,
+        }
+        true
+    }
+
+    // the data of every registered component in the world
+    pub fn all(&self, all_storages: &AllStorages) -> Vec<(EntityId, ComponentData)> {
+        self.components
+            .iter()
+            .flat_map(|entry| (entry.all)(all_storages))
+            .collect()
+    }
+}
+
+fn all_component_data<T: Edit + Send + Sync>(
+    all_storages: &AllStorages,
+) -> Vec<(EntityId, ComponentData)> {
+    all_storages.run(|c: View<T>| c.iter().with_id().map(|(e, c)| (e, c.to_data())).collect())
+}
+
+pub struct EngineBuilder {
+    world: World,
+    schedule: Schedule,
+    components: ComponentRegistry,
+    render_passes: Vec<Box<dyn RenderPass>>,
+    scene_uis: Vec<Box<dyn SceneUi>>,
+}
+
+impl EngineBuilder {
+    // starts with the core of the engine: time, input, cameras, scripts and prefabs
+    pub fn new() -> Self {
+        let builder = EngineBuilder {
+            world: World::new(),
+            schedule: Schedule::new(),
+            components: ComponentRegistry::default(),
+            render_passes: Vec::new(),
+            scene_uis: Vec::new(),
+        };
+        builder.add_plugin(CorePlugin)
+    }
+
+    pub fn add_plugin(mut self, plugin: impl Plugin) -> Self {
+        plugin.build(&mut self);
+        self
+    }
+
+    // for plugins which configure uniques of the plugins added before them
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn add_unique(&mut self, unique: impl Unique + Send + Sync) -> &mut Self {
+        self.world.add_unique(unique);
+        self
+    }
+
+    // make the component editable by name, default is used when it is added by name
+    pub fn add_component<T: Edit<Tracking = track::All> + Send + Sync>(
+        &mut self,
+        default: fn() -> T,
+    ) -> &mut Self {
+        self.components.components.push(ComponentEntry {
+            name: T::name(),
+            get: component_data::<T>,
+            set: set_component_data::<T>,
+            all: all_component_data::<T>,
+            add: Box::new(move |all_storages, entity| {
+                add_component(all_storages, entity, default())
+            }),
+        });
+        self
+    }
+
+    // systems of the Startup stage run once in Engine::init, the others in every Engine::update
+    pub fn add_system<B, S: IntoWorkloadSystem<B, ()>>(
+        &mut self,
+        stage: Stage,
+        system: S,
+    ) -> &mut Self {
+        self.schedule.add_system(stage, system);
+        self
+    }
+
+    pub fn add_render_pass(&mut self, render_pass: impl RenderPass + 'static) -> &mut Self {
+        self.render_passes.push(Box::new(render_pass));
+        self
+    }
+
+    pub fn add_scene_ui(&mut self, scene_ui: impl SceneUi + 'static) -> &mut Self {
+        self.scene_uis.push(Box::new(scene_ui));
+        self
+    }
+
+    pub fn build(mut self) -> Box<dyn Engine> {
+        self.world.add_unique(self.components);
+        self.schedule.build(&self.world);
+        Box::new(EngineImpl {
+            world: self.world,
+            schedule: self.schedule,
+            render_passes: self.render_passes,
+            scene_uis: self.scene_uis,
+            textures: Default::default(),
+            shaders: Default::default(),
+            msaa_samples: 4,
+            msaa_image: None,
+            scene_render_pass: None,
+        })
+    }
+}
@@ -0,0 +1,410 @@
+use super::{
+    add_component, add_component_by_name,
+    asset::{Asset, AssetReader, Handle},
+    component_data_by_name, set_component_data_by_name, AssetServer, ComponentData, Edit, Value,
+    Variant,
+};
+use glam::{Vec2, Vec3, Vec4};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use shipyard::{
+    AllStorages, AllStoragesView, Component, EntitiesViewMut, EntityId, Get, IntoIter, IntoWithId,
+    Unique, UniqueView, UniqueViewMut, View, ViewMut,
+};
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+// An entity spawned from the prefab at path. Overrides are the variants which were set
+// on purpose for this instance, changes of the prefab do not propagate to them.
+#[derive(Component, Debug, Default)]
+#[track(All)]
+pub struct PrefabInstance {
+    path: String,
+    overrides: Vec<ComponentData>,
+}
+
+impl PrefabInstance {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn is_overridden(&self, component: &str, variant: &str) -> bool {
+        self.overrides
+            .iter()
+            .filter(|data| data.name == component)
+            .any(|data| data.variants.iter().any(|v| v.name == variant))
+    }
+
+    fn add_override(&mut self, data: &ComponentData) {
+        let index = match self.overrides.iter().position(|o| o.name == data.name) {
+            Some(index) => index,
+            None => {
+                self.overrides.push(ComponentData::new(data.name));
+                self.overrides.len() - 1
+            }
+        };
+        let overrides = &mut self.overrides[index].variants;
+        for v in &data.variants {
+            match overrides.iter_mut().find(|o| o.name == v.name) {
+                Some(o) => o.value = v.value.clone(),
+                None => overrides.push(v.clone()),
+            }
+        }
+    }
+}
+
+impl Edit for PrefabInstance {
+    fn name() -> &'static str {
+        "PrefabInstance"
+    }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "path",
+            value: Value::String(self.path.clone()),
+        });
+        data
+    }
+}
+
+// A prefab file is a json object of components, each of them is an object of variants:
+// { "Transform2D": { "position": [0.0, 1.0, 0.0] }, "Renderer2D": {} }
+// Variants which are not listed keep the default value of the component.
+pub struct Prefab {
+    components: JsonMap<String, JsonValue>,
+}
+
+impl Asset for Prefab {
+    fn load(_path: &str, data: Vec<u8>, _reader: &AssetReader) -> Result<Self, String> {
+        serde_json::from_slice(&data)
+            .map(|components| Prefab { components })
+            .map_err(|e| e.to_string())
+    }
+}
+
+struct LoadedPrefab {
+    handle: Handle<Prefab>,
+    loaded: Option<Arc<Prefab>>, // the version of the handle components were taken from
+    components: JsonMap<String, JsonValue>, // with the edits which were not reloaded yet
+}
+
+#[derive(Unique)]
+pub struct PrefabManager {
+    builtin_paths: Vec<String>,
+    prefabs: HashMap<String, LoadedPrefab>,
+}
+
+impl PrefabManager {
+    pub fn new() -> Self {
+        PrefabManager {
+            builtin_paths: Vec::new(),
+            prefabs: HashMap::new(),
+        }
+    }
+
+    // source is compiled into the engine, so it is available where files are not, e.g. on android
+    pub fn add_builtin(
+        &mut self,
+        asset_server: &mut AssetServer,
+        path: impl Into<String>,
+        source: &'static str,
+    ) {
+        let path = path.into();
+        asset_server.add_builtin(path.clone(), source.as_bytes());
+        self.builtin_paths.push(path);
+    }
+
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths = self
+            .builtin_paths
+            .iter()
+            .chain(self.prefabs.keys())
+            .cloned()
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    // the components of the prefab at path, it is loaded on first use and waited for
+    fn load(
+        &mut self,
+        asset_server: &AssetServer,
+        path: &str,
+    ) -> Option<&JsonMap<String, JsonValue>> {
+        if !self.prefabs.contains_key(path) {
+            let handle = asset_server.load::<Prefab>(path);
+            let loaded = handle.wait()?;
+            let prefab = LoadedPrefab {
+                handle,
+                components: loaded.components.clone(),
+                loaded: Some(loaded),
+            };
+            self.prefabs.insert(path.to_string(), prefab);
+        }
+        self.prefabs.get(path).map(|prefab| &prefab.components)
+    }
+
+    // returns the paths of the prefabs the asset server reloaded with their previous components
+    fn reload(&mut self) -> Vec<(String, JsonMap<String, JsonValue>)> {
+        let mut changed = Vec::new();
+        for (path, prefab) in &mut self.prefabs {
+            let Some(loaded) = prefab.handle.get() else {
+                continue;
+            };
+            if prefab
+                .loaded
+                .as_ref()
+                .is_some_and(|l| Arc::ptr_eq(l, &loaded))
+            {
+                continue;
+            }
+            let previous = std::mem::replace(&mut prefab.components, loaded.components.clone());
+            prefab.loaded = Some(loaded);
+            changed.push((path.clone(), previous));
+        }
+        changed
+    }
+
+    // merge data into the prefab at path and save it if it was loaded from a file,
+    // returns the previous components
+    fn edit(&mut self, path: &str, data: &[ComponentData]) -> Option<JsonMap<String, JsonValue>> {
+        let prefab = self.prefabs.get_mut(path)?;
+        let previous = prefab.components.clone();
+        for data in data {
+            let variants = prefab
+                .components
+                .entry(data.name)
+                .or_insert_with(|| JsonValue::Object(JsonMap::new()));
+            if let Some(variants) = variants.as_object_mut() {
+                for v in &data.variants {
+                    if let Some(json) = value_to_json(&v.value) {
+                        variants.insert(v.name.to_string(), json);
+                    }
+                }
+            }
+        }
+        // builtins have no file, the asset server reloads the saved file without changes
+        if Path::new(path).is_file() {
+            let json = serde_json::to_string_pretty(&prefab.components).unwrap();
+            if let Err(e) = std::fs::write(path, json) {
+                log::error!("Failed to save prefab {path}: {e}");
+            }
+        }
+        Some(previous)
+    }
+}
+
+// spawn an instance of the prefab at path, overrides are applied on top of the prefab
+pub fn spawn_prefab(
+    all_storages: &AllStorages,
+    path: &str,
+    overrides: Vec<ComponentData>,
+) -> Option<EntityId> {
+    let components = {
+        let asset_server = all_storages.borrow::<UniqueView<AssetServer>>().unwrap();
+        let mut prefab_manager = all_storages
+            .borrow::<UniqueViewMut<PrefabManager>>()
+            .unwrap();
+        prefab_manager.load(&asset_server, path)?.clone()
+    };
+    let entity = all_storages
+        .borrow::<EntitiesViewMut>()
+        .unwrap()
+        .add_entity((), ());
+    for (component, variants) in &components {
+        if !add_component_by_name(all_storages, entity, component) {
+            log::warn!("Prefab {path} has unknown component {component}");
+            continue;
+        }
+        if let Some(variants) = variants.as_object() {
+            apply_json(all_storages, entity, component, variants, |_| true);
+        }
+    }
+    for data in &overrides {
+        set_component_data_by_name(all_storages, entity, data.clone());
+    }
+    add_component(
+        all_storages,
+        entity,
+        PrefabInstance {
+            path: path.to_string(),
+            overrides,
+        },
+    );
+    Some(entity)
+}
+
+// remember edits of the editor on prefab instances, so that prefab changes do not revert them,
+// writes of scripts are not recorded, see set_variants of the script module
+pub fn record_override(all_storages: &AllStorages, entity: EntityId, data: &ComponentData) {
+    let mut prefab_instance = all_storages.borrow::<ViewMut<PrefabInstance>>().unwrap();
+    if let Ok(mut prefab_instance) = (&mut prefab_instance).get(entity) {
+        prefab_instance.add_override(data);
+    }
+}
+
+// move the overrides of the instance into its prefab, except where the instance is placed
+pub fn apply_to_prefab(all_storages: &AllStorages, entity: EntityId) {
+    let placement = |component: &str, variant: &str| {
+        component == "Transform2D" && (variant == "position" || variant == "rotation")
+    };
+    let (path, applied) = {
+        let mut prefab_instance = all_storages.borrow::<ViewMut<PrefabInstance>>().unwrap();
+        let Ok(mut prefab_instance) = (&mut prefab_instance).get(entity) else {
+            return;
+        };
+        let mut applied = Vec::new();
+        for data in &mut prefab_instance.overrides {
+            let (kept, moved) = std::mem::take(&mut data.variants)
+                .into_iter()
+                .partition(|v| placement(data.name, v.name));
+            data.variants = kept;
+            applied.push(ComponentData {
+                name: data.name,
+                variants: moved,
+            });
+        }
+        prefab_instance
+            .overrides
+            .retain(|data| !data.variants.is_empty());
+        (prefab_instance.path.clone(), applied)
+    };
+    let previous = all_storages
+        .borrow::<UniqueViewMut<PrefabManager>>()
+        .unwrap()
+        .edit(&path, &applied);
+    if let Some(previous) = previous {
+        propagate(all_storages, &path, &previous);
+    }
+}
+
+pub fn prefab_reload_system(all_storages: AllStoragesView) {
+    let changed = all_storages
+        .borrow::<UniqueViewMut<PrefabManager>>()
+        .unwrap()
+        .reload();
+    for (path, previous) in changed {
+        propagate(&all_storages, &path, &previous);
+    }
+}
+
+// push the variants which changed since previous to the instances which do not override them
+fn propagate(all_storages: &AllStorages, path: &str, previous: &JsonMap<String, JsonValue>) {
+    let Some(components) = all_storages
+        .borrow::<UniqueView<PrefabManager>>()
+        .unwrap()
+        .prefabs
+        .get(path)
+        .map(|prefab| prefab.components.clone())
+    else {
+        return;
+    };
+    let instances = all_storages
+        .borrow::<View<PrefabInstance>>()
+        .unwrap()
+        .iter()
+        .with_id()
+        .filter(|(_, instance)| instance.path == path)
+        .map(|(e, instance)| (e, instance.overrides.clone()))
+        .collect::<Vec<_>>();
+    for (e, overrides) in instances {
+        let instance = PrefabInstance {
+            path: path.to_string(),
+            overrides,
+        };
+        for (component, variants) in &components {
+            let Some(variants) = variants.as_object() else {
+                continue;
+            };
+            // components removed from the prefab are kept on the instances
+            if component_data_by_name(all_storages, e, component).is_none()
+                && !add_component_by_name(all_storages, e, component)
+            {
+                continue;
+            }
+            let previous = previous.get(component).and_then(|v| v.as_object());
+            apply_json(all_storages, e, component, variants, |variant| {
+                previous.and_then(|p| p.get(variant)) != variants.get(variant)
+                    && !instance.is_overridden(component, variant)
+            });
+        }
+    }
+}
+
+// set the variants of the component which pass filter from their json values
+fn apply_json(
+    all_storages: &AllStorages,
+    entity: EntityId,
+    component: &str,
+    variants: &JsonMap<String, JsonValue>,
+    filter: impl Fn(&str) -> bool,
+) {
+    let Some(current) = component_data_by_name(all_storages, entity, component) else {
+        return;
+    };
+    let variants = current
+        .variants
+        .into_iter()
+        .filter(|v| filter(v.name))
+        .filter_map(|v| {
+            let json = variants.get(v.name)?;
+            match json_to_value(json, &v.value) {
+                Some(value) => Some(Variant {
+                    name: v.name,
+                    value,
+                }),
+                None => {
+                    log::warn!("Prefab variant {component}.{} has a wrong type", v.name);
+                    None
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    if !variants.is_empty() {
+        set_component_data_by_name(
+            all_storages,
+            entity,
+            ComponentData {
+                name: current.name,
+                variants,
+            },
+        );
+    }
+}
+
+// convert to the type of the current value, None if the types do not match
+fn json_to_value(json: &JsonValue, current: &Value) -> Option<Value> {
+    let floats = |n: usize| {
+        json.as_array()
+            .filter(|array| array.len() == n)?
+            .iter()
+            .map(|v| v.as_f64().map(|v| v as f32))
+            .collect::<Option<Vec<_>>>()
+    };
+    Some(match current {
+        Value::Bool(_) => Value::Bool(json.as_bool()?),
+        Value::Int32(_) => Value::Int32(json.as_i64()? as i32),
+        Value::Float32(_) => Value::Float32(json.as_f64()? as f32),
+        Value::String(_) => Value::String(json.as_str()?.to_string()),
+        Value::Vec2(_) => Value::Vec2(Vec2::from_slice(&floats(2)?)),
+        Value::Vec3(_) => Value::Vec3(Vec3::from_slice(&floats(3)?)),
+        Value::Vec4(_) => Value::Vec4(Vec4::from_slice(&floats(4)?)),
+        Value::Entity(_) => return None, // entity ids only mean something while running
+    })
+}
+
+// floats are written with the shortest digits of the f32, e.g. 0.1 and not 0.10000000149011612
+fn value_to_json(value: &Value) -> Option<JsonValue> {
+    let float = |v: f32| JsonValue::from(v.to_string().parse::<f64>().unwrap());
+    let floats = |v: &[f32]| JsonValue::Array(v.iter().copied().map(float).collect());
+    Some(match value {
+        Value::Bool(v) => (*v).into(),
+        Value::Int32(v) => (*v).into(),
+        Value::Float32(v) => float(*v),
+        Value::String(v) => v.clone().into(),
+        Value::Vec2(v) => floats(&v.to_array()),
+        Value::Vec3(v) => floats(&v.to_array()),
+        Value::Vec4(v) => floats(&v.to_array()),
+        Value::Entity(_) => return None,
+    })
+}
@@ -0,0 +1,422 @@
+use super::{
+    active_cameras, material::Material2D, Camera2D, EditorCamera2D, Interpolation2D,
+    Physics2DManager, ShaderManager, Transform2D,
+};
+use glam::{Mat4, UVec2, Vec2, Vec3, Vec4};
+use shipyard::{AllStoragesView, Unique, UniqueView, UniqueViewMut, View};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{
+        AutoCommandBufferBuilder, ClearAttachment, ClearRect, CopyBufferToImageInfo,
+        PrimaryAutoCommandBuffer, RenderPassBeginInfo,
+    },
+    device::{Device, Queue},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageUsage, SampleCount},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    render_pass::{Framebuffer, FramebufferCreateInfo, Subpass},
+};
+
+// ids of decoded TextureImages
+static NEXT_TEXTURE_ID: AtomicU64 = AtomicU64::new(1);
+
+// Records its part of the frame into the scene image from the render list. Passes run in the
+// order they were added on a rayon thread while the next update runs, so they can not borrow
+// the world. The image is cleared, then every pass prepares the frame with all cameras, outside
+// of any render pass, e.g. by drawing into its own offscreen images. Then the engine begins one
+// render pass on the image and the cameras are drawn one after another in its subpass: each
+// clears its viewport and then all passes draw a render list with only that camera, so nothing
+// of the cameras below shows through. With MSAA the passes draw into a multisampled image which
+// is resolved after the render pass.
+pub trait RenderPass: Send {
+    // records what has to happen before the render pass of the scene begins, once per frame
+    fn prepare(
+        &mut self,
+        _render_list: &RenderList,
+        _info: &RenderInfo,
+        _builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) {
+    }
+
+    // records draws into info.subpass, which is already begun, pipelines are created for it
+    fn draw(
+        &mut self,
+        render_list: &RenderList,
+        info: &RenderInfo,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    );
+}
+
+pub struct RenderInfo {
+    pub device: Arc<Device>,
+    pub memory_allocator: Arc<StandardMemoryAllocator>,
+    pub queue: Arc<Queue>,
+    pub format: Format,                         // format of the scene image
+    pub image: Arc<ImageView>,                  // the scene image, multisampled with MSAA
+    pub samples: SampleCount,                   // of image, render passes and pipelines use it
+    pub subpass: Subpass, // of the render pass of the engine on image, while passes draw
+    pub window_size: Vec2, // size of the scene in pixels of image, drawn at its top left
+    pub scale_factor: f32, // pixels per logical pixel, screen space text is in logical pixels
+    pub last_camera: bool, // the top camera is drawn, screen space overlays are drawn with it
+    pub textures: HashMap<u64, Arc<ImageView>>, // every texture of the render list by id
+    pub shaders: Arc<ShaderManager>,
+}
+
+// of the scene image where no camera draws
+pub const CLEAR_COLOR: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+// the pixels of the viewport, within the image
+pub fn clear_rect(offset: Vec2, extent: Vec2, image_extent: [u32; 3]) -> ClearRect {
+    let min = offset.round().max(Vec2::ZERO).as_uvec2();
+    let max = (offset + extent)
+        .round()
+        .as_uvec2()
+        .min(UVec2::new(image_extent[0], image_extent[1]));
+    ClearRect {
+        offset: min.to_array(),
+        extent: max.saturating_sub(min).max(UVec2::ONE).to_array(),
+        array_layers: 0..1,
+    }
+}
+
+// the render pass the engine begins on the scene image after the passes prepared the frame,
+// it loads and stores the image
+pub fn scene_render_pass(
+    device: &Arc<Device>,
+    format: Format,
+    samples: SampleCount,
+) -> Arc<vulkano::render_pass::RenderPass> {
+    vulkano::single_pass_renderpass!(
+        device.clone(),
+        attachments: {
+            color: {
+                format: format,
+                samples: samples,
+                load_op: Load,
+                store_op: Store,
+            },
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {},
+        },
+    )
+    .unwrap()
+}
+
+// begins the render pass of info.subpass on the scene image
+pub fn begin_scene(
+    info: &RenderInfo,
+    builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+) {
+    let framebuffer = Framebuffer::new(
+        info.subpass.render_pass().clone(),
+        FramebufferCreateInfo {
+            attachments: vec![info.image.clone()],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    builder
+        .begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: vec![None],
+                ..RenderPassBeginInfo::framebuffer(framebuffer)
+            },
+            Default::default(),
+        )
+        .unwrap();
+}
+
+// clears the viewport of the camera in the scene image before the passes draw it, in the render
+// pass of the scene
+pub fn clear_viewport(
+    camera: &Camera2D,
+    info: &RenderInfo,
+    builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+) {
+    let (offset, extent) = camera.viewport_rect(info.window_size);
+    if extent.x < 1.0 || extent.y < 1.0 {
+        return;
+    }
+    builder
+        .clear_attachments(
+            [ClearAttachment::Color {
+                color_attachment: 0,
+                clear_value: CLEAR_COLOR.into(),
+            }]
+            .into_iter()
+            .collect(),
+            [clear_rect(offset, extent, info.image.image().extent())]
+                .into_iter()
+                .collect(),
+        )
+        .unwrap();
+}
+
+// A quad drawn by Renderer2DPass
+#[derive(Clone)]
+pub struct Quad2D {
+    pub model: Mat4,
+    pub color: Vec4,
+    pub material: Arc<Material2D>,
+    pub texture: TextureImage, // of the material, white if it has none
+}
+
+// A line drawn by DebugDrawPass, in world space
+#[derive(Debug, Clone, Copy)]
+pub struct Line2D {
+    pub start: Vec2,
+    pub end: Vec2,
+    pub color: Vec4,
+}
+
+// where the corners of a Glyph2D are
+#[derive(Debug, Clone, Copy)]
+pub enum TextSpace {
+    World,          // world units
+    Screen,         // logical pixels from the top left of the scene
+    Anchored(Vec2), // logical pixels from where this world position is seen by each camera
+}
+
+// A quad of a glyph atlas drawn by Text2DPass
+#[derive(Debug, Clone, Copy)]
+pub struct Glyph2D {
+    pub positions: [Vec2; 4], // top left, bottom left, bottom right, top right of the glyph
+    pub uv_min: Vec2,
+    pub uv_max: Vec2,
+    pub color: Vec4,
+    pub space: TextSpace,
+}
+
+// the glyph atlas of FontManager, one coverage byte per pixel
+#[derive(Clone)]
+pub struct GlyphAtlasImage {
+    pub size: u32,
+    pub pixels: Arc<[u8]>,
+    pub version: u64, // changes whenever glyphs are added, so passes only upload new versions
+}
+
+// the pixels of an image file, e.g. a tileset or a sprite atlas, rgba
+#[derive(Clone)]
+pub struct TextureImage {
+    pub id: u64, // unique per decoded image, so passes upload each image once
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Arc<[u8]>,
+}
+
+impl TextureImage {
+    // pixels are rgba, row by row from the top left
+    pub fn new(width: u32, height: u32, pixels: Arc<[u8]>) -> Self {
+        TextureImage {
+            id: NEXT_TEXTURE_ID.fetch_add(1, Ordering::Relaxed),
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    // png, jpeg or any other format of the image crate
+    pub fn decode(data: &[u8]) -> Result<Self, String> {
+        let image = image::load_from_memory(data)
+            .map_err(|e| e.to_string())?
+            .to_rgba8();
+        Ok(TextureImage::new(
+            image.width(),
+            image.height(),
+            image.into_raw().into(),
+        ))
+    }
+
+    // records the upload of the pixels into a new sampled image, which has to happen before the
+    // render pass begins
+    pub fn upload(
+        &self,
+        info: &RenderInfo,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) -> Arc<ImageView> {
+        let staging_buffer = Buffer::from_iter(
+            info.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::HOST_SEQUENTIAL_WRITE
+                    | MemoryTypeFilter::PREFER_HOST,
+                ..Default::default()
+            },
+            self.pixels.iter().copied(),
+        )
+        .unwrap();
+        let image = Image::new(
+            info.memory_allocator.clone(),
+            ImageCreateInfo {
+                usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+                format: Format::R8G8B8A8_SRGB,
+                extent: [self.width, self.height, 1],
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        builder
+            .copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(
+                staging_buffer,
+                image.clone(),
+            ))
+            .unwrap();
+        ImageView::new_default(image).unwrap()
+    }
+}
+
+// a tile of a TileChunk2D
+#[derive(Debug, Clone, Copy)]
+pub struct TileQuad {
+    pub min: Vec2,
+    pub max: Vec2,
+    pub uv_min: Vec2, // the top left of the tile in the tileset image
+    pub uv_max: Vec2,
+}
+
+// A chunk of a Tilemap drawn by TilemapPass, in world space
+#[derive(Clone)]
+pub struct TileChunk2D {
+    pub id: u64, // changes whenever the quads change, so passes only upload new chunks
+    pub tileset: TextureImage,
+    pub min: Vec2, // bounds of the chunk for culling
+    pub max: Vec2,
+    pub quads: Arc<[TileQuad]>,
+}
+
+// A frame of a sprite atlas drawn by Sprite2DPass on the unit square around the origin,
+// transformed by model like a Quad2D
+#[derive(Clone)]
+pub struct Sprite2D {
+    pub model: Mat4,
+    pub uv_min: Vec2, // the top left of the frame in the atlas
+    pub uv_max: Vec2,
+    pub color: Vec4, // multiplied with the atlas
+    pub atlas: TextureImage,
+    pub normal_map: Option<TextureImage>, // drawn into the normals of Light2DPass
+}
+
+// a corner of a triangle of a Mesh2D
+#[derive(Debug, Clone, Copy)]
+pub struct MeshVertex2D {
+    pub position: Vec2,
+    pub uv: Vec2, // from the top left of the texture
+}
+
+// The triangles of a mesh file drawn by Mesh2DPass, transformed by model like a Quad2D
+#[derive(Clone)]
+pub struct Mesh2D {
+    pub id: u64, // unique per loaded mesh, so passes upload each mesh once
+    pub model: Mat4,
+    pub color: Vec4, // multiplied with the texture
+    pub texture: TextureImage,
+    pub vertices: Arc<[MeshVertex2D]>, // three per triangle
+}
+
+// A particle drawn by Particle2DPass, a square in world space
+#[derive(Clone)]
+pub struct Particle2D {
+    pub position: Vec2,
+    pub size: f32,
+    pub color: Vec4, // multiplied with the texture
+    pub texture: TextureImage,
+}
+
+// A point or spot light of Light2DPass, in world space
+#[derive(Debug, Clone, Copy)]
+pub struct Light2D {
+    pub position: Vec2,
+    pub color: Vec3, // already multiplied with the intensity
+    pub radius: f32,
+    pub height: f32,              // above the scene, for normal maps
+    pub direction: Vec2,          // the center of the cone of spot lights
+    pub cone: Option<(f32, f32)>, // cosines of the inner and outer angle of spot lights
+    pub shadows: bool,
+}
+
+// A convex polygon which blocks the lights with shadows, in world space
+#[derive(Debug, Clone)]
+pub struct ShadowShape2D {
+    pub points: Vec<Vec2>, // counter clockwise
+    pub opacity: f32,      // 1 blocks all of the light
+}
+
+// Everything a frame draws, copied out of the world by the systems of the RenderExtract stage.
+// Engine::update_and_draw takes it after every update, so extract systems only push.
+#[derive(Unique, Default)]
+pub struct RenderList {
+    pub cameras: Vec<Camera2D>, // sorted by order, follow and interpolation are already applied
+    pub alpha: f32,             // interpolation between the last two physics steps
+    pub quads: Vec<Quad2D>,
+    pub glyphs: Vec<Glyph2D>,
+    pub glyph_atlas: Option<GlyphAtlasImage>,
+    pub lines: Vec<Line2D>,
+    pub tile_chunks: Vec<TileChunk2D>,
+    pub sprites: Vec<Sprite2D>,
+    pub meshes: Vec<Mesh2D>,
+    pub particles: Vec<Particle2D>,
+    pub lights: Vec<Light2D>,
+    pub shadow_shapes: Vec<ShadowShape2D>,
+    pub ambient_light: Option<Vec3>, // the scene is lit by Light2DPass when it is set or there are lights
+}
+
+impl RenderList {
+    // every TextureImage the passes draw, the engine uploads them before the passes run
+    pub fn textures(&self) -> impl Iterator<Item = &TextureImage> {
+        self.tile_chunks
+            .iter()
+            .map(|chunk| &chunk.tileset)
+            .chain(self.sprites.iter().map(|sprite| &sprite.atlas))
+            .chain(
+                self.sprites
+                    .iter()
+                    .filter_map(|sprite| sprite.normal_map.as_ref()),
+            )
+            .chain(self.meshes.iter().map(|mesh| &mesh.texture))
+            .chain(self.particles.iter().map(|particle| &particle.texture))
+            .chain(self.quads.iter().map(|quad| &quad.texture))
+    }
+}
+
+// the first extract system, the others run after it to use alpha
+pub fn camera2d_extract_system(all_storages: AllStoragesView) {
+    // 1.0 without Physics2DPlugin, there is nothing to interpolate then
+    let alpha = all_storages
+        .borrow::<UniqueView<Physics2DManager>>()
+        .map_or(1.0, |physics2d_manager| {
+            physics2d_manager.interpolation_alpha()
+        });
+    all_storages.run(
+        |transform2d: View<Transform2D>,
+         interpolation2d: View<Interpolation2D>,
+         camera2d: View<Camera2D>,
+         editor_camera2d: UniqueView<EditorCamera2D>,
+         mut render_list: UniqueViewMut<RenderList>| {
+            render_list.cameras = active_cameras(
+                editor_camera2d.enabled,
+                &camera2d,
+                &editor_camera2d,
+                &transform2d,
+                &interpolation2d,
+                alpha,
+            );
+            render_list.alpha = alpha;
+        },
+    );
+}
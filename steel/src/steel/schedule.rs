@@ -0,0 +1,78 @@
+use shipyard::{IntoWorkloadSystem, Workload, World};
+
+// The stages of Engine::update in the order they run, Startup runs once in Engine::init.
+// Each stage is a shipyard workload, so systems inside a stage may run in parallel unless
+// their borrows conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Startup,       // create the initial entities
+    PreUpdate,     // time, input driven gameplay and scripts
+    Physics,       // sync components into rapier and step the simulation
+    PostPhysics,   // react to the simulation, e.g. collisions and cameras
+    RenderExtract, // read the final state of the frame for drawing and debugging
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [
+        Stage::Startup,
+        Stage::PreUpdate,
+        Stage::Physics,
+        Stage::PostPhysics,
+        Stage::RenderExtract,
+    ];
+
+    pub const UPDATE: [Stage; 4] = [
+        Stage::PreUpdate,
+        Stage::Physics,
+        Stage::PostPhysics,
+        Stage::RenderExtract,
+    ];
+
+    // the workload name
+    fn label(self) -> &'static str {
+        match self {
+            Stage::Startup => "startup",
+            Stage::PreUpdate => "pre_update",
+            Stage::Physics => "physics",
+            Stage::PostPhysics => "post_physics",
+            Stage::RenderExtract => "render_extract",
+        }
+    }
+}
+
+// Collects the systems of every stage before the workloads are added to the world.
+// Systems run in the order they were added when their borrows conflict, use
+// before_all/after_all of shipyard::SystemModificator to order them explicitly.
+pub struct Schedule {
+    workloads: Vec<Option<Workload>>, // indexed by the position of the stage in Stage::ALL
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Schedule {
+            workloads: Stage::ALL
+                .iter()
+                .map(|stage| Some(Workload::new(stage.label())))
+                .collect(),
+        }
+    }
+
+    pub fn add_system<B, S: IntoWorkloadSystem<B, ()>>(&mut self, stage: Stage, system: S) {
+        let workload = &mut self.workloads[stage as usize];
+        let w = workload.take().expect("Schedule is already built");
+        *workload = Some(w.with_system(system));
+    }
+
+    // add the workloads to the world, no systems can be added afterwards
+    pub fn build(&mut self, world: &World) {
+        for workload in &mut self.workloads {
+            if let Some(workload) = workload.take() {
+                workload.add_to_world(world).unwrap();
+            }
+        }
+    }
+
+    pub fn run(&self, world: &World, stage: Stage) {
+        world.run_workload(stage.label()).unwrap();
+    }
+}
@@ -0,0 +1,718 @@
+use super::{
+    asset::read_file, audio::listener_pan, component_data_by_name, placement, prefab::spawn_prefab,
+    set_component_data_by_name, AnimationManager, AssetServer, AudioManager, Camera2D,
+    ComponentData, DebugDraw, DebugText, Edit, GamepadAxis, Input, Physics2DManager, QueryFilter2D,
+    RayHit2D, Time, Value, Variant,
+};
+use glam::{Vec2, Vec3, Vec4};
+use rapier2d::prelude::SharedShape;
+use rhai::{
+    Array, CallFnOptions, Dynamic, Engine as ScriptEngine, FuncArgs, Map, Scope, AST, FLOAT, INT,
+};
+use scoped_tls::scoped_thread_local;
+use shader_library::FileWatcher;
+use shipyard::{
+    AllStorages, AllStoragesView, Component, EntityId, IntoIter, IntoWithId, Unique, UniqueView,
+    UniqueViewMut, View, ViewMut,
+};
+use std::collections::HashMap;
+
+// the world the running script may access, only set while a script callback is called
+scoped_thread_local!(static STORAGES: AllStorages);
+
+// Runs the script at path for its entity, the script can define these functions:
+// fn on_start(entity), fn on_update(entity, dt), fn on_collision(entity, other, started) and
+// fn on_animation_event(entity, clip, name).
+// Inside them `this` is an object map which keeps its values between calls.
+#[derive(Component, Debug)]
+#[track(All)]
+pub struct Script {
+    path: String,
+}
+
+impl Script {
+    pub fn new(path: impl Into<String>) -> Self {
+        Script { path: path.into() }
+    }
+}
+
+impl Edit for Script {
+    fn name() -> &'static str {
+        "Script"
+    }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "path",
+            value: Value::String(self.path.clone()),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            if let ("path", Value::String(path)) = (v.name, v.value) {
+                self.path = path;
+            }
+        }
+    }
+}
+
+struct LoadedScript {
+    ast: Option<AST>, // the last version which compiled, None if none did
+}
+
+struct ScriptInstance {
+    path: String,
+    this: Dynamic, // the `this` object map of the entity
+    started: bool,
+}
+
+#[derive(Unique)]
+pub struct ScriptManager {
+    engine: ScriptEngine,
+    builtin_sources: HashMap<String, &'static str>, // used when the file can not be read, e.g. on android
+    scripts: HashMap<String, LoadedScript>,
+    instances: HashMap<EntityId, ScriptInstance>,
+    watcher: FileWatcher<String>, // the files of the loaded scripts, builtin sources have none
+}
+
+impl ScriptManager {
+    pub fn new() -> Self {
+        let mut engine = ScriptEngine::new();
+        engine.on_print(|text| log::info!("[script] {text}"));
+        engine.on_debug(|text, source, position| {
+            log::debug!("[script] {}:{position} {text}", source.unwrap_or_default())
+        });
+        register_math(&mut engine);
+        register_api(&mut engine);
+        ScriptManager {
+            engine,
+            builtin_sources: HashMap::new(),
+            scripts: HashMap::new(),
+            instances: HashMap::new(),
+            watcher: FileWatcher::new(),
+        }
+    }
+
+    // source is compiled into the engine, so it is available where files are not, e.g. on android
+    pub fn add_builtin(&mut self, path: impl Into<String>, source: &'static str) {
+        self.builtin_sources.insert(path.into(), source);
+    }
+
+    // compile the script at path if it is not loaded yet
+    fn load(&mut self, path: &str) {
+        if self.scripts.contains_key(path) {
+            return;
+        }
+        self.watcher.watch(path.to_string(), path);
+        self.compile(path);
+    }
+
+    // compile the script at path from its file or builtin source
+    fn compile(&mut self, path: &str) {
+        let loaded = self.scripts.get(path);
+        let source = match read_file(path).map(|data| String::from_utf8_lossy(&data).into_owned()) {
+            Ok(source) => source,
+            Err(e) => match self.builtin_sources.get(path) {
+                Some(source) => source.to_string(),
+                None => {
+                    log::error!("Failed to read script {path}: {e}");
+                    String::new()
+                }
+            },
+        };
+        let ast = match self.engine.compile(source) {
+            Ok(ast) => {
+                if loaded.is_some() {
+                    log::info!("Reloaded script {path}");
+                }
+                Some(ast)
+            }
+            Err(e) => {
+                // keep running the last good version
+                log::error!("Failed to compile script {path}: {e}");
+                loaded.and_then(|loaded| loaded.ast.clone())
+            }
+        };
+        self.scripts.insert(path.to_string(), LoadedScript { ast });
+    }
+
+    // compile the scripts whose files changed again
+    fn reload(&mut self) {
+        for path in self.watcher.changed() {
+            self.compile(&path);
+        }
+    }
+
+    // call the function of the script of entity if it defines it, errors are logged
+    fn call(&mut self, entity: EntityId, name: &str, args: impl FuncArgs) {
+        let Some(instance) = self.instances.get_mut(&entity) else {
+            return;
+        };
+        let Some(ast) = self
+            .scripts
+            .get(&instance.path)
+            .and_then(|loaded| loaded.ast.as_ref())
+        else {
+            return;
+        };
+        if !ast.iter_functions().any(|f| f.name == name) {
+            return;
+        }
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut instance.this);
+        if let Err(e) =
+            self.engine
+                .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), ast, name, args)
+        {
+            log::error!("Script {} {name} failed: {e}", instance.path);
+        }
+    }
+}
+
+// start new scripts and update all of them
+pub fn script_update_system(all_storages: AllStoragesView) {
+    let mut script_manager = all_storages
+        .borrow::<UniqueViewMut<ScriptManager>>()
+        .unwrap();
+    let delta = all_storages.borrow::<UniqueView<Time>>().unwrap().delta;
+    script_manager.reload();
+
+    {
+        let script = all_storages.borrow::<ViewMut<Script>>().unwrap();
+        script_manager.instances.retain(|e, _| script.contains(*e));
+        for (e, script) in script.inserted_or_modified().iter().with_id() {
+            script_manager.load(&script.path);
+            script_manager.instances.insert(
+                e,
+                ScriptInstance {
+                    path: script.path.clone(),
+                    this: Map::new().into(),
+                    started: false,
+                },
+            );
+        }
+        script.clear_all_inserted_and_modified();
+    }
+
+    let entities = script_manager.instances.keys().copied().collect::<Vec<_>>();
+    STORAGES.set(&all_storages, || {
+        for &e in &entities {
+            let instance = script_manager.instances.get_mut(&e).unwrap();
+            if !instance.started {
+                instance.started = true;
+                script_manager.call(e, "on_start", (e,));
+            }
+        }
+        for &e in &entities {
+            script_manager.call(e, "on_update", (e, delta as FLOAT));
+        }
+    });
+}
+
+// tell the scripts of both entities about collisions of the last physics update
+pub fn script_collision_system(all_storages: AllStoragesView) {
+    let mut script_manager = all_storages
+        .borrow::<UniqueViewMut<ScriptManager>>()
+        .unwrap();
+    let events = all_storages
+        .borrow::<UniqueView<Physics2DManager>>()
+        .unwrap()
+        .collision_events
+        .clone();
+    STORAGES.set(&all_storages, || {
+        for event in events {
+            script_manager.call(
+                event.entity1,
+                "on_collision",
+                (event.entity1, event.entity2, event.started),
+            );
+            script_manager.call(
+                event.entity2,
+                "on_collision",
+                (event.entity2, event.entity1, event.started),
+            );
+        }
+    });
+}
+
+// tell the scripts about the frame events their Animator2D fired in this update
+pub fn script_animation_event_system(all_storages: AllStoragesView) {
+    let mut script_manager = all_storages
+        .borrow::<UniqueViewMut<ScriptManager>>()
+        .unwrap();
+    let events = all_storages
+        .borrow::<UniqueView<AnimationManager>>()
+        .unwrap()
+        .events
+        .clone();
+    STORAGES.set(&all_storages, || {
+        for event in events {
+            script_manager.call(
+                event.entity,
+                "on_animation_event",
+                (event.entity, event.clip, event.name),
+            );
+        }
+    });
+}
+
+fn value_to_dynamic(value: Value) -> Dynamic {
+    match value {
+        Value::Bool(v) => v.into(),
+        Value::Int32(v) => (v as INT).into(),
+        Value::Float32(v) => (v as FLOAT).into(),
+        Value::String(v) => v.into(),
+        Value::Vec2(v) => Dynamic::from(v),
+        Value::Vec3(v) => Dynamic::from(v),
+        Value::Vec4(v) => Dynamic::from(v),
+        Value::Entity(v) => Dynamic::from(v),
+    }
+}
+
+// convert to the type of the current value, None if the types do not match
+fn dynamic_to_value(dynamic: Dynamic, current: &Value) -> Option<Value> {
+    let float = |d: &Dynamic| {
+        d.as_float()
+            .ok()
+            .or_else(|| d.as_int().ok().map(|i| i as FLOAT))
+    };
+    Some(match current {
+        Value::Bool(_) => Value::Bool(dynamic.as_bool().ok()?),
+        Value::Int32(_) => Value::Int32(dynamic.as_int().ok()? as i32),
+        Value::Float32(_) => Value::Float32(float(&dynamic)? as f32),
+        Value::String(_) => Value::String(dynamic.into_string().ok()?),
+        Value::Vec2(_) => Value::Vec2(dynamic.try_cast()?),
+        Value::Vec3(_) => Value::Vec3(dynamic.try_cast()?),
+        Value::Vec4(_) => Value::Vec4(dynamic.try_cast()?),
+        Value::Entity(_) => Value::Entity(dynamic.try_cast()?),
+    })
+}
+
+// read a variant of a component through the edit path, () if the entity does not have it
+fn get_variant(entity: EntityId, component: &str, variant: &str) -> Dynamic {
+    STORAGES.with(|all_storages| {
+        component_data_by_name(all_storages, entity, component)
+            .and_then(|data| data.variants.into_iter().find(|v| v.name == variant))
+            .map_or(Dynamic::UNIT, |v| value_to_dynamic(v.value))
+    })
+}
+
+// Write variants of a component through the edit path, so that the change is tracked. Unlike
+// edits of the editor they are not recorded as overrides of prefab instances, scripts change
+// the state of the running game every frame, and recording it would stop prefab changes from
+// reaching the instances and move that state into the prefab with apply_to_prefab.
+fn set_variants(entity: EntityId, component: &str, values: Vec<(&str, Dynamic)>) {
+    STORAGES.with(|all_storages| {
+        let Some(data) = component_data_by_name(all_storages, entity, component) else {
+            return;
+        };
+        let mut values = values;
+        let variants = data
+            .variants
+            .into_iter()
+            .filter_map(|v| {
+                let index = values.iter().position(|(name, _)| *name == v.name)?;
+                let (_, dynamic) = values.swap_remove(index);
+                match dynamic_to_value(dynamic, &v.value) {
+                    Some(value) => Some(Variant {
+                        name: v.name,
+                        value,
+                    }),
+                    None => {
+                        log::error!("Script set {component}.{} with a wrong type", v.name);
+                        None
+                    }
+                }
+            })
+            .collect();
+        set_component_data_by_name(
+            all_storages,
+            entity,
+            ComponentData {
+                name: data.name,
+                variants,
+            },
+        );
+    })
+}
+
+fn register_api(engine: &mut ScriptEngine) {
+    engine
+        .register_type_with_name::<EntityId>("Entity")
+        .register_fn("to_string", |e: &mut EntityId| format!("{e:?}"))
+        .register_fn("to_debug", |e: &mut EntityId| format!("{e:?}"))
+        .register_fn("==", |a: EntityId, b: EntityId| a == b)
+        .register_fn("!=", |a: EntityId, b: EntityId| a != b)
+        // generic access to every editable component
+        .register_fn("get", |e: &mut EntityId, component: &str, variant: &str| {
+            get_variant(*e, component, variant)
+        })
+        .register_fn(
+            "set",
+            |e: &mut EntityId, component: &str, variant: &str, value: Dynamic| {
+                set_variants(*e, component, vec![(variant, value)])
+            },
+        )
+        // shortcuts for the common ones
+        .register_get_set(
+            "position",
+            |e: &mut EntityId| match get_variant(*e, "Transform2D", "position").try_cast::<Vec3>() {
+                Some(position) => Dynamic::from(position.truncate()),
+                None => Dynamic::UNIT,
+            },
+            |e: &mut EntityId, position: Dynamic| {
+                let (Some(position), Some(current)) = (
+                    position.try_cast::<Vec2>(),
+                    get_variant(*e, "Transform2D", "position").try_cast::<Vec3>(),
+                ) else {
+                    return;
+                };
+                set_variants(
+                    *e,
+                    "Transform2D",
+                    vec![("position", Dynamic::from(position.extend(current.z)))],
+                )
+            },
+        )
+        .register_get_set(
+            "rotation",
+            |e: &mut EntityId| get_variant(*e, "Transform2D", "rotation"),
+            |e: &mut EntityId, rotation: Dynamic| {
+                set_variants(*e, "Transform2D", vec![("rotation", rotation)])
+            },
+        )
+        .register_get_set(
+            "velocity",
+            |e: &mut EntityId| get_variant(*e, "RigidBody2D", "linear_velocity"),
+            |e: &mut EntityId, velocity: Dynamic| {
+                // setting a velocity wakes the body up
+                set_variants(
+                    *e,
+                    "RigidBody2D",
+                    vec![("linear_velocity", velocity), ("sleeping", false.into())],
+                )
+            },
+        )
+        .register_get_set(
+            "angular_velocity",
+            |e: &mut EntityId| get_variant(*e, "RigidBody2D", "angular_velocity"),
+            |e: &mut EntityId, angular_velocity: Dynamic| {
+                set_variants(
+                    *e,
+                    "RigidBody2D",
+                    vec![
+                        ("angular_velocity", angular_velocity),
+                        ("sleeping", false.into()),
+                    ],
+                )
+            },
+        );
+
+    // input
+    let input = |f: fn(&Input, &str) -> bool| {
+        move |action: &str| STORAGES.with(|s| f(&s.borrow::<UniqueView<Input>>().unwrap(), action))
+    };
+    engine
+        .register_fn("action_pressed", input(Input::action_pressed))
+        .register_fn("action_just_pressed", input(Input::action_just_pressed))
+        .register_fn("action_just_released", input(Input::action_just_released))
+        // left_x, left_y, right_x or right_y, 0 for other names
+        .register_fn("gamepad_axis", |axis: &str| {
+            STORAGES.with(|s| {
+                GamepadAxis::from_name(axis).map_or(0.0, |axis| {
+                    s.borrow::<UniqueView<Input>>().unwrap().gamepad_axis(axis) as FLOAT
+                })
+            })
+        })
+        .register_fn("cursor_world_position", || {
+            STORAGES.with(|s| {
+                s.borrow::<UniqueView<Input>>()
+                    .unwrap()
+                    .cursor_world_position
+                    .map_or(Dynamic::UNIT, Dynamic::from)
+            })
+        });
+
+    // one shot sounds and bus volumes, they do nothing without AudioPlugin
+    let audio = |f: &dyn Fn(&mut AudioManager, &AssetServer, &View<Camera2D>)| {
+        STORAGES.with(|s| {
+            if let Ok((mut audio_manager, asset_server, camera2d)) = s.borrow::<(
+                UniqueViewMut<AudioManager>,
+                UniqueView<AssetServer>,
+                View<Camera2D>,
+            )>() {
+                f(&mut audio_manager, &asset_server, &camera2d);
+            }
+        })
+    };
+    engine
+        .register_fn("play_sound", move |path: &str| {
+            audio(&|a, asset_server, _| a.play(asset_server, path, "master", 1.0, 0.0))
+        })
+        .register_fn("play_sound", move |path: &str, position: Vec2| {
+            audio(&|a, asset_server, camera2d| {
+                let pan = listener_pan(camera2d, position);
+                a.play(asset_server, path, "master", 1.0, pan)
+            })
+        })
+        .register_fn("set_bus_volume", move |bus: &str, volume: FLOAT| {
+            audio(&|a, _, _| a.set_bus_volume(bus, volume as f32))
+        });
+
+    // physics queries, they find nothing without Physics2DPlugin
+    engine
+        .register_fn(
+            "cast_ray",
+            |origin: Vec2, direction: Vec2, max_distance: FLOAT| {
+                cast_ray(origin, direction, max_distance, None)
+            },
+        )
+        .register_fn(
+            "cast_ray",
+            |origin: Vec2, direction: Vec2, max_distance: FLOAT, exclude: EntityId| {
+                cast_ray(origin, direction, max_distance, Some(exclude))
+            },
+        )
+        .register_fn("entities_at", |point: Vec2| {
+            STORAGES.with(|s| {
+                s.borrow::<UniqueView<Physics2DManager>>()
+                    .map(|physics2d_manager| {
+                        physics2d_manager
+                            .intersections_with_point(point, QueryFilter2D::default())
+                            .into_iter()
+                            .map(Dynamic::from)
+                            .collect::<Array>()
+                    })
+                    .unwrap_or_default()
+            })
+        })
+        .register_fn(
+            "cast_ray_all",
+            |origin: Vec2, direction: Vec2, max_distance: FLOAT| {
+                physics_query(|p, filter| {
+                    let hits = p.cast_ray_all(origin, direction, max_distance as f32, true, filter);
+                    Some(hits.into_iter().map(ray_hit).collect::<Array>().into())
+                })
+            },
+        )
+        // sweeps a circle from position along velocity, the hit has the time it touches at
+        .register_fn(
+            "cast_circle",
+            |position: Vec2, radius: FLOAT, velocity: Vec2, max_time: FLOAT| {
+                physics_query(|p, filter| {
+                    let shape = SharedShape::ball(radius as f32);
+                    let hit =
+                        p.cast_shape(&shape, position, 0.0, velocity, max_time as f32, filter)?;
+                    let mut map = Map::new();
+                    map.insert("entity".into(), Dynamic::from(hit.entity));
+                    map.insert("time".into(), (hit.time as FLOAT).into());
+                    map.insert("point".into(), Dynamic::from(hit.point));
+                    map.insert("normal".into(), Dynamic::from(hit.normal));
+                    Some(map.into())
+                })
+            },
+        )
+        .register_fn("project_point", |point: Vec2| {
+            physics_query(|p, filter| {
+                let projection = p.project_point(point, true, filter)?;
+                let mut map = Map::new();
+                map.insert("entity".into(), Dynamic::from(projection.entity));
+                map.insert("point".into(), Dynamic::from(projection.point));
+                map.insert("is_inside".into(), projection.is_inside.into());
+                Some(map.into())
+            })
+        })
+        .register_fn("entities_in_aabb", |min: Vec2, max: Vec2| {
+            physics_query(|p, filter| {
+                let entities = p.intersections_with_aabb(min, max, filter);
+                Some(
+                    entities
+                        .into_iter()
+                        .map(Dynamic::from)
+                        .collect::<Array>()
+                        .into(),
+                )
+            })
+        });
+
+    // prefab instances, () if the prefab can not be loaded
+    let spawn = |path: &str, overrides| {
+        STORAGES.with(|s| spawn_prefab(s, path, overrides).map_or(Dynamic::UNIT, Dynamic::from))
+    };
+    engine
+        .register_fn("spawn", move |path: &str| spawn(path, Vec::new()))
+        .register_fn("spawn", move |path: &str, position: Vec2| {
+            spawn(path, vec![placement(position)])
+        });
+
+    // text for one frame, they show nothing without Text2DPlugin
+    let debug_text = |f: &dyn Fn(&mut DebugText)| {
+        STORAGES.with(|s| {
+            if let Ok(mut debug_text) = s.borrow::<UniqueViewMut<DebugText>>() {
+                f(&mut debug_text);
+            }
+        })
+    };
+    engine
+        .register_fn("debug_text", move |text: &str| {
+            debug_text(&|d| d.line(text))
+        })
+        .register_fn("debug_label", move |position: Vec2, text: &str| {
+            debug_text(&|d| d.label(position, text))
+        });
+
+    // lines in world space for one frame, they draw nothing without DebugDrawPlugin
+    let debug_draw = |f: &dyn Fn(&mut DebugDraw)| {
+        STORAGES.with(|s| {
+            if let Ok(mut debug_draw) = s.borrow::<UniqueViewMut<DebugDraw>>() {
+                f(&mut debug_draw);
+            }
+        })
+    };
+    engine
+        .register_fn("debug_line", move |start: Vec2, end: Vec2, color: Vec4| {
+            debug_draw(&|d| d.line(start, end, color))
+        })
+        .register_fn("debug_arrow", move |start: Vec2, end: Vec2, color: Vec4| {
+            debug_draw(&|d| d.arrow(start, end, color))
+        })
+        .register_fn(
+            "debug_circle",
+            move |center: Vec2, radius: FLOAT, color: Vec4| {
+                debug_draw(&|d| d.circle(center, radius as f32, color))
+            },
+        )
+        .register_fn("debug_aabb", move |min: Vec2, max: Vec2, color: Vec4| {
+            debug_draw(&|d| d.aabb(min, max, color))
+        });
+
+    // all entities with the given component, e.g. entities_with("Camera2D")
+    engine.register_fn("entities_with", |component: &str| {
+        STORAGES.with(|s| {
+            let entities = s.borrow::<shipyard::EntitiesView>().unwrap();
+            entities
+                .iter()
+                .filter(|e| component_data_by_name(s, *e, component).is_some())
+                .map(Dynamic::from)
+                .collect::<Array>()
+        })
+    });
+}
+
+// the closest hit as #{entity, distance, point, normal}, () if nothing was hit
+fn cast_ray(
+    origin: Vec2,
+    direction: Vec2,
+    max_distance: FLOAT,
+    exclude: Option<EntityId>,
+) -> Dynamic {
+    STORAGES.with(|s| {
+        let filter = QueryFilter2D {
+            exclude,
+            ..Default::default()
+        };
+        s.borrow::<UniqueView<Physics2DManager>>()
+            .ok()
+            .and_then(|physics2d_manager| {
+                physics2d_manager.cast_ray(origin, direction, max_distance as f32, true, filter)
+            })
+            .map_or(Dynamic::UNIT, ray_hit)
+    })
+}
+
+fn ray_hit(hit: RayHit2D) -> Dynamic {
+    let mut map = Map::new();
+    map.insert("entity".into(), Dynamic::from(hit.entity));
+    map.insert("distance".into(), (hit.distance as FLOAT).into());
+    map.insert("point".into(), Dynamic::from(hit.point));
+    map.insert("normal".into(), Dynamic::from(hit.normal));
+    map.into()
+}
+
+// the result of a scene query with the default filter, () without Physics2DPlugin or if the
+// query found nothing
+fn physics_query(f: impl FnOnce(&Physics2DManager, QueryFilter2D) -> Option<Dynamic>) -> Dynamic {
+    STORAGES.with(|s| {
+        s.borrow::<UniqueView<Physics2DManager>>()
+            .ok()
+            .and_then(|physics2d_manager| f(&physics2d_manager, QueryFilter2D::default()))
+            .unwrap_or(Dynamic::UNIT)
+    })
+}
+
+// glam vectors with their components, constructors and basic operators
+fn register_math(engine: &mut ScriptEngine) {
+    engine
+        .register_type_with_name::<Vec2>("Vec2")
+        .register_fn("vec2", |x: FLOAT, y: FLOAT| Vec2::new(x as f32, y as f32))
+        .register_get_set(
+            "x",
+            |v: &mut Vec2| v.x as FLOAT,
+            |v: &mut Vec2, x: FLOAT| v.x = x as f32,
+        )
+        .register_get_set(
+            "y",
+            |v: &mut Vec2| v.y as FLOAT,
+            |v: &mut Vec2, y: FLOAT| v.y = y as f32,
+        )
+        .register_fn("+", |a: Vec2, b: Vec2| a + b)
+        .register_fn("-", |a: Vec2, b: Vec2| a - b)
+        .register_fn("*", |a: Vec2, b: FLOAT| a * b as f32)
+        .register_fn("length", |v: &mut Vec2| v.length() as FLOAT)
+        .register_fn("normalize", |v: &mut Vec2| v.normalize_or_zero())
+        .register_fn("to_string", |v: &mut Vec2| v.to_string())
+        .register_fn("to_debug", |v: &mut Vec2| format!("{v:?}"));
+    engine
+        .register_type_with_name::<Vec3>("Vec3")
+        .register_fn("vec3", |x: FLOAT, y: FLOAT, z: FLOAT| {
+            Vec3::new(x as f32, y as f32, z as f32)
+        })
+        .register_get_set(
+            "x",
+            |v: &mut Vec3| v.x as FLOAT,
+            |v: &mut Vec3, x: FLOAT| v.x = x as f32,
+        )
+        .register_get_set(
+            "y",
+            |v: &mut Vec3| v.y as FLOAT,
+            |v: &mut Vec3, y: FLOAT| v.y = y as f32,
+        )
+        .register_get_set(
+            "z",
+            |v: &mut Vec3| v.z as FLOAT,
+            |v: &mut Vec3, z: FLOAT| v.z = z as f32,
+        )
+        .register_fn("to_string", |v: &mut Vec3| v.to_string())
+        .register_fn("to_debug", |v: &mut Vec3| format!("{v:?}"));
+    engine
+        .register_type_with_name::<Vec4>("Vec4")
+        .register_fn("vec4", |x: FLOAT, y: FLOAT, z: FLOAT, w: FLOAT| {
+            Vec4::new(x as f32, y as f32, z as f32, w as f32)
+        })
+        .register_get_set(
+            "x",
+            |v: &mut Vec4| v.x as FLOAT,
+            |v: &mut Vec4, x: FLOAT| v.x = x as f32,
+        )
+        .register_get_set(
+            "y",
+            |v: &mut Vec4| v.y as FLOAT,
+            |v: &mut Vec4, y: FLOAT| v.y = y as f32,
+        )
+        .register_get_set(
+            "z",
+            |v: &mut Vec4| v.z as FLOAT,
+            |v: &mut Vec4, z: FLOAT| v.z = z as f32,
+        )
+        .register_get_set(
+            "w",
+            |v: &mut Vec4| v.w as FLOAT,
+            |v: &mut Vec4, w: FLOAT| v.w = w as f32,
+        )
+        .register_fn("to_string", |v: &mut Vec4| v.to_string())
+        .register_fn("to_debug", |v: &mut Vec4| format!("{v:?}"));
+}
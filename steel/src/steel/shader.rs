@@ -0,0 +1,220 @@
+use shader_library::ShaderError;
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::{Arc, Mutex},
+};
+use vulkano::{
+    device::Device,
+    pipeline::{
+        layout::PipelineDescriptorSetLayoutCreateInfo, GraphicsPipeline, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    shader::EntryPoint,
+};
+
+// checks a shader against the vertex types, push constants and descriptor sets of its pass
+type ValidateShader = fn(&shader_library::Shader) -> Result<(), ShaderError>;
+
+// A GLSL file of a pass and the SPIR-V the build script compiled into the engine from it
+pub struct Shader {
+    pub path: &'static str, // relative to the steel crate, e.g. "shaders/quad.vert"
+    pub spirv: &'static [u8],
+    pub validate: ValidateShader,
+}
+
+#[derive(Default)]
+struct ShaderFile {
+    builtin: Option<Arc<shader_library::Shader>>,
+    good: Option<Arc<shader_library::Shader>>, // the last version of the file a pipeline was created with
+    new: Option<Arc<shader_library::Shader>>, // loaded from the file, but not used in a pipeline yet
+    error: Option<String>,                    // why the last version of the file is not used
+    #[cfg(not(target_os = "android"))]
+    watcher: Option<shader_library::FileWatcher<&'static str>>,
+}
+
+impl ShaderFile {
+    fn update(&mut self, device: &Arc<Device>, shader: &Shader) {
+        if self.builtin.is_none() {
+            let builtin = shader_library::Shader::from_spirv(device, shader.path, shader.spirv)
+                .and_then(|builtin| (shader.validate)(&builtin).map(|_| builtin))
+                .unwrap_or_else(|e| panic!("{e}"));
+            self.builtin = Some(Arc::new(builtin));
+        }
+        #[cfg(not(target_os = "android"))]
+        {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(shader.path);
+            // the builtin module was compiled from the file as it is when the engine starts
+            let watcher = self.watcher.get_or_insert_with(|| {
+                let mut watcher = shader_library::FileWatcher::new();
+                watcher.watch(shader.path, &path);
+                watcher
+            });
+            if watcher.changed().is_empty() {
+                return;
+            }
+            match shader_library::Shader::load(device, &path)
+                .and_then(|new| (shader.validate)(&new).map(|_| new))
+            {
+                Ok(new) => {
+                    log::info!("Recompiled shader {}", shader.path);
+                    self.new = Some(Arc::new(new));
+                }
+                Err(mut e) => {
+                    e.name = shader.path.into();
+                    log::error!("Failed to load shader {e}");
+                    self.new = None;
+                    self.error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    fn latest(&self) -> Arc<shader_library::Shader> {
+        self.new.clone().unwrap_or_else(|| self.good())
+    }
+
+    fn good(&self) -> Arc<shader_library::Shader> {
+        self.good.clone().or_else(|| self.builtin.clone()).unwrap()
+    }
+}
+
+// The shaders of the passes. In desktop builds their files are recompiled when they change, a
+// version which does not compile, does not fit the Rust side of its pass or the pipeline is
+// reported and the last good one is kept.
+#[derive(Default)]
+pub struct ShaderManager {
+    files: Mutex<HashMap<&'static str, ShaderFile>>,
+    // why pipelines a pass creates itself are not rebuilt, by the name the pass reports them with
+    pipeline_errors: Mutex<HashMap<String, String>>,
+}
+
+impl ShaderManager {
+    // create calls GraphicsPipeline::new with the vertex and fragment shader and the layout.
+    // The layout is always the one of the builtin modules, so the pass can keep binding the same
+    // descriptor sets and push constants.
+    pub fn graphics_pipeline(
+        &self,
+        device: &Arc<Device>,
+        [vs, fs]: &[Shader; 2],
+        create: impl Fn(
+            EntryPoint,
+            EntryPoint,
+            Arc<PipelineLayout>,
+        ) -> Result<Arc<GraphicsPipeline>, Box<dyn Error>>,
+    ) -> Arc<GraphicsPipeline> {
+        let mut files = self.files.lock().unwrap();
+        files.entry(vs.path).or_default().update(device, vs);
+        files.entry(fs.path).or_default().update(device, fs);
+        let entry_point = |shader: Arc<shader_library::Shader>| shader.entry_point();
+
+        let builtin_stages = [vs, fs].map(|shader| {
+            PipelineShaderStageCreateInfo::new(entry_point(
+                files[shader.path].builtin.clone().unwrap(),
+            ))
+        });
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages(&builtin_stages)
+                .into_pipeline_layout_create_info(device.clone())
+                .unwrap(),
+        )
+        .unwrap();
+
+        match create(
+            entry_point(files[vs.path].latest()),
+            entry_point(files[fs.path].latest()),
+            layout.clone(),
+        ) {
+            Ok(pipeline) => {
+                for shader in [vs, fs] {
+                    let file = files.get_mut(shader.path).unwrap();
+                    if let Some(module) = file.new.take() {
+                        file.good = Some(module);
+                        file.error = None;
+                    }
+                }
+                return pipeline;
+            }
+            Err(e) => {
+                // only a changed file can break a pipeline which could be created before
+                for shader in [vs, fs] {
+                    let file = files.get_mut(shader.path).unwrap();
+                    if file.new.take().is_some() {
+                        log::error!("Failed to use shader {}: {e}", shader.path);
+                        file.error = Some(format!("{}: {e}", shader.path));
+                    }
+                }
+            }
+        }
+        create(
+            entry_point(files[vs.path].good()),
+            entry_point(files[fs.path].good()),
+            layout,
+        )
+        .unwrap()
+    }
+
+    // the latest version of a shader, for pipelines a pass creates itself, e.g. with the fragment
+    // shader of a material. The pass reports with used or failed whether a pipeline could be
+    // created with it, so a new version becomes the good one or is dropped with its error.
+    pub fn current(&self, device: &Arc<Device>, shader: &Shader) -> Arc<shader_library::Shader> {
+        let mut files = self.files.lock().unwrap();
+        let file = files.entry(shader.path).or_default();
+        file.update(device, shader);
+        file.latest()
+    }
+
+    // the pipeline name of a pass was created with module, the version of shader from current
+    pub fn used(&self, shader: &Shader, module: &Arc<shader_library::Shader>, name: &str) {
+        let mut files = self.files.lock().unwrap();
+        let file = files.entry(shader.path).or_default();
+        if file
+            .new
+            .as_ref()
+            .is_some_and(|new| Arc::ptr_eq(new, module))
+        {
+            file.good = file.new.take();
+            file.error = None;
+        }
+        self.pipeline_errors.lock().unwrap().remove(name);
+    }
+
+    // the pipeline name of a pass could not be created with module, the version of shader from
+    // current, the pass keeps its last good pipeline
+    pub fn failed(
+        &self,
+        shader: &Shader,
+        module: &Arc<shader_library::Shader>,
+        name: &str,
+        error: &dyn Error,
+    ) {
+        log::error!("Failed to create the pipeline of {name}: {error}");
+        let mut files = self.files.lock().unwrap();
+        let file = files.entry(shader.path).or_default();
+        if file
+            .new
+            .as_ref()
+            .is_some_and(|new| Arc::ptr_eq(new, module))
+        {
+            file.new = None;
+            file.error = Some(format!("{}: {name}: {error}", shader.path));
+        }
+        self.pipeline_errors
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), format!("{name}: {error}"));
+    }
+
+    // why the current versions of shader files are not used
+    pub fn errors(&self) -> Vec<String> {
+        let files = self.files.lock().unwrap();
+        let mut errors = files
+            .values()
+            .filter_map(|file| file.error.clone())
+            .chain(self.pipeline_errors.lock().unwrap().values().cloned())
+            .collect::<Vec<_>>();
+        errors.sort();
+        errors
+    }
+}
@@ -0,0 +1,933 @@
+use super::{
+    asset::{Asset, AssetCache, AssetReader},
+    camera2d_extract_system,
+    prefab::PrefabInstance,
+    AssetServer, ComponentData, Edit, EngineBuilder, Glyph2D, GlyphAtlasImage, Interpolation2D,
+    Plugin, RenderInfo, RenderList, RenderPass, Shader, Stage, TextSpace, Time, Transform2D, Value,
+    Variant,
+};
+use ab_glyph::{point, Font, FontArc, GlyphId, ScaleFont};
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use shader_library::include_spirv;
+use shipyard::{
+    Component, Get, IntoIter, IntoWithId, SystemModificator, Unique, UniqueView, UniqueViewMut,
+    View,
+};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage},
+    command_buffer::{AutoCommandBufferBuilder, CopyBufferToImageInfo, PrimaryAutoCommandBuffer},
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorType, DescriptorSet,
+        WriteDescriptorSet,
+    },
+    format::Format,
+    image::{
+        sampler::{Sampler, SamplerCreateInfo},
+        view::ImageView,
+        Image, ImageCreateInfo, ImageUsage,
+    },
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{
+        graphics::{
+            color_blend::{AttachmentBlend, ColorBlendAttachmentState, ColorBlendState},
+            input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            rasterization::RasterizationState,
+            vertex_input::{Vertex, VertexDefinition},
+            viewport::{Viewport, ViewportState},
+            GraphicsPipelineCreateInfo,
+        },
+        DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineShaderStageCreateInfo,
+    },
+};
+
+// glyphs are rasterized at this size in pixels, text of other sizes scales them
+const GLYPH_PIXELS: f32 = 48.0;
+const ATLAS_SIZE: u32 = 1024;
+// builtin fonts, the ones egui uses for its proportional and monospace text
+pub const DEFAULT_FONT: &str = "fonts/Ubuntu-Light.ttf";
+pub const MONOSPACE_FONT: &str = "fonts/Hack-Regular.ttf";
+
+// Text2D, the fonts and glyph atlas it is drawn with and the DebugText labels
+pub struct Text2DPlugin;
+
+impl Plugin for Text2DPlugin {
+    fn build(&self, builder: &mut EngineBuilder) {
+        {
+            let mut asset_server = builder
+                .world()
+                .borrow::<UniqueViewMut<AssetServer>>()
+                .unwrap();
+            let egui_fonts = egui::FontDefinitions::default().font_data;
+            for (path, name) in [(DEFAULT_FONT, "Ubuntu-Light"), (MONOSPACE_FONT, "Hack")] {
+                if let Some(Cow::Borrowed(font)) = egui_fonts.get(name).map(|data| &data.font) {
+                    asset_server.add_builtin(path, font);
+                }
+            }
+        }
+        builder
+            .add_unique(FontManager::new())
+            .add_unique(DebugText::default())
+            .add_component(Text2D::default)
+            .add_system(
+                Stage::RenderExtract,
+                text2d_extract_system.after_all(camera2d_extract_system),
+            )
+            .add_system(
+                Stage::RenderExtract,
+                debug_text_extract_system.after_all(text2d_extract_system),
+            )
+            .add_render_pass(Text2DPass::default());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+impl TextAlign {
+    fn name(&self) -> &'static str {
+        match self {
+            TextAlign::Left => "Left",
+            TextAlign::Center => "Center",
+            TextAlign::Right => "Right",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Left" => Some(TextAlign::Left),
+            "Center" => Some(TextAlign::Center),
+            "Right" => Some(TextAlign::Right),
+            _ => None,
+        }
+    }
+}
+
+// Text drawn at the Transform2D of its entity, the position is the top of the first line.
+// In screen space position and size are in pixels from the top left of the scene image,
+// otherwise in world units.
+#[derive(Component, Debug, Clone)]
+#[track(All)]
+pub struct Text2D {
+    pub text: String,
+    pub font: String, // path of a TTF or OTF file
+    pub size: f32,    // height of a line
+    pub color: Vec4,
+    pub align: TextAlign,
+    pub wrap_width: f32, // lines are wrapped between words at this width, 0 to never wrap
+    pub screen_space: bool,
+}
+
+impl Default for Text2D {
+    fn default() -> Self {
+        Text2D {
+            text: String::new(),
+            font: DEFAULT_FONT.into(),
+            size: 1.0,
+            color: Vec4::ONE,
+            align: TextAlign::Left,
+            wrap_width: 0.0,
+            screen_space: false,
+        }
+    }
+}
+
+impl Edit for Text2D {
+    fn name() -> &'static str {
+        "Text2D"
+    }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "text",
+            value: Value::String(self.text.clone()),
+        });
+        data.variants.push(Variant {
+            name: "font",
+            value: Value::String(self.font.clone()),
+        });
+        data.variants.push(Variant {
+            name: "size",
+            value: Value::Float32(self.size),
+        });
+        data.variants.push(Variant {
+            name: "color",
+            value: Value::Vec4(self.color),
+        });
+        data.variants.push(Variant {
+            name: "align",
+            value: Value::String(self.align.name().into()),
+        });
+        data.variants.push(Variant {
+            name: "wrap_width",
+            value: Value::Float32(self.wrap_width),
+        });
+        data.variants.push(Variant {
+            name: "screen_space",
+            value: Value::Bool(self.screen_space),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            match (v.name, v.value) {
+                ("text", Value::String(text)) => self.text = text,
+                ("font", Value::String(font)) => self.font = font,
+                ("size", Value::Float32(size)) => self.size = size,
+                ("color", Value::Vec4(color)) => self.color = color,
+                ("align", Value::String(align)) => {
+                    if let Some(align) = TextAlign::from_name(&align) {
+                        self.align = align;
+                    }
+                }
+                ("wrap_width", Value::Float32(wrap_width)) => self.wrap_width = wrap_width,
+                ("screen_space", Value::Bool(screen_space)) => self.screen_space = screen_space,
+                _ => (),
+            }
+        }
+    }
+}
+
+// where a rasterized glyph is in the atlas, in pixels of GLYPH_PIXELS sized text
+#[derive(Clone, Copy)]
+struct AtlasGlyph {
+    uv_min: Vec2,
+    uv_max: Vec2,
+    offset: Vec2, // top left relative to the pen position on the baseline
+    size: Vec2,
+}
+
+// glyphs are packed in rows from the top left
+struct GlyphAtlas {
+    pixels: Vec<u8>,
+    // None for glyphs without outline and glyphs which did not fit
+    glyphs: HashMap<(String, GlyphId), Option<AtlasGlyph>>,
+    used: HashSet<(String, GlyphId)>, // glyphs of the text laid out since the last clear_used
+    full: bool,                       // a glyph did not fit since the last clear_used
+    cursor: (u32, u32),
+    row_height: u32,
+    version: u64,
+    image: Option<GlyphAtlasImage>, // the pixels of the current version
+}
+
+impl GlyphAtlas {
+    fn new() -> Self {
+        GlyphAtlas {
+            pixels: vec![0; (ATLAS_SIZE * ATLAS_SIZE) as usize],
+            glyphs: HashMap::new(),
+            used: HashSet::new(),
+            full: false,
+            cursor: (0, 0),
+            row_height: 0,
+            version: 0,
+            image: None,
+        }
+    }
+
+    fn clear_used(&mut self) {
+        self.used.clear();
+        self.full = false;
+    }
+
+    // the atlas holds glyphs which were not used since the last clear_used, so starting over
+    // with an empty one makes room when it is full
+    fn has_unused(&self) -> bool {
+        self.glyphs.keys().any(|key| !self.used.contains(key))
+    }
+
+    // empty, the glyphs of text laid out before are not in it any more
+    fn clear(&mut self) {
+        *self = GlyphAtlas {
+            version: self.version + 1,
+            ..GlyphAtlas::new()
+        };
+    }
+
+    // None for glyphs without outline, larger than the atlas or which do not fit any more, only
+    // the last ones are not cached, so they are tried again after the atlas starts over
+    fn glyph(&mut self, font_path: &str, font: &FontArc, id: GlyphId) -> Option<AtlasGlyph> {
+        let key = (font_path.to_string(), id);
+        if let Some(glyph) = self.glyphs.get(&key) {
+            self.used.insert(key);
+            return *glyph;
+        }
+        let outlined = font
+            .outline_glyph(id.with_scale_and_position(GLYPH_PIXELS, point(0.0, 0.0)))
+            .filter(|outlined| {
+                let bounds = outlined.px_bounds();
+                (bounds.width() as u32) < ATLAS_SIZE && (bounds.height() as u32) < ATLAS_SIZE
+            });
+        let Some(outlined) = outlined else {
+            self.glyphs.insert(key.clone(), None);
+            self.used.insert(key);
+            return None;
+        };
+        let bounds = outlined.px_bounds();
+        let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+        if self.cursor.0 + width + 1 > ATLAS_SIZE {
+            self.cursor = (0, self.cursor.1 + self.row_height + 1);
+            self.row_height = 0;
+        }
+        if self.cursor.1 + height + 1 > ATLAS_SIZE {
+            self.full = true;
+            return None;
+        }
+        let (x, y) = self.cursor;
+        outlined.draw(|gx, gy, coverage| {
+            let index = ((y + gy) * ATLAS_SIZE + x + gx) as usize;
+            if let Some(pixel) = self.pixels.get_mut(index) {
+                *pixel = (coverage.clamp(0.0, 1.0) * 255.0) as u8;
+            }
+        });
+        self.cursor.0 += width + 1;
+        self.row_height = self.row_height.max(height);
+        self.version += 1;
+        let glyph = Some(AtlasGlyph {
+            uv_min: Vec2::new(x as f32, y as f32) / ATLAS_SIZE as f32,
+            uv_max: Vec2::new((x + width) as f32, (y + height) as f32) / ATLAS_SIZE as f32,
+            offset: Vec2::new(bounds.min.x, bounds.min.y),
+            size: Vec2::new(width as f32, height as f32),
+        });
+        self.glyphs.insert(key.clone(), glyph);
+        self.used.insert(key);
+        glyph
+    }
+
+    fn image(&mut self) -> GlyphAtlasImage {
+        if self
+            .image
+            .as_ref()
+            .is_none_or(|image| image.version != self.version)
+        {
+            self.image = Some(GlyphAtlasImage {
+                size: ATLAS_SIZE,
+                pixels: self.pixels.as_slice().into(),
+                version: self.version,
+            });
+        }
+        self.image.clone().unwrap()
+    }
+}
+
+// a glyph of laid out text, in pixels of GLYPH_PIXELS sized text with y pointing down
+struct LaidOutGlyph {
+    min: Vec2,
+    max: Vec2,
+    uv_min: Vec2,
+    uv_max: Vec2,
+}
+
+impl Asset for FontArc {
+    fn load(_path: &str, data: Vec<u8>, _reader: &AssetReader) -> Result<Self, String> {
+        FontArc::try_from_vec(data).map_err(|e| e.to_string())
+    }
+}
+
+// Fonts of the text by path and the glyph atlas of all text
+#[derive(Unique)]
+pub struct FontManager {
+    fonts: AssetCache<FontArc>,
+    atlas: GlyphAtlas,
+    texts: Vec<(Text2D, Mat4, TextSpace)>, // pushed in this frame, laid out together by extract
+}
+
+impl FontManager {
+    pub fn new() -> Self {
+        FontManager {
+            fonts: AssetCache::new(),
+            atlas: GlyphAtlas::new(),
+            texts: Vec::new(),
+        }
+    }
+
+    // wrap_width is in pixels of GLYPH_PIXELS sized text, lines start at y = 0
+    fn layout(
+        &mut self,
+        asset_server: &AssetServer,
+        text: &str,
+        font_path: &str,
+        align: TextAlign,
+        wrap_width: f32,
+    ) -> Vec<LaidOutGlyph> {
+        let Some(font) = self.fonts.get(asset_server, font_path) else {
+            return Vec::new();
+        };
+        let scaled = font.as_scaled(GLYPH_PIXELS);
+
+        // the glyphs of every line with their pen position, and the width of the line
+        let mut lines: Vec<(Vec<(GlyphId, f32)>, f32)> = Vec::new();
+        for paragraph in text.split('\n') {
+            let (mut line, mut x, mut width) = (Vec::new(), 0.0, 0.0);
+            let mut previous = None;
+            for word in paragraph.split_inclusive(' ') {
+                let word_width = word
+                    .trim_end()
+                    .chars()
+                    .map(|c| scaled.h_advance(font.glyph_id(c)))
+                    .sum::<f32>();
+                if wrap_width > 0.0 && !line.is_empty() && x + word_width > wrap_width {
+                    lines.push((std::mem::take(&mut line), width));
+                    (x, width, previous) = (0.0, 0.0, None);
+                }
+                for c in word.chars() {
+                    let id = font.glyph_id(c);
+                    if let Some(previous) = previous {
+                        x += scaled.kern(previous, id);
+                    }
+                    line.push((id, x));
+                    x += scaled.h_advance(id);
+                    if !c.is_whitespace() {
+                        width = x;
+                    }
+                    previous = Some(id);
+                }
+            }
+            lines.push((line, width));
+        }
+
+        let line_height = scaled.ascent() - scaled.descent() + scaled.line_gap();
+        let mut glyphs = Vec::new();
+        for (i, (line, width)) in lines.into_iter().enumerate() {
+            let baseline = scaled.ascent() + i as f32 * line_height;
+            let start = match align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => -width / 2.0,
+                TextAlign::Right => -width,
+            };
+            for (id, x) in line {
+                if let Some(glyph) = self.atlas.glyph(font_path, &font, id) {
+                    let min = Vec2::new(start + x, baseline) + glyph.offset;
+                    glyphs.push(LaidOutGlyph {
+                        min,
+                        max: min + glyph.size,
+                        uv_min: glyph.uv_min,
+                        uv_max: glyph.uv_max,
+                    });
+                }
+            }
+        }
+        glyphs
+    }
+
+    // the text is laid out by extract, transform maps text of size 1 with y pointing down to space
+    fn push_text(&mut self, text2d: Text2D, transform: Mat4, space: TextSpace) {
+        self.texts.push((text2d, transform, space));
+    }
+
+    // pushes the glyphs of the texts of this frame and the atlas they are in. When the atlas is
+    // full and has glyphs of earlier frames, it starts over and the texts are laid out again,
+    // otherwise the glyphs which do not fit are left out until then.
+    fn extract(&mut self, asset_server: &AssetServer, render_list: &mut RenderList) {
+        let texts = std::mem::take(&mut self.texts);
+        self.atlas.clear_used();
+        for (text2d, transform, space) in &texts {
+            self.lay_out_text(asset_server, render_list, text2d, *transform, *space);
+        }
+        if self.atlas.full && self.atlas.has_unused() {
+            log::info!("Glyph atlas is full, rebuilding it with the glyphs of this frame");
+            self.atlas.clear();
+            render_list.glyphs.clear();
+            for (text2d, transform, space) in &texts {
+                self.lay_out_text(asset_server, render_list, text2d, *transform, *space);
+            }
+        }
+        if self.atlas.full {
+            log::warn!("Glyph atlas is full, some glyphs are not drawn");
+        }
+        if !render_list.glyphs.is_empty() {
+            render_list.glyph_atlas = Some(self.atlas.image());
+        }
+    }
+
+    fn lay_out_text(
+        &mut self,
+        asset_server: &AssetServer,
+        render_list: &mut RenderList,
+        text2d: &Text2D,
+        transform: Mat4,
+        space: TextSpace,
+    ) {
+        let scale = text2d.size / GLYPH_PIXELS;
+        let transform = transform * Mat4::from_scale(Vec3::splat(scale));
+        let wrap_width = text2d.wrap_width / scale;
+        for glyph in self.layout(
+            asset_server,
+            &text2d.text,
+            &text2d.font,
+            text2d.align,
+            wrap_width,
+        ) {
+            let corners = [
+                glyph.min,
+                Vec2::new(glyph.min.x, glyph.max.y),
+                glyph.max,
+                Vec2::new(glyph.max.x, glyph.min.y),
+            ];
+            render_list.glyphs.push(Glyph2D {
+                positions: corners
+                    .map(|corner| transform.transform_point3(corner.extend(0.0)).truncate()),
+                uv_min: glyph.uv_min,
+                uv_max: glyph.uv_max,
+                color: text2d.color,
+                space,
+            });
+        }
+    }
+}
+
+// Text which is shown for one frame, call it every update to keep it on screen.
+// Lines are listed in the top left of the scene, labels follow a world position.
+#[derive(Unique)]
+pub struct DebugText {
+    lines: Vec<String>,
+    labels: Vec<(Vec2, String)>,
+    pub show_fps: bool,
+    pub show_entity_labels: bool, // label every entity with a Transform2D with its id and prefab
+    pub size: f32,                // line height in pixels
+    pub color: Vec4,
+    fps: f32, // smoothed over the last updates
+}
+
+impl Default for DebugText {
+    fn default() -> Self {
+        DebugText {
+            lines: Vec::new(),
+            labels: Vec::new(),
+            show_fps: false,
+            show_entity_labels: false,
+            size: 16.0,
+            color: Vec4::new(1.0, 1.0, 0.0, 1.0),
+            fps: 0.0,
+        }
+    }
+}
+
+impl DebugText {
+    pub fn line(&mut self, text: impl Into<String>) {
+        self.lines.push(text.into());
+    }
+
+    pub fn label(&mut self, position: Vec2, text: impl Into<String>) {
+        self.labels.push((position, text.into()));
+    }
+}
+
+fn text2d_extract_system(
+    transform2d: View<Transform2D>,
+    text2d: View<Text2D>,
+    interpolation2d: View<Interpolation2D>,
+    mut font_manager: UniqueViewMut<FontManager>,
+    render_list: UniqueView<RenderList>,
+) {
+    let alpha = render_list.alpha;
+    for (e, (transform2d, text2d)) in (&transform2d, &text2d).iter().with_id() {
+        let (position, rotation) = match interpolation2d.get(e) {
+            Ok(interpolation2d) => interpolation2d.blend(transform2d, alpha),
+            Err(_) => (transform2d.position, transform2d.rotation),
+        };
+        // laid out text points down, world space points up
+        let (flip, space) = if text2d.screen_space {
+            (1.0, TextSpace::Screen)
+        } else {
+            (-1.0, TextSpace::World)
+        };
+        let transform = Mat4::from_scale_rotation_translation(
+            Vec3::new(transform2d.scale.x, transform2d.scale.y * flip, 1.0),
+            Quat::from_axis_angle(Vec3::Z, rotation),
+            position,
+        );
+        font_manager.push_text(text2d.clone(), transform, space);
+    }
+}
+
+// the last text extract system, it also lays out the text of the frame
+fn debug_text_extract_system(
+    asset_server: UniqueView<AssetServer>,
+    transform2d: View<Transform2D>,
+    prefab_instance: View<PrefabInstance>,
+    time: UniqueView<Time>,
+    mut font_manager: UniqueViewMut<FontManager>,
+    mut debug_text: UniqueViewMut<DebugText>,
+    mut render_list: UniqueViewMut<RenderList>,
+) {
+    let debug_text = &mut *debug_text;
+    if time.delta > 0.0 {
+        debug_text.fps = if debug_text.fps > 0.0 {
+            debug_text.fps * 0.95 + 0.05 / time.delta
+        } else {
+            1.0 / time.delta
+        };
+    }
+    if debug_text.show_fps {
+        let fps = format!("FPS: {:.0}", debug_text.fps);
+        debug_text.lines.insert(0, fps);
+    }
+    if debug_text.show_entity_labels {
+        for (e, transform2d) in transform2d.iter().with_id() {
+            let label = match prefab_instance.get(e) {
+                Ok(prefab_instance) => format!("{e:?} {}", prefab_instance.path()),
+                Err(_) => format!("{e:?}"),
+            };
+            debug_text
+                .labels
+                .push((transform2d.position.truncate(), label));
+        }
+    }
+
+    let mut text2d = Text2D {
+        text: std::mem::take(&mut debug_text.lines).join("\n"),
+        font: MONOSPACE_FONT.into(),
+        size: debug_text.size,
+        color: debug_text.color,
+        screen_space: true,
+        ..Default::default()
+    };
+    let margin = Mat4::from_translation(Vec3::new(8.0, 8.0, 0.0));
+    font_manager.push_text(text2d.clone(), margin, TextSpace::Screen);
+    text2d.align = TextAlign::Center;
+    for (position, label) in std::mem::take(&mut debug_text.labels) {
+        text2d.text = label;
+        font_manager.push_text(
+            text2d.clone(),
+            Mat4::IDENTITY,
+            TextSpace::Anchored(position),
+        );
+    }
+
+    font_manager.extract(&asset_server, &mut render_list);
+    font_manager.fonts.retain_used();
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct TextVertex {
+    #[format(R32G32_SFLOAT)]
+    position: [f32; 2],
+    #[format(R32G32_SFLOAT)]
+    uv: [f32; 2],
+    #[format(R32G32B32A32_SFLOAT)]
+    color: [f32; 4],
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct TextPushConstants {
+    #[format(R32G32B32A32_SFLOAT)]
+    projection: [[f32; 4]; 4],
+}
+
+const TEXT_SHADERS: [Shader; 2] = [
+    Shader {
+        path: "shaders/text.vert",
+        spirv: include_spirv!("shaders/text.vert"),
+        validate: |vs| {
+            vs.validate_vertex_input(&[TextVertex::per_vertex()])?;
+            vs.validate_push_constants::<TextPushConstants>()
+        },
+    },
+    Shader {
+        path: "shaders/text.frag",
+        spirv: include_spirv!("shaders/text.frag"),
+        validate: |fs| {
+            fs.validate_descriptor(0, 0, DescriptorType::Sampler)?;
+            fs.validate_descriptor(0, 1, DescriptorType::SampledImage)
+        },
+    },
+];
+
+// maps pixels from the top left of an area of this size to clip space
+fn pixel_projection(size: Vec2) -> Mat4 {
+    Mat4::orthographic_rh(0.0, size.x, 0.0, size.y, -1.0, 1.0)
+}
+
+fn text_vertices(glyphs: &[&Glyph2D], offset: Vec2) -> Vec<TextVertex> {
+    let mut vertices = Vec::with_capacity(glyphs.len() * 6);
+    for glyph in glyphs {
+        let uvs = [
+            glyph.uv_min,
+            Vec2::new(glyph.uv_min.x, glyph.uv_max.y),
+            glyph.uv_max,
+            Vec2::new(glyph.uv_max.x, glyph.uv_min.y),
+        ];
+        for i in [0, 1, 2, 2, 3, 0] {
+            vertices.push(TextVertex {
+                position: (glyph.positions[i] + offset).to_array(),
+                uv: uvs[i].to_array(),
+                color: glyph.color.to_array(),
+            });
+        }
+    }
+    vertices
+}
+
+// the glyph atlas on the gpu, uploaded again when its version changes
+struct AtlasTexture {
+    version: u64,
+    image_view: Arc<ImageView>,
+}
+
+// draws the glyphs of the render list, world space text with every camera
+#[derive(Default)]
+struct Text2DPass {
+    atlas: Option<AtlasTexture>,
+    descriptor_set_allocator: Option<Arc<StandardDescriptorSetAllocator>>,
+}
+
+impl Text2DPass {
+    fn upload_atlas(
+        &mut self,
+        atlas: &GlyphAtlasImage,
+        info: &RenderInfo,
+        command_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) {
+        if self
+            .atlas
+            .as_ref()
+            .is_some_and(|t| t.version == atlas.version)
+        {
+            return;
+        }
+        let staging_buffer = Buffer::from_iter(
+            info.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::HOST_SEQUENTIAL_WRITE
+                    | MemoryTypeFilter::PREFER_HOST,
+                ..Default::default()
+            },
+            atlas.pixels.iter().copied(),
+        )
+        .unwrap();
+        let image = Image::new(
+            info.memory_allocator.clone(),
+            ImageCreateInfo {
+                usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+                format: Format::R8_UNORM,
+                extent: [atlas.size, atlas.size, 1],
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        command_buffer_builder
+            .copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(
+                staging_buffer,
+                image.clone(),
+            ))
+            .unwrap();
+        self.atlas = Some(AtlasTexture {
+            version: atlas.version,
+            image_view: ImageView::new_default(image).unwrap(),
+        });
+    }
+}
+
+impl RenderPass for Text2DPass {
+    // the upload has to be recorded before the render pass begins
+    fn prepare(
+        &mut self,
+        render_list: &RenderList,
+        info: &RenderInfo,
+        command_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) {
+        if let Some(atlas) = render_list.glyph_atlas.as_ref() {
+            if !render_list.glyphs.is_empty() {
+                self.upload_atlas(atlas, info, command_buffer_builder);
+            }
+        }
+    }
+
+    fn draw(
+        &mut self,
+        render_list: &RenderList,
+        info: &RenderInfo,
+        command_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) {
+        let Some(image_view) = self.atlas.as_ref().map(|atlas| atlas.image_view.clone()) else {
+            return;
+        };
+        if render_list.glyphs.is_empty() {
+            return;
+        }
+
+        let subpass = info.subpass.clone();
+        let pipeline =
+            info.shaders
+                .graphics_pipeline(&info.device, &TEXT_SHADERS, |vs, fs, layout| {
+                    let vertex_input_state = TextVertex::per_vertex().definition(&vs)?;
+                    let stages = [
+                        PipelineShaderStageCreateInfo::new(vs),
+                        PipelineShaderStageCreateInfo::new(fs),
+                    ];
+                    Ok(GraphicsPipeline::new(
+                        info.device.clone(),
+                        None,
+                        GraphicsPipelineCreateInfo {
+                            stages: stages.into_iter().collect(),
+                            vertex_input_state: Some(vertex_input_state),
+                            input_assembly_state: Some(InputAssemblyState::default()),
+                            rasterization_state: Some(RasterizationState::default()),
+                            multisample_state: Some(MultisampleState {
+                                rasterization_samples: subpass.num_samples().unwrap(),
+                                ..Default::default()
+                            }),
+                            color_blend_state: Some(ColorBlendState::with_attachment_states(
+                                subpass.num_color_attachments(),
+                                ColorBlendAttachmentState {
+                                    blend: Some(AttachmentBlend::alpha()),
+                                    ..Default::default()
+                                },
+                            )),
+                            viewport_state: Some(ViewportState::default()),
+                            dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                            subpass: Some(subpass.clone().into()),
+                            ..GraphicsPipelineCreateInfo::layout(layout)
+                        },
+                    )?)
+                });
+
+        let sampler = Sampler::new(
+            info.device.clone(),
+            SamplerCreateInfo::simple_repeat_linear_no_mipmap(),
+        )
+        .unwrap();
+        let descriptor_set_allocator = self
+            .descriptor_set_allocator
+            .get_or_insert_with(|| {
+                Arc::new(StandardDescriptorSetAllocator::new(
+                    info.device.clone(),
+                    Default::default(),
+                ))
+            })
+            .clone();
+        let descriptor_set = DescriptorSet::new(
+            descriptor_set_allocator,
+            pipeline.layout().set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::sampler(0, sampler),
+                WriteDescriptorSet::image_view(1, image_view),
+            ],
+            [],
+        )
+        .unwrap();
+
+        command_buffer_builder
+            .bind_pipeline_graphics(pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                pipeline.layout().clone(),
+                0,
+                descriptor_set,
+            )
+            .unwrap();
+
+        let mut draw = |vertices: Vec<TextVertex>, offset: Vec2, extent: Vec2, projection: Mat4| {
+            if vertices.is_empty() {
+                return;
+            }
+            let vertex_count = vertices.len() as u32;
+            let vertex_buffer = Buffer::from_iter(
+                info.memory_allocator.clone(),
+                BufferCreateInfo {
+                    usage: BufferUsage::VERTEX_BUFFER,
+                    ..Default::default()
+                },
+                AllocationCreateInfo {
+                    memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                        | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                    ..Default::default()
+                },
+                vertices,
+            )
+            .unwrap();
+            command_buffer_builder
+                .set_viewport(
+                    0,
+                    [Viewport {
+                        offset: offset.into(),
+                        extent: extent.into(),
+                        depth_range: 0.0..=1.0,
+                    }]
+                    .into_iter()
+                    .collect(),
+                )
+                .unwrap()
+                .push_constants(
+                    pipeline.layout().clone(),
+                    0,
+                    TextPushConstants {
+                        projection: projection.to_cols_array_2d(),
+                    },
+                )
+                .unwrap()
+                .bind_vertex_buffers(0, vertex_buffer)
+                .unwrap();
+            unsafe { command_buffer_builder.draw(vertex_count, 1, 0, 0) }.unwrap();
+        };
+
+        let world = render_list
+            .glyphs
+            .iter()
+            .filter(|glyph| matches!(glyph.space, TextSpace::World))
+            .collect::<Vec<_>>();
+        for camera in &render_list.cameras {
+            let (offset, extent) = camera.viewport_rect(info.window_size);
+            if extent.x < 1.0 || extent.y < 1.0 {
+                continue;
+            }
+            draw(
+                text_vertices(&world, Vec2::ZERO),
+                offset,
+                extent,
+                camera.projection_view(extent),
+            );
+            // anchored glyphs in logical pixels of the viewport
+            let mut anchored = Vec::new();
+            for glyph in &render_list.glyphs {
+                if let TextSpace::Anchored(position) = glyph.space {
+                    if let Some(anchor) = camera.world_to_screen(position, info.window_size) {
+                        let anchor = (anchor - offset) / info.scale_factor;
+                        anchored.extend(text_vertices(&[glyph], anchor));
+                    }
+                }
+            }
+            draw(
+                anchored,
+                offset,
+                extent,
+                pixel_projection(extent / info.scale_factor),
+            );
+        }
+        let screen = render_list
+            .glyphs
+            .iter()
+            .filter(|glyph| matches!(glyph.space, TextSpace::Screen))
+            .collect::<Vec<_>>();
+        if info.last_camera && info.window_size.x >= 1.0 && info.window_size.y >= 1.0 {
+            draw(
+                text_vertices(&screen, Vec2::ZERO),
+                Vec2::ZERO,
+                info.window_size,
+                pixel_projection(info.window_size / info.scale_factor),
+            );
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use serde_json::Value as JsonValue;
+
+// flip and rotation flags in the high bits of a Tiled gid
+const GID_FLAGS: u32 = 0xE000_0000;
+
+// The parts of a Tiled map which a Tilemap uses
+#[derive(Debug)]
+pub struct TiledMap {
+    pub width: i32,
+    pub height: i32,
+    pub tiles: Vec<i32>, // 0 is empty, n is tile n - 1 of the tileset
+    pub tileset: String, // path of the tileset image
+    pub tile_pixels: i32,
+}
+
+// The tileset of a map, the image path is already resolved
+struct TiledTileset {
+    first_gid: u32,
+    tile_pixels: i32,
+    image: String,
+}
+
+// Reads the first tile layer and the first tileset of a Tiled .tmx or .tmj map. Paths in the
+// map are relative to path, read loads external .tsx or .tsj tilesets. Tiles of other tilesets
+// are left empty, margin and spacing of the tileset image are not supported.
+pub fn parse_tiled(
+    path: &str,
+    data: &[u8],
+    read: impl Fn(&str) -> Option<Vec<u8>>,
+) -> Result<TiledMap, String> {
+    let text = std::str::from_utf8(data).map_err(|e| e.to_string())?;
+    if path.ends_with(".tmx") {
+        parse_tmx(path, text, read)
+    } else {
+        parse_tmj(path, text, read)
+    }
+}
+
+// path relative to the directory of the file at base
+fn relative_to(base: &str, path: &str) -> String {
+    match base.rfind('/') {
+        Some(i) => format!("{}/{path}", &base[..i]),
+        None => path.to_string(),
+    }
+}
+
+fn gid_to_tile(gid: u32, tileset: &TiledTileset) -> i32 {
+    let gid = gid & !GID_FLAGS;
+    if gid >= tileset.first_gid {
+        (gid - tileset.first_gid + 1) as i32
+    } else {
+        0
+    }
+}
+
+fn attribute<T: std::str::FromStr>(node: roxmltree::Node, name: &str) -> Result<T, String> {
+    node.attribute(name)
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("<{}> has no valid {name}", node.tag_name().name()))
+}
+
+fn parse_tmx(
+    path: &str,
+    text: &str,
+    read: impl Fn(&str) -> Option<Vec<u8>>,
+) -> Result<TiledMap, String> {
+    let document = roxmltree::Document::parse(text).map_err(|e| e.to_string())?;
+    let map = document.root_element();
+    let tileset = map
+        .children()
+        .find(|node| node.has_tag_name("tileset"))
+        .ok_or("the map has no tileset")?;
+    let first_gid = attribute(tileset, "firstgid")?;
+    let tileset = match tileset.attribute("source") {
+        Some(source) => external_tileset(&relative_to(path, source), first_gid, &read)?,
+        None => tsx_tileset(path, tileset, first_gid)?,
+    };
+
+    let layer = map
+        .children()
+        .find(|node| node.has_tag_name("layer"))
+        .ok_or("the map has no tile layer")?;
+    let data = layer
+        .children()
+        .find(|node| node.has_tag_name("data"))
+        .ok_or("the tile layer has no data")?;
+    let gids = match data.attribute("encoding") {
+        Some("csv") => data
+            .text()
+            .unwrap_or_default()
+            .split(',')
+            .map(|gid| gid.trim().parse::<u32>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => data
+            .children()
+            .filter(|node| node.has_tag_name("tile"))
+            .map(|tile| {
+                tile.attribute("gid")
+                    .map_or(Ok(0), |gid| gid.parse::<u32>())
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?,
+        Some(encoding) => return Err(format!("{encoding} encoded layers are not supported")),
+    };
+
+    tiled_map(
+        attribute(layer, "width")?,
+        attribute(layer, "height")?,
+        &gids,
+        tileset,
+    )
+}
+
+// a .tsx or .tsj file at path
+fn external_tileset(
+    path: &str,
+    first_gid: u32,
+    read: &impl Fn(&str) -> Option<Vec<u8>>,
+) -> Result<TiledTileset, String> {
+    let data = read(path).ok_or(format!("failed to read {path}"))?;
+    let text = std::str::from_utf8(&data).map_err(|e| e.to_string())?;
+    if path.ends_with(".tsx") {
+        let document = roxmltree::Document::parse(text).map_err(|e| e.to_string())?;
+        tsx_tileset(path, document.root_element(), first_gid)
+    } else {
+        tsj_tileset(path, &parse_json(text)?, first_gid)
+    }
+}
+
+// a <tileset> element of a .tmx map or the root of a .tsx file at path
+fn tsx_tileset(
+    path: &str,
+    tileset: roxmltree::Node,
+    first_gid: u32,
+) -> Result<TiledTileset, String> {
+    let image = tileset
+        .children()
+        .find(|node| node.has_tag_name("image"))
+        .and_then(|image| image.attribute("source"))
+        .ok_or("the tileset has no image")?;
+    Ok(TiledTileset {
+        first_gid,
+        tile_pixels: attribute(tileset, "tilewidth")?,
+        image: relative_to(path, image),
+    })
+}
+
+fn parse_json(text: &str) -> Result<JsonValue, String> {
+    serde_json::from_str(text).map_err(|e| e.to_string())
+}
+
+fn json_i64(value: &JsonValue, name: &str) -> Result<i64, String> {
+    value[name]
+        .as_i64()
+        .ok_or_else(|| format!("{name} is missing or not a number"))
+}
+
+fn parse_tmj(
+    path: &str,
+    text: &str,
+    read: impl Fn(&str) -> Option<Vec<u8>>,
+) -> Result<TiledMap, String> {
+    let map = parse_json(text)?;
+    let tileset = map["tilesets"].get(0).ok_or("the map has no tileset")?;
+    let first_gid = json_i64(tileset, "firstgid")? as u32;
+    let tileset = match tileset["source"].as_str() {
+        Some(source) => external_tileset(&relative_to(path, source), first_gid, &read)?,
+        None => tsj_tileset(path, tileset, first_gid)?,
+    };
+
+    let layer = map["layers"]
+        .as_array()
+        .and_then(|layers| layers.iter().find(|layer| layer["type"] == "tilelayer"))
+        .ok_or("the map has no tile layer")?;
+    let gids = match &layer["data"] {
+        JsonValue::Array(data) => data
+            .iter()
+            .map(|gid| {
+                gid.as_u64()
+                    .map(|gid| gid as u32)
+                    .ok_or("a gid is not a number")
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => return Err("only tile layers with array data are supported".into()),
+    };
+
+    tiled_map(
+        json_i64(layer, "width")? as i32,
+        json_i64(layer, "height")? as i32,
+        &gids,
+        tileset,
+    )
+}
+
+// a tileset of a .tmj map or the root of a .tsj file at path
+fn tsj_tileset(path: &str, tileset: &JsonValue, first_gid: u32) -> Result<TiledTileset, String> {
+    let image = tileset["image"]
+        .as_str()
+        .ok_or("the tileset has no image")?;
+    Ok(TiledTileset {
+        first_gid,
+        tile_pixels: json_i64(tileset, "tilewidth")? as i32,
+        image: relative_to(path, image),
+    })
+}
+
+fn tiled_map(
+    width: i32,
+    height: i32,
+    gids: &[u32],
+    tileset: TiledTileset,
+) -> Result<TiledMap, String> {
+    if gids.len() != (width * height) as usize {
+        return Err(format!(
+            "the tile layer has {} tiles instead of {width}x{height}",
+            gids.len()
+        ));
+    }
+    Ok(TiledMap {
+        width,
+        height,
+        tiles: gids.iter().map(|gid| gid_to_tile(*gid, &tileset)).collect(),
+        tileset: tileset.image,
+        tile_pixels: tileset.tile_pixels,
+    })
+}
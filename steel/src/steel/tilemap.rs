@@ -0,0 +1,772 @@
+use super::{
+    asset::{Asset, AssetCache, AssetReader, Handle, LoadState},
+    camera2d_extract_system, physics2d_update_system,
+    tiled::{parse_tiled, TiledMap},
+    AssetServer, ComponentData, Edit, EngineBuilder, Physics2DManager, Plugin, RenderInfo,
+    RenderList, RenderPass, Shader, Stage, TextureImage, TileChunk2D, TileQuad, Transform2D, Value,
+    Variant,
+};
+use glam::Vec2;
+use rapier2d::prelude::*;
+use shader_library::include_spirv;
+use shipyard::{
+    Component, EntityId, IntoIter, IntoWithId, SystemModificator, Unique, UniqueView,
+    UniqueViewMut, View, ViewMut,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorType, DescriptorSet,
+        WriteDescriptorSet,
+    },
+    image::sampler::{Filter, Sampler, SamplerCreateInfo},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+    pipeline::{
+        graphics::{
+            color_blend::{AttachmentBlend, ColorBlendAttachmentState, ColorBlendState},
+            input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            rasterization::RasterizationState,
+            vertex_input::{Vertex, VertexDefinition},
+            viewport::{Viewport, ViewportState},
+            GraphicsPipelineCreateInfo,
+        },
+        DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineShaderStageCreateInfo,
+    },
+};
+
+// width and height of a chunk in tiles, chunks are meshed and culled as a whole
+const CHUNK_SIZE: i32 = 16;
+
+// Tilemap components, their colliders and the pass which draws them behind the other passes
+// added after it
+pub struct TilemapPlugin;
+
+impl Plugin for TilemapPlugin {
+    fn build(&self, builder: &mut EngineBuilder) {
+        builder
+            .add_unique(TilemapManager::new())
+            .add_component(Tilemap::default)
+            .add_system(Stage::PreUpdate, tilemap_load_system)
+            .add_system(
+                Stage::Physics,
+                tilemap_collision_system
+                    .before_all(physics2d_update_system)
+                    .skip_if_missing_unique::<Physics2DManager>(),
+            )
+            .add_system(
+                Stage::RenderExtract,
+                tilemap_extract_system.after_all(camera2d_extract_system),
+            )
+            .add_render_pass(TilemapPass::default());
+    }
+}
+
+// A grid of tiles from a tileset image. The Transform2D position is the top left corner of
+// tile (0, 0), rows go down from there. Rotation and scale of the Transform2D are ignored.
+// Solid tiles get merged into as few fixed colliders as possible when Physics2DPlugin is added.
+#[derive(Component, Debug)]
+#[track(All)]
+pub struct Tilemap {
+    pub width: i32,
+    pub height: i32,
+    pub tiles: Vec<i32>, // row by row from the top left, 0 is empty, n is tile n - 1 of the tileset
+    pub tileset: String, // path of the tileset image, its tiles are numbered row by row
+    pub tile_pixels: i32, // width and height of a tile in the tileset image
+    pub tile_size: Vec2, // width and height of a tile in the world
+    pub solid: Vec<i32>, // tiles which collide, every tile does if it is empty
+    pub source: String,  // the tiles are loaded from this Tiled .tmx or .tmj file if it is set
+    loaded_source: String,
+}
+
+impl Default for Tilemap {
+    fn default() -> Self {
+        Tilemap {
+            width: 0,
+            height: 0,
+            tiles: Vec::new(),
+            tileset: String::new(),
+            tile_pixels: 16,
+            tile_size: Vec2::ONE,
+            solid: Vec::new(),
+            source: String::new(),
+            loaded_source: String::new(),
+        }
+    }
+}
+
+impl Tilemap {
+    // a tilemap which is loaded from a Tiled .tmx or .tmj file
+    pub fn from_tiled(source: impl Into<String>) -> Self {
+        Tilemap {
+            source: source.into(),
+            ..Default::default()
+        }
+    }
+
+    // 0 outside of the map
+    pub fn tile(&self, x: i32, y: i32) -> i32 {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return 0;
+        }
+        self.tiles
+            .get((y * self.width + x) as usize)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // returns false outside of the map
+    pub fn set_tile(&mut self, x: i32, y: i32, tile: i32) -> bool {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return false;
+        }
+        match self.tiles.get_mut((y * self.width + x) as usize) {
+            Some(t) => *t = tile,
+            None => return false,
+        }
+        true
+    }
+
+    // the tile at a world position, origin is the position of the Transform2D
+    pub fn tile_coords(&self, origin: Vec2, position: Vec2) -> Option<(i32, i32)> {
+        let local = (position - origin) / self.tile_size;
+        let (x, y) = (local.x.floor() as i32, (-local.y).floor() as i32);
+        (x >= 0 && y >= 0 && x < self.width && y < self.height).then_some((x, y))
+    }
+
+    fn is_solid(&self, tile: i32) -> bool {
+        tile != 0 && (self.solid.is_empty() || self.solid.contains(&tile))
+    }
+
+    // world bounds of the tiles from (x0, y0) to (x1, y1), exclusive
+    fn rect(&self, origin: Vec2, x0: i32, y0: i32, x1: i32, y1: i32) -> (Vec2, Vec2) {
+        (
+            origin + Vec2::new(x0 as f32, -y1 as f32) * self.tile_size,
+            origin + Vec2::new(x1 as f32, -y0 as f32) * self.tile_size,
+        )
+    }
+
+    // covers the solid tiles with rectangles, each is grown right first and then down
+    fn solid_rects(&self, origin: Vec2) -> Vec<(Vec2, Vec2)> {
+        let mut covered = vec![false; (self.width * self.height).max(0) as usize];
+        let free = |covered: &[bool], x: i32, y: i32| {
+            self.is_solid(self.tile(x, y)) && !covered[(y * self.width + x) as usize]
+        };
+        let mut rects = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if !free(&covered, x, y) {
+                    continue;
+                }
+                let mut x1 = x + 1;
+                while x1 < self.width && free(&covered, x1, y) {
+                    x1 += 1;
+                }
+                let mut y1 = y + 1;
+                while y1 < self.height && (x..x1).all(|x| free(&covered, x, y1)) {
+                    y1 += 1;
+                }
+                for cy in y..y1 {
+                    for cx in x..x1 {
+                        covered[(cy * self.width + cx) as usize] = true;
+                    }
+                }
+                rects.push(self.rect(origin, x, y, x1, y1));
+            }
+        }
+        rects
+    }
+}
+
+fn to_csv(values: &[i32]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn from_csv(csv: &str) -> Vec<i32> {
+    csv.split(',')
+        .filter_map(|v| v.trim().parse().ok())
+        .collect()
+}
+
+impl Edit for Tilemap {
+    fn name() -> &'static str {
+        "Tilemap"
+    }
+
+    fn to_data(&self) -> ComponentData {
+        let mut data = ComponentData::new(Self::name());
+        data.variants.push(Variant {
+            name: "width",
+            value: Value::Int32(self.width),
+        });
+        data.variants.push(Variant {
+            name: "height",
+            value: Value::Int32(self.height),
+        });
+        data.variants.push(Variant {
+            name: "tiles",
+            value: Value::String(to_csv(&self.tiles)),
+        });
+        data.variants.push(Variant {
+            name: "tileset",
+            value: Value::String(self.tileset.clone()),
+        });
+        data.variants.push(Variant {
+            name: "tile_pixels",
+            value: Value::Int32(self.tile_pixels),
+        });
+        data.variants.push(Variant {
+            name: "tile_size",
+            value: Value::Vec2(self.tile_size),
+        });
+        data.variants.push(Variant {
+            name: "solid",
+            value: Value::String(to_csv(&self.solid)),
+        });
+        data.variants.push(Variant {
+            name: "source",
+            value: Value::String(self.source.clone()),
+        });
+        data
+    }
+
+    fn from_data(&mut self, data: ComponentData) {
+        for v in data.variants {
+            match (v.name, v.value) {
+                ("width", Value::Int32(width)) => self.width = width.max(0),
+                ("height", Value::Int32(height)) => self.height = height.max(0),
+                ("tiles", Value::String(tiles)) => self.tiles = from_csv(&tiles),
+                ("tileset", Value::String(tileset)) => self.tileset = tileset,
+                ("tile_pixels", Value::Int32(tile_pixels)) => self.tile_pixels = tile_pixels,
+                ("tile_size", Value::Vec2(tile_size)) => self.tile_size = tile_size,
+                ("solid", Value::String(solid)) => self.solid = from_csv(&solid),
+                ("source", Value::String(source)) => self.source = source,
+                _ => (),
+            }
+        }
+        // keep one tile per cell when the size changed
+        self.tiles.resize((self.width * self.height) as usize, 0);
+    }
+}
+
+// the chunk meshes of a tilemap and what they were built from
+struct TilemapMesh {
+    origin: Vec2,
+    tile_size: Vec2,
+    tileset_id: u64,
+    tile_pixels: i32,
+    chunks: HashMap<(i32, i32), (Vec<i32>, TileChunk2D)>,
+}
+
+// the colliders of a tilemap and the solid rectangles they were built from
+struct TilemapColliders {
+    rects: Vec<(Vec2, Vec2)>,
+    handles: Vec<ColliderHandle>,
+}
+
+// Tileset images by path, the Tiled files being loaded and the meshes and colliders of all
+// tilemaps
+#[derive(Unique)]
+pub struct TilemapManager {
+    tilesets: AssetCache<TextureImage>,
+    sources: HashMap<EntityId, Handle<TiledMap>>, // until the tiles are copied into the tilemap
+    meshes: HashMap<EntityId, TilemapMesh>,
+    colliders: HashMap<EntityId, TilemapColliders>,
+    next_id: u64,
+}
+
+impl TilemapManager {
+    pub fn new() -> Self {
+        TilemapManager {
+            tilesets: AssetCache::new(),
+            sources: HashMap::new(),
+            meshes: HashMap::new(),
+            colliders: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    // mesh of the chunk with its top left tile at (x, y)
+    fn chunk(
+        &mut self,
+        tilemap: &Tilemap,
+        origin: Vec2,
+        tileset: &TextureImage,
+        x: i32,
+        y: i32,
+    ) -> TileChunk2D {
+        let columns = tileset.width / tilemap.tile_pixels.max(1) as u32;
+        let rows = tileset.height / tilemap.tile_pixels.max(1) as u32;
+        let tileset_size = Vec2::new(tileset.width as f32, tileset.height as f32);
+        let tile_pixels = Vec2::splat(tilemap.tile_pixels as f32);
+        let mut quads = Vec::new();
+        for ty in y..(y + CHUNK_SIZE).min(tilemap.height) {
+            for tx in x..(x + CHUNK_SIZE).min(tilemap.width) {
+                let index = tilemap.tile(tx, ty) - 1;
+                if index < 0 || index as u32 >= columns * rows {
+                    continue;
+                }
+                let (column, row) = (index as u32 % columns, index as u32 / columns);
+                let uv_min = Vec2::new(column as f32, row as f32) * tile_pixels / tileset_size;
+                let (min, max) = tilemap.rect(origin, tx, ty, tx + 1, ty + 1);
+                quads.push(TileQuad {
+                    min,
+                    max,
+                    uv_min,
+                    uv_max: uv_min + tile_pixels / tileset_size,
+                });
+            }
+        }
+        let (min, max) = tilemap.rect(origin, x, y, x + CHUNK_SIZE, y + CHUNK_SIZE);
+        TileChunk2D {
+            id: self.next_id(),
+            tileset: tileset.clone(),
+            min,
+            max,
+            quads: quads.into(),
+        }
+    }
+}
+
+impl Asset for TiledMap {
+    fn load(path: &str, data: Vec<u8>, reader: &AssetReader) -> Result<Self, String> {
+        parse_tiled(path, &data, |path| {
+            reader
+                .read(path)
+                .map_err(|e| log::error!("Failed to read {path}: {e}"))
+                .ok()
+        })
+    }
+}
+
+// starts loading the Tiled source of tilemaps whose source changed, and copies the tiles into
+// them once it is loaded
+fn tilemap_load_system(
+    asset_server: UniqueView<AssetServer>,
+    mut tilemap: ViewMut<Tilemap>,
+    mut tilemap_manager: UniqueViewMut<TilemapManager>,
+) {
+    tilemap_manager.sources.retain(|e, _| tilemap.contains(*e));
+    for (e, mut tilemap) in (&mut tilemap).iter().with_id() {
+        if tilemap.source != tilemap.loaded_source {
+            // remember failed sources too, so they are not read again every update
+            tilemap.loaded_source = tilemap.source.clone();
+            if tilemap.source.is_empty() {
+                tilemap_manager.sources.remove(&e);
+            } else {
+                let source = asset_server.load(&tilemap.source);
+                tilemap_manager.sources.insert(e, source);
+            }
+        }
+        let Some(source) = tilemap_manager.sources.get(&e) else {
+            continue;
+        };
+        match source.state() {
+            LoadState::Loading => continue,
+            LoadState::Loaded => {
+                let map = source.get().unwrap();
+                tilemap.width = map.width;
+                tilemap.height = map.height;
+                tilemap.tiles.clone_from(&map.tiles);
+                tilemap.tileset.clone_from(&map.tileset);
+                tilemap.tile_pixels = map.tile_pixels;
+            }
+            // the AssetServer logs why it failed
+            LoadState::Failed(_) => (),
+        }
+        tilemap_manager.sources.remove(&e);
+    }
+}
+
+// keeps one fixed collider per solid rectangle of every tilemap, the user data of the colliders
+// is the tilemap entity so collisions with tiles are reported for it, the rectangles are only
+// merged again when the tilemap or its transform changed
+fn tilemap_collision_system(
+    tilemap: View<Tilemap>,
+    transform2d: View<Transform2D>,
+    mut physics2d_manager: UniqueViewMut<Physics2DManager>,
+    mut tilemap_manager: UniqueViewMut<TilemapManager>,
+) {
+    let physics2d_manager = physics2d_manager.as_mut();
+    let mut old_colliders = std::mem::take(&mut tilemap_manager.colliders);
+    let mut changed = false;
+    let dirty = |e| tilemap.is_inserted_or_modified(e) || transform2d.is_inserted_or_modified(e);
+    for (e, (tilemap, transform2d)) in (&tilemap, &transform2d).iter().with_id() {
+        let old = match old_colliders.remove(&e) {
+            Some(colliders) if !dirty(e) => {
+                tilemap_manager.colliders.insert(e, colliders);
+                continue;
+            }
+            old => old,
+        };
+        let rects = tilemap.solid_rects(transform2d.position.truncate());
+        if let Some(colliders) = old {
+            if colliders.rects == rects {
+                tilemap_manager.colliders.insert(e, colliders);
+                continue;
+            }
+            remove_colliders(physics2d_manager, colliders.handles);
+        }
+        let handles = rects
+            .iter()
+            .map(|(min, max)| {
+                let half_extents = (*max - *min) / 2.0;
+                let center = *min + half_extents;
+                let collider = ColliderBuilder::cuboid(half_extents.x, half_extents.y)
+                    .translation(vector![center.x, center.y])
+                    .user_data(e.inner() as u128)
+                    .build();
+                physics2d_manager.collider_set.insert(collider)
+            })
+            .collect();
+        tilemap_manager
+            .colliders
+            .insert(e, TilemapColliders { rects, handles });
+        changed = true;
+    }
+    // the tilemaps which were removed
+    for (_, colliders) in old_colliders {
+        remove_colliders(physics2d_manager, colliders.handles);
+        changed = true;
+    }
+    if changed {
+        physics2d_manager.query_pipeline.update(
+            &physics2d_manager.rigid_body_set,
+            &physics2d_manager.collider_set,
+        );
+    }
+}
+
+fn remove_colliders(physics2d_manager: &mut Physics2DManager, handles: Vec<ColliderHandle>) {
+    for handle in handles {
+        physics2d_manager.collider_set.remove(
+            handle,
+            &mut physics2d_manager.island_manager,
+            &mut physics2d_manager.rigid_body_set,
+            true,
+        );
+    }
+}
+
+// the tiles of the chunk with its top left tile at (x, y)
+fn chunk_tiles(tilemap: &Tilemap, x: i32, y: i32) -> Vec<i32> {
+    (y..y + CHUNK_SIZE)
+        .flat_map(|ty| (x..x + CHUNK_SIZE).map(move |tx| (tx, ty)))
+        .map(|(tx, ty)| tilemap.tile(tx, ty))
+        .collect()
+}
+
+// pushes the chunks of every tilemap, the tiles of the chunks are only compared again when the
+// tilemap or its transform changed and only chunks whose tiles changed are meshed again
+fn tilemap_extract_system(
+    asset_server: UniqueView<AssetServer>,
+    tilemap: View<Tilemap>,
+    transform2d: View<Transform2D>,
+    mut tilemap_manager: UniqueViewMut<TilemapManager>,
+    mut render_list: UniqueViewMut<RenderList>,
+) {
+    let tilemap_manager = tilemap_manager.as_mut();
+    let mut old_meshes = std::mem::take(&mut tilemap_manager.meshes);
+    let dirty = |e| tilemap.is_inserted_or_modified(e) || transform2d.is_inserted_or_modified(e);
+    for (e, (tilemap, transform2d)) in (&tilemap, &transform2d).iter().with_id() {
+        let Some(tileset) = tilemap_manager
+            .tilesets
+            .get(&asset_server, &tilemap.tileset)
+            .map(|tileset| (*tileset).clone())
+        else {
+            continue;
+        };
+        let origin = transform2d.position.truncate();
+        let old_mesh = old_meshes.remove(&e).filter(|mesh| {
+            mesh.origin == origin
+                && mesh.tile_size == tilemap.tile_size
+                && mesh.tileset_id == tileset.id
+                && mesh.tile_pixels == tilemap.tile_pixels
+        });
+        let mut old_chunks = match old_mesh {
+            Some(mesh) if !dirty(e) => {
+                for (_, chunk) in mesh.chunks.values() {
+                    if !chunk.quads.is_empty() {
+                        render_list.tile_chunks.push(chunk.clone());
+                    }
+                }
+                tilemap_manager.meshes.insert(e, mesh);
+                continue;
+            }
+            old_mesh => old_mesh.map(|mesh| mesh.chunks).unwrap_or_default(),
+        };
+        let mut chunks = HashMap::new();
+        for y in (0..tilemap.height).step_by(CHUNK_SIZE as usize) {
+            for x in (0..tilemap.width).step_by(CHUNK_SIZE as usize) {
+                let tiles = chunk_tiles(tilemap, x, y);
+                let chunk = match old_chunks.remove(&(x, y)) {
+                    Some((old_tiles, chunk)) if old_tiles == tiles => chunk,
+                    _ => tilemap_manager.chunk(tilemap, origin, &tileset, x, y),
+                };
+                if !chunk.quads.is_empty() {
+                    render_list.tile_chunks.push(chunk.clone());
+                }
+                chunks.insert((x, y), (tiles, chunk));
+            }
+        }
+        tilemap_manager.meshes.insert(
+            e,
+            TilemapMesh {
+                origin,
+                tile_size: tilemap.tile_size,
+                tileset_id: tileset.id,
+                tile_pixels: tilemap.tile_pixels,
+                chunks,
+            },
+        );
+    }
+    tilemap_manager.tilesets.retain_used();
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct TileVertex {
+    #[format(R32G32_SFLOAT)]
+    position: [f32; 2],
+    #[format(R32G32_SFLOAT)]
+    uv: [f32; 2],
+}
+
+#[derive(BufferContents, Vertex)]
+#[repr(C)]
+struct TilePushConstants {
+    #[format(R32G32B32A32_SFLOAT)]
+    projection_view: [[f32; 4]; 4],
+}
+
+const TILE_SHADERS: [Shader; 2] = [
+    Shader {
+        path: "shaders/tile.vert",
+        spirv: include_spirv!("shaders/tile.vert"),
+        validate: |vs| {
+            vs.validate_vertex_input(&[TileVertex::per_vertex()])?;
+            vs.validate_push_constants::<TilePushConstants>()
+        },
+    },
+    Shader {
+        path: "shaders/tile.frag",
+        spirv: include_spirv!("shaders/tile.frag"),
+        validate: |fs| {
+            fs.validate_descriptor(0, 0, DescriptorType::Sampler)?;
+            fs.validate_descriptor(0, 1, DescriptorType::SampledImage)
+        },
+    },
+];
+
+fn tile_vertices(quads: &[TileQuad]) -> Vec<TileVertex> {
+    let mut vertices = Vec::with_capacity(quads.len() * 6);
+    for quad in quads {
+        // top left, bottom left, bottom right, top right
+        let corners = [
+            (Vec2::new(quad.min.x, quad.max.y), quad.uv_min),
+            (quad.min, Vec2::new(quad.uv_min.x, quad.uv_max.y)),
+            (Vec2::new(quad.max.x, quad.min.y), quad.uv_max),
+            (quad.max, Vec2::new(quad.uv_max.x, quad.uv_min.y)),
+        ];
+        for i in [0, 1, 2, 2, 3, 0] {
+            vertices.push(TileVertex {
+                position: corners[i].0.to_array(),
+                uv: corners[i].1.to_array(),
+            });
+        }
+    }
+    vertices
+}
+
+// draws the tile chunks of the render list with every camera, chunks outside of the view of a
+// camera are skipped. Chunk meshes stay on the gpu while they are used.
+#[derive(Default)]
+struct TilemapPass {
+    vertex_buffers: HashMap<u64, Subbuffer<[TileVertex]>>,
+    descriptor_set_allocator: Option<Arc<StandardDescriptorSetAllocator>>,
+}
+
+impl TilemapPass {
+    fn vertex_buffer(&mut self, chunk: &TileChunk2D, info: &RenderInfo) -> Subbuffer<[TileVertex]> {
+        self.vertex_buffers
+            .entry(chunk.id)
+            .or_insert_with(|| {
+                Buffer::from_iter(
+                    info.memory_allocator.clone(),
+                    BufferCreateInfo {
+                        usage: BufferUsage::VERTEX_BUFFER,
+                        ..Default::default()
+                    },
+                    AllocationCreateInfo {
+                        memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                            | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                        ..Default::default()
+                    },
+                    tile_vertices(&chunk.quads),
+                )
+                .unwrap()
+            })
+            .clone()
+    }
+}
+
+impl RenderPass for TilemapPass {
+    fn draw(
+        &mut self,
+        render_list: &RenderList,
+        info: &RenderInfo,
+        command_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) {
+        // drop the meshes the chunks of this frame do not use anymore
+        let tileset_ids = render_list
+            .tile_chunks
+            .iter()
+            .map(|chunk| chunk.tileset.id)
+            .collect::<HashSet<_>>();
+        let chunk_ids = render_list
+            .tile_chunks
+            .iter()
+            .map(|chunk| chunk.id)
+            .collect::<HashSet<_>>();
+        self.vertex_buffers.retain(|id, _| chunk_ids.contains(id));
+        if render_list.tile_chunks.is_empty() {
+            return;
+        }
+
+        let subpass = info.subpass.clone();
+        let pipeline =
+            info.shaders
+                .graphics_pipeline(&info.device, &TILE_SHADERS, |vs, fs, layout| {
+                    let vertex_input_state = TileVertex::per_vertex().definition(&vs)?;
+                    let stages = [
+                        PipelineShaderStageCreateInfo::new(vs),
+                        PipelineShaderStageCreateInfo::new(fs),
+                    ];
+                    Ok(GraphicsPipeline::new(
+                        info.device.clone(),
+                        None,
+                        GraphicsPipelineCreateInfo {
+                            stages: stages.into_iter().collect(),
+                            vertex_input_state: Some(vertex_input_state),
+                            input_assembly_state: Some(InputAssemblyState::default()),
+                            rasterization_state: Some(RasterizationState::default()),
+                            multisample_state: Some(MultisampleState {
+                                rasterization_samples: subpass.num_samples().unwrap(),
+                                ..Default::default()
+                            }),
+                            color_blend_state: Some(ColorBlendState::with_attachment_states(
+                                subpass.num_color_attachments(),
+                                ColorBlendAttachmentState {
+                                    blend: Some(AttachmentBlend::alpha()),
+                                    ..Default::default()
+                                },
+                            )),
+                            viewport_state: Some(ViewportState::default()),
+                            dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                            subpass: Some(subpass.clone().into()),
+                            ..GraphicsPipelineCreateInfo::layout(layout)
+                        },
+                    )?)
+                });
+
+        // nearest filtering keeps the pixels of the tiles sharp
+        let sampler = Sampler::new(
+            info.device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Nearest,
+                min_filter: Filter::Nearest,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let descriptor_set_allocator = self
+            .descriptor_set_allocator
+            .get_or_insert_with(|| {
+                Arc::new(StandardDescriptorSetAllocator::new(
+                    info.device.clone(),
+                    Default::default(),
+                ))
+            })
+            .clone();
+        let descriptor_sets = tileset_ids
+            .into_iter()
+            .map(|id| {
+                let descriptor_set = DescriptorSet::new(
+                    descriptor_set_allocator.clone(),
+                    pipeline.layout().set_layouts()[0].clone(),
+                    [
+                        WriteDescriptorSet::sampler(0, sampler.clone()),
+                        WriteDescriptorSet::image_view(1, info.textures[&id].clone()),
+                    ],
+                    [],
+                )
+                .unwrap();
+                (id, descriptor_set)
+            })
+            .collect::<HashMap<_, _>>();
+
+        command_buffer_builder
+            .bind_pipeline_graphics(pipeline.clone())
+            .unwrap();
+
+        for camera in &render_list.cameras {
+            let (offset, extent) = camera.viewport_rect(info.window_size);
+            if extent.x < 1.0 || extent.y < 1.0 {
+                continue;
+            }
+            let (view_min, view_max) = camera.view_aabb(extent);
+            command_buffer_builder
+                .set_viewport(
+                    0,
+                    [Viewport {
+                        offset: offset.into(),
+                        extent: extent.into(),
+                        depth_range: 0.0..=1.0,
+                    }]
+                    .into_iter()
+                    .collect(),
+                )
+                .unwrap()
+                .push_constants(
+                    pipeline.layout().clone(),
+                    0,
+                    TilePushConstants {
+                        projection_view: camera.projection_view(extent).to_cols_array_2d(),
+                    },
+                )
+                .unwrap();
+            for chunk in &render_list.tile_chunks {
+                if chunk.max.cmplt(view_min).any() || chunk.min.cmpgt(view_max).any() {
+                    continue;
+                }
+                let vertex_buffer = self.vertex_buffer(chunk, info);
+                let vertex_count = vertex_buffer.len() as u32;
+                command_buffer_builder
+                    .bind_descriptor_sets(
+                        PipelineBindPoint::Graphics,
+                        pipeline.layout().clone(),
+                        0,
+                        descriptor_sets[&chunk.tileset.id].clone(),
+                    )
+                    .unwrap()
+                    .bind_vertex_buffers(0, vertex_buffer)
+                    .unwrap();
+                unsafe { command_buffer_builder.draw(vertex_count, 1, 0, 0) }.unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,10 @@
+#version 460
+
+layout(location = 0) in vec2 position;
+
+layout(location = 0) out vec2 tex_coord;
+
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+    tex_coord = position + vec2(0.5);
+}
@@ -6,7 +6,10 @@ use winit::{
     application::ApplicationHandler,
     event::WindowEvent,
     event_loop::{ControlFlow, EventLoop},
+    keyboard::Key,
 };
+#[cfg(not(target_os = "android"))]
+mod hot_reload;
 mod texture_renderer;
 use texture_renderer::TextureRenderer;
 
@@ -45,13 +48,14 @@ fn _main(event_loop: EventLoop<()>) {
 struct Application {
     context: VulkanoContext,
     windows: VulkanoWindows,
+    texture_renderer: TextureRenderer,
 }
 
 impl ApplicationHandler for Application {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         log::debug!("Resumed");
         self.windows.create_window(
-            &event_loop,
+            event_loop,
             &self.context,
             &WindowDescriptor::default(),
             |_| {},
@@ -80,7 +84,8 @@ impl ApplicationHandler for Application {
                 if let Some(renderer) = self.windows.get_primary_renderer_mut() {
                     let before_future = renderer.acquire(None, |_| {}).unwrap();
                     let after_future =
-                        TextureRenderer::draw(before_future, &self.context, renderer);
+                        self.texture_renderer
+                            .draw(before_future, &self.context, renderer);
                     renderer.present(after_future, true);
                 }
             }
@@ -91,6 +96,17 @@ impl ApplicationHandler for Application {
                     renderer.window().request_redraw();
                 }
             }
+            // M switches between off, 2x, 4x and 8x MSAA
+            WindowEvent::KeyboardInput { event, .. }
+                if event.state.is_pressed()
+                    && !event.repeat
+                    && event.logical_key.as_ref() == Key::Character("m") =>
+            {
+                self.texture_renderer.next_msaa_samples();
+                if let Some(renderer) = self.windows.get_primary_renderer_mut() {
+                    renderer.window().request_redraw();
+                }
+            }
             _ => (),
         }
     }
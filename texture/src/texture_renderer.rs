@@ -1,5 +1,8 @@
-use image::{GenericImageView, ImageReader};
-use std::{io::Cursor, sync::Arc};
+#[cfg(not(target_os = "android"))]
+use crate::hot_reload::{load_shader, read_file, watch};
+use image::{DynamicImage, GenericImageView, ImageReader};
+use shader_library::{include_spirv, msaa_sample_count, FileWatcher, Shader, ShaderError};
+use std::{error::Error, io::Cursor, sync::Arc};
 use vulkano::{
     buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage},
     command_buffer::{
@@ -8,13 +11,15 @@ use vulkano::{
         SubpassContents,
     },
     descriptor_set::{
-        allocator::StandardDescriptorSetAllocator, DescriptorSet, WriteDescriptorSet,
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorType, DescriptorSet,
+        WriteDescriptorSet,
     },
+    device::Device,
     format::Format,
     image::{
         sampler::{Sampler, SamplerCreateInfo},
         view::ImageView,
-        Image, ImageCreateInfo, ImageUsage,
+        Image, ImageCreateInfo, ImageUsage, SampleCount,
     },
     memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
     pipeline::{
@@ -31,7 +36,7 @@ use vulkano::{
         DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
         PipelineShaderStageCreateInfo,
     },
-    render_pass::{Framebuffer, FramebufferCreateInfo, Subpass},
+    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
     sync::GpuFuture,
 };
 use vulkano_util::{context::VulkanoContext, renderer::VulkanoWindowRenderer};
@@ -43,48 +48,243 @@ struct MyVertex {
     position: [f32; 2],
 }
 
-mod vs {
-    vulkano_shaders::shader! {
-        ty: "vertex",
-        src: r"
-            #version 460
+// the files the texture and shaders are compiled from, they are reloaded when they change on desktop
+#[cfg(not(target_os = "android"))]
+const TEXTURE_PATH: &str = "texture.jpg";
+const VS_PATH: &str = "shaders/texture.vert";
+const FS_PATH: &str = "shaders/texture.frag";
 
-            layout(location = 0) in vec2 position;
+// the shaders take the vertices of MyVertex and the sampler and image of the descriptor set below
+fn validate_shaders(vs: &Shader, fs: &Shader) -> Result<(), ShaderError> {
+    vs.validate_vertex_input(&[MyVertex::per_vertex()])?;
+    fs.validate_descriptor(0, 0, DescriptorType::Sampler)?;
+    fs.validate_descriptor(0, 1, DescriptorType::SampledImage)
+}
 
-            layout(location = 0) out vec2 tex_coord;
+fn decode(data: &[u8]) -> Result<DynamicImage, String> {
+    ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?
+        .decode()
+        .map_err(|e| e.to_string())
+}
 
-            void main() {
-                gl_Position = vec4(position, 0.0, 1.0);
-                tex_coord = position + vec2(0.5);
-            }
-        ",
+// draws into a multisampled color attachment which is resolved into the swapchain image,
+// or into the swapchain image directly without MSAA
+fn get_render_pass(device: Arc<Device>, format: Format, samples: SampleCount) -> Arc<RenderPass> {
+    if samples == SampleCount::Sample1 {
+        return vulkano::single_pass_renderpass!(
+            device,
+            attachments: {
+                color: {
+                    format: format,
+                    samples: 1,
+                    load_op: Clear,
+                    store_op: Store,
+                },
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {},
+            },
+        )
+        .unwrap();
     }
+    vulkano::single_pass_renderpass!(
+        device,
+        attachments: {
+            msaa: {
+                format: format,
+                samples: samples,
+                load_op: Clear,
+                store_op: DontCare,
+            },
+            color: {
+                format: format,
+                samples: 1,
+                load_op: DontCare,
+                store_op: Store,
+            },
+        },
+        pass: {
+            color: [msaa],
+            color_resolve: [color],
+            depth_stencil: {},
+        },
+    )
+    .unwrap()
 }
 
-mod fs {
-    vulkano_shaders::shader! {
-        ty: "fragment",
-        src: r"
-            #version 460
+pub struct TextureRenderer {
+    image: DynamicImage,
+    shaders: Option<[Arc<Shader>; 2]>, // the last good vertex and fragment shader
+    msaa_samples: u32,                 // 1 disables MSAA, clamped down to what the device supports
+    msaa_image: Option<Arc<ImageView>>, // resolved into the swapchain image
+    #[cfg(not(target_os = "android"))]
+    watcher: FileWatcher<&'static str>,
+}
 
-            layout(location = 0) in vec2 tex_coord;
+impl Default for TextureRenderer {
+    fn default() -> Self {
+        TextureRenderer {
+            image: decode(include_bytes!("../texture.jpg")).unwrap(),
+            shaders: None,
+            msaa_samples: 4,
+            msaa_image: None,
+            #[cfg(not(target_os = "android"))]
+            watcher: watch(&[TEXTURE_PATH, VS_PATH, FS_PATH]),
+        }
+    }
+}
 
-            layout(location = 0) out vec4 f_color;
+impl TextureRenderer {
+    // switch to the next of off, 2x, 4x and 8x MSAA
+    pub fn next_msaa_samples(&mut self) {
+        self.msaa_samples = match self.msaa_samples {
+            1 => 2,
+            2 => 4,
+            4 => 8,
+            _ => 1,
+        };
+        log::info!("MSAA samples={}", self.msaa_samples);
+    }
 
-            layout(set = 0, binding = 0) uniform sampler s;
-            layout(set = 0, binding = 1) uniform texture2D tex;
+    // the multisampled image for the swapchain image, recreated when its size, format or the
+    // sample count changes
+    fn msaa_image(
+        &mut self,
+        context: &VulkanoContext,
+        swapchain_image: &Arc<Image>,
+        samples: SampleCount,
+    ) -> Arc<ImageView> {
+        if let Some(msaa_image) = self.msaa_image.as_ref().filter(|msaa_image| {
+            let image = msaa_image.image();
+            image.extent() == swapchain_image.extent()
+                && image.format() == swapchain_image.format()
+                && image.samples() == samples
+        }) {
+            return msaa_image.clone();
+        }
+        let image = Image::new(
+            context.memory_allocator().clone(),
+            ImageCreateInfo {
+                format: swapchain_image.format(),
+                extent: swapchain_image.extent(),
+                samples,
+                usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSIENT_ATTACHMENT,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )
+        .unwrap();
+        let msaa_image = ImageView::new_default(image).unwrap();
+        self.msaa_image = Some(msaa_image.clone());
+        msaa_image
+    }
 
-            void main() {
-                f_color = texture(sampler2D(tex, s), tex_coord);
+    fn get_pipeline(
+        device: Arc<Device>,
+        [vs, fs]: [Arc<Shader>; 2],
+        layout: Arc<PipelineLayout>,
+        subpass: Subpass,
+    ) -> Result<Arc<GraphicsPipeline>, Box<dyn Error>> {
+        let vs = vs.entry_point();
+        let fs = fs.entry_point();
+        let vertex_input_state = MyVertex::per_vertex().definition(&vs)?;
+        let stages = [
+            PipelineShaderStageCreateInfo::new(vs),
+            PipelineShaderStageCreateInfo::new(fs),
+        ];
+        Ok(GraphicsPipeline::new(
+            device,
+            None,
+            GraphicsPipelineCreateInfo {
+                stages: stages.into_iter().collect(),
+                vertex_input_state: Some(vertex_input_state),
+                input_assembly_state: Some(InputAssemblyState::default()),
+                rasterization_state: Some(RasterizationState::default()),
+                multisample_state: Some(MultisampleState {
+                    rasterization_samples: subpass.num_samples().unwrap(),
+                    ..Default::default()
+                }),
+                color_blend_state: Some(ColorBlendState::with_attachment_states(
+                    subpass.num_color_attachments(),
+                    ColorBlendAttachmentState::default(),
+                )),
+                viewport_state: Some(ViewportState::default()),
+                dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                subpass: Some(subpass.into()),
+                ..GraphicsPipelineCreateInfo::layout(layout)
+            },
+        )?)
+    }
+
+    // decodes texture.jpg again if it changed, a version which does not decode is logged
+    #[cfg(not(target_os = "android"))]
+    fn reload_texture(&mut self, changed: &[&str]) {
+        if !changed.contains(&TEXTURE_PATH) {
+            return;
+        }
+        match read_file(TEXTURE_PATH)
+            .map_err(|e| e.to_string())
+            .and_then(|data| decode(&data))
+        {
+            Ok(image) => {
+                log::info!("Reloaded {TEXTURE_PATH}");
+                self.image = image;
             }
-        ",
+            Err(e) => log::error!("Failed to reload {TEXTURE_PATH}: {e}"),
+        }
     }
-}
 
-pub struct TextureRenderer;
+    // recompiles the shaders whose files changed, a version which does not compile or does not
+    // fit the pipeline is logged and the last good one kept
+    #[cfg(not(target_os = "android"))]
+    fn reload_shaders(
+        &mut self,
+        changed: &[&str],
+        device: &Arc<Device>,
+        layout: &Arc<PipelineLayout>,
+        subpass: &Subpass,
+    ) {
+        if !changed.contains(&VS_PATH) && !changed.contains(&FS_PATH) {
+            return;
+        }
+        let [vs, fs] = self.shaders.clone().unwrap();
+        let load = |path, shader| {
+            if changed.contains(&path) {
+                load_shader(device, path).map(Arc::new)
+            } else {
+                Ok(shader)
+            }
+        };
+        let shaders = match (load(VS_PATH, vs), load(FS_PATH, fs)) {
+            (Ok(vs), Ok(fs)) => [vs, fs],
+            (Err(e), _) | (_, Err(e)) => {
+                log::error!("Failed to load shader: {e}");
+                return;
+            }
+        };
+        if let Err(e) = validate_shaders(&shaders[0], &shaders[1]) {
+            log::error!("Failed to use the changed shaders: {e}");
+            return;
+        }
+        match Self::get_pipeline(
+            device.clone(),
+            shaders.clone(),
+            layout.clone(),
+            subpass.clone(),
+        ) {
+            Ok(_) => {
+                log::info!("Reloaded shaders");
+                self.shaders = Some(shaders);
+            }
+            Err(e) => log::error!("Failed to create the pipeline with the changed shaders: {e}"),
+        }
+    }
 
-impl TextureRenderer {
     pub fn draw(
+        &mut self,
         before_future: Box<dyn GpuFuture>,
         context: &VulkanoContext,
         renderer: &VulkanoWindowRenderer,
@@ -94,11 +294,11 @@ impl TextureRenderer {
             Default::default(),
         ));
 
-        let dynamic_image = ImageReader::new(Cursor::new(include_bytes!("../texture.jpg")))
-            .with_guessed_format()
-            .unwrap()
-            .decode()
-            .unwrap();
+        #[cfg(not(target_os = "android"))]
+        let changed = self.watcher.changed();
+        #[cfg(not(target_os = "android"))]
+        self.reload_texture(&changed);
+        let dynamic_image = &self.image;
         let image_staging_buffer = Buffer::new_slice(
             context.memory_allocator().clone(),
             BufferCreateInfo {
@@ -163,27 +363,29 @@ impl TextureRenderer {
             .wait(None)
             .unwrap();
 
-        let render_pass = vulkano::single_pass_renderpass!(
+        let samples = msaa_sample_count(context.device(), self.msaa_samples);
+        // set the format the same as the swapchain
+        let render_pass = get_render_pass(
             context.device().clone(),
-            attachments: {
-                color: {
-                    format: renderer.swapchain_format(), // set the format the same as the swapchain
-                    samples: 1,
-                    load_op: Clear,
-                    store_op: Store,
-                },
-            },
-            pass: {
-                color: [color],
-                depth_stencil: {},
-            },
-        )
-        .unwrap();
+            renderer.swapchain_format(),
+            samples,
+        );
+
+        let mut attachments = vec![renderer.swapchain_image_view()];
+        let mut clear_values = vec![Some([0.0, 0.0, 1.0, 1.0].into())];
+        if samples == SampleCount::Sample1 {
+            self.msaa_image = None;
+        } else {
+            let msaa_image =
+                self.msaa_image(context, renderer.swapchain_image_view().image(), samples);
+            attachments.insert(0, msaa_image);
+            clear_values.push(None);
+        }
 
         let framebuffer = Framebuffer::new(
             render_pass.clone(),
             FramebufferCreateInfo {
-                attachments: vec![renderer.swapchain_image_view()],
+                attachments,
                 ..Default::default()
             },
         )
@@ -212,7 +414,7 @@ impl TextureRenderer {
                     | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
                 ..Default::default()
             },
-            vec![vertex1, vertex2, vertex3, vertex4].into_iter(),
+            vec![vertex1, vertex2, vertex3, vertex4],
         )
         .unwrap();
 
@@ -227,29 +429,28 @@ impl TextureRenderer {
                     | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
                 ..Default::default()
             },
-            vec![0u16, 1, 2, 2, 3, 0].into_iter(),
+            vec![0u16, 1, 2, 2, 3, 0],
         )
         .unwrap();
 
-        let vs = vs::load(context.device().clone())
-            .unwrap()
-            .entry_point("main")
-            .unwrap();
-        let fs = fs::load(context.device().clone())
-            .unwrap()
-            .entry_point("main")
-            .unwrap();
-
-        let vertex_input_state = MyVertex::per_vertex().definition(&vs).unwrap();
-
-        let stages = [
-            PipelineShaderStageCreateInfo::new(vs),
-            PipelineShaderStageCreateInfo::new(fs),
+        let load = |path, spirv| {
+            Arc::new(
+                Shader::from_spirv(context.device(), path, spirv).unwrap_or_else(|e| panic!("{e}")),
+            )
+        };
+        let builtin_shaders = [
+            load(VS_PATH, include_spirv!("shaders/texture.vert")),
+            load(FS_PATH, include_spirv!("shaders/texture.frag")),
         ];
-
+        validate_shaders(&builtin_shaders[0], &builtin_shaders[1])
+            .unwrap_or_else(|e| panic!("{e}"));
+        let builtin_stages = builtin_shaders
+            .clone()
+            .map(|shader| PipelineShaderStageCreateInfo::new(shader.entry_point()));
+        // the layout of the builtin shaders, changed shaders have to fit the descriptor set below
         let layout = PipelineLayout::new(
             context.device().clone(),
-            PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+            PipelineDescriptorSetLayoutCreateInfo::from_stages(&builtin_stages)
                 .into_pipeline_layout_create_info(context.device().clone())
                 .unwrap(),
         )
@@ -263,24 +464,14 @@ impl TextureRenderer {
 
         let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
 
-        let pipeline = GraphicsPipeline::new(
+        self.shaders.get_or_insert(builtin_shaders);
+        #[cfg(not(target_os = "android"))]
+        self.reload_shaders(&changed, context.device(), &layout, &subpass);
+        let pipeline = Self::get_pipeline(
             context.device().clone(),
-            None,
-            GraphicsPipelineCreateInfo {
-                stages: stages.into_iter().collect(),
-                vertex_input_state: Some(vertex_input_state),
-                input_assembly_state: Some(InputAssemblyState::default()),
-                rasterization_state: Some(RasterizationState::default()),
-                multisample_state: Some(MultisampleState::default()),
-                color_blend_state: Some(ColorBlendState::with_attachment_states(
-                    subpass.num_color_attachments(),
-                    ColorBlendAttachmentState::default(),
-                )),
-                viewport_state: Some(ViewportState::default()),
-                dynamic_state: [DynamicState::Viewport].into_iter().collect(),
-                subpass: Some(subpass.into()),
-                ..GraphicsPipelineCreateInfo::layout(layout)
-            },
+            self.shaders.clone().unwrap(),
+            layout,
+            subpass,
         )
         .unwrap();
 
@@ -310,7 +501,7 @@ impl TextureRenderer {
             builder
                 .begin_render_pass(
                     RenderPassBeginInfo {
-                        clear_values: vec![Some([0.0, 0.0, 1.0, 1.0].into())],
+                        clear_values,
                         ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
                     },
                     SubpassBeginInfo {
@@ -342,9 +533,9 @@ impl TextureRenderer {
             builder.build().unwrap()
         };
 
-        return command_buffer
+        command_buffer
             .execute_after(before_future, renderer.graphics_queue())
             .unwrap()
-            .boxed();
+            .boxed()
     }
 }
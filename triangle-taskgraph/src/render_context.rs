@@ -1,9 +1,11 @@
 use crate::vulkan_context::VulkanContext;
+use shader_library::{include_spirv, Shader, ShaderError};
 use std::slice;
 use std::sync::Arc;
 use vulkano::buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage};
 use vulkano::device::Device;
-use vulkano::image::ImageUsage;
+use vulkano::format::Format;
+use vulkano::image::{Image, ImageCreateInfo, ImageUsage, SampleCount};
 use vulkano::memory::allocator::{AllocationCreateInfo, DeviceLayout, MemoryTypeFilter};
 use vulkano::pipeline::graphics::color_blend::{ColorBlendAttachmentState, ColorBlendState};
 use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
@@ -17,10 +19,9 @@ use vulkano::pipeline::{
     DynamicState, GraphicsPipeline, PipelineLayout, PipelineShaderStageCreateInfo,
 };
 use vulkano::render_pass::Subpass;
-use vulkano::shader::ShaderModule;
 use vulkano::swapchain::{Surface, Swapchain, SwapchainCreateInfo};
 use vulkano::{Validated, VulkanError};
-use vulkano_taskgraph::command_buffer::RecordingCommandBuffer;
+use vulkano_taskgraph::command_buffer::{RecordingCommandBuffer, ResolveImageInfo};
 use vulkano_taskgraph::graph::{
     AttachmentInfo, CompileInfo, ExecutableTaskGraph, ExecuteError, TaskGraph,
 };
@@ -38,34 +39,40 @@ struct MyVertex {
     position: [f32; 2],
 }
 
-mod vs {
-    vulkano_shaders::shader! {
-        ty: "vertex",
-        src: r"
-            #version 460
-
-            layout(location = 0) in vec2 position;
-
-            void main() {
-                gl_Position = vec4(position, 0.0, 1.0);
-            }
-        ",
-    }
+// every device supports 4 samples for color attachments, so there is always a multisampled
+// image to resolve into the swapchain image
+const MSAA_SAMPLES: SampleCount = SampleCount::Sample4;
+
+// the multisampled image the triangle is drawn into, of the size of the swapchain images
+fn create_msaa_image(resources: &Resources, format: Format, extent: [u32; 2]) -> Id<Image> {
+    resources
+        .create_image(
+            ImageCreateInfo {
+                format,
+                extent: [extent[0], extent[1], 1],
+                samples: MSAA_SAMPLES,
+                usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )
+        .unwrap()
 }
 
-mod fs {
-    vulkano_shaders::shader! {
-        ty: "fragment",
-        src: r"
-            #version 460
-
-            layout(location = 0) out vec4 f_color;
-
-            void main() {
-                f_color = vec4(1.0, 0.0, 0.0, 1.0);
-            }
-        ",
-    }
+// the shaders take the vertices of MyVertex and nothing else
+fn load_shaders(device: &Arc<Device>) -> Result<[Shader; 2], ShaderError> {
+    let vs = Shader::from_spirv(
+        device,
+        "triangle.vert",
+        include_spirv!("shaders/triangle.vert"),
+    )?;
+    let fs = Shader::from_spirv(
+        device,
+        "triangle.frag",
+        include_spirv!("shaders/triangle.frag"),
+    )?;
+    vs.validate_vertex_input(&[MyVertex::per_vertex()])?;
+    Ok([vs, fs])
 }
 
 pub struct RenderContext {
@@ -77,8 +84,11 @@ pub struct RenderContext {
     flight_id: Id<Flight>,
 
     swapchain_id: Id<Swapchain>,
+    swapchain_format: Format,
+    msaa_image_id: Id<Image>,
     task_graph: ExecutableTaskGraph<Self>,
     virtual_swapchain_id: Id<Swapchain>,
+    virtual_msaa_image_id: Id<Image>,
 }
 
 impl RenderContext {
@@ -91,8 +101,7 @@ impl RenderContext {
         let surface = Surface::from_window(context.instance().clone(), window.clone())
             .expect("failed to create surface");
 
-        let vs = vs::load(context.device().clone()).expect("failed to create shader module");
-        let fs = fs::load(context.device().clone()).expect("failed to create shader module");
+        let shaders = load_shaders(context.device()).unwrap_or_else(|e| panic!("{e}"));
 
         let viewport = Viewport {
             offset: [0.0, 0.0],
@@ -169,7 +178,7 @@ impl RenderContext {
                             min_image_count: caps.min_image_count,
                             image_format,
                             image_extent: window.inner_size().into(),
-                            image_usage: ImageUsage::COLOR_ATTACHMENT,
+                            image_usage: ImageUsage::TRANSFER_DST,
                             composite_alpha,
                             ..Default::default()
                         },
@@ -185,6 +194,12 @@ impl RenderContext {
             image_format: swapchain_format,
             ..Default::default()
         });
+        let virtual_msaa_image_id = task_graph.add_image(&ImageCreateInfo {
+            format: swapchain_format,
+            samples: MSAA_SAMPLES,
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        });
         let virtual_framebuffer_id = task_graph.add_framebuffer();
 
         let render_node_id = task_graph
@@ -193,7 +208,7 @@ impl RenderContext {
                 QueueFamilyType::Graphics,
                 RenderTask {
                     resources: context.resources().clone(),
-                    swapchain_id: virtual_swapchain_id,
+                    msaa_image_id: virtual_msaa_image_id,
                     pipeline: None,
                     vertex_buffer_id,
                     vertex_count: vertices.len() as _,
@@ -201,7 +216,7 @@ impl RenderContext {
             )
             .framebuffer(virtual_framebuffer_id)
             .color_attachment(
-                virtual_swapchain_id.current_image_id(),
+                virtual_msaa_image_id,
                 AccessTypes::COLOR_ATTACHMENT_WRITE,
                 ImageLayoutType::Optimal,
                 &AttachmentInfo {
@@ -211,6 +226,29 @@ impl RenderContext {
             )
             .buffer_access(vertex_buffer_id, AccessTypes::VERTEX_ATTRIBUTE_READ)
             .build();
+        let resolve_node_id = task_graph
+            .create_task_node(
+                "Resolve",
+                QueueFamilyType::Graphics,
+                ResolveTask {
+                    msaa_image_id: virtual_msaa_image_id,
+                    swapchain_id: virtual_swapchain_id,
+                },
+            )
+            .image_access(
+                virtual_msaa_image_id,
+                AccessTypes::RESOLVE_TRANSFER_READ,
+                ImageLayoutType::Optimal,
+            )
+            .image_access(
+                virtual_swapchain_id.current_image_id(),
+                AccessTypes::RESOLVE_TRANSFER_WRITE,
+                ImageLayoutType::Optimal,
+            )
+            .build();
+        task_graph
+            .add_edge(render_node_id, resolve_node_id)
+            .unwrap();
 
         let mut task_graph = unsafe {
             task_graph.compile(&CompileInfo {
@@ -226,8 +264,7 @@ impl RenderContext {
 
         let pipeline = Self::create_pipeline(
             context.device().clone(),
-            vs,
-            fs,
+            shaders,
             node.subpass().unwrap().clone(),
         );
 
@@ -236,6 +273,12 @@ impl RenderContext {
             .unwrap()
             .pipeline = Some(pipeline);
 
+        let msaa_image_id = create_msaa_image(
+            context.resources(),
+            swapchain_format,
+            window.inner_size().into(),
+        );
+
         RenderContext {
             window,
             viewport,
@@ -243,19 +286,21 @@ impl RenderContext {
             resources: context.resources().clone(),
             flight_id: context.flight_id(),
             swapchain_id,
+            swapchain_format,
+            msaa_image_id,
             task_graph,
             virtual_swapchain_id,
+            virtual_msaa_image_id,
         }
     }
 
     fn create_pipeline(
         device: Arc<Device>,
-        vs: Arc<ShaderModule>,
-        fs: Arc<ShaderModule>,
+        [vs, fs]: [Shader; 2],
         subpass: Subpass,
     ) -> Arc<GraphicsPipeline> {
-        let vs = vs.entry_point("main").unwrap();
-        let fs = fs.entry_point("main").unwrap();
+        let vs = vs.entry_point();
+        let fs = fs.entry_point();
         let vertex_input_state = MyVertex::per_vertex().definition(&vs).unwrap();
         let stages = [
             PipelineShaderStageCreateInfo::new(vs),
@@ -276,7 +321,10 @@ impl RenderContext {
                 vertex_input_state: Some(vertex_input_state),
                 input_assembly_state: Some(InputAssemblyState::default()),
                 rasterization_state: Some(RasterizationState::default()),
-                multisample_state: Some(MultisampleState::default()),
+                multisample_state: Some(MultisampleState {
+                    rasterization_samples: subpass.num_samples().unwrap(),
+                    ..Default::default()
+                }),
                 color_blend_state: Some(ColorBlendState::with_attachment_states(
                     subpass.num_color_attachments(),
                     ColorBlendAttachmentState::default(),
@@ -305,6 +353,9 @@ impl RenderContext {
             return;
         }
 
+        let flight = self.resources.flight(self.flight_id).unwrap();
+        flight.wait(None).unwrap();
+
         if self.recreate_swapchain {
             self.swapchain_id = self
                 .resources
@@ -314,6 +365,11 @@ impl RenderContext {
                 })
                 .expect("failed to recreate swapchain");
 
+            // the frames which used the old image are done after the wait above
+            unsafe { self.resources.remove_image(self.msaa_image_id) }.unwrap();
+            self.msaa_image_id =
+                create_msaa_image(&self.resources, self.swapchain_format, window_size.into());
+
             self.recreate_swapchain = false;
             self.viewport.extent = window_size.into();
         }
@@ -321,15 +377,13 @@ impl RenderContext {
         let resource_map = resource_map!(
             &self.task_graph,
             self.virtual_swapchain_id => self.swapchain_id,
+            self.virtual_msaa_image_id => self.msaa_image_id,
         )
         .unwrap();
 
-        let flight = self.resources.flight(self.flight_id).unwrap();
-        flight.wait(None).unwrap();
-
         match unsafe {
             self.task_graph
-                .execute(resource_map, &self, || self.window.pre_present_notify())
+                .execute(resource_map, self, || self.window.pre_present_notify())
         } {
             Ok(_) => {}
             Err(ExecuteError::Swapchain {
@@ -348,12 +402,14 @@ impl RenderContext {
 impl Drop for RenderContext {
     fn drop(&mut self) {
         unsafe { self.resources.remove_swapchain(self.swapchain_id).unwrap() };
+        unsafe { self.resources.remove_image(self.msaa_image_id).unwrap() };
     }
 }
 
+// draws the triangle into the multisampled image
 struct RenderTask {
     resources: Arc<Resources>,
-    swapchain_id: Id<Swapchain>,
+    msaa_image_id: Id<Image>,
     pipeline: Option<Arc<GraphicsPipeline>>,
     vertex_buffer_id: Id<Buffer>,
     vertex_count: u32,
@@ -363,7 +419,7 @@ impl Task for RenderTask {
     type World = RenderContext;
 
     fn clear_values(&self, clear_values: &mut vulkano_taskgraph::ClearValues<'_>) {
-        clear_values.set(self.swapchain_id.current_image_id(), [0.0, 0.0, 1.0, 1.0]);
+        clear_values.set(self.msaa_image_id, [0.0, 0.0, 1.0, 1.0]);
     }
 
     unsafe fn execute(
@@ -387,3 +443,29 @@ impl Drop for RenderTask {
         }
     }
 }
+
+// resolves the multisampled image into the swapchain image after the triangle is drawn
+struct ResolveTask {
+    msaa_image_id: Id<Image>,
+    swapchain_id: Id<Swapchain>,
+}
+
+impl Task for ResolveTask {
+    type World = RenderContext;
+
+    unsafe fn execute(
+        &self,
+        command_buffer: &mut RecordingCommandBuffer<'_>,
+        _task_context: &mut TaskContext<'_>,
+        _world: &Self::World,
+    ) -> TaskResult {
+        unsafe {
+            command_buffer.resolve_image(&ResolveImageInfo {
+                src_image: self.msaa_image_id,
+                dst_image: self.swapchain_id.current_image_id(),
+                ..Default::default()
+            })
+        }?;
+        Ok(())
+    }
+}
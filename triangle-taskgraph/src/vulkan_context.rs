@@ -9,7 +9,7 @@ use vulkano_taskgraph::resource::{Flight, Resources};
 use vulkano_taskgraph::Id;
 use winit::raw_window_handle::HasDisplayHandle;
 
-const VALIDATION_LAYER: &'static str = "VK_LAYER_KHRONOS_validation";
+const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
 const MAX_FRAMES_IN_FLIGHT: u32 = 2;
 
 pub struct VulkanContext {
@@ -0,0 +1,22 @@
+use shader_library::{FileWatcher, Shader, ShaderError};
+use std::{path::PathBuf, sync::Arc};
+use vulkano::device::Device;
+
+// the file at path relative to the crate, where the files compiled into the app are
+fn crate_path(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(path)
+}
+
+// watches files of the crate for changes during development, desktop builds only
+pub fn watch(paths: &[&'static str]) -> FileWatcher<&'static str> {
+    let mut watcher = FileWatcher::new();
+    for path in paths {
+        watcher.watch(*path, crate_path(path));
+    }
+    watcher
+}
+
+// loads the shader file at path, GLSL files are compiled with shaderc
+pub fn load_shader(device: &Arc<Device>, path: &str) -> Result<Shader, ShaderError> {
+    Shader::load(device, crate_path(path))
+}
@@ -1,3 +1,5 @@
+#[cfg(not(target_os = "android"))]
+mod hot_reload;
 mod render_context;
 mod vulkan_context;
 
@@ -6,6 +8,7 @@ use winit::{
     application::ApplicationHandler,
     event::WindowEvent,
     event_loop::{ActiveEventLoop, EventLoop},
+    keyboard::Key,
 };
 
 #[cfg(target_os = "android")]
@@ -83,6 +86,17 @@ impl ApplicationHandler for Application {
                     renderer.window().request_redraw();
                 }
             }
+            // M switches between off, 2x, 4x and 8x MSAA
+            WindowEvent::KeyboardInput { event, .. }
+                if event.state.is_pressed()
+                    && !event.repeat
+                    && event.logical_key.as_ref() == Key::Character("m") =>
+            {
+                if let Some(renderer) = self.renderer.as_mut() {
+                    renderer.next_msaa_samples();
+                    renderer.window().request_redraw();
+                }
+            }
             _ => (),
         }
     }
@@ -1,4 +1,5 @@
-use std::sync::Arc;
+use shader_library::{include_spirv, msaa_sample_count, FileWatcher, Shader, ShaderError};
+use std::{error::Error, sync::Arc};
 use vulkano::buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer};
 use vulkano::command_buffer::allocator::CommandBufferAllocator;
 use vulkano::command_buffer::{
@@ -6,9 +7,10 @@ use vulkano::command_buffer::{
     PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents,
 };
 use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
 use vulkano::image::view::ImageView;
-use vulkano::image::{Image, ImageUsage};
-use vulkano::memory::allocator::{AllocationCreateInfo, MemoryTypeFilter};
+use vulkano::image::{Image, ImageCreateInfo, ImageUsage, SampleCount};
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryAllocator, MemoryTypeFilter};
 use vulkano::pipeline::graphics::color_blend::{ColorBlendAttachmentState, ColorBlendState};
 use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
 use vulkano::pipeline::graphics::multisample::MultisampleState;
@@ -19,7 +21,6 @@ use vulkano::pipeline::graphics::GraphicsPipelineCreateInfo;
 use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
 use vulkano::pipeline::{GraphicsPipeline, PipelineLayout, PipelineShaderStageCreateInfo};
 use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
-use vulkano::shader::ShaderModule;
 use vulkano::swapchain::{
     self, PresentFuture, Surface, Swapchain, SwapchainAcquireFuture, SwapchainCreateInfo,
     SwapchainPresentInfo,
@@ -30,8 +31,14 @@ use vulkano::{Validated, VulkanError};
 use winit::event_loop::ActiveEventLoop;
 use winit::window::Window;
 
+#[cfg(not(target_os = "android"))]
+use crate::hot_reload::{load_shader, watch};
 use crate::vulkan_context::VulkanContext;
 
+type FrameFence = FenceSignalFuture<
+    PresentFuture<CommandBufferExecFuture<JoinFuture<Box<dyn GpuFuture>, SwapchainAcquireFuture>>>,
+>;
+
 #[derive(BufferContents, Vertex)]
 #[repr(C)]
 struct MyVertex {
@@ -39,62 +46,36 @@ struct MyVertex {
     position: [f32; 2],
 }
 
-mod vs {
-    vulkano_shaders::shader! {
-        ty: "vertex",
-        src: r"
-            #version 460
-
-            layout(location = 0) in vec2 position;
+// the files the shaders are compiled from, the build script embeds them as SPIR-V and they are
+// recompiled when they change on desktop
+const VS_PATH: &str = "shaders/triangle.vert";
+const FS_PATH: &str = "shaders/triangle.frag";
 
-            void main() {
-                gl_Position = vec4(position, 0.0, 1.0);
-            }
-        ",
-    }
-}
-
-mod fs {
-    vulkano_shaders::shader! {
-        ty: "fragment",
-        src: r"
-            #version 460
-
-            layout(location = 0) out vec4 f_color;
-
-            void main() {
-                f_color = vec4(1.0, 0.0, 0.0, 1.0);
-            }
-        ",
-    }
+// the shaders take the vertices of MyVertex and nothing else
+fn validate_shaders(vs: &Shader, _fs: &Shader) -> Result<(), ShaderError> {
+    vs.validate_vertex_input(&[MyVertex::per_vertex()])
 }
 
 pub struct RenderContext {
     window: Arc<Window>,
     swapchain: Arc<Swapchain>,
     render_pass: Arc<RenderPass>,
+    framebuffers: Vec<Arc<Framebuffer>>,
 
     vertex_buffer: Subbuffer<[MyVertex]>,
-    vs: Arc<ShaderModule>,
-    fs: Arc<ShaderModule>,
+    vs: Arc<Shader>,
+    fs: Arc<Shader>,
     viewport: Viewport,
     command_buffers: Vec<Arc<PrimaryAutoCommandBuffer>>,
+    #[cfg(not(target_os = "android"))]
+    watcher: FileWatcher<&'static str>,
+
+    msaa_samples: u32, // 1 disables MSAA, clamped down to what the device supports
 
     window_resized: bool,
     recreate_swapchain: bool,
-    fences: Vec<
-        Option<
-            Arc<
-                FenceSignalFuture<
-                    PresentFuture<
-                        CommandBufferExecFuture<
-                            JoinFuture<Box<dyn GpuFuture>, SwapchainAcquireFuture>,
-                        >,
-                    >,
-                >,
-            >,
-        >,
-    >, // TODO: simplify
+    msaa_changed: bool,
+    fences: Vec<Option<Arc<FrameFence>>>,
     previous_fence_i: u32,
 }
 
@@ -139,8 +120,17 @@ impl RenderContext {
             .unwrap()
         };
 
-        let render_pass = Self::get_render_pass(context.device().clone(), swapchain.clone());
-        let framebuffers = Self::get_framebuffers(&swapchain_images, render_pass.clone());
+        let msaa_samples = 4;
+        let render_pass = Self::get_render_pass(
+            context.device().clone(),
+            swapchain.image_format(), // set the format the same as the swapchain
+            msaa_sample_count(context.device(), msaa_samples),
+        );
+        let framebuffers = Self::get_framebuffers(
+            context.memory_allocator(),
+            &swapchain_images,
+            render_pass.clone(),
+        );
 
         let vertex1 = MyVertex {
             position: [-0.5, -0.5],
@@ -162,12 +152,18 @@ impl RenderContext {
                     | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
                 ..Default::default()
             },
-            vec![vertex1, vertex2, vertex3].into_iter(),
+            vec![vertex1, vertex2, vertex3],
         )
         .unwrap();
 
-        let vs = vs::load(context.device().clone()).expect("failed to create shader module");
-        let fs = fs::load(context.device().clone()).expect("failed to create shader module");
+        let load = |path, spirv| {
+            Arc::new(
+                Shader::from_spirv(context.device(), path, spirv).unwrap_or_else(|e| panic!("{e}")),
+            )
+        };
+        let vs = load(VS_PATH, include_spirv!("shaders/triangle.vert"));
+        let fs = load(FS_PATH, include_spirv!("shaders/triangle.frag"));
+        validate_shaders(&vs, &fs).unwrap_or_else(|e| panic!("{e}"));
 
         let viewport = Viewport {
             offset: [0.0, 0.0],
@@ -181,7 +177,8 @@ impl RenderContext {
             fs.clone(),
             render_pass.clone(),
             viewport.clone(),
-        );
+        )
+        .unwrap();
 
         let command_buffers = Self::get_command_buffers(
             context.command_buffer_allocator(),
@@ -195,49 +192,103 @@ impl RenderContext {
             window,
             swapchain,
             render_pass,
+            framebuffers,
             vertex_buffer,
             vs,
             fs,
             viewport,
             command_buffers,
+            #[cfg(not(target_os = "android"))]
+            watcher: watch(&[VS_PATH, FS_PATH]),
+            msaa_samples,
             window_resized: false,
             recreate_swapchain: false,
+            msaa_changed: false,
             fences: vec![None; swapchain_images.len()],
             previous_fence_i: 0,
         }
     }
 
-    fn get_render_pass(device: Arc<Device>, swapchain: Arc<Swapchain>) -> Arc<RenderPass> {
+    // draws into a multisampled color attachment which is resolved into the swapchain image,
+    // or into the swapchain image directly without MSAA
+    fn get_render_pass(
+        device: Arc<Device>,
+        format: Format,
+        samples: SampleCount,
+    ) -> Arc<RenderPass> {
+        if samples == SampleCount::Sample1 {
+            return vulkano::single_pass_renderpass!(
+                device,
+                attachments: {
+                    color: {
+                        format: format,
+                        samples: 1,
+                        load_op: Clear,
+                        store_op: Store,
+                    },
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {},
+                },
+            )
+            .unwrap();
+        }
         vulkano::single_pass_renderpass!(
             device,
             attachments: {
+                msaa: {
+                    format: format,
+                    samples: samples,
+                    load_op: Clear,
+                    store_op: DontCare,
+                },
                 color: {
-                    format: swapchain.image_format(), // set the format the same as the swapchain
+                    format: format,
                     samples: 1,
-                    load_op: Clear,
+                    load_op: DontCare,
                     store_op: Store,
                 },
             },
             pass: {
-                color: [color],
+                color: [msaa],
+                color_resolve: [color],
                 depth_stencil: {},
             },
         )
         .unwrap()
     }
 
+    // every swapchain image gets its own multisampled image, frames in flight must not share one
     fn get_framebuffers(
+        memory_allocator: &Arc<dyn MemoryAllocator>,
         images: &[Arc<Image>],
         render_pass: Arc<RenderPass>,
     ) -> Vec<Arc<Framebuffer>> {
+        let samples = render_pass.attachments()[0].samples;
         images
             .iter()
             .map(|image| {
-                let view = ImageView::new_default(image.clone()).unwrap();
+                let mut attachments = vec![ImageView::new_default(image.clone()).unwrap()];
+                if samples != SampleCount::Sample1 {
+                    let msaa_image = Image::new(
+                        memory_allocator.clone(),
+                        ImageCreateInfo {
+                            format: image.format(),
+                            extent: image.extent(),
+                            samples,
+                            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSIENT_ATTACHMENT,
+                            ..Default::default()
+                        },
+                        AllocationCreateInfo::default(),
+                    )
+                    .unwrap();
+                    attachments.insert(0, ImageView::new_default(msaa_image).unwrap());
+                }
                 Framebuffer::new(
                     render_pass.clone(),
                     FramebufferCreateInfo {
-                        attachments: vec![view],
+                        attachments,
                         ..Default::default()
                     },
                 )
@@ -248,14 +299,14 @@ impl RenderContext {
 
     fn get_pipeline(
         device: Arc<Device>,
-        vs: Arc<ShaderModule>,
-        fs: Arc<ShaderModule>,
+        vs: Arc<Shader>,
+        fs: Arc<Shader>,
         render_pass: Arc<RenderPass>,
         viewport: Viewport,
-    ) -> Arc<GraphicsPipeline> {
-        let vs = vs.entry_point("main").unwrap();
-        let fs = fs.entry_point("main").unwrap();
-        let vertex_input_state = MyVertex::per_vertex().definition(&vs).unwrap();
+    ) -> Result<Arc<GraphicsPipeline>, Box<dyn Error>> {
+        let vs = vs.entry_point();
+        let fs = fs.entry_point();
+        let vertex_input_state = MyVertex::per_vertex().definition(&vs)?;
         let stages = [
             PipelineShaderStageCreateInfo::new(vs),
             PipelineShaderStageCreateInfo::new(fs),
@@ -263,12 +314,10 @@ impl RenderContext {
         let layout = PipelineLayout::new(
             device.clone(),
             PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
-                .into_pipeline_layout_create_info(device.clone())
-                .unwrap(),
-        )
-        .unwrap();
+                .into_pipeline_layout_create_info(device.clone())?,
+        )?;
         let subpass = Subpass::from(render_pass, 0).unwrap();
-        GraphicsPipeline::new(
+        Ok(GraphicsPipeline::new(
             device.clone(),
             None,
             GraphicsPipelineCreateInfo {
@@ -276,7 +325,10 @@ impl RenderContext {
                 vertex_input_state: Some(vertex_input_state),
                 input_assembly_state: Some(InputAssemblyState::default()),
                 rasterization_state: Some(RasterizationState::default()),
-                multisample_state: Some(MultisampleState::default()),
+                multisample_state: Some(MultisampleState {
+                    rasterization_samples: subpass.num_samples().unwrap(),
+                    ..Default::default()
+                }),
                 color_blend_state: Some(ColorBlendState::with_attachment_states(
                     subpass.num_color_attachments(),
                     ColorBlendAttachmentState::default(),
@@ -289,8 +341,7 @@ impl RenderContext {
                 subpass: Some(subpass.into()),
                 ..GraphicsPipelineCreateInfo::layout(layout)
             },
-        )
-        .unwrap()
+        )?)
     }
 
     fn get_command_buffers(
@@ -310,10 +361,16 @@ impl RenderContext {
                 )
                 .unwrap();
 
+                // the resolved swapchain image is not cleared, it is overwritten
+                let mut clear_values = vec![Some([0.0, 0.0, 1.0, 1.0].into())];
+                if framebuffer.attachments().len() > 1 {
+                    clear_values.push(None);
+                }
+
                 builder
                     .begin_render_pass(
                         RenderPassBeginInfo {
-                            clear_values: vec![Some([0.0, 0.0, 1.0, 1.0].into())],
+                            clear_values,
                             ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
                         },
                         SubpassBeginInfo {
@@ -343,8 +400,75 @@ impl RenderContext {
         self.window_resized = true;
     }
 
+    // switch to the next of off, 2x, 4x and 8x MSAA, the render pass, framebuffers and pipeline
+    // are rebuilt with the next frame
+    pub fn next_msaa_samples(&mut self) {
+        self.msaa_samples = match self.msaa_samples {
+            1 => 2,
+            2 => 4,
+            4 => 8,
+            _ => 1,
+        };
+        self.msaa_changed = true;
+        log::info!("MSAA samples={}", self.msaa_samples);
+    }
+
+    // recompiles the shaders whose files changed and rebuilds the pipeline with them, a version
+    // which does not compile or does not fit the pipeline is logged and the last good one kept
+    #[cfg(not(target_os = "android"))]
+    fn reload_shaders(&mut self, context: &VulkanContext) {
+        let changed = self.watcher.changed();
+        if changed.is_empty() {
+            return;
+        }
+        let load = |path, shader: &Arc<Shader>| {
+            if changed.contains(&path) {
+                load_shader(context.device(), path).map(Arc::new)
+            } else {
+                Ok(shader.clone())
+            }
+        };
+        let (vs, fs) = match (load(VS_PATH, &self.vs), load(FS_PATH, &self.fs)) {
+            (Ok(vs), Ok(fs)) => (vs, fs),
+            (Err(e), _) | (_, Err(e)) => {
+                log::error!("Failed to load shader: {e}");
+                return;
+            }
+        };
+        if let Err(e) = validate_shaders(&vs, &fs) {
+            log::error!("Failed to use the changed shaders: {e}");
+            return;
+        }
+        let pipeline = match Self::get_pipeline(
+            context.device().clone(),
+            vs.clone(),
+            fs.clone(),
+            self.render_pass.clone(),
+            self.viewport.clone(),
+        ) {
+            Ok(pipeline) => pipeline,
+            Err(e) => {
+                log::error!("Failed to create the pipeline with the changed shaders: {e}");
+                return;
+            }
+        };
+        log::info!("Reloaded shaders {changed:?}");
+        (self.vs, self.fs) = (vs, fs);
+        self.command_buffers = Self::get_command_buffers(
+            context.command_buffer_allocator(),
+            context.queue(),
+            &pipeline,
+            &self.framebuffers,
+            &self.vertex_buffer,
+        );
+    }
+
+    #[allow(clippy::arc_with_non_send_sync)] // the fence future holds a Box<dyn GpuFuture>
     pub fn draw_frame(&mut self, context: &VulkanContext) {
-        if self.window_resized || self.recreate_swapchain {
+        #[cfg(not(target_os = "android"))]
+        self.reload_shaders(context);
+
+        if self.window_resized || self.recreate_swapchain || self.msaa_changed {
             self.recreate_swapchain = false;
 
             let new_dimensions = self.window.inner_size();
@@ -357,10 +481,22 @@ impl RenderContext {
                 Err(e) => panic!("failed to recreate swapchain: {e}"),
             };
             self.swapchain = new_swapchain;
-            let new_framebuffers = Self::get_framebuffers(&new_images, self.render_pass.clone());
+            if self.msaa_changed {
+                self.render_pass = Self::get_render_pass(
+                    context.device().clone(),
+                    self.swapchain.image_format(),
+                    msaa_sample_count(context.device(), self.msaa_samples),
+                );
+            }
+            self.framebuffers = Self::get_framebuffers(
+                context.memory_allocator(),
+                &new_images,
+                self.render_pass.clone(),
+            );
 
-            if self.window_resized {
+            if self.window_resized || self.msaa_changed {
                 self.window_resized = false;
+                self.msaa_changed = false;
 
                 self.viewport.extent = new_dimensions.into();
                 let new_pipeline = Self::get_pipeline(
@@ -369,12 +505,13 @@ impl RenderContext {
                     self.fs.clone(),
                     self.render_pass.clone(),
                     self.viewport.clone(),
-                );
+                )
+                .unwrap();
                 self.command_buffers = Self::get_command_buffers(
                     context.command_buffer_allocator(),
                     context.queue(),
                     &new_pipeline,
-                    &new_framebuffers,
+                    &self.framebuffers,
                     &self.vertex_buffer,
                 );
             }